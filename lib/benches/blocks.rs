@@ -0,0 +1,265 @@
+// 채굴, 블록 검증, utxo 재구성, 동기화 중 블록 추가의 성능을 측정한다.
+// CI에서는 `cargo bench -p btclib -- --quick`으로 짧게 돌릴 수 있다
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::params::ChainParams;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::{MerkleRoot, Savable};
+use btclib::U256;
+use chrono::{Duration, Utc};
+use criterion::{
+    criterion_group, criterion_main, BatchSize, Criterion, Throughput,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// 채굴 bench에서 한 번에 시도하는 nonce 수
+const MINE_STEPS: usize = 10_000;
+// 검증할 블록의 높이. 보상 계산에만 쓴다
+const VERIFY_HEIGHT: u64 = 1;
+// rebuild_utxos bench의 체인 길이
+const CHAIN_LENGTH: usize = 500;
+// add_blocks bench에서 genesis 다음에 받는 블록 수와 블록마다 담긴 tx 수
+const SYNC_LENGTH: usize = 200;
+const SYNC_WIDTH: usize = 20;
+
+const PREV_OUTPUT_VALUE: u64 = 10_000;
+const FEE: u64 = 1_000;
+
+type Utxos = HashMap<Hash, (bool, TransactionOutput)>;
+
+fn output(key: &PrivateKey, value: u64) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    }
+}
+
+fn spend(key: &PrivateKey, prev: &TransactionOutput, value: u64) -> Transaction {
+    let prev_hash = prev.hash();
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![output(key, value)],
+    )
+}
+
+fn header(transactions: &[Transaction], prev_block_hash: Hash) -> BlockHeader {
+    BlockHeader::new(
+        Utc::now(),
+        0,
+        prev_block_hash,
+        MerkleRoot::calculate(transactions),
+        btclib::MIN_TARGET,
+    )
+}
+
+// MAX_BLOCK_WEIGHT를 넘지 않는 만큼 1-input 1-output tx로 채운 블록과
+// 그 tx들이 소비하는 utxo
+fn full_block(key: &PrivateKey) -> (Block, Utxos) {
+    let reward = Blockchain::block_reward_at(VERIFY_HEIGHT);
+    // coinbase 값은 마지막에 정해지지만 크기는 같으므로 weight 계산에는 지장이 없다
+    let mut transactions = vec![Transaction::new(vec![], vec![output(key, reward)])];
+    let mut utxos = Utxos::new();
+    let mut weight = transactions[0].weight();
+
+    loop {
+        let prev = output(key, PREV_OUTPUT_VALUE);
+        let transaction = spend(key, &prev, PREV_OUTPUT_VALUE - FEE);
+        if weight + transaction.weight() > btclib::MAX_BLOCK_WEIGHT {
+            break;
+        }
+        weight += transaction.weight();
+        utxos.insert(prev.hash(), (false, prev));
+        transactions.push(transaction);
+    }
+
+    let fees = FEE * (transactions.len() as u64 - 1);
+    transactions[0].outputs[0].value = reward + fees;
+
+    let block = Block::new(header(&transactions, Hash::zero()), transactions);
+    (block, utxos)
+}
+
+// Blockchain과 같은 형식으로 직렬화하기 위한 구조체.
+// rebuild_utxos는 블록을 검증하지 않으므로 채굴하지 않은 블록으로 체인을 만든다
+#[derive(Serialize)]
+struct StoredChain<'a> {
+    utxos: Utxos,
+    target: U256,
+    blocks: &'a [Block],
+}
+
+// 매 블록마다 coinbase와, 이전 블록의 coinbase를 소비하는 tx 하나가 담긴 체인
+fn chain(key: &PrivateKey, length: usize) -> Blockchain {
+    let start = Utc::now() - Duration::seconds(length as i64 * 10);
+    let mut blocks: Vec<Block> = Vec::with_capacity(length);
+
+    for height in 0..length {
+        let reward = Blockchain::block_reward_at(height as u64);
+        let mut transactions = vec![Transaction::new(vec![], vec![output(key, reward)])];
+        let prev_block_hash = match blocks.last() {
+            Some(prev) => {
+                let prev_coinbase = &prev.transactions[0].outputs[0];
+                transactions.push(spend(key, prev_coinbase, prev_coinbase.value));
+                prev.hash()
+            }
+            None => Hash::zero(),
+        };
+
+        let mut header = header(&transactions, prev_block_hash);
+        header.timestamp = start + Duration::seconds(height as i64 * 10);
+        blocks.push(Block::new(header, transactions));
+    }
+
+    let mut serialized = vec![];
+    ciborium::into_writer(
+        &StoredChain {
+            utxos: Utxos::new(),
+            target: btclib::MIN_TARGET,
+            blocks: &blocks,
+        },
+        &mut serialized,
+    )
+    .expect("failed to serialize chain");
+    Blockchain::load(serialized.as_slice()).expect("failed to load chain")
+}
+
+// genesis의 coinbase를 SYNC_WIDTH개로 나눈 뒤, 블록마다 직전 블록의 output을
+// 하나씩 소비하는 tx를 SYNC_WIDTH개 담은 채굴된 블록들과 genesis만 있는 체인.
+// 서명 검증이 대부분이므로 add_blocks가 미리 병렬로 검증하는 효과를 볼 수 있다
+fn sync_blocks(key: &PrivateKey, params: &ChainParams) -> (Blockchain, Vec<Block>) {
+    let start = Utc::now() - Duration::seconds((SYNC_LENGTH as i64 + 1) * 10);
+    let payouts = vec![(key.public_key(), 1); SYNC_WIDTH];
+    let coinbase =
+        Transaction::coinbase_with_payouts(0, Blockchain::block_reward_at(0), 0, &payouts)
+            .expect("failed to split the genesis coinbase");
+    let mut prev_outputs = coinbase.outputs.clone();
+    let mut transactions = vec![coinbase];
+    // 난이도 조정을 따라가기 위해 만든 블록을 차례로 추가해 둔다
+    let mut blockchain = Blockchain::with_params(params.clone()).expect("invalid chain params");
+    let mut genesis = None;
+
+    for height in 0..=SYNC_LENGTH {
+        let prev_block_hash = blockchain.tip_hash().unwrap_or(Hash::zero());
+        let mut header = header(&transactions, prev_block_hash);
+        header.timestamp = start + Duration::seconds(height as i64 * 10);
+        header.target = blockchain.target();
+        while !header.mine(MINE_STEPS) {}
+        blockchain
+            .add_block(Block::new(header, transactions))
+            .expect("sync block must be valid");
+        genesis.get_or_insert_with(|| blockchain.clone());
+
+        let next_height = height as u64 + 1;
+        let reward = Blockchain::block_reward_at(next_height);
+        transactions = vec![Transaction::coinbase(next_height, reward, 0, &key.public_key())];
+        for prev in &prev_outputs {
+            transactions.push(spend(key, prev, prev.value));
+        }
+        prev_outputs = transactions[1..]
+            .iter()
+            .map(|transaction| transaction.outputs[0].clone())
+            .collect();
+    }
+
+    let blocks = blockchain.blocks().skip(1).cloned().collect();
+    (genesis.expect("genesis was added first"), blocks)
+}
+
+fn mine(c: &mut Criterion) {
+    let key = PrivateKey::new_key();
+    let transactions = vec![Transaction::new(vec![], vec![output(&key, 1)])];
+    // 절대 만족할 수 없는 target이라 항상 MINE_STEPS번 시도한다
+    let mut header = header(&transactions, Hash::zero());
+    header.target = U256::zero();
+
+    let mut group = c.benchmark_group("mine");
+    group.throughput(Throughput::Elements(MINE_STEPS as u64));
+    group.bench_function("header", |b| {
+        b.iter(|| assert!(!header.mine(MINE_STEPS)))
+    });
+    group.finish();
+}
+
+fn verify_transactions(c: &mut Criterion) {
+    let key = PrivateKey::new_key();
+    let (block, utxos) = full_block(&key);
+    block
+        .verify_transactions(VERIFY_HEIGHT, &utxos)
+        .expect("benchmark block must be valid");
+
+    let mut group = c.benchmark_group("verify_transactions");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(block.transactions.len() as u64));
+    group.bench_function("full_block", |b| {
+        b.iter(|| block.verify_transactions(VERIFY_HEIGHT, &utxos))
+    });
+    group.finish();
+}
+
+fn rebuild_utxos(c: &mut Criterion) {
+    let key = PrivateKey::new_key();
+    let blockchain = chain(&key, CHAIN_LENGTH);
+
+    let mut group = c.benchmark_group("rebuild_utxos");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(CHAIN_LENGTH as u64));
+    group.bench_function("500_blocks", |b| {
+        b.iter_batched_ref(
+            || blockchain.clone(),
+            |blockchain| blockchain.rebuild_utxos(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn add_blocks(c: &mut Criterion) {
+    let key = PrivateKey::new_key();
+    // 블록을 준비하는 동안의 채굴이 오래 걸리지 않도록 target을 쉽게 한다
+    let params = ChainParams {
+        min_target: U256::MAX >> 4,
+        ..ChainParams::default()
+    };
+    let (genesis, blocks) = sync_blocks(&key, &params);
+
+    let mut group = c.benchmark_group("add_blocks");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(SYNC_LENGTH as u64));
+    // 블록을 하나씩 검증하고 추가한다
+    group.bench_function("one_by_one", |b| {
+        b.iter_batched(
+            || (genesis.clone(), blocks.clone()),
+            |(mut blockchain, blocks)| {
+                for block in blocks {
+                    blockchain.add_block(block).expect("sync block must be valid");
+                }
+                blockchain
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    // 서명을 미리 병렬로 검증한 뒤 추가한다
+    group.bench_function("pipelined", |b| {
+        b.iter_batched(
+            || (genesis.clone(), blocks.clone()),
+            |(mut blockchain, blocks)| {
+                blockchain.add_blocks(blocks).expect("sync blocks must be valid");
+                blockchain
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, mine, verify_transactions, rebuild_utxos, add_blocks);
+criterion_main!(benches);
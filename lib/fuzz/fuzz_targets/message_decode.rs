@@ -0,0 +1,13 @@
+// peer가 보낼 수 있는 임의의 바이트로 메시지 해석이 panic 하지 않는지 확인한다.
+// cargo +nightly fuzz run message_decode
+#![no_main]
+
+use btclib::network::Message;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // 본문만
+    let _ = Message::decode(data);
+    // 길이 prefix가 붙은 frame
+    let _ = Message::receive(&mut &data[..]);
+});
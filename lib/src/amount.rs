@@ -0,0 +1,76 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+
+use crate::error::{BtcError, Result};
+
+// 1 BTC = 10^8 satoshis
+pub const SATOSHIS_PER_BTC: u64 = 100_000_000;
+
+// satoshi 단위의 금액. 화면에 보여주거나 입력받을 때만 쓰고,
+// tx와 블록에는 지금처럼 u64 satoshi로 저장한다
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_sat(satoshis: u64) -> Self {
+        Amount(satoshis)
+    }
+
+    pub const fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    // 소수점 8자리 아래는 가장 가까운 satoshi로 반올림한다 (정확히 중간이면 짝수 쪽).
+    // 음수이거나 u64 satoshi로 나타낼 수 없는 금액은 거절한다
+    pub fn from_btc(btc: &BigDecimal) -> Result<Self> {
+        (btc * BigDecimal::from(SATOSHIS_PER_BTC))
+            .with_scale_round(0, RoundingMode::HalfEven)
+            .to_u64()
+            .map(Amount)
+            .ok_or(BtcError::InvalidAmount)
+    }
+
+    pub fn to_btc(self) -> BigDecimal {
+        BigDecimal::new(self.0.into(), 8)
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(satoshis: u64) -> Self {
+        Amount(satoshis)
+    }
+}
+
+impl From<Amount> for u64 {
+    fn from(amount: Amount) -> Self {
+        amount.0
+    }
+}
+
+impl FromStr for Amount {
+    type Err = BtcError;
+
+    // "1.5" 같은 BTC 단위 십진수. Display처럼 " BTC"가 붙어 있어도 된다
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let s = s.strip_suffix("BTC").map_or(s, str::trim_end);
+        let btc =
+            BigDecimal::from_str(s).map_err(|_| BtcError::InvalidAmount)?;
+        Amount::from_btc(&btc)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:08} BTC",
+            self.0 / SATOSHIS_PER_BTC,
+            self.0 % SATOSHIS_PER_BTC
+        )
+    }
+}
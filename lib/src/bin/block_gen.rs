@@ -1,6 +1,9 @@
+use btclib::amount::SATOSHIS_PER_BTC;
 use btclib::crypto::PrivateKey;
 use btclib::sha256::Hash;
-use btclib::types::{Block, BlockHeader, Transaction, TransactionOutput};
+use btclib::types::{
+    Block, BlockHeader, LockingCondition, Transaction, TransactionOutput,
+};
 use btclib::util::{MerkleRoot, Savable};
 use chrono::Utc;
 use std::env;
@@ -20,8 +23,8 @@ fn main() {
         vec![],
         vec![TransactionOutput {
             unique_id: Uuid::new_v4(),
-            value: btclib::INITIAL_REWARD * 10u64.pow(8),
-            pubkey: private_key.public_key(),
+            value: btclib::INITIAL_REWARD * SATOSHIS_PER_BTC,
+            lock: LockingCondition::P2PK(private_key.public_key()),
         }],
     )];
     let merkle_root = MerkleRoot::calculate(&transactions);
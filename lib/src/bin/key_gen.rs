@@ -0,0 +1,50 @@
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::util::Savable;
+use std::env;
+use std::fs;
+use std::process::exit;
+
+fn main() {
+    let name = if let Some(arg) = env::args().nth(1) {
+        arg
+    } else {
+        eprintln!("Usage: key_gen <name> [cbor|hex]");
+        exit(1);
+    };
+    let format = env::args().nth(2).unwrap_or_else(|| "cbor".to_owned());
+
+    let private_key = PrivateKey::new_key();
+    let public_key = private_key.public_key();
+
+    // cbor는 PrivateKey::load_from_file로 읽을 수 있는 형태,
+    // hex는 32 byte secret과 SEC1 압축 형식의 public key
+    match format.as_str() {
+        "cbor" => private_key
+            .save_to_file(format!("{name}.priv.cbor"))
+            .expect("Failed to save private key"),
+        "hex" => {
+            fs::write(
+                format!("{name}.priv.hex"),
+                hex::encode(private_key.0.to_bytes()),
+            )
+            .expect("Failed to save private key");
+            fs::write(
+                format!("{name}.pub.hex"),
+                hex::encode(public_key.to_sec1_bytes()),
+            )
+            .expect("Failed to save public key");
+        }
+        _ => {
+            eprintln!("Unknown format: {format}. Use cbor or hex");
+            exit(1);
+        }
+    }
+
+    // 형식과 관계없이 miner의 --public-key-file이 읽을 수 있는 PEM도 저장
+    public_key
+        .save_to_file(format!("{name}.pub.pem"))
+        .expect("Failed to save public key");
+
+    println!("address: {}", Hash::hash(&public_key));
+}
@@ -1,5 +1,6 @@
+use btclib::amount::SATOSHIS_PER_BTC;
 use btclib::crypto::PrivateKey;
-use btclib::types::{Transaction, TransactionOutput};
+use btclib::types::{LockingCondition, Transaction, TransactionOutput};
 use btclib::util::Savable;
 use std::env;
 use std::process::exit;
@@ -17,8 +18,8 @@ fn main() {
         vec![],
         vec![TransactionOutput {
             unique_id: Uuid::new_v4(),
-            value: btclib::INITIAL_REWARD * 10u64.pow(8),
-            pubkey: private_key.public_key(),
+            value: btclib::INITIAL_REWARD * SATOSHIS_PER_BTC,
+            lock: LockingCondition::P2PK(private_key.public_key()),
         }],
     );
     transaction.save_to_file(path).expect("Failed to save transaction");
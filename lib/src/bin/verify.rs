@@ -0,0 +1,33 @@
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use std::env;
+use std::process::exit;
+
+// 노드 없이 체인 파일을 검증하는 도구.
+// 노드가 시작할 때처럼 모든 블록을 처음부터 다시 검증해서 처음으로 실패한 블록을 알려준다.
+// 유효하면 OK를 출력하고, 검증에 실패하면 2, 파일을 읽지 못하면 1로 종료한다
+fn main() {
+    let path = if let Some(arg) = env::args().nth(1) {
+        arg
+    } else {
+        eprintln!("Usage: verify <blockchain_file>");
+        exit(1);
+    };
+
+    let blockchain = match Blockchain::load_from_file(&path) {
+        Ok(blockchain) => blockchain,
+        Err(e) => {
+            eprintln!("Failed to load {path}: {e}");
+            exit(1);
+        }
+    };
+
+    match blockchain.find_invalid_block() {
+        None => println!("OK ({} blocks)", blockchain.block_height()),
+        Some((height, e)) => {
+            let hash = blockchain.blocks().nth(height as usize).unwrap().hash();
+            println!("FAILED at block {height} ({hash}): {e}");
+            exit(2);
+        }
+    }
+}
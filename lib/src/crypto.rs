@@ -3,6 +3,7 @@ use ecdsa::{
     signature::{Signer, Verifier},
     Signature as ECDSASignature, SigningKey, VerifyingKey,
 };
+use k256::elliptic_curve::scalar::IsHigh;
 use k256::Secp256k1;
 use serde::{Deserialize, Serialize};
 use spki::EncodePublicKey;
@@ -23,8 +24,27 @@ impl Signature {
     }
 
     pub fn verify(&self, output_hash: &Hash, public_key: &PublicKey) -> bool {
+        // (r, s)와 (r, n - s)는 모두 유효한 서명이므로, bitcoin처럼 low-S 형태만 허용한다
+        if !self.is_low_s() {
+            return false;
+        }
         public_key.0.verify(&output_hash.as_bytes(), &self.0).is_ok()
     }
+
+    // BIP 62의 canonical(low-S) 서명인지
+    pub fn is_low_s(&self) -> bool {
+        !bool::from(self.0.s().is_high())
+    }
+
+    // high-S 서명을 low-S 형태로 바꾼다. 이미 low-S라면 그대로 반환
+    pub fn normalize_s(&self) -> Self {
+        Signature(self.0.normalize_s().unwrap_or(self.0))
+    }
+
+    // (r, s)를 이어 붙인 64 bytes
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.0.to_bytes().into()
+    }
 }
 // ----------------------------------------------
 /// secp256k1 곡선의 공개키. 특정 private key로 서명되었는가 signature를 검증
@@ -33,6 +53,13 @@ impl Signature {
 )]
 pub struct PublicKey(VerifyingKey<Secp256k1>);
 
+impl PublicKey {
+    // SEC1 압축 형식 33 bytes
+    pub fn to_sec1_bytes(&self) -> Vec<u8> {
+        self.0.to_encoded_point(true).as_bytes().to_vec()
+    }
+}
+
 impl Savable for PublicKey {
     fn load<I: Read>(mut reader: I) -> IoResult<Self> {
         // read PEM-encoded public key into string
@@ -73,7 +100,7 @@ impl PrivateKey {
     }
 
     pub fn public_key(&self) -> PublicKey {
-        PublicKey(self.0.verifying_key().clone())
+        PublicKey(*self.0.verifying_key())
     }
 }
 
@@ -99,6 +126,7 @@ impl Savable for PrivateKey {
 }
 
 mod signkey_serde {
+    use serde::de::Error;
     use serde::Deserialize;
 
     pub fn serialize<S>(
@@ -119,6 +147,7 @@ mod signkey_serde {
         D: serde::Deserializer<'de>,
     {
         let bytes: Vec<u8> = Vec::<u8>::deserialize(deserializer)?;
-        Ok(super::SigningKey::from_slice(&bytes).unwrap())
+        super::SigningKey::from_slice(&bytes)
+            .map_err(|_| D::Error::custom("invalid private key"))
     }
 }
@@ -0,0 +1,103 @@
+use crate::params::ChainParams;
+use crate::U256;
+use bigdecimal::BigDecimal;
+use std::fmt;
+
+// 조정 구간이 끝날 때마다 다음 target을 정하는 난이도 조정 알고리즘.
+// ChainParams::difficulty_algo로 고르고, 결과는 ChainParams::adjust_target이
+// 허용 범위(25%~400%, [MIN_DIFFICULTY_TARGET, min_target]) 안으로 자른다
+pub trait DifficultyAlgo: fmt::Debug + Send + Sync {
+    // 직전 조정 구간(difficulty_update_interval개의 블록)에 timespan초가 걸렸을 때
+    // target 다음에 올 target. timespan은 1 이상이다
+    fn next_target(
+        &self,
+        params: &ChainParams,
+        target: U256,
+        timespan: u64,
+    ) -> U256;
+}
+
+// 직전 구간이 기대보다 빨랐던(느렸던) 비율만큼 target을 그대로 낮춘다(높인다).
+// 구간 하나의 블록 시간만 보므로 hashrate 변화를 바로 따라가지만,
+// 구간이 짧으면 우연히 빠르거나 느렸던 구간에도 크게 흔들린다
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimpleRatio;
+
+impl DifficultyAlgo for SimpleRatio {
+    fn next_target(
+        &self,
+        params: &ChainParams,
+        target: U256,
+        timespan: u64,
+    ) -> U256 {
+        // 이전 구간의 블록이 생성된 시간이 IDLE한 blocktime과 얼마나 차이가 났는지?
+        let target_seconds =
+            params.ideal_block_time * params.difficulty_update_interval;
+
+        // 실제 bitcoin에서는 leading zero 의 갯수를 늘려서 난이도를 증가 시킴.
+        // 여기서는 간이적으로 처리
+        // target * (실제 시간 / 기대시간)
+        // 너무 빨리 되었다면 (실제 시간 / 기대시간) < 1 -> target이 더 어려워지게 (target이 낮아질수록 조건을 만족하는 해시 만들기가 어려움)
+        // 너무 느리게 되었다면 (실제 시간 / 기대 시간) > 1 -> target이 더 쉬워지게
+        scale(
+            target,
+            BigDecimal::from(timespan) / BigDecimal::from(target_seconds),
+        )
+    }
+}
+
+// 지수 이동 평균(EMA). 매 구간 SimpleRatio가 가려는 곳으로 1/window만큼만 움직인다.
+// 지난 구간들의 비율이 (1 - 1/window)^k의 가중치로 누적되므로 대략 window개 구간을
+// 평균 내는 셈이라, 한 구간의 우연한 흔들림이나 짧은 hashrate 급증에 덜 반응한다.
+// 대신 hashrate가 실제로 바뀌었을 때 따라잡는 데 여러 구간이 걸린다
+#[derive(Debug, Clone, Copy)]
+pub struct Ema {
+    // 평활 구간 수. 1이면 SimpleRatio와 같다
+    pub window: u64,
+}
+
+impl Default for Ema {
+    fn default() -> Self {
+        Self { window: 8 }
+    }
+}
+
+impl DifficultyAlgo for Ema {
+    fn next_target(
+        &self,
+        params: &ChainParams,
+        target: U256,
+        timespan: u64,
+    ) -> U256 {
+        let window = self.window.max(1);
+        let target_seconds =
+            params.ideal_block_time * params.difficulty_update_interval;
+
+        // target * ((window - 1) + 실제 시간 / 기대시간) / window
+        let expected = BigDecimal::from(target_seconds);
+        let ratio = (BigDecimal::from(window - 1) * &expected
+            + BigDecimal::from(timespan))
+            / (BigDecimal::from(window) * expected);
+        scale(target, ratio)
+    }
+}
+
+// target * ratio의 정수 부분. U256 범위를 넘으면 U256::MAX
+fn scale(target: U256, ratio: BigDecimal) -> U256 {
+    let new_target = BigDecimal::parse_bytes(target.to_string().as_bytes(), 10)
+        .expect("BUG: impossible")
+        * ratio;
+
+    // cut off decimal point and everything after
+    // it from string representation of new_target
+    let new_target_str = new_target
+        .to_string()
+        .split('.')
+        .next()
+        .expect("BUG: Expected a decimal point")
+        .to_owned();
+
+    // target이 min_target 근처일 때 오래 걸린 구간이면 U256 범위를 넘을 수 있다.
+    // 십진수 문자열은 숫자로만 되어 있으므로 실패한다면 overflow 뿐이다
+    U256::from_str_radix(&new_target_str, 10).unwrap_or(U256::MAX)
+}
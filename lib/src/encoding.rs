@@ -0,0 +1,79 @@
+use crate::sha256::Hash;
+use crate::U256;
+
+// hash 계산에만 쓰는 고정된 바이트 인코딩의 버전.
+// serde derive의 필드 순서나 CBOR 인코더가 바뀌어도 hash가 바뀌지 않도록
+// 모든 필드를 정해진 순서와 폭으로 직접 쓴다. 저장과 전송에는 여전히 CBOR를 쓴다.
+// 인코딩을 바꿔야 한다면 이 값을 올려서 이전 인코딩과 섞이지 않게 한다
+pub const HASH_ENCODING_VERSION: u8 = 1;
+
+// 무엇을 인코딩했는지 구분하는 tag. 서로 다른 종류의 값이 같은 바이트가 되지 않도록 한다
+pub(crate) const TAG_BLOCK_HEADER: u8 = b'H';
+pub(crate) const TAG_TXID: u8 = b'T';
+pub(crate) const TAG_WTXID: u8 = b'W';
+
+// 정수는 big-endian 고정 폭, 가변 길이 값은 u32 길이를 앞에 붙인다.
+// U256과 Hash는 Hash::as_bytes와 같은 little-endian 32 bytes
+pub(crate) struct HashEncoder(Vec<u8>);
+
+impl HashEncoder {
+    pub fn new(tag: u8) -> Self {
+        HashEncoder(vec![HASH_ENCODING_VERSION, tag])
+    }
+
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn i64(&mut self, value: i64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn u256(&mut self, value: &U256) -> &mut Self {
+        let mut bytes = [0u8; 32];
+        value.to_little_endian(&mut bytes);
+        self.0.extend_from_slice(&bytes);
+        self
+    }
+
+    pub fn hash(&mut self, hash: &Hash) -> &mut Self {
+        self.0.extend_from_slice(&hash.as_bytes());
+        self
+    }
+
+    // 길이가 정해진 값. 길이를 붙이지 않는다
+    pub fn fixed(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    // 길이가 변하는 값. 길이를 앞에 붙여 경계가 모호하지 않게 한다
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.count(bytes.len());
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    // 뒤따르는 항목의 수
+    pub fn count(&mut self, count: usize) -> &mut Self {
+        let count =
+            u32::try_from(count).expect("BUG: too many items to encode");
+        self.u32(count)
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0)
+    }
+}
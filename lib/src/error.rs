@@ -1,3 +1,4 @@
+use crate::sha256::Hash;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,9 +6,18 @@ pub enum BtcError {
     #[error("Invalid transaction")]
     InvalidTransaction,
 
+    #[error("First transaction of the block is not a coinbase")]
+    InvalidCoinbase,
+
     #[error("Invalid block")]
     InvalidBlock,
 
+    #[error("Block is already in the chain")]
+    DuplicateBlock,
+
+    #[error("Block timestamp is too far in the future")]
+    BlockFromFuture,
+
     #[error("Invalid block header")]
     InvalidBlockHeader,
 
@@ -20,17 +30,61 @@ pub enum BtcError {
     #[error("Invalid Merkle root")]
     InvalidMerkleRoot,
 
+    #[error("Block hash does not meet target")]
+    InvalidProofOfWork,
+
+    #[error("Invalid target")]
+    InvalidTarget,
+
+    #[error("Total supply does not match issuance schedule")]
+    InvalidSupply,
+
     #[error("Invalid hash")]
     InvalidHash,
 
     #[error("Invalid signature")]
     InvalidSignature,
 
+    #[error("Output is already spent")]
+    DoubleSpend,
+
+    /// 충돌하는 mempool tx의 txid
+    #[error("Conflicts with mempool transaction {0}, which does not signal replaceability")]
+    TxConflictNonReplaceable(Hash),
+
+    #[error("Too many unconfirmed ancestors or descendants")]
+    MempoolChainTooLong,
+
+    #[error("Transaction fee is below the minimum relay fee")]
+    InsufficientFee,
+
+    #[error("Output value is below the dust limit")]
+    DustOutput,
+
+    #[error("Replacement fee does not exceed the replaced fees plus the relay fee")]
+    InsufficientReplacementFee,
+
+    /// (되돌려야 하는 블록 수, 허용하는 최대 깊이)
+    #[error("Reorg of {0} blocks exceeds the maximum depth of {1}")]
+    ReorgTooDeep(u64, u64),
+
+    #[error("Invalid UTXO snapshot")]
+    InvalidUtxoSnapshot,
+
+    #[error("UTXO snapshot does not match any checkpoint")]
+    UtxoSnapshotNotCheckpointed,
+
+    #[error("Invalid amount")]
+    InvalidAmount,
+
     #[error("Invalid public key")]
     InvalidPublicKey,
 
     #[error("Invalid private key")]
     InvalidPrivateKey,
+
+    #[error("Invalid chain parameters")]
+    InvalidChainParams,
 }
 
 pub type Result<T> = std::result::Result<T, BtcError>;
@@ -1,17 +1,25 @@
-use serde::{Deserialize, Serialize};
-use uint::construct_uint;
-
+pub mod amount;
 pub mod crypto;
+pub mod difficulty;
+pub mod encoding;
 pub mod error;
 pub mod network;
+pub mod params;
 pub mod sha256;
 pub mod types;
 pub mod util;
 
-construct_uint! {
-    #[derive(Serialize, Deserialize)]
-    pub struct U256(4);
+#[allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+mod u256 {
+    use serde::{Deserialize, Serialize};
+    use uint::construct_uint;
+
+    construct_uint! {
+        #[derive(Serialize, Deserialize)]
+        pub struct U256(4);
+    }
 }
+pub use u256::U256;
 
 // 채굴 보상. 50 × 10^8 = 5,000,000,000 satoshis
 pub const INITIAL_REWARD: u64 = 50;
@@ -31,11 +39,50 @@ pub const MIN_TARGET: U256 = U256([
     0x0000_FFFF_FFFF_FFFF,
 ]);
 
+// 난이도 조정으로 내려갈 수 있는 target의 하한.
+// target이 0이 되면 어떤 hash도 조건을 만족하지 못해 채굴이 영원히 멈추므로,
+// target은 항상 [MIN_DIFFICULTY_TARGET, MIN_TARGET] 범위 안에 있다
+// 0x000000000000000000000000000000000000000000000000FFFFFFFFFFFFFFFF
+pub const MIN_DIFFICULTY_TARGET: U256 = U256([0xFFFF_FFFF_FFFF_FFFF, 0, 0, 0]);
+
+// 블록 timestamp가 로컬 시계보다 이 시간(초) 넘게 앞서면 받지 않는다 (실제 bitcoin과 동일).
+// timestamp를 미래로 당겨 난이도 조정을 유리하게 만드는 것을 막는다
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
+
 // 난이도 조정 (실제 bitcoin은 2016 블록마다 조정)
 pub const DIFFICULTY_UPDATE_INTERVAL: u64 = 50;
 
-// 600 블록이 지나도 mempool에서 소비되지 않으면 tx를 버린다
+// 기본값: 600초가 지나도 mempool에서 소비되지 않으면 tx를 버린다.
+// node는 설정으로 바꿀 수 있다
 pub const MAX_MEMPOOL_TRANSACTION_AGE: u64 = 600;
 
-// 블록당 최대 20개의 블록만 허용
-pub const BLOCK_TRANSACTION_CAP: usize = 20;
+// 기본값: 이보다 많은 블록을 되돌려야 하는 reorg는 하지 않는다.
+// 잠시 많은 hashpower를 빌린 공격자가 오래 전에 확정된 블록까지 갈아엎는 것을 막는다.
+// node는 설정으로 바꿀 수 있다
+pub const MAX_REORG_DEPTH: u64 = 100;
+
+// input의 기본 sequence. tx를 교체(RBF)할 수 없다
+pub const SEQUENCE_FINAL: u32 = u32::MAX;
+
+// 이 값 이하의 sequence를 가진 input이 있으면 tx를 교체(RBF)할 수 있다 (BIP 125)
+pub const MAX_RBF_SEQUENCE: u32 = u32::MAX - 2;
+
+// 블록당 최대 weight. tx 갯수가 아니라 직렬화된 크기로 블록을 제한한다 (실제 bitcoin과 동일)
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+
+// non-witness 데이터 1 byte가 차지하는 weight
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+// mempool이 받아들이는 최소 수수료율 (satoshi / vbyte).
+// 수수료 없는 tx로 mempool을 채우는 spam을 막는 정책일 뿐, 블록에는 그대로 담길 수 있다
+pub const MIN_RELAY_FEE_RATE: u64 = 1;
+
+// 값이 이 이하인 output은 나중에 쓰는 비용이 더 커서 utxo만 차지한다 (dust).
+// MIN_RELAY_FEE_RATE와 마찬가지로 mempool 정책이다
+pub const DUST_LIMIT: u64 = 100;
+
+// mempool에서 tx 하나에 딸린 미확정 조상/자손 tx의 최대 수 (자기 자신 포함).
+// 미확정 사슬이 끝없이 길어지면 교체나 템플릿 조립 때마다 사슬 전체를 다시 계산해야 하고,
+// 긴 자손 사슬로 부모 tx의 교체를 막는 pinning 공격도 가능하다
+pub const MAX_ANCESTORS: usize = 25;
+pub const MAX_DESCENDANTS: usize = 25;
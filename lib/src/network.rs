@@ -1,12 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-use std::io::{Error as IoError, Read, Write};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Write};
+use std::ops::BitOr;
 use tokio::io::{
     AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
 };
 
 use crate::crypto::PublicKey;
-use crate::types::{Block, Transaction, TransactionOutput};
+use crate::sha256::Hash;
+use crate::types::{
+    Block, BlockHeader, DifficultyInfo, MempoolAcceptance, Transaction,
+    TransactionOutput,
+};
+use crate::U256;
+use thiserror::Error;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Message {
@@ -18,6 +25,9 @@ pub enum Message {
     SubmitTransaction(Transaction),
     /// Broadcast a new transaction to other nodes
     NewTransaction(Transaction),
+    /// How the submitted transaction was handled by the
+    /// mempool, or None if the transaction is invalid
+    TransactionAcceptance(Option<MempoolAcceptance>),
 
     /// Ask the node to prepare the optimal block template
     /// with the coinbase transaction paying the specified
@@ -25,6 +35,8 @@ pub enum Message {
     FetchTemplate(PublicKey),
     /// The template
     Template(Block),
+    /// The request was rate-limited, try again later
+    Throttled,
     /// Ask the node to validate a block template.
     /// This is to prevent the node from mining an invalid
     /// block (e.g. if one has been found in the meantime,
@@ -40,17 +52,184 @@ pub enum Message {
     DiscoverNodes,
     /// This is the response to DiscoverNodes
     NodeList(Vec<String>),
+    /// Tell the node we connected to which services we
+    /// provide. Sent first on a connection; peers that
+    /// never send it are treated as full nodes.
+    /// There is no response
+    Version(ServiceFlags),
+    /// Tell the node we connected to which port we listen
+    /// on, so it can connect back and relay to us as well.
+    /// There is no response
+    Announce(u16),
+    /// Tell the node we are dropping this connection on
+    /// purpose and will not take it back for a while, so it
+    /// should close it instead of reconnecting.
+    /// There is no response
+    Disconnect,
     /// Ask a node whats the highest block it knows about
     /// in comparison to the local blockchain
     AskDifference(u32),
     /// This is the response to AskDifference
     Difference(i32),
+    /// Ask a node for the cumulative work of its chain
+    FetchChainWork,
+    /// This is the response to FetchChainWork:
+    /// cumulative work and block height
+    ChainWork(U256, u64),
+    /// Ask a node for the genesis block of its chain, so
+    /// peers can check they are on the same network before
+    /// syncing
+    GetGenesis,
+    /// This is the response to GetGenesis.
+    /// None if the node has no blocks yet
+    Genesis(Option<Block>),
     /// Ask a node to send a block with the specified height
     FetchBlock(usize),
+    /// Ask a node to send up to `count` blocks starting
+    /// at the specified height
+    FetchBlocks(usize, usize),
+    /// Ask a node to send up to `count` blocks following
+    /// the last block it shares with the sender. `locator`
+    /// is the sender's Blockchain::block_locator. Answered
+    /// with Blocks, starting from genesis if nothing is
+    /// shared
+    GetBlocks { locator: Vec<Hash>, count: usize },
+    /// This is the response to FetchBlocks and GetBlocks
+    Blocks(Vec<Block>),
+    /// Ask a node to stream its blocks from the specified
+    /// height up to its tip. Answered with a sequence of
+    /// BlockChunk. The node stops sending while too many
+    /// chunks are unacknowledged
+    StreamBlocks { from_height: u64 },
+    /// Consecutive blocks of a stream. An empty chunk marks
+    /// the end of the stream
+    BlockChunk(Vec<Block>),
+    /// Tell the streaming node that a chunk has been
+    /// applied, so it can send more
+    BlockChunkAck,
     /// Broadcast a new block to other nodes
     NewBlock(Block),
+
+    /// Ask a node for the height and hash of its chain tip
+    FetchTip,
+    /// This is the response to FetchTip.
+    /// None if the node has no blocks yet
+    Tip(Option<(u64, Hash)>),
+    /// Ask a node for the block with the specified hash
+    FetchBlockByHash(Hash),
+    /// This is the response to FetchBlockByHash
+    FoundBlock(Option<Block>),
+    /// Ask a node for a transaction with the specified hash,
+    /// either confirmed or in the mempool
+    FetchTransaction(Hash),
+    /// This is the response to FetchTransaction
+    FoundTransaction(Option<Transaction>),
+    /// Ask a node for up to `limit` mempool transactions
+    /// with the highest fee rate. The node may send fewer
+    GetTopMempool { limit: u32 },
+    /// This is the response to GetTopMempool, in the order
+    /// a block template would include them
+    TopMempool(Vec<Transaction>),
+
+    /// Ask a node for its current difficulty and how far
+    /// away the next adjustment is
+    FetchDifficulty,
+    /// This is the response to FetchDifficulty
+    Difficulty(DifficultyInfo),
+
+    /// Ask a node for its validation counters and mempool
+    /// size
+    FetchMetrics,
+    /// This is the response to FetchMetrics, in the
+    /// Prometheus text exposition format
+    Metrics(String),
+
+    /// Subscribe to newly accepted blocks. The node keeps
+    /// sending BlockNotification until the client disconnects
+    Subscribe,
+    /// Header of a block the node has just accepted
+    BlockNotification(BlockHeader),
+    /// Subscribe to mempool changes. The node keeps
+    /// sending MempoolEvent until the client disconnects,
+    /// and drops clients that fall too far behind
+    SubscribeMempool,
+    /// A transaction entered (added) or left (removed)
+    /// the mempool. Exactly one of the two is set
+    MempoolEvent {
+        added: Option<Transaction>,
+        removed: Option<Hash>,
+    },
+}
+
+// Version handshake로 알려주는, 연결한 쪽이 제공하는 기능들
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct ServiceFlags(u64);
+
+impl ServiceFlags {
+    pub const NONE: ServiceFlags = ServiceFlags(0);
+    // 블록과 tx를 검증하고 전파하는 full node
+    pub const NETWORK: ServiceFlags = ServiceFlags(1);
+    // 템플릿을 받아 채굴하는 miner
+    pub const MINING: ServiceFlags = ServiceFlags(1 << 1);
+
+    pub const fn contains(self, other: ServiceFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // 새 블록과 tx를 먼저 보내줄 상대인지.
+    // miner는 템플릿으로 새 블록을 알게 되므로 full node에게만 전파한다
+    pub const fn wants_relay(self) -> bool {
+        self.contains(ServiceFlags::NETWORK)
+    }
+}
+
+impl BitOr for ServiceFlags {
+    type Output = ServiceFlags;
+
+    fn bitor(self, other: ServiceFlags) -> ServiceFlags {
+        ServiceFlags(self.0 | other.0)
+    }
 }
 
+// 메시지를 보낼 때 생기는 에러
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error("Peer closed the connection: {0}")]
+    PeerClosed(IoError),
+
+    #[error("Failed to encode message: {0}")]
+    Encode(ciborium::ser::Error<IoError>),
+
+    #[error("Failed to decode message: {0}")]
+    Decode(ciborium::de::Error<IoError>),
+
+    #[error("Message too large: {0} bytes")]
+    TooLarge(u64),
+
+    #[error("Network I/O error: {0}")]
+    Io(IoError),
+}
+
+impl From<IoError> for NetworkError {
+    // 상대가 연결을 끊어서 생긴 에러는 따로 구분한다
+    fn from(e: IoError) -> Self {
+        match e.kind() {
+            IoErrorKind::BrokenPipe
+            | IoErrorKind::ConnectionReset
+            | IoErrorKind::ConnectionAborted
+            | IoErrorKind::NotConnected
+            | IoErrorKind::WriteZero
+            | IoErrorKind::UnexpectedEof => NetworkError::PeerClosed(e),
+            _ => NetworkError::Io(e),
+        }
+    }
+}
+
+// 한 메시지의 최대 크기. peer가 보낸 길이만큼 그대로 메모리를 할당하지 않도록 제한한다
+pub const MAX_MESSAGE_SIZE: usize = 32 * 1024 * 1024;
+
 // We are going to use length-prefixed encoding for message
 // And we are going to use ciborium (CBOR) for serialization
 impl Message {
@@ -63,30 +242,35 @@ impl Message {
         Ok(bytes)
     }
 
-    pub fn decode(
-        data: &[u8],
-    ) -> Result<Self, ciborium::de::Error<IoError>> {
-        ciborium::from_reader(data)
+    // peer가 보낸 임의의 바이트를 해석한다. 잘못된 입력에는 panic 하지 않고 에러를 반환한다.
+    // 본문이 이미 메모리에 있으므로 중간에 끝난 본문도 연결 문제가 아닌 Decode 에러다
+    pub fn decode(data: &[u8]) -> Result<Self, NetworkError> {
+        ciborium::from_reader(data).map_err(NetworkError::Decode)
     }
 
-    pub fn send(
-        &self,
-        stream: &mut impl Write,
-    ) -> Result<(), ciborium::ser::Error<IoError>> {
-        let bytes = self.encode()?;
-        let len = bytes.len() as u64;
-        stream.write_all(&len.to_be_bytes())?;
-        stream.write_all(&bytes)?;
+    pub fn send(&self, stream: &mut impl Write) -> Result<(), NetworkError> {
+        let frame = self.frame()?;
+        stream.write_all(&frame)?;
+        stream.flush()?;
 
         Ok(())
     }
 
-    pub fn receive(
-        stream: &mut impl Read,
-    ) -> Result<Self, ciborium::de::Error<IoError>> {
+    // 길이 prefix와 본문을 한 버퍼에 담는다.
+    // 한 번의 write_all로 보내므로 중간에 실패해도 길이만 보내고 끝나는 일이 없다
+    fn frame(&self) -> Result<Vec<u8>, NetworkError> {
+        let bytes = self.encode().map_err(NetworkError::Encode)?;
+        let mut frame = Vec::with_capacity(8 + bytes.len());
+        frame.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        frame.extend_from_slice(&bytes);
+
+        Ok(frame)
+    }
+
+    pub fn receive(stream: &mut impl Read) -> Result<Self, NetworkError> {
         let mut len_bytes = [0u8; 8];
         stream.read_exact(&mut len_bytes)?;
-        let len = u64::from_be_bytes(len_bytes) as usize;
+        let len = Self::frame_len(len_bytes)?;
 
         let mut data = vec![0u8; len];
         stream.read_exact(&mut data)?;
@@ -94,24 +278,31 @@ impl Message {
         Self::decode(&data)
     }
 
+    fn frame_len(len_bytes: [u8; 8]) -> Result<usize, NetworkError> {
+        let len = u64::from_be_bytes(len_bytes);
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return Err(NetworkError::TooLarge(len));
+        }
+        Ok(len as usize)
+    }
+
     pub async fn send_async(
         &self,
         stream: &mut (impl AsyncWrite + Unpin),
-    ) -> Result<(), ciborium::ser::Error<IoError>> {
-        let bytes = self.encode()?;
-        let len = bytes.len() as u64;
-        stream.write_all(&len.to_be_bytes()).await?;
-        stream.write_all(&bytes).await?;
+    ) -> Result<(), NetworkError> {
+        let frame = self.frame()?;
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
 
         Ok(())
     }
 
     pub async fn receive_async(
         stream: &mut (impl AsyncRead + Unpin),
-    ) -> Result<Self, ciborium::de::Error<IoError>> {
+    ) -> Result<Self, NetworkError> {
         let mut len_bytes = [0u8; 8];
         stream.read_exact(&mut len_bytes).await?;
-        let len = u64::from_be_bytes(len_bytes) as usize;
+        let len = Self::frame_len(len_bytes)?;
 
         let mut data = vec![0u8; len];
         stream.read_exact(&mut data).await?;
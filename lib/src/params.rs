@@ -0,0 +1,88 @@
+use crate::difficulty::{DifficultyAlgo, SimpleRatio};
+use crate::error::{BtcError, Result};
+use crate::sha256::Hash;
+use crate::U256;
+use std::sync::Arc;
+
+// 체인마다 달라질 수 있는 합의 파라미터.
+// 기본값은 lib.rs의 상수들이고, test에서는 짧은 블록 시간과 쉬운 target으로 체인을 빠르게 돌려볼 수 있다
+#[derive(Clone, Debug)]
+pub struct ChainParams {
+    // 블록 생성 시간 목표치 (초)
+    pub ideal_block_time: u64,
+    // 몇 블록마다 난이도를 조정하는지
+    pub difficulty_update_interval: u64,
+    // 가장 쉬운 target. 첫 블록의 target이고, 난이도 조정도 이보다 쉬워지지 않는다
+    pub min_target: U256,
+    // 조정 구간마다 다음 target을 정하는 알고리즘
+    pub difficulty_algo: Arc<dyn DifficultyAlgo>,
+    // 받아들일 수 있는 utxo snapshot들 (Blockchain::import_utxo_snapshot)
+    pub utxo_checkpoints: Vec<UtxoCheckpoint>,
+}
+
+// 검증된 체인에서 만든 utxo snapshot의 식별 정보.
+// 믿을 수 있는 경로로 배포된 이 값과 일치하는 snapshot만 받아들인다
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtxoCheckpoint {
+    // snapshot에 반영된 블록 수
+    pub height: u64,
+    // 마지막으로 반영된 블록의 hash. height가 0이면 Hash::zero()
+    pub block_hash: Hash,
+    // Blockchain::export_utxo_snapshot이 돌려준 utxo set의 hash
+    pub utxo_hash: Hash,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            ideal_block_time: crate::IDEAL_BLOCK_TIME,
+            difficulty_update_interval: crate::DIFFICULTY_UPDATE_INTERVAL,
+            min_target: crate::MIN_TARGET,
+            difficulty_algo: Arc::new(SimpleRatio),
+            utxo_checkpoints: vec![],
+        }
+    }
+}
+
+impl ChainParams {
+    // 블록 시간이나 조정 구간이 0이면 조정 구간 계산이 0으로 나누게 된다
+    pub fn validate(&self) -> Result<()> {
+        if self.ideal_block_time == 0 || self.difficulty_update_interval == 0 {
+            return Err(BtcError::InvalidChainParams);
+        }
+        Ok(())
+    }
+
+    // 직전 조정 구간에 time_diff가 걸렸을 때 target 다음에 올 target.
+    // 결과는 target의 25%~400%, 그리고 [MIN_DIFFICULTY_TARGET, min_target] 범위 안에 있다.
+    // min_target이 MIN_DIFFICULTY_TARGET보다 작다면 항상 min_target이다
+    pub fn adjust_target(
+        &self,
+        target: U256,
+        time_diff: chrono::Duration,
+    ) -> U256 {
+        // 구간 내 블록들의 timestamp가 모두 같거나 역전되어 있으면 0 이하가 되어
+        // target이 0으로 무너지므로, 최소 1초가 걸린 것으로 간주한다
+        let time_diff_seconds = time_diff.num_seconds().max(1) as u64;
+
+        let new_target =
+            self.difficulty_algo.next_target(self, target, time_diff_seconds);
+
+        // 현재 난이도의 25%, 400% 내에서만 움직이도록 clamp 처리한다. 너무 급격한 난이도 변경을 방지.
+        // target * 4도 U256 범위를 넘을 수 있으므로 saturating으로 곱하고 min_target으로 자른다
+        let upper_bound =
+            target.saturating_mul(U256::from(4)).min(self.min_target);
+        let new_target = if new_target < target / 4 {
+            target / 4
+        } else if new_target > upper_bound {
+            upper_bound
+        } else {
+            new_target
+        };
+
+        // 최소보다는 커야 하고, 하한 아래로 내려가 0이 되지 않도록 한다.
+        // min_target이 MIN_DIFFICULTY_TARGET보다 어렵게 설정되었다면 min_target을 따른다
+        // (clamp는 이 경우 panic 한다)
+        new_target.max(crate::MIN_DIFFICULTY_TARGET).min(self.min_target)
+    }
+}
@@ -1,13 +1,27 @@
 use std::fmt;
+use std::str::FromStr;
 
+use crate::error::BtcError;
 use crate::U256;
 use serde::{Deserialize, Serialize};
 use sha256::digest;
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
 pub struct Hash(U256);
 
 impl Hash {
+    #[allow(clippy::self_named_constructors)]
     // hash anything that can be serde Serialized via ciborium
     pub fn hash<T: serde::Serialize>(data: &T) -> Self {
         let mut serialized: Vec<u8> = vec![];
@@ -21,11 +35,16 @@ impl Hash {
             );
         }
 
-        let hash = digest(&serialized);
+        Self::hash_bytes(&serialized)
+    }
+    // 이미 인코딩된 바이트를 그대로 hash 한다
+    pub fn hash_bytes(data: &[u8]) -> Self {
+        let hash = digest(data);
         let hash_bytes = hex::decode(hash).unwrap();
         let hash_array: [u8; 32] = hash_bytes.as_slice().try_into().unwrap();
         Hash(U256::from(hash_array))
     }
+
     // check if a hash matches a target
     // hash가 target 이하라면 채굴한 것으로 간주
     // 본래는 leading zero를 만족하는 해시를 찾아내야 하는데, 여기서는 단순 값 비교로 간이처리
@@ -43,6 +62,17 @@ impl Hash {
     }
 }
 
+impl FromStr for Hash {
+    type Err = BtcError;
+
+    // Display와 같은 16진수 문자열로부터 파싱
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        U256::from_str_radix(s, 16)
+            .map(Hash)
+            .map_err(|_| BtcError::InvalidHash)
+    }
+}
+
 impl fmt::Display for Hash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:x}", self.0)
@@ -3,5 +3,7 @@ mod blockchain;
 mod transaction;
 
 pub use block::{Block, BlockHeader};
-pub use blockchain::Blockchain;
-pub use transaction::{Transaction, TransactionInput, TransactionOutput};
+pub use blockchain::{Blockchain, DifficultyInfo, MempoolAcceptance};
+pub use transaction::{
+    LockingCondition, Transaction, TransactionInput, TransactionOutput,
+};
@@ -1,11 +1,13 @@
+use crate::encoding::{HashEncoder, TAG_BLOCK_HEADER};
 use crate::error::{BtcError, Result};
 use crate::sha256::Hash;
 use crate::types::transaction::{Transaction, TransactionOutput};
+use crate::types::Blockchain;
 use crate::util::{MerkleRoot, Savable};
 use crate::U256;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -22,44 +24,32 @@ impl Block {
         }
     }
 
+    // 블록의 id. prev_block_hash, block_index, tip_hash와 utxo checkpoint가 모두 이 값을 쓴다.
+    // header만이 아니라 블록 전체의 serde(CBOR) 직렬화를 hash 하므로 canonical_bytes와 달리
+    // Block, BlockHeader, Transaction의 serde 표현이 바뀌면 값도 바뀐다.
+    // lib/tests/encoding.rs에 고정해 둔 값이 깨지면 저장된 체인의 연결이 모두 깨진 것이다
     pub fn hash(&self) -> Hash {
         Hash::hash(self)
     }
 
+    // header의 PoW가 header에 적힌 target을 만족하는지.
+    // target이 기대한 값인지는 확인하지 않는다 (BlockHeader::validate)
+    pub fn check_pow(&self) -> bool {
+        self.header.check_pow()
+    }
+
+    // 블록에 포함된 모든 tx의 weight 합
+    pub fn weight(&self) -> usize {
+        self.transactions.iter().map(|tx| tx.weight()).sum()
+    }
+
+    // 블록의 tx들이 낸 수수료의 합. 잠금 조건(서명)은 확인하지 않지만,
+    // 이중 지출과 금액은 블록 검증과 같은 기준으로 확인한 input들로만 계산한다
     pub fn calculate_miner_fees(
         &self,
         utxos: &HashMap<Hash, (bool, TransactionOutput)>,
     ) -> Result<u64> {
-        let mut inputs: HashMap<Hash, TransactionOutput> = HashMap::new();
-        let mut outputs: HashMap<Hash, TransactionOutput> = HashMap::new();
-
-        for transaction in self.transactions.iter().skip(1) {
-            // input
-            for input in &transaction.inputs {
-                let prev_output =
-                    utxos.get(&input.prev_transaction_output_hash).map(|(_, output)| output);
-                if prev_output.is_none() {
-                    return Err(BtcError::InvalidTransaction);
-                }
-                let prev_output = prev_output.unwrap();
-                if inputs.contains_key(&input.prev_transaction_output_hash) {
-                    return Err(BtcError::InvalidTransaction);
-                }
-                inputs.insert(input.prev_transaction_output_hash, prev_output.clone());
-            }
-
-            // output
-            for output in &transaction.outputs {
-                if outputs.contains_key(&output.hash()) {
-                    return Err(BtcError::InvalidTransaction);
-                }
-                outputs.insert(output.hash(), output.clone());
-            }
-        }
-
-        let input_value: u64 = inputs.values().map(|output| output.value).sum();
-        let output_value: u64 = outputs.values().map(|output| output.value).sum();
-        Ok(input_value - output_value)
+        self.check_spends(utxos, None)
     }
 
     pub fn verify_coinbase_transaction(
@@ -67,27 +57,33 @@ impl Block {
         predicted_block_height: u64,
         utxos: &HashMap<Hash, (bool, TransactionOutput)>,
     ) -> Result<()> {
+        // 사용자들이 낸 수수료
+        let miner_fees = self.calculate_miner_fees(utxos)?;
+        self.check_coinbase(predicted_block_height, miner_fees)
+    }
+
+    // coinbase tx가 블록 보상과 miner_fees를 정확히 가져가는지
+    fn check_coinbase(&self, predicted_block_height: u64, miner_fees: u64) -> Result<()> {
         let coinbase_transaction = &self.transactions[0];
 
-        if coinbase_transaction.inputs.len() != 0 {
-            return Err(BtcError::InvalidTransaction);
+        // input이 있다면 coinbase가 아니다. 보상을 받으면서 utxo까지 소비할 수는 없다
+        if !coinbase_transaction.is_coinbase() {
+            return Err(BtcError::InvalidCoinbase);
         }
-        if coinbase_transaction.outputs.len() == 0 {
-            return Err(BtcError::InvalidTransaction);
+        // 값이 0인 output은 아무것도 지급하지 않으면서 utxo만 차지한다
+        if coinbase_transaction.outputs.iter().any(|output| output.value == 0) {
+            return Err(BtcError::InvalidTransactionOutput);
         }
 
-        // 사용자들이 낸 수수료
-        let miner_fees = self.calculate_miner_fees(utxos)?;
-
-        // 보상 * 사토시 변환 / 반감기에 따른 2승수 나눗셈
-        let block_reward = crate::INITIAL_REWARD * 10u64.pow(8)
-            / 2u64.pow((predicted_block_height / crate::HALVING_INTERVAL) as u32);
+        // 64번 반감된 뒤로는 보상이 0이다
+        let block_reward = Blockchain::block_reward_at(predicted_block_height);
 
         // coinbase tx의 출력값의 합은 블록 보상과 miner fee의 합과 동일하다.
-        let total_coinbase_outputs: u64 =
-            coinbase_transaction.outputs.iter().map(|output| output.value).sum();
-
-        if total_coinbase_outputs != block_reward + miner_fees {
+        // 더 많이 가져가면 새 코인을 찍어낸 셈이고, 덜 가져간 값은 어디에도 남지 않는다
+        let total_coinbase_outputs = checked_sum(&coinbase_transaction.outputs)?;
+        let expected_coinbase_outputs =
+            block_reward.checked_add(miner_fees).ok_or(BtcError::InvalidTransaction)?;
+        if total_coinbase_outputs != expected_coinbase_outputs {
             return Err(BtcError::InvalidTransaction);
         }
 
@@ -99,59 +95,114 @@ impl Block {
         predicted_block_height: u64,
         utxos: &HashMap<Hash, (bool, TransactionOutput)>,
     ) -> Result<()> {
-        // 해당 블록 내 소비될 utxo
-        // 같은 블록 내 이중 지출을 막기 위한 로컬 변수
-        let mut inputs: HashMap<Hash, TransactionOutput> = HashMap::new();
+        self.check_transactions(predicted_block_height, utxos, true)
+    }
 
+    // verify_locks가 false라면 input의 잠금 조건(서명)은 이미 검증된 것으로 보고 건너뛴다.
+    // 나머지 검증(이중 지출, 금액, coinbase 등)은 그대로 한다
+    pub(crate) fn check_transactions(
+        &self,
+        predicted_block_height: u64,
+        utxos: &HashMap<Hash, (bool, TransactionOutput)>,
+        verify_locks: bool,
+    ) -> Result<()> {
         // tx를 하나도 안 들고 있는 블록 처리
         if self.transactions.is_empty() {
             return Err(BtcError::InvalidTransaction);
         }
 
-        self.verify_coinbase_transaction(predicted_block_height, utxos)?;
+        // 첫 번째 tx의 input은 check_spends가 보지 않으므로, 다른 검증보다 먼저 확인한다
+        if !self.transactions[0].is_coinbase() {
+            return Err(BtcError::InvalidCoinbase);
+        }
+
+        // 블록 weight 한도 초과
+        if self.weight() > crate::MAX_BLOCK_WEIGHT {
+            return Err(BtcError::InvalidBlock);
+        }
+
+        // 한 tx 안에서 unique_id가 겹치면 output hash가 충돌할 수 있다
+        if !self.transactions.iter().all(|tx| tx.has_unique_output_ids()) {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        // 수수료는 검증을 통과한 input들로만 계산되므로, coinbase는 그 다음에 확인한다
+        let locks_at = verify_locks.then_some(predicted_block_height);
+        let miner_fees = self.check_spends(utxos, locks_at)?;
+        self.check_coinbase(predicted_block_height, miner_fees)
+    }
+
+    // coinbase를 제외한 tx들의 input과 output을 검증하고 수수료의 합을 돌려준다.
+    // 블록 전체에서 하나의 input 집합으로 이중 지출을 찾으므로, 여러 tx가 같은 output을 소비해서
+    // 수수료를 부풀릴 수 없다. locks_at이 있다면 그 높이의 블록으로 보고 잠금 조건도 확인한다
+    fn check_spends(
+        &self,
+        utxos: &HashMap<Hash, (bool, TransactionOutput)>,
+        locks_at: Option<u64>,
+    ) -> Result<u64> {
+        // 해당 블록 내 소비될 utxo
+        // 같은 블록 내 이중 지출을 막기 위한 로컬 변수
+        let mut inputs: HashSet<Hash> = HashSet::new();
+        // 같은 블록 내 앞선 tx들이 만든 output. 미확정 부모 tx와 자식 tx가 함께 채굴될 수 있도록 한다
+        let mut block_outputs: HashMap<Hash, TransactionOutput> = HashMap::new();
+        let mut miner_fees: u64 = 0;
 
         // 일반적인 tx 검증. except coinbase (first tx)
         for transaction in self.transactions.iter().skip(1) {
-            let mut input_value = 0;
-            let mut output_value = 0;
+            // coinbase는 블록의 첫 번째 tx로 하나만 있을 수 있다
+            if transaction.is_coinbase() {
+                return Err(BtcError::InvalidTransaction);
+            }
+
+            // 자기 자신의 output을 소비할 수 없다
+            if transaction.spends_own_output() {
+                return Err(BtcError::InvalidTransaction);
+            }
+
+            let mut input_value: u64 = 0;
 
             // input 검증
             for input in &transaction.inputs {
                 // input 해시가 참조하는 이전 tx
-                let prev_output =
-                    utxos.get(&input.prev_transaction_output_hash).map(|(_, output)| output);
-                if prev_output.is_none() {
-                    return Err(BtcError::InvalidTransaction);
-                }
-                let prev_output = prev_output.unwrap();
+                let prev_output = utxos
+                    .get(&input.prev_transaction_output_hash)
+                    .map(|(_, output)| output)
+                    .or_else(|| block_outputs.get(&input.prev_transaction_output_hash))
+                    .ok_or(BtcError::InvalidTransaction)?;
 
                 // double-spending 방지
-                // 로컬 변수인 inputs 상에 누적된 input들 중 이전 tx 중 사용된 것이 하나라도 있으면 그것은 이중 지출이므로 걸러낸다.
-                if inputs.contains_key(&input.prev_transaction_output_hash) {
-                    return Err(BtcError::InvalidTransaction);
+                // 블록 내 앞선 input들(다른 tx 포함) 중 같은 output을 사용한 것이 있으면 이중 지출이므로 걸러낸다.
+                if !inputs.insert(input.prev_transaction_output_hash) {
+                    return Err(BtcError::DoubleSpend);
                 }
 
-                // input으로 사용될 tx의 이전 output이 올바른 소유자에 의해 서명된 것인지 확인
-                if !input.signature.verify(&input.prev_transaction_output_hash, &prev_output.pubkey)
-                {
-                    return Err(BtcError::InvalidSignature);
+                // input으로 사용될 tx의 이전 output의 잠금 조건을 만족하는지 확인.
+                // 지원하지 않는 조건이라면 거부한다
+                if let Some(height) = locks_at {
+                    prev_output.lock.verify(input, height)?;
                 }
-                input_value += prev_output.value;
-                inputs.insert(input.prev_transaction_output_hash, prev_output.clone());
+                // 값 부풀리기를 막기 위해 overflow 시 거부한다
+                input_value = input_value
+                    .checked_add(prev_output.value)
+                    .ok_or(BtcError::InvalidTransaction)?;
             }
 
             // output 처리
+            let output_value = checked_sum(&transaction.outputs)?;
             for output in &transaction.outputs {
-                output_value += output.value;
+                if block_outputs.insert(output.hash(), output.clone()).is_some() {
+                    return Err(BtcError::InvalidTransaction);
+                }
             }
 
             // 채굴 보상이 있으므로 output 값어치는 input 값어치보다 항상 적어야 한다.
-            if input_value < output_value {
-                return Err(BtcError::InvalidTransaction);
-            }
+            let fee = input_value
+                .checked_sub(output_value)
+                .ok_or(BtcError::InvalidTransaction)?;
+            miner_fees = miner_fees.checked_add(fee).ok_or(BtcError::InvalidTransaction)?;
         }
 
-        Ok(())
+        Ok(miner_fees)
     }
 }
 
@@ -195,12 +246,71 @@ impl BlockHeader {
         }
     }
 
+    // header의 hash. serde 직렬화가 아니라 고정된 인코딩(canonical_bytes)을 hash 한다
     pub fn hash(&self) -> Hash {
-        Hash::hash(self)
+        Hash::hash_bytes(&self.canonical_bytes())
+    }
+
+    // hash 계산에 쓰는 고정된 바이트 인코딩 (crate::encoding)
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        HashEncoder::new(TAG_BLOCK_HEADER)
+            .i64(self.timestamp.timestamp())
+            .u32(self.timestamp.timestamp_subsec_nanos())
+            .u64(self.nonce)
+            .hash(&self.prev_block_hash)
+            .hash(self.merkle_root.hash())
+            .u256(&self.target)
+            .finish()
+    }
+
+    // tx를 보지 않고 header만으로 할 수 있는 검증. prev 블록 다음에 올 header로서
+    // prev hash가 이어지는지, expected_target보다 쉽지 않은지, PoW를 만족하는지,
+    // timestamp가 prev 이후인지 확인한다.
+    // 블록은 block_index, locator와 같이 Block::hash로 식별하므로 prev의 Block::hash로 잇는다
+    pub fn validate(&self, prev: &Block, expected_target: U256) -> Result<()> {
+        // 블록체인 상 마지막 블록의 해시는 현재 채굴된 블록의 prev_block_hash와 동일해야 한다
+        if self.prev_block_hash != prev.hash() {
+            println!("prev hash is wrong");
+            return Err(BtcError::InvalidBlock);
+        }
+
+        // 채굴자가 임의로 쉬운 target을 쓰지 못하도록 노드가 기대하는 target과 비교한다
+        if self.target > expected_target {
+            println!("target is easier than expected");
+            return Err(BtcError::InvalidTarget);
+        }
+
+        // 현재 채굴된 block은 지정된 target보다는 커야 한다
+        if !self.check_pow() {
+            println!("does not match target");
+            return Err(BtcError::InvalidProofOfWork);
+        }
+
+        // 채굴된 시간이 마지막 블록 채굴된 시간 이후여야 한다
+        if self.timestamp <= prev.header.timestamp {
+            return Err(BtcError::InvalidBlock);
+        }
+
+        Ok(())
+    }
+
+    // header hash <= target
+    pub fn check_pow(&self) -> bool {
+        self.hash().matches_target(self.target)
     }
 
     pub fn mine(&mut self, steps: usize) -> bool {
-        if self.hash().matches_target(self.target) {
+        self.mine_with_clock(steps, Utc::now)
+    }
+
+    /// nonce overflow 시 timestamp를 갱신할 clock을 주입받는 mine.
+    /// 시작 nonce는 header의 nonce 필드를 그대로 사용하므로,
+    /// 같은 header와 같은 clock이면 항상 같은 결과가 나온다.
+    pub fn mine_with_clock<F>(&mut self, steps: usize, mut clock: F) -> bool
+    where
+        F: FnMut() -> DateTime<Utc>,
+    {
+        if self.check_pow() {
             return true;
         }
         for _ in 0..steps {
@@ -208,12 +318,19 @@ impl BlockHeader {
                 self.nonce = new_nonce;
             } else {
                 self.nonce = 0;
-                self.timestamp = Utc::now()
+                self.timestamp = clock()
             }
-            if self.hash().matches_target(self.target) {
+            if self.check_pow() {
                 return true;
             }
         }
         false
     }
 }
+
+// output value의 합. u64를 넘어가면 에러
+fn checked_sum<'a>(outputs: impl IntoIterator<Item = &'a TransactionOutput>) -> Result<u64> {
+    outputs.into_iter().try_fold(0u64, |sum, output| {
+        sum.checked_add(output.value).ok_or(BtcError::InvalidTransaction)
+    })
+}
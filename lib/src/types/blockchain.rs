@@ -1,166 +1,952 @@
+use crate::crypto::PublicKey;
+use crate::encoding::HASH_ENCODING_VERSION;
 use crate::error::{BtcError, Result};
+use crate::params::{ChainParams, UtxoCheckpoint};
 use crate::sha256::Hash;
-use crate::types::block::Block;
+use crate::types::block::{Block, BlockHeader};
 use crate::types::transaction::{Transaction, TransactionOutput};
-use crate::util::{MerkleRoot, Savable};
+use crate::util::{MerkleAccumulator, MerkleRoot, Savable};
 use crate::U256;
-use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::{
     Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write,
 };
 
+// add_block_inner가 건너뛸 수 있는 검사들
+#[derive(Clone, Copy)]
+struct BlockChecks {
+    // input의 잠금 조건(서명). 미리 병렬로 검증한 블록이라면 false
+    locks: bool,
+    // timestamp가 로컬 시계보다 MAX_FUTURE_BLOCK_TIME 넘게 앞서지 않는지.
+    // 이미 받아들였던 블록을 디스크에서 다시 쌓을 때는 로컬 시계가 틀어졌더라도
+    // 체인을 읽을 수 있도록 false
+    future_time: bool,
+}
+
+impl BlockChecks {
+    const ALL: Self = Self {
+        locks: true,
+        future_time: true,
+    };
+    const STORED: Self = Self {
+        locks: true,
+        future_time: false,
+    };
+}
+
+/// mempool에 tx를 제출한 결과
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum MempoolAcceptance {
+    /// mempool에 추가되었다
+    Accepted,
+    /// 같은 utxo를 쓰던 tx들을 RBF로 교체하고 추가되었다. 제거된 tx(자손 포함)의 txid
+    Replaced(Vec<Hash>),
+    /// 교체할 수 없는 mempool tx와 같은 output을 쓰므로 거부되었다. 그 mempool tx의 txid.
+    /// 미확정 output을 이미 소비 중인 tx이거나, 확정된 utxo를 소비 중이지만 RBF 신호를 보내지 않은 tx다
+    Conflict(Hash),
+}
+
+// add_to_mempool이 mempool을 바꾸기 전에 내린 판단
+enum MempoolEntry {
+    // 받아들일 수 있다. RBF로 교체될, 같은 확정 utxo를 소비 중인 mempool tx들의 txid
+    Admissible(Vec<Hash>),
+    // 교체할 수 없는 mempool tx와 충돌한다. 그 tx의 txid
+    Conflict(Hash),
+}
+
+// export_utxo_snapshot이 쓰는 파일 형식
+#[derive(Serialize, Deserialize)]
+struct UtxoSnapshot {
+    height: u64,
+    block_hash: Hash,
+    burned: u64,
+    // output hash 순으로 정렬되어 있다
+    outputs: Vec<TransactionOutput>,
+    utxo_hash: Hash,
+}
+
+impl UtxoSnapshot {
+    // 같은 utxo set이라면 어느 노드에서 만들어도 같은 hash가 나오도록 정렬된 outputs로 계산한다
+    fn compute_hash(burned: u64, outputs: &[TransactionOutput]) -> Hash {
+        Hash::hash(&(burned, outputs))
+    }
+}
+
+/// 현재 난이도와 다음 난이도 조정까지의 정보
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DifficultyInfo {
+    /// 다음 블록이 만족해야 하는 target
+    pub current_target: U256,
+    /// MIN_TARGET 대비 몇 배 어려운지
+    pub difficulty: f64,
+    /// 다음 난이도 조정까지 남은 블록 수
+    pub blocks_until_adjustment: u64,
+    /// 현재 조정 구간의 블록 시간이 유지된다고 가정했을 때 다음 조정 후의 target.
+    /// 조정 지점에서만 주는 것이 아니라 구간 중간에도 지금까지의 간격으로 추정한다.
+    /// 구간에 블록이 두 개 이상 쌓여야 추정할 수 있으므로, 조정 직후에는 None이다
+    pub estimated_next_target: Option<U256>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Blockchain {
     // mark(true) 라면 해당 utxo가 현재 mempool의 다른 트랜잭션에서 사용 중인지
     utxos: HashMap<Hash, (bool, TransactionOutput)>,
+    // utxos의 key를 hash 순으로 들고 있다. utxos_page가 호출될 때마다 정렬하지 않도록
+    // utxos와 함께 갱신한다. 직렬화하지 않으므로 load 후 rebuild_indexes로 다시 만든다
+    #[serde(skip)]
+    utxo_order: BTreeSet<Hash>,
+    // 체인의 Unspendable output에 담겨 소각된 값의 합. utxos와 함께 다시 계산한다
+    #[serde(default)]
+    burned: u64,
     target: U256,
+    // 난이도 조정마다 (조정한 높이, 조정 후 target)를 덧붙인다. 지난 target을 헤더를 훑지 않고 볼 수 있다.
+    // 이 필드가 없던 파일은 rebuild_indexes에서 블록들로부터 다시 계산한다
+    #[serde(default)]
+    target_history: Vec<(u64, U256)>,
+    // 블록들의 header hash와 merkle root를 만든 고정 인코딩의 버전 (crate::encoding).
+    // 이 필드가 없던 파일은 load에서 블록들로부터 판단한다
+    #[serde(default)]
+    hash_encoding: Option<u8>,
     blocks: Vec<Block>,
     #[serde(default, skip_serializing)]
     mempool: Vec<(DateTime<Utc>, Transaction)>,
+    // block hash -> height. 직렬화하지 않으므로 load 후 rebuild_indexes로 다시 만든다
+    #[serde(skip)]
+    block_index: HashMap<Hash, usize>,
+    // txid -> 그 tx가 포함된 block height
+    #[serde(skip)]
+    transaction_index: HashMap<Hash, usize>,
+    // mempool이 바뀔 때마다(변경 작업 한 번당 정확히 1씩) 단조 증가한다.
+    // 템플릿이나 조회 결과 캐시의 무효화에 사용
+    #[serde(default, skip_serializing)]
+    mempool_generation: u64,
+    // 합의 파라미터. 체인의 상태가 아니므로 저장하지 않는다
+    #[serde(skip)]
+    params: ChainParams,
+}
+
+impl Default for Blockchain {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Blockchain {
     pub fn new() -> Self {
+        Self::empty(ChainParams::default())
+    }
+
+    // 기본값이 아닌 합의 파라미터를 쓰는 빈 체인
+    pub fn with_params(params: ChainParams) -> Result<Self> {
+        params.validate()?;
+        Ok(Self::empty(params))
+    }
+
+    // 이미 검증된 파라미터로 만드는 빈 체인
+    fn empty(params: ChainParams) -> Self {
         Blockchain {
             utxos: HashMap::new(),
-            target: crate::MIN_TARGET,
+            utxo_order: BTreeSet::new(),
+            burned: 0,
+            target: params.min_target,
+            target_history: vec![],
+            hash_encoding: Some(HASH_ENCODING_VERSION),
             blocks: vec![],
             mempool: vec![],
+            block_index: HashMap::new(),
+            transaction_index: HashMap::new(),
+            mempool_generation: 0,
+            params,
         }
     }
 
+    pub fn params(&self) -> &ChainParams {
+        &self.params
+    }
+
+    // 파일에서 읽은 체인은 기본 파라미터를 쓴다.
+    // 다른 파라미터를 쓰는 체인이라면 읽은 직후, 블록을 더 쌓기 전에 바꾼다
+    pub fn set_params(&mut self, params: ChainParams) -> Result<()> {
+        params.validate()?;
+        self.params = params;
+        Ok(())
+    }
+
     // utxos getter
     pub fn utxos(&self) -> &HashMap<Hash, (bool, TransactionOutput)> {
         &self.utxos
     }
+    // utxo를 hash 순으로 정렬해 offset부터 최대 limit개만 돌려준다.
+    // 순서가 고정되어 있으므로 offset을 늘려가며 호출하면 전체 utxo를 겹치지 않게 훑을 수 있다
+    pub fn utxos_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<(Hash, TransactionOutput)> {
+        self.utxo_order
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|hash| (*hash, self.utxos[hash].1.clone()))
+            .collect()
+    }
     // target getter
     pub fn target(&self) -> U256 {
         self.target
     }
+    // n번째(0부터) 난이도 조정의 (높이, 조정 후 target)
+    pub fn target_at_adjustment(&self, n: usize) -> Option<(u64, U256)> {
+        self.target_history.get(n).copied()
+    }
+    // 지금까지의 모든 난이도 조정
+    pub fn target_history(&self) -> &[(u64, U256)] {
+        &self.target_history
+    }
     // blocks getter
     pub fn blocks(&self) -> impl Iterator<Item = &Block> {
         self.blocks.iter()
     }
+    // tip부터 genesis 방향으로 blocks를 순회한다
+    pub fn blocks_rev(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter().rev()
+    }
     // mempool getter
     pub fn mempool(&self) -> &[(DateTime<Utc>, Transaction)] {
         &self.mempool
     }
+    // mempool generation getter
+    pub fn mempool_generation(&self) -> u64 {
+        self.mempool_generation
+    }
+
+    pub fn block_by_hash(&self, hash: &Hash) -> Option<&Block> {
+        self.block_index.get(hash).map(|height| &self.blocks[*height])
+    }
+
+    // tip에서 genesis 방향으로 처음 10개는 연속으로, 그 뒤로는 간격을 두 배씩 늘려가며 고른 block hash들.
+    // genesis는 항상 마지막에 들어간다. 상대는 이 중 자신도 가진 첫 hash로 갈라진 지점을 찾는다
+    pub fn block_locator(&self) -> Vec<Hash> {
+        let mut locator = vec![];
+        let Some(mut height) = self.blocks.len().checked_sub(1) else {
+            return locator;
+        };
+
+        let mut step = 1;
+        loop {
+            locator.push(self.blocks[height].hash());
+            if height == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            height = height.saturating_sub(step);
+        }
+
+        locator
+    }
+
+    // locator의 hash 중 이 체인에도 있는 첫 블록의 높이. 두 체인이 공유하는 마지막 블록이다.
+    // 하나도 없다면 genesis부터 다르다
+    pub fn find_fork_point(&self, locator: &[Hash]) -> Option<u64> {
+        locator
+            .iter()
+            .find_map(|hash| self.block_index.get(hash))
+            .map(|height| *height as u64)
+    }
+
+    // 체인에 확정된 tx를 txid로 찾는다
+    pub fn transaction_by_hash(&self, txid: &Hash) -> Option<&Transaction> {
+        self.transaction_index.get(txid).and_then(|height| {
+            self.blocks[*height]
+                .transactions
+                .iter()
+                .find(|transaction| transaction.hash() == *txid)
+        })
+    }
 
     pub fn block_height(&self) -> u64 {
         self.blocks.len() as u64
     }
 
+    // tip 블록의 높이. block_height는 블록 수이므로 하나 작다. 블록이 없으면 None
+    pub fn height(&self) -> Option<u64> {
+        self.block_height().checked_sub(1)
+    }
+
+    // tip 블록의 hash. block_by_hash나 FetchTip이 쓰는 블록 식별자다
+    pub fn tip_hash(&self) -> Option<Hash> {
+        self.blocks.last().map(Block::hash)
+    }
+
+    // tip 블록의 header 사본. 다음 블록은 이 header의 hash로 이어진다
+    pub fn tip_header(&self) -> Option<BlockHeader> {
+        self.blocks.last().map(|block| block.header.clone())
+    }
+
+    // 연속한 두 블록 사이의 시간 간격 (초). 난이도 조정이 어떻게 동작하는지 살펴볼 때 쓴다.
+    // timestamp가 역전된 경우 음수가 될 수 있다
+    pub fn block_intervals(&self) -> Vec<i64> {
+        self.blocks
+            .windows(2)
+            .map(|pair| {
+                (pair[1].header.timestamp - pair[0].header.timestamp)
+                    .num_seconds()
+            })
+            .collect()
+    }
+
+    // 최근 window개 간격의 평균 블록 시간 (초). 간격이 하나도 없으면 None
+    pub fn average_block_time(&self, window: usize) -> Option<f64> {
+        let intervals = self.block_intervals();
+        let recent = &intervals[intervals.len().saturating_sub(window)..];
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().sum::<i64>() as f64 / recent.len() as f64)
+    }
+
+    // 체인에 들어간 누적 작업량. 각 블록의 target을 만족하는 해시를 찾기 위해 기대되는 시도 횟수의 합
+    pub fn total_work(&self) -> U256 {
+        self.blocks
+            .iter()
+            .map(|block| Self::block_work(block.header.target))
+            .fold(U256::zero(), |total, work| total.saturating_add(work))
+    }
+
+    // 채굴자에게 보여줄 현재 난이도와 다음 조정까지 남은 블록 수.
+    // 조정 지점에서 새 target은 이미 current_target에 반영되므로,
+    // estimated_next_target은 구간이 진행되는 동안의 추정치로 준다
+    pub fn difficulty_info(&self) -> DifficultyInfo {
+        let interval = self.params.difficulty_update_interval;
+        let height = self.block_height();
+        let current_target = self.expected_target(height);
+
+        // 현재 조정 구간에 이미 쌓인 블록들
+        let window = &self.blocks[(height - height % interval) as usize..];
+        let estimated_next_target = match (window.first(), window.last()) {
+            (Some(first), Some(last)) if window.len() >= 2 => {
+                // 지금까지의 평균 간격으로 구간 전체(interval - 1개의 간격)가 걸릴 시간을 추정한다
+                let elapsed = (last.header.timestamp - first.header.timestamp)
+                    .num_seconds();
+                let timespan = elapsed.saturating_mul(interval as i64 - 1)
+                    / (window.len() as i64 - 1);
+                let timespan = chrono::Duration::try_seconds(timespan)
+                    .unwrap_or(chrono::Duration::MAX);
+                Some(self.params.adjust_target(current_target, timespan))
+            }
+            _ => None,
+        };
+
+        DifficultyInfo {
+            current_target,
+            difficulty: u256_to_f64(self.params.min_target)
+                / u256_to_f64(current_target),
+            blocks_until_adjustment: interval - height % interval,
+            estimated_next_target,
+        }
+    }
+
+    // 2^256 / (target + 1). U256으로 2^256을 표현할 수 없으므로
+    // bitcoin과 같이 (!target / (target + 1)) + 1 로 계산한다
+    fn block_work(target: U256) -> U256 {
+        (!target / target.saturating_add(U256::one())) + U256::one()
+    }
+
     pub fn calculate_block_reward(&self) -> u64 {
-        let block_height = self.block_height();
-        let halvings = block_height / crate::HALVING_INTERVAL;
+        Self::block_reward_at(self.block_height())
+    }
+
+    // 높이 height의 블록이 받을 수 있는 채굴 보상
+    pub fn block_reward_at(height: u64) -> u64 {
+        let halvings = height / crate::HALVING_INTERVAL;
 
         if halvings >= 64 {
             // After 64 halvings, the reward becomes 0
             0
         } else {
-            (crate::INITIAL_REWARD * 10u64.pow(8)) >> halvings
+            (crate::INITIAL_REWARD * crate::amount::SATOSHIS_PER_BTC) >> halvings
         }
     }
 
-    // 외부에서 전송 받은 tx를 mempool에 추가한다.
-    pub fn add_to_mempool(&mut self, transaction: Transaction) -> Result<()> {
-        let mut known_inputs = HashSet::new();
+    // 블록 height개가 채굴되기까지 발행 스케줄에 따라 발행된 보상의 총합
+    // 반감기 구간 안에서는 보상이 같으므로 블록마다가 아니라 구간마다 더한다
+    pub fn cumulative_block_reward(height: u64) -> u64 {
+        let interval = crate::HALVING_INTERVAL;
+        // 64번 반감된 뒤의 구간은 보상이 0이다
+        let eras = (height / interval).min(64);
+        let completed: u64 = (0..eras)
+            .map(|era| Self::block_reward_at(era * interval) * interval)
+            .sum();
+        completed + Self::block_reward_at(height) * (height % interval)
+    }
 
-        for input in &transaction.inputs {
-            // input이 유래한 output이 utxo에 존재해야만 한다.
-            if !self.utxos.contains_key(&input.prev_transaction_output_hash) {
-                return Err(BtcError::InvalidTransaction);
-            }
-            // utxo의 이중 사용은 불가하므로 이미 set에 존재한다면 바른 tx가 아니다.
-            if known_inputs.contains(&input.prev_transaction_output_hash) {
-                return Err(BtcError::InvalidTransaction);
+    // 현재 utxo 중 key로 잠긴 것들의 값의 합
+    pub fn balance_for(&self, key: &PublicKey) -> u64 {
+        self.utxos
+            .values()
+            .filter(|(_, output)| output.lock.pubkey() == Some(key))
+            .map(|(_, output)| output.value)
+            .sum()
+    }
+
+    // 블록 height개가 쌓였을 때 key로 잠긴 utxo 값의 합.
+    // 매번 처음부터 height까지의 블록을 다시 적용하므로 체인 길이에 비례하는 비용이 든다.
+    // 자주 조회해야 한다면 높이별 utxo 스냅샷을 캐시하는 편이 낫다
+    pub fn balance_for_at(&self, key: &PublicKey, height: u64) -> u64 {
+        self.utxos_at(height)
+            .0
+            .values()
+            .filter(|output| output.lock.pubkey() == Some(key))
+            .map(|output| output.value)
+            .sum()
+    }
+
+    // 블록 height개가 쌓였을 때의 utxo set과 그때까지 소각된 값의 합
+    fn utxos_at(&self, height: u64) -> (HashMap<Hash, TransactionOutput>, u64) {
+        let mut utxos = HashMap::new();
+        let mut burned: u64 = 0;
+        for block in self.blocks.iter().take(height as usize) {
+            for transaction in &block.transactions {
+                for input in &transaction.inputs {
+                    utxos.remove(&input.prev_transaction_output_hash);
+                }
+                for output in &transaction.outputs {
+                    if output.lock.is_unspendable() {
+                        burned = burned.saturating_add(output.value);
+                    } else {
+                        utxos.insert(output.hash(), output.clone());
+                    }
+                }
             }
+        }
+        (utxos, burned)
+    }
 
-            // utxo의 소비한 output hash를 inputs에 넣는다.
-            known_inputs.insert(input.prev_transaction_output_hash);
+    // 블록 height개가 쌓였을 때 마지막 블록의 hash. height가 0이면 Hash::zero()
+    fn block_hash_at(&self, height: u64) -> Option<Hash> {
+        match height {
+            0 => Some(Hash::zero()),
+            _ => self.blocks.get(height as usize - 1).map(Block::hash),
         }
+    }
 
-        // -----------------------------------
-        // RBF (Replace-By-Fee) 로직
-        // 원래라면 실제 비트코인에서는 수수료 비교해서 miner fee가 더 나오는 것을 선택함.
-        // 여기서는 단순하게 나중에 온 것을 우선시하고, 이전에 있던 건 mempool에서 삭제
+    // 블록 height개가 쌓였을 때의 utxo set을 snapshot으로 writer에 쓰고, utxo set의 hash를 돌려준다.
+    // 이 hash를 UtxoCheckpoint로 배포하면 새 노드가 블록을 처음부터 다시 적용하지 않고
+    // import_utxo_snapshot으로 utxo set을 받아들일 수 있다
+    pub fn export_utxo_snapshot<O: Write>(
+        &self,
+        height: u64,
+        writer: O,
+    ) -> IoResult<Hash> {
+        let block_hash = self.block_hash_at(height).ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::InvalidInput,
+                "Snapshot height is above the chain tip",
+            )
+        })?;
 
-        // 이 utxo가 이미 mempool의 다른 트랜잭션에서 사용 중이면
-        // 그 트랜잭션을 찾아서 제거하고
-        // 그 트랜잭션이 사용한 모든 utxo의 마킹을 해제
-        for input in &transaction.inputs {
-            // 이미 사용된 output이 utxo에 존재하는 경우, 이중 사용된 output임.
-            if let Some((true, _)) =
-                self.utxos.get(&input.prev_transaction_output_hash)
-            {
-                // 해당 utxo를 사용한, 먼저 mempool에 있던 tx를 찾아냄
-                let referencing_transaction = self
-                    .mempool
+        let (utxos, burned) = self.utxos_at(height);
+        let mut outputs: Vec<(Hash, TransactionOutput)> =
+            utxos.into_iter().collect();
+        outputs.sort_by_key(|(hash, _)| *hash);
+        let outputs: Vec<TransactionOutput> =
+            outputs.into_iter().map(|(_, output)| output).collect();
+        let utxo_hash = UtxoSnapshot::compute_hash(burned, &outputs);
+
+        let snapshot = UtxoSnapshot {
+            height,
+            block_hash,
+            burned,
+            outputs,
+            utxo_hash,
+        };
+        ciborium::ser::into_writer(&snapshot, writer).map_err(|_| {
+            IoError::new(
+                IoErrorKind::InvalidData,
+                "Failed to serialize utxo snapshot",
+            )
+        })?;
+        Ok(utxo_hash)
+    }
+
+    // export_utxo_snapshot으로 만든 snapshot을 읽어서, 블록들을 다시 적용하는 대신 utxo set으로 쓴다.
+    // snapshot은 params의 utxo_checkpoints 중 하나와 일치해야 하고, 이 체인의 같은 높이에
+    // 같은 블록이 있어야 한다. snapshot 이후의 블록들은 그 위에 적용한다.
+    // 블록 자체는 검증하지 않으므로 checkpoint를 믿을 수 있을 때만 쓴다
+    pub fn import_utxo_snapshot<I: Read>(&mut self, reader: I) -> Result<()> {
+        let snapshot: UtxoSnapshot = ciborium::de::from_reader(reader)
+            .map_err(|_| BtcError::InvalidUtxoSnapshot)?;
+
+        // 기록된 hash는 파일이 손상되지 않았는지만 알려준다.
+        // 믿을 수 있는지는 checkpoint와 비교해서 정한다
+        let utxo_hash =
+            UtxoSnapshot::compute_hash(snapshot.burned, &snapshot.outputs);
+        if utxo_hash != snapshot.utxo_hash {
+            return Err(BtcError::InvalidUtxoSnapshot);
+        }
+        let checkpoint = UtxoCheckpoint {
+            height: snapshot.height,
+            block_hash: snapshot.block_hash,
+            utxo_hash,
+        };
+        if !self.params.utxo_checkpoints.contains(&checkpoint) {
+            return Err(BtcError::UtxoSnapshotNotCheckpointed);
+        }
+        if self.block_hash_at(snapshot.height) != Some(snapshot.block_hash) {
+            return Err(BtcError::InvalidUtxoSnapshot);
+        }
+
+        self.utxos = snapshot
+            .outputs
+            .into_iter()
+            .map(|output| (output.hash(), (false, output)))
+            .collect();
+        self.utxo_order = self.utxos.keys().copied().collect();
+        self.burned = snapshot.burned;
+
+        let blocks = std::mem::take(&mut self.blocks);
+        for block in &blocks[snapshot.height as usize..] {
+            self.apply_block_to_utxos(block);
+        }
+        self.blocks = blocks;
+        self.mark_mempool_spends();
+
+        Ok(())
+    }
+
+    // 현재 utxo 값의 총합. 즉 유통 중인 전체 화폐량. 소각된 값은 포함하지 않는다
+    pub fn total_supply(&self) -> u64 {
+        self.utxos.values().map(|(_, output)| output.value).sum()
+    }
+
+    // 지금까지 소각된 값의 합
+    pub fn burned_supply(&self) -> u64 {
+        self.burned
+    }
+
+    // 블록을 반영했을 때까지 발행된 총량 (유통량 + 소각된 값). 값이 맞지 않으면 None
+    fn supply_after(&self, block: &Block) -> Option<u64> {
+        let mut block_outputs: HashMap<Hash, u64> = HashMap::new();
+        let mut spent: u64 = 0;
+        let mut created: u64 = 0;
+
+        for transaction in &block.transactions {
+            for input in &transaction.inputs {
+                let value = self
+                    .utxos
+                    .get(&input.prev_transaction_output_hash)
+                    .map(|(_, output)| output.value)
+                    .or_else(|| {
+                        block_outputs
+                            .get(&input.prev_transaction_output_hash)
+                            .copied()
+                    })?;
+                spent = spent.checked_add(value)?;
+            }
+            for output in &transaction.outputs {
+                created = created.checked_add(output.value)?;
+                block_outputs.insert(output.hash(), output.value);
+            }
+        }
+
+        self.total_supply()
+            .checked_add(self.burned)?
+            .checked_add(created)?
+            .checked_sub(spent)
+    }
+
+    // mempool에 있는 아직 확정되지 않은 tx들이 만든 output.
+    // 확정된 utxo 위에 얹어서 보는 임시 overlay로, 미확정 부모 tx의 output을 소비하는 자식 tx(CPFP)를 허용하기 위함
+    fn mempool_outputs(&self) -> HashMap<Hash, TransactionOutput> {
+        self.mempool
+            .iter()
+            .flat_map(|(_, transaction)| transaction.outputs.iter())
+            .map(|output| (output.hash(), output.clone()))
+            .collect()
+    }
+
+    // 해당 tx와, 그 tx의 output을 (재귀적으로) 소비하는 자손 tx들을 mempool에서 제거하고
+    // 제거된 tx들이 사용하던 utxo의 마킹을 해제한다. 제거된 tx들을 반환
+    fn evict_from_mempool(&mut self, txid: Hash) -> Vec<Transaction> {
+        let mut to_evict = vec![txid];
+        let mut evicted = vec![];
+
+        while let Some(txid) = to_evict.pop() {
+            let Some(idx) = self
+                .mempool
+                .iter()
+                .position(|(_, transaction)| transaction.hash() == txid)
+            else {
+                continue;
+            };
+            let (_, transaction) = self.mempool.remove(idx);
+
+            for input in &transaction.inputs {
+                self.utxos
+                    .entry(input.prev_transaction_output_hash)
+                    .and_modify(|(marked, _)| {
+                        *marked = false;
+                    });
+            }
+
+            // 제거된 tx의 output을 소비하던 자식 tx들도 더 이상 유효하지 않다
+            let outputs: HashSet<Hash> =
+                transaction.outputs.iter().map(|output| output.hash()).collect();
+            to_evict.extend(
+                self.mempool
                     .iter()
-                    .enumerate()
-                    .find(|(_, (_, transaction))| {
-                        transaction.outputs.iter().any(|output| {
-                            output.hash() == input.prev_transaction_output_hash
+                    .filter(|(_, child)| {
+                        child.inputs.iter().any(|input| {
+                            outputs.contains(&input.prev_transaction_output_hash)
                         })
-                    });
+                    })
+                    .map(|(_, child)| child.hash()),
+            );
+            evicted.push(transaction);
+        }
 
-                // 지워야 할 기존 tx가 사용한 input들을 모두 사용 가능한 형태(mark=false) 로 되돌린다.
-                if let Some((idx, (_, referencing_transaction))) =
-                    referencing_transaction
-                {
-                    for input in &referencing_transaction.inputs {
-                        self.utxos
-                            .entry(input.prev_transaction_output_hash)
-                            .and_modify(|(marked, _)| {
-                                *marked = false;
-                            });
-                    }
+        evicted
+    }
 
-                    // remove the transaction from the mempool
-                    self.mempool.remove(idx);
-                } else {
-                    // 분명 이중 사용된 utxo이었을 텐데, 그걸 사용한 기존 tx를 mempool에서 발견하지 못했다?
-                    // 이상한 케이스가 맞지만 해당 utxo의 mark를 false (아직 사용되지 않음) 으로 바꾼다
-                    self.utxos
-                        .entry(input.prev_transaction_output_hash)
-                        .and_modify(|(marked, _)| {
-                            *marked = false;
-                        });
+    // add_to_mempool과 같은 검사(check_mempool_entry)를 하되 mempool에는 넣지 않는다.
+    // RBF로 교체할 수 있는 충돌은 통과시키고, add_to_mempool이 Conflict로 거부할 충돌은
+    // TxConflictNonReplaceable로, 그 외에는 어떤 검사에서 걸렸는지를 에러로 알려준다
+    pub fn check_transaction(&self, transaction: &Transaction) -> Result<()> {
+        match self.check_mempool_entry(transaction)? {
+            MempoolEntry::Admissible(_) => Ok(()),
+            MempoolEntry::Conflict(txid) => {
+                Err(BtcError::TxConflictNonReplaceable(txid))
+            }
+        }
+    }
+
+    // transaction을 mempool에 넣으면 미확정 사슬이 MAX_ANCESTORS/MAX_DESCENDANTS를 넘는지 확인한다.
+    // transaction의 조상 수와, 조상마다 transaction이 더해진 뒤의 자손 수를 센다
+    fn check_chain_limits(&self, transaction: &Transaction) -> Result<()> {
+        // output hash -> 그 output을 만든 mempool tx의 index
+        let creators: HashMap<Hash, usize> = self
+            .mempool
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, (_, transaction))| {
+                transaction.outputs.iter().map(move |output| (output.hash(), idx))
+            })
+            .collect();
+        let parents_of = |transaction: &Transaction| -> Vec<usize> {
+            transaction
+                .inputs
+                .iter()
+                .filter_map(|input| {
+                    creators.get(&input.prev_transaction_output_hash).copied()
+                })
+                .collect()
+        };
+
+        let mut children: Vec<Vec<usize>> = vec![vec![]; self.mempool.len()];
+        for (idx, (_, mempool_tx)) in self.mempool.iter().enumerate() {
+            for parent in parents_of(mempool_tx) {
+                children[parent].push(idx);
+            }
+        }
+
+        let mut ancestors = HashSet::new();
+        let mut to_visit = parents_of(transaction);
+        while let Some(idx) = to_visit.pop() {
+            if ancestors.insert(idx) {
+                to_visit.extend(parents_of(&self.mempool[idx].1));
+            }
+        }
+        if ancestors.len() + 1 > crate::MAX_ANCESTORS {
+            return Err(BtcError::MempoolChainTooLong);
+        }
+
+        for &ancestor in &ancestors {
+            // ancestor 자신과 이미 있는 자손들, 그리고 새로 들어올 transaction
+            let mut descendants = HashSet::new();
+            let mut to_visit = vec![ancestor];
+            while let Some(idx) = to_visit.pop() {
+                if descendants.insert(idx) {
+                    to_visit.extend(&children[idx]);
                 }
             }
+            if descendants.len() + 1 > crate::MAX_DESCENDANTS {
+                return Err(BtcError::MempoolChainTooLong);
+            }
         }
 
-        // -----------------------------------
-        // input이 활용한 이전 block의 output value를 모두 모은다
+        Ok(())
+    }
+
+    // mempool tx가 소비 중인 output -> 그 output을 소비하는 mempool tx.
+    // input마다 mempool 전체를 훑지 않도록 한 번에 만들어 둔다
+    fn mempool_spenders(&self) -> HashMap<Hash, &Transaction> {
+        self.mempool
+            .iter()
+            .flat_map(|(_, transaction)| {
+                transaction.inputs.iter().map(move |input| {
+                    (input.prev_transaction_output_hash, transaction)
+                })
+            })
+            .collect()
+    }
+
+    // mempool에 받아들이고 전파할지를 정하는 정책(policy) 검사. fee는 tx가 내는 수수료.
+    // 합의 규칙(consensus)이 아니므로 블록 검증에는 쓰지 않는다.
+    // 다른 노드가 채굴한 블록에 정책을 어긴 tx가 있더라도 블록은 유효하다
+    pub fn check_policy(transaction: &Transaction, fee: u64) -> Result<()> {
+        // spam 방지를 위한 최소 수수료율
+        let min_fee = (transaction.vsize() as u64)
+            .saturating_mul(crate::MIN_RELAY_FEE_RATE);
+        if fee < min_fee {
+            return Err(BtcError::InsufficientFee);
+        }
+
+        // 쓰는 비용보다 가치가 작은 output은 utxo set만 불린다
+        if transaction
+            .outputs
+            .iter()
+            .any(|output| output.value <= crate::DUST_LIMIT)
+        {
+            return Err(BtcError::DustOutput);
+        }
+
+        Ok(())
+    }
+
+    // add_to_mempool과 check_transaction이 공유하는 검사. mempool을 바꾸지 않는다.
+    // 실패하면 어떤 검사에서 걸렸는지를 구체적인 에러로 알려준다.
+    // 수수료와 정책 검사까지 통과해야 Admissible이므로,
+    // 거부될 tx가 기존 tx를 RBF로 공짜로 밀어내는 일은 없다
+    fn check_mempool_entry(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<MempoolEntry> {
+        // coinbase는 블록 안에서만 유효하다. 템플릿에 두 번째 coinbase가 담기지 않도록 한다
+        if transaction.is_coinbase() {
+            return Err(BtcError::InvalidTransactionInput);
+        }
+        if !transaction.has_unique_output_ids() {
+            return Err(BtcError::InvalidTransactionOutput);
+        }
+
+        // 자기 자신의 output은 소비할 수 없다 (cycle 방지)
+        if transaction.spends_own_output() {
+            return Err(BtcError::InvalidTransactionInput);
+        }
+
+        let mempool_outputs = self.mempool_outputs();
+        let mempool_spenders = self.mempool_spenders();
+
+        let mut known_inputs = HashSet::new();
+        // RBF로 교체할, 같은 확정 utxo를 소비 중인 mempool tx들
+        let mut conflicts = vec![];
+        let mut all_inputs: u64 = 0;
+        for input in &transaction.inputs {
+            // input이 유래한 output이 utxo나 mempool overlay에 존재해야만 한다.
+            let confirmed =
+                self.utxos.get(&input.prev_transaction_output_hash);
+            let prev_output = confirmed
+                .map(|(_, output)| output)
+                .or_else(|| {
+                    mempool_outputs.get(&input.prev_transaction_output_hash)
+                })
+                .ok_or(BtcError::InvalidTransactionInput)?;
+
+            // 서명이 틀린 tx로 mempool이 채워지지 않도록 채굴 때까지 미루지 않고 여기서 검증한다.
+            // 다음 블록에 담긴다고 보고 잠금 조건을 검사한다
+            prev_output.lock.verify(input, self.block_height())?;
+
+            // utxo의 이중 사용은 불가하므로 이미 set에 존재한다면 바른 tx가 아니다.
+            if !known_inputs.insert(input.prev_transaction_output_hash) {
+                return Err(BtcError::DoubleSpend);
+            }
+
+            // -----------------------------------
+            // RBF (Replace-By-Fee) 로직
+            // 이 output을 이미 소비 중인 mempool tx가 있다면 교체할 수 있는지 본다.
+            // 미확정 output은 RBF 대상이 아니고, 확정된 utxo라도 기존 tx가
+            // RBF 신호를 보내지 않았다면(opt-out) 교체하지 않는다
+            if let Some(spender) =
+                mempool_spenders.get(&input.prev_transaction_output_hash)
+            {
+                if confirmed.is_none() || !spender.signals_rbf() {
+                    return Ok(MempoolEntry::Conflict(spender.hash()));
+                }
+                conflicts.push(spender.hash());
+            }
+
+            all_inputs = all_inputs
+                .checked_add(prev_output.value)
+                .ok_or(BtcError::InvalidTransactionInput)?;
+        }
+
+        // 교체되어 사라질 tx(와 그 자손)의 output은 소비할 수 없다
+        let mut replaced_fee: u64 = 0;
+        if !conflicts.is_empty() {
+            let replaced = self.mempool_descendants(&conflicts);
+            let replaced_outputs: HashSet<Hash> = self
+                .mempool
+                .iter()
+                .filter(|(_, transaction)| {
+                    replaced.contains(&transaction.hash())
+                })
+                .flat_map(|(_, transaction)| transaction.outputs.iter())
+                .map(|output| output.hash())
+                .collect();
+            if transaction.inputs.iter().any(|input| {
+                replaced_outputs.contains(&input.prev_transaction_output_hash)
+            }) {
+                return Err(BtcError::InvalidTransactionInput);
+            }
+
+            replaced_fee = self
+                .mempool
+                .iter()
+                .filter(|(_, transaction)| {
+                    replaced.contains(&transaction.hash())
+                })
+                .map(|(_, transaction)| {
+                    self.mempool_fee(transaction, &mempool_outputs)
+                })
+                .fold(0u64, u64::saturating_add);
+        }
+
+        // 결과로 생성된 이번 블록의 output value를 더한다.
+        // 수수료를 생각하면 input이 항상 output보다 커야 한다
+        let all_outputs = transaction
+            .outputs
+            .iter()
+            .try_fold(0u64, |sum, output| sum.checked_add(output.value))
+            .ok_or(BtcError::InvalidTransactionOutput)?;
+        let fee = all_inputs
+            .checked_sub(all_outputs)
+            .ok_or(BtcError::InvalidTransaction)?;
+        Self::check_policy(transaction, fee)?;
+        if !conflicts.is_empty() {
+            Self::check_replacement(transaction, fee, replaced_fee)?;
+        }
+
+        // 미확정 사슬 길이 제한 (policy)
+        self.check_chain_limits(transaction)?;
+
+        Ok(MempoolEntry::Admissible(conflicts))
+    }
+
+    // mempool tx가 내는 수수료. 소비하는 output은 utxo나 mempool overlay에 있다
+    fn mempool_fee(
+        &self,
+        transaction: &Transaction,
+        mempool_outputs: &HashMap<Hash, TransactionOutput>,
+    ) -> u64 {
         let all_inputs = transaction
             .inputs
             .iter()
-            .map(|input| {
+            .filter_map(|input| {
                 self.utxos
                     .get(&input.prev_transaction_output_hash)
-                    .expect("BUG: impossible")
-                    .1
-                    .value
+                    .map(|(_, output)| output)
+                    .or_else(|| {
+                        mempool_outputs.get(&input.prev_transaction_output_hash)
+                    })
             })
+            .map(|output| output.value)
             .sum::<u64>();
+        let all_outputs = transaction
+            .outputs
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        all_inputs.saturating_sub(all_outputs)
+    }
 
-        // 결과로 생성된 이번 블록의 output value를 더한다.
-        let all_outputs =
-            transaction.outputs.iter().map(|output| output.value).sum::<u64>();
+    // roots와, 그 output을 (재귀적으로) 소비하는 mempool tx들의 txid
+    fn mempool_descendants(&self, roots: &[Hash]) -> HashSet<Hash> {
+        let spenders = self.mempool_spenders();
+        let transactions: HashMap<Hash, &Transaction> = self
+            .mempool
+            .iter()
+            .map(|(_, transaction)| (transaction.hash(), transaction))
+            .collect();
 
-        // 수수료를 생각하면 input이 항상 output보다 커야 한다
-        if all_inputs < all_outputs {
-            return Err(BtcError::InvalidTransaction);
+        let mut descendants = HashSet::new();
+        let mut to_visit = roots.to_vec();
+        while let Some(txid) = to_visit.pop() {
+            if !descendants.insert(txid) {
+                continue;
+            }
+            let Some(transaction) = transactions.get(&txid) else {
+                continue;
+            };
+            to_visit.extend(transaction.outputs.iter().filter_map(|output| {
+                spenders.get(&output.hash()).map(|child| child.hash())
+            }));
+        }
+        descendants
+    }
+
+    // RBF 교체 정책. fee는 교체하는 tx의 수수료, replaced_fee는 교체되는 tx들(자손 포함)의 수수료 합.
+    // 교체되는 tx들보다 수수료를 더 내는 것만으로는 부족하고, 늘어난 수수료로 교체하는 tx 자신이
+    // 전파되는 비용(vsize × MIN_RELAY_FEE_RATE)까지 내야 한다.
+    // 그렇지 않으면 수수료를 1 satoshi씩 올려가며 같은 tx를 거의 공짜로 계속 전파시킬 수 있다
+    pub fn check_replacement(
+        transaction: &Transaction,
+        fee: u64,
+        replaced_fee: u64,
+    ) -> Result<()> {
+        let relay_fee = (transaction.vsize() as u64)
+            .saturating_mul(crate::MIN_RELAY_FEE_RATE);
+        if fee <= replaced_fee.saturating_add(relay_fee) {
+            return Err(BtcError::InsufficientReplacementFee);
+        }
+        Ok(())
+    }
+
+    // 외부에서 전송 받은 tx를 mempool에 추가한다.
+    // 추가되었는지, RBF로 기존 tx를 교체했는지, 교체할 수 없는 충돌로 거부되었는지 알려준다.
+    // 충돌은 소비하는 output이 확정되었든 아니든 항상 Conflict로 알리고,
+    // 그 외의 이유로 유효하지 않은 tx라면 에러.
+    // 모든 검사를 통과한 뒤에만 mempool을 바꾸므로, 에러나 Conflict라면 mempool은 그대로다
+    pub fn add_to_mempool(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<MempoolAcceptance> {
+        let conflicts = match self.check_mempool_entry(&transaction)? {
+            MempoolEntry::Admissible(conflicts) => conflicts,
+            MempoolEntry::Conflict(txid) => {
+                return Ok(MempoolAcceptance::Conflict(txid));
+            }
+        };
+
+        // 교체되는 tx(와 그 자손들)를 제거하고, 그 tx들이 사용한 모든 utxo의 마킹을 해제
+        let mut replaced = vec![];
+        for txid in conflicts {
+            replaced.extend(
+                self.evict_from_mempool(txid)
+                    .iter()
+                    .map(|evicted| evicted.hash()),
+            );
         }
 
         // -----------------------------------
+        // 확정된 utxo 중 이 tx가 소비하는 것들은 사용 중(mark=true)으로 표시한다
+        for input in &transaction.inputs {
+            self.utxos
+                .entry(input.prev_transaction_output_hash)
+                .and_modify(|(marked, _)| {
+                    *marked = true;
+                });
+        }
+
         // mempool에 tx를 추가한다
         self.mempool.push((Utc::now(), transaction));
+        self.mempool_generation += 1;
 
         // miner fee를 maximize하기 위해서 정렬한다
+        let mempool_outputs = self.mempool_outputs();
         self.mempool.sort_by_key(|(_, transaction)| {
             let all_inputs = transaction
                 .inputs
@@ -168,8 +954,12 @@ impl Blockchain {
                 .map(|input| {
                     self.utxos
                         .get(&input.prev_transaction_output_hash)
+                        .map(|(_, output)| output)
+                        .or_else(|| {
+                            mempool_outputs
+                                .get(&input.prev_transaction_output_hash)
+                        })
                         .expect("BUG: impossible")
-                        .1
                         .value
                 })
                 .sum::<u64>();
@@ -180,44 +970,398 @@ impl Blockchain {
                 .map(|output| output.value)
                 .sum::<u64>();
 
-            let miner_fee = all_inputs - all_outputs;
-            miner_fee
+            all_inputs - all_outputs
         });
 
-        Ok(())
+        if replaced.is_empty() {
+            Ok(MempoolAcceptance::Accepted)
+        } else {
+            Ok(MempoolAcceptance::Replaced(replaced))
+        }
     }
 
-    pub fn cleanup_mempool(&mut self) {
-        let now = Utc::now();
-        let mut utxo_hashes_to_unmark: Vec<Hash> = vec![];
+    // 채굴할 블록 템플릿을 만든다. coinbase tx는 pubkey에게 블록 보상과 수수료를 지급한다
+    pub fn build_template(&self, pubkey: PublicKey) -> Result<Block> {
+        self.build_template_with_payouts(vec![(pubkey, 1)])
+    }
 
-        // 시간 지났으면 지워야 할 tx가 소비했던 input utxo들을 저장해뒀다가 mark=false로 바꾼다
-        self.mempool.retain(|(timestamp, transaction)| {
-            if now - *timestamp
-                > chrono::Duration::seconds(
-                    crate::MAX_MEMPOOL_TRANSACTION_AGE as i64,
-                )
-            {
-                utxo_hashes_to_unmark.extend(
-                    transaction
-                        .inputs
-                        .iter()
-                        .map(|input| input.prev_transaction_output_hash),
-                );
-                false
-            } else {
-                true
+    // 블록 보상과 수수료를 (pubkey, 지분) 목록의 지분 비율대로 나눠 지급하는 템플릿.
+    // 나누고 남은 자투리는 첫 번째 pubkey에게 주므로 coinbase output의 합은
+    // 항상 보상 + 수수료와 같다. 목록이 비었거나 지분이 0인 항목이 있으면 에러
+    pub fn build_template_with_payouts(
+        &self,
+        payouts: Vec<(PublicKey, u64)>,
+    ) -> Result<Block> {
+        let height = self.block_height();
+        let reward = self.calculate_block_reward();
+
+        // 수수료를 알기 전이므로 값이 가장 큰 coinbase로 weight를 잡아둔다.
+        // 값이 클수록 직렬화된 크기도 크므로 실제 coinbase는 이보다 가볍다
+        let placeholder =
+            Transaction::coinbase_with_payouts(height, u64::MAX, 0, &payouts)?;
+
+        let max_weight = crate::MAX_BLOCK_WEIGHT - placeholder.weight();
+        let mut merkle = MerkleAccumulator::new();
+        merkle.push(&placeholder);
+        let mut transactions = vec![placeholder];
+        for transaction in self.select_mempool_transactions(max_weight) {
+            merkle.push(&transaction);
+            transactions.push(transaction);
+        }
+        // BlockHeader::validate와 같이 이전 블록의 hash로 잇는다
+        let prev_block_hash = self.tip_hash().unwrap_or(Hash::zero());
+        let mut block = Block::new(
+            BlockHeader::new(
+                Utc::now(),
+                0,
+                prev_block_hash,
+                merkle.root().expect("BUG: template without a coinbase"),
+                self.target,
+            ),
+            transactions,
+        );
+
+        // update coinbase tx with reward
+        let miner_fees = block.calculate_miner_fees(&self.utxos)?;
+        block.transactions[0] = Transaction::coinbase_with_payouts(
+            height, reward, miner_fees, &payouts,
+        )?;
+
+        // coinbase만 바뀌었으므로 전체를 다시 계산하지 않고 첫 번째 leaf만 바꾼다
+        merkle.replace_first(&block.transactions[0]);
+        block.header.merkle_root =
+            merkle.root().expect("BUG: template without a coinbase");
+
+        Ok(block)
+    }
+
+    // 수수료율이 가장 높은 mempool tx를 최대 limit개, 템플릿에 담길 순서대로 돌려준다.
+    // package 단위로 고르므로 수수료율이 낮은 부모 tx가 자식 tx보다 앞에 올 수 있고,
+    // 블록 하나(MAX_BLOCK_WEIGHT)에 담을 수 있는 만큼만 돌려준다
+    pub fn top_mempool(&self, limit: usize) -> Vec<Transaction> {
+        let mut transactions =
+            self.select_mempool_transactions(crate::MAX_BLOCK_WEIGHT);
+        transactions.truncate(limit);
+        transactions
+    }
+
+    // 템플릿에 담을 mempool tx를 고른다.
+    // 각 tx를 아직 선택되지 않은 조상들과 묶은 package 단위의 fee rate로 비교하므로,
+    // 수수료가 낮은 부모 tx도 수수료가 높은 자식 tx가 있으면 함께 담긴다 (CPFP).
+    // 부모 tx는 항상 자식 tx보다 앞에 위치하고, 조상 없이 자식만 담기는 일은 없다
+    fn select_mempool_transactions(&self, max_weight: usize) -> Vec<Transaction> {
+        let mempool_outputs = self.mempool_outputs();
+
+        // output hash -> 그 output을 만든 mempool tx의 index
+        let creators: HashMap<Hash, usize> = self
+            .mempool
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, (_, transaction))| {
+                transaction.outputs.iter().map(move |output| (output.hash(), idx))
+            })
+            .collect();
+
+        // 각 tx가 소비하는 output을 만든 mempool 내 부모 tx들
+        let parents: Vec<HashSet<usize>> = self
+            .mempool
+            .iter()
+            .map(|(_, transaction)| {
+                transaction
+                    .inputs
+                    .iter()
+                    .filter_map(|input| {
+                        creators.get(&input.prev_transaction_output_hash).copied()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // 각 tx의 조상 전체(자신 포함). 한 번만 계산한다
+        let ancestors: Vec<HashSet<usize>> = (0..self.mempool.len())
+            .map(|idx| {
+                let mut ancestors = HashSet::from([idx]);
+                let mut stack = vec![idx];
+                while let Some(current) = stack.pop() {
+                    for parent in &parents[current] {
+                        if ancestors.insert(*parent) {
+                            stack.push(*parent);
+                        }
+                    }
+                }
+                ancestors
+            })
+            .collect();
+
+        // 각 tx의 자손들. 조상이 담기면 자손들의 package에서 그 조상을 뺀다
+        let mut descendants: Vec<Vec<usize>> = vec![vec![]; self.mempool.len()];
+        for (idx, ancestors) in ancestors.iter().enumerate() {
+            for ancestor in ancestors {
+                if *ancestor != idx {
+                    descendants[*ancestor].push(idx);
+                }
             }
-        });
+        }
+
+        let fees: Vec<u64> = self
+            .mempool
+            .iter()
+            .map(|(_, transaction)| {
+                self.mempool_fee(transaction, &mempool_outputs)
+            })
+            .collect();
+
+        let weights: Vec<usize> = self
+            .mempool
+            .iter()
+            .map(|(_, transaction)| transaction.weight())
+            .collect();
+
+        // tx마다 (자신 + 선택되지 않은 조상) package의 (수수료, weight).
+        // 매번 다시 구하지 않고, tx가 담길 때마다 그 자손들의 값만 갱신한다
+        let mut packages: Vec<(u64, usize)> = ancestors
+            .iter()
+            .map(|ancestors| {
+                let fee = ancestors.iter().map(|i| fees[*i]).sum();
+                let weight = ancestors.iter().map(|i| weights[*i]).sum();
+                (fee, weight)
+            })
+            .collect();
+
+        let mut selected: Vec<usize> = vec![];
+        let mut selected_set: HashSet<usize> = HashSet::new();
+        // 한도를 넘어 담지 못한 tx
+        let mut skipped: HashSet<usize> = HashSet::new();
+        let mut block_weight = 0;
+
+        loop {
+            let best = (0..self.mempool.len())
+                .filter(|idx| {
+                    !selected_set.contains(idx) && !skipped.contains(idx)
+                })
+                // fee / weight 가 가장 큰 package
+                .max_by(|a, b| {
+                    let (fee_a, weight_a) = packages[*a];
+                    let (fee_b, weight_b) = packages[*b];
+                    (fee_a as u128 * weight_b as u128)
+                        .cmp(&(fee_b as u128 * weight_a as u128))
+                });
+
+            let Some(idx) = best else {
+                break;
+            };
+
+            let weight = packages[idx].1;
+            if block_weight + weight > max_weight {
+                skipped.insert(idx);
+                continue;
+            }
+            block_weight += weight;
+
+            // 자손은 조상보다 조상 수가 많으므로 조상 수 순으로 담으면 조상이 항상 먼저 온다
+            let mut package: Vec<usize> = ancestors[idx]
+                .iter()
+                .filter(|i| !selected_set.contains(i))
+                .copied()
+                .collect();
+            package.sort_by_key(|i| ancestors[*i].len());
+            for i in package {
+                for descendant in &descendants[i] {
+                    packages[*descendant].0 -= fees[i];
+                    packages[*descendant].1 -= weights[i];
+                }
+                selected_set.insert(i);
+                selected.push(i);
+            }
+        }
+
+        selected
+            .into_iter()
+            .map(|idx| self.mempool[idx].1.clone())
+            .collect()
+    }
+
+    // mempool에 max_age보다 오래 머문 tx를 버리고, 버린 tx들을 반환한다.
+    // 버린 tx의 output을 소비하던 자손 tx도 더 이상 유효하지 않으므로 함께 버린다.
+    // 버린 tx가 소비했던 utxo는 mark=false로 되돌린다
+    pub fn cleanup_mempool(
+        &mut self,
+        max_age: chrono::Duration,
+    ) -> Vec<Transaction> {
+        let now = Utc::now();
+        let aged: Vec<Hash> = self
+            .mempool
+            .iter()
+            .filter(|(timestamp, _)| now - *timestamp > max_age)
+            .map(|(_, transaction)| transaction.hash())
+            .collect();
 
-        for hash in utxo_hashes_to_unmark {
-            self.utxos.entry(hash).and_modify(|(marked, _)| {
-                *marked = false;
-            });
+        // 앞서 다른 tx의 자손으로 이미 버려진 tx는 evict_from_mempool이 건너뛴다
+        let mut evicted = vec![];
+        for txid in aged {
+            evicted.extend(self.evict_from_mempool(txid));
         }
+
+        if !evicted.is_empty() {
+            self.mempool_generation += 1;
+        }
+
+        evicted
     }
 
     pub fn add_block(&mut self, block: Block) -> Result<()> {
+        self.add_block_inner(block, BlockChecks::ALL)
+    }
+
+    // 동기화 중 받은 연속된 블록들을 순서대로 체인에 추가한다.
+    // utxo 반영은 순서대로 해야 하지만, 서명 검증은 소비하는 output의 잠금 조건만 알면 되므로
+    // 모든 블록의 input을 먼저 병렬로 검증해 두고 추가할 때는 다시 검증하지 않는다.
+    // 미리 검증하지 못한 블록은 add_block과 똑같이 검증하므로, 에러는 add_block을
+    // 하나씩 호출했을 때와 같다. 에러가 나면 그 앞의 블록들까지만 추가된다
+    pub fn add_blocks(&mut self, blocks: Vec<Block>) -> Result<()> {
+        let verified = self.preverify_locks(&blocks);
+        for (block, verified) in blocks.into_iter().zip(verified) {
+            let checks = BlockChecks {
+                locks: !verified,
+                ..BlockChecks::ALL
+            };
+            self.add_block_inner(block, checks)?;
+        }
+        Ok(())
+    }
+
+    // 각 블록의 모든 input이 잠금 조건을 만족하는지 병렬로 확인한다.
+    // 앞선 블록(과 같은 블록의 앞선 tx)이 만든 output도 참조할 수 있으므로 함께 찾아본다.
+    // output은 내용으로 hash되므로 같은 hash라면 언제 찾든 잠금 조건도 같다.
+    // 아직 반영 전인 블록의 output을 믿는 것은 그 블록이 추가될 때만 의미가 있는데,
+    // add_blocks는 앞선 블록이 거부되면 뒤의 블록을 추가하지 않으므로 안전하다
+    fn preverify_locks(&self, blocks: &[Block]) -> Vec<bool> {
+        let start_height = self.block_height();
+        let mut batch_outputs: HashMap<Hash, &TransactionOutput> = HashMap::new();
+        let mut found = vec![true; blocks.len()];
+        // (블록 index, 소비하는 output, input)
+        let mut jobs = vec![];
+
+        for (index, block) in blocks.iter().enumerate() {
+            for transaction in &block.transactions {
+                for input in &transaction.inputs {
+                    let hash = &input.prev_transaction_output_hash;
+                    let prev_output = self
+                        .utxos
+                        .get(hash)
+                        .map(|(_, output)| output)
+                        .or_else(|| batch_outputs.get(hash).copied());
+                    match prev_output {
+                        Some(prev_output) => jobs.push((index, prev_output, input)),
+                        // 어차피 거부될 블록이다. 순서대로 검증할 때 정확한 에러가 나온다
+                        None => found[index] = false,
+                    }
+                }
+                for output in &transaction.outputs {
+                    batch_outputs.insert(output.hash(), output);
+                }
+            }
+        }
+
+        let failed: HashSet<usize> = jobs
+            .par_iter()
+            .filter(|(index, prev_output, input)| {
+                prev_output
+                    .lock
+                    .verify(input, start_height + *index as u64)
+                    .is_err()
+            })
+            .map(|(index, _, _)| *index)
+            .collect();
+
+        found
+            .into_iter()
+            .enumerate()
+            .map(|(index, found)| found && !failed.contains(&index))
+            .collect()
+    }
+
+    // 합의 규칙(check_consensus)만 검사한다. checks에서 끈 검사는 건너뛴다
+    fn add_block_inner(&mut self, block: Block, checks: BlockChecks) -> Result<()> {
+        self.check_block(&block, checks)?;
+
+        // 채굴된 블록의 tx를 모아서 mempool에서 지운다 (처리된 것이므로)
+        let block_transactions: HashSet<_> =
+            block.transactions.iter().map(|tx| tx.hash()).collect();
+        let mempool_len = self.mempool.len();
+        self.mempool.retain(|(_, tx)| !block_transactions.contains(&tx.hash()));
+
+        // 블록이 소비한 output을 쓰던 mempool tx는 더 이상 유효하지 않다.
+        // 남겨 두면 사라진 utxo를 참조하게 되므로 자손까지 함께 지운다
+        let block_spends: HashSet<_> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.inputs)
+            .map(|input| input.prev_transaction_output_hash)
+            .collect();
+        let conflicts: Vec<_> = self
+            .mempool
+            .iter()
+            .filter(|(_, tx)| {
+                tx.inputs.iter().any(|input| {
+                    block_spends.contains(&input.prev_transaction_output_hash)
+                })
+            })
+            .map(|(_, tx)| tx.hash())
+            .collect();
+        for txid in conflicts {
+            self.evict_from_mempool(txid);
+        }
+        if self.mempool.len() != mempool_len {
+            self.mempool_generation += 1;
+        }
+
+        self.apply_block_to_utxos(&block);
+        // 확정된 tx의 output을 쓰는 자식 tx는 이제 utxo를 쓰고 있다
+        self.mark_mempool_spends();
+        self.index_block(self.blocks.len(), &block);
+        self.blocks.push(block);
+
+        self.try_adjust_target();
+
+        Ok(())
+    }
+
+    // add_block이 하는 모든 검증을 체인을 바꾸지 않고 수행한다.
+    // 외부 도구가 블록이 받아들여질지 미리 확인할 때 쓴다
+    pub fn would_accept(&self, block: &Block) -> Result<()> {
+        self.check_consensus(block)
+    }
+
+    // 블록이 합의 규칙(consensus)을 모두 지키는지. add_block은 이 검사만 통과하면 블록을 받는다.
+    // 수수료율, dust 같은 mempool 정책(check_policy)은 검사하지 않는다
+    pub fn check_consensus(&self, block: &Block) -> Result<()> {
+        self.check_block(block, BlockChecks::ALL)
+    }
+
+    fn check_block(&self, block: &Block, checks: BlockChecks) -> Result<()> {
+        // 같은 블록이 다시 제출되거나 전파된 경우. 이미 체인에 있으므로 할 일이 없다
+        if self.block_index.contains_key(&block.hash()) {
+            return Err(BtcError::DuplicateBlock);
+        }
+
+        // 로컬 시계보다 너무 앞선 블록은 받지 않는다.
+        // 시간이 지나면 유효해질 수 있으므로 InvalidBlockHeader와 구분한다
+        let max_future =
+            chrono::Duration::seconds(crate::MAX_FUTURE_BLOCK_TIME as i64);
+        if checks.future_time
+            && block.header.timestamp > Utc::now() + max_future
+        {
+            println!("block timestamp is too far in the future");
+            return Err(BtcError::BlockFromFuture);
+        }
+
+        // 블록이 주장하는 target은 네트워크 최소 난이도(MIN_TARGET)보다 쉬울 수 없다
+        if block.header.target > self.params.min_target {
+            println!("target is easier than minimum");
+            return Err(BtcError::InvalidTarget);
+        }
+
         // 체인에 블록이 하나도 없다면
         if self.blocks.is_empty() {
             // 제네시스 블록의 prev는 zero hash여야만 한다
@@ -226,61 +1370,256 @@ impl Blockchain {
                 return Err(BtcError::InvalidBlock);
             }
         } else {
-            // 새 블록의 prev block hash는 이전 블록 해시와 일치해야 한다
+            // prev hash, target, PoW, timestamp를 검증한다
             let last_block = self.blocks.last().unwrap();
+            let expected_target = self.expected_target(self.block_height());
+            block.header.validate(last_block, expected_target)?;
+        }
 
-            // 블록체인 상 마지막 블록의 해시는 현재 채굴된 블록의 prev_block_hash와 동일해야 한다
-            if block.header.prev_block_hash != last_block.hash() {
-                println!("prev hash is wrong");
-                return Err(BtcError::InvalidBlock);
+        // 이하는 genesis도 다른 블록과 똑같이 검증한다.
+        // tx가 하나도 없다면 merkle root를 계산할 수 없다
+        if block.transactions.is_empty() {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        // merkel root가 바르게 계산되었는지 체크한다 (tx 변조, 추가, 누락 여부 확인)
+        let calculated_merkle_root = MerkleRoot::calculate(&block.transactions);
+        if calculated_merkle_root != block.header.merkle_root {
+            println!("invalid merkle root");
+            return Err(BtcError::InvalidMerkleRoot);
+        }
+
+        // 각 block이 포함한 tx를 다양한 형태로 검증한다.
+        // genesis라면 utxo가 비어 있으므로 coinbase 외의 tx는 통과할 수 없다
+        block.check_transactions(
+            self.block_height(),
+            &self.utxos,
+            checks.locks,
+        )?;
+
+        // supply invariant: 블록을 반영한 뒤 발행된 총량(유통량 + 소각된 값)은 발행 스케줄과 정확히 일치해야 한다.
+        // 검증 로직의 버그로 보상이 부풀려진 coinbase가 통과하더라도 여기서 걸러낸다
+        let expected_supply =
+            Self::cumulative_block_reward(self.block_height() + 1);
+        if self.supply_after(block) != Some(expected_supply) {
+            println!("total supply does not match issuance schedule");
+            return Err(BtcError::InvalidSupply);
+        }
+
+        Ok(())
+    }
+
+    // 블록이 소비한 utxo를 지우고 새로 만든 output을 utxo에 추가한다
+    fn apply_block_to_utxos(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            for input in &transaction.inputs {
+                self.utxos.remove(&input.prev_transaction_output_hash);
+                self.utxo_order.remove(&input.prev_transaction_output_hash);
             }
+            // input은 output hash로 utxo를 참조하므로 output hash를 key로 쓴다.
+            // 소각된 output은 소비할 수 없으므로 utxo에 넣지 않는다
+            for output in transaction.outputs.iter() {
+                if output.lock.is_unspendable() {
+                    self.burned = self.burned.saturating_add(output.value);
+                } else {
+                    self.utxos.insert(output.hash(), (false, output.clone()));
+                    self.utxo_order.insert(output.hash());
+                }
+            }
+        }
+    }
 
-            // 현재 채굴된 block은 지정된 target보다는 커야 한다
-            if !block.header.hash().matches_target(block.header.target) {
-                println!("does not match target");
-                return Err(BtcError::InvalidBlock);
+    fn index_block(&mut self, height: usize, block: &Block) {
+        self.block_index.insert(block.hash(), height);
+        for transaction in &block.transactions {
+            self.transaction_index.insert(transaction.hash(), height);
+        }
+    }
+
+    // 직렬화되지 않는 block/tx index와 utxo 순서를 다시 만든다
+    pub fn rebuild_indexes(&mut self) {
+        self.block_index.clear();
+        self.transaction_index.clear();
+        self.utxo_order = self.utxos.keys().copied().collect();
+
+        let blocks = std::mem::take(&mut self.blocks);
+        for (height, block) in blocks.iter().enumerate() {
+            self.index_block(height, block);
+        }
+        self.blocks = blocks;
+
+        // target_history는 저장된 값을 믿지 않고 블록들로부터 다시 계산한다.
+        // 조정마다 직전 target에서 이어서 계산하므로 체인 길이에 비례한다
+        let interval = self.params.difficulty_update_interval;
+        let mut target = self.params.min_target;
+        self.target_history.clear();
+        for n in 1..=self.block_height() / interval {
+            let height = n * interval;
+            target = self.retarget(height, target);
+            self.target_history.push((height, target));
+        }
+    }
+
+    // 저장된 체인이 지금과 같은 hash 인코딩으로 만들어졌는지 확인한다.
+    // 인코딩이 다르면 모든 header hash와 merkle root가 달라져 검증이 실패하므로,
+    // 어느 블록이 왜 틀렸는지 알 수 없는 검증 에러 대신 읽을 때 거부한다.
+    // 버전이 기록되기 전의 파일은 genesis의 merkle root를 지금 인코딩으로 다시 계산해 판단한다
+    fn check_hash_encoding(&mut self) -> IoResult<()> {
+        let version = match (self.hash_encoding, self.blocks.first()) {
+            (Some(version), _) => version,
+            (None, Some(genesis))
+                if MerkleRoot::calculate(&genesis.transactions)
+                    != genesis.header.merkle_root =>
+            {
+                0
             }
+            (None, _) => HASH_ENCODING_VERSION,
+        };
+        if version != HASH_ENCODING_VERSION {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                format!(
+                    "blockchain was stored with hash encoding v{version}, \
+                    expected v{HASH_ENCODING_VERSION}; sync it again"
+                ),
+            ));
+        }
+        self.hash_encoding = Some(version);
+        Ok(())
+    }
 
-            // merkel root가 바르게 계산되었는지 체크한다 (tx 변조, 추가, 누락 여부 확인)
-            let calculated_merkle_root =
-                MerkleRoot::calculate(&block.transactions);
-            if calculated_merkle_root != block.header.merkle_root {
-                println!("invalid merkle root");
-                return Err(BtcError::InvalidMerkleRoot);
+    // 파일에서 읽어온 체인을 그대로 믿지 않고, 모든 블록을 처음부터 다시 검증한다
+    // (prev hash 연결, PoW, merkle root, tx 등 add_block이 하는 모든 검증)
+    pub fn verify_integrity(&self) -> Result<()> {
+        match self.find_invalid_block() {
+            Some((height, e)) => {
+                println!("block {height} failed validation: {e}");
+                Err(e)
             }
+            None => Ok(()),
+        }
+    }
 
-            // 채굴된 시간이 마지막 블록 채굴된 시간 이후여야 한다
-            if block.header.timestamp <= last_block.header.timestamp {
-                return Err(BtcError::InvalidBlock);
+    // verify_integrity처럼 모든 블록을 다시 검증해서, 처음으로 실패한 블록의 높이와 이유를 돌려준다.
+    // 모두 유효하다면 None
+    pub fn find_invalid_block(&self) -> Option<(u64, BtcError)> {
+        let mut replay = Blockchain::empty(self.params.clone());
+        self.blocks.iter().enumerate().find_map(|(height, block)| {
+            replay
+                .add_block_inner(block.clone(), BlockChecks::STORED)
+                .err()
+                .map(|e| (height as u64, e))
+        })
+    }
+
+    // 저장된 utxo와 index를 버리고, 모든 블록을 처음부터 다시 검증하며 쌓아 올린다.
+    // 검증에 실패한 블록이 있으면 그 직전까지만 남기고 에러를 돌려준다
+    pub fn reindex(&mut self) -> Result<()> {
+        let blocks = std::mem::take(&mut self.blocks);
+        let mempool = std::mem::take(&mut self.mempool);
+        let old_utxos = std::mem::take(&mut self.utxos);
+        let old_target = self.target;
+
+        *self = Blockchain::empty(self.params.clone());
+        for (height, block) in blocks.into_iter().enumerate() {
+            if let Err(e) = self.add_block_inner(block, BlockChecks::STORED) {
+                println!("block {height} failed validation: {e}");
+                return Err(e);
             }
+        }
 
-            // 각 block이 포함한 tx를 다양한 형태로 검증한다.
-            block.verify_transactions(self.block_height(), &self.utxos)?;
+        // 저장되어 있던 상태와 다시 계산한 상태가 다르면 알려준다
+        let utxos_match = old_utxos.len() == self.utxos.len()
+            && old_utxos.keys().all(|hash| self.utxos.contains_key(hash));
+        if !utxos_match {
+            println!(
+                "utxo set was inconsistent ({} stored, {} rebuilt)",
+                old_utxos.len(),
+                self.utxos.len()
+            );
+        }
+        if old_target != self.target {
+            println!(
+                "target was inconsistent (stored {}, rebuilt {})",
+                old_target, self.target
+            );
         }
 
-        // 채굴된 블록의 tx를 모아서 mempool에서 지운다 (처리된 것이므로)
-        let block_transactions: HashSet<_> =
-            block.transactions.iter().map(|tx| tx.hash()).collect();
-        self.mempool.retain(|(_, tx)| !block_transactions.contains(&tx.hash()));
+        // mempool tx는 새 utxo 기준으로 다시 받아들인다
+        for (_, transaction) in mempool {
+            let _ = self.add_to_mempool(transaction);
+        }
 
-        self.blocks.push(block);
+        Ok(())
+    }
 
-        self.try_adjust_target();
+    // 아래에서부터 height개의 블록만 남기고 그 위의 블록들을 떼어내 돌려준다.
+    // 더 무거운 체인으로 갈아탈 때 갈라진 지점까지 되돌리는 데 쓴다.
+    // 떼어낸 블록의 tx는 mempool로 돌려보내서 새 체인에 다시 담길 수 있게 한다
+    pub fn rewind_to(&mut self, height: u64) -> Vec<Block> {
+        let height = height.min(self.block_height());
+        let removed = self.blocks.split_off(height as usize);
+        let mempool = std::mem::take(&mut self.mempool);
+        if !mempool.is_empty() {
+            self.mempool_generation += 1;
+        }
 
-        Ok(())
+        self.target_history.retain(|(adjusted, _)| *adjusted <= height);
+        self.target = self.expected_target(height);
+        self.rebuild_utxos();
+        self.rebuild_indexes();
+
+        // 떼어낸 블록의 tx가 먼저 확정된 것이므로 원래 mempool tx보다 먼저 받아들인다.
+        // 새 utxo로는 쓸 수 없게 된 tx는 버린다
+        let transactions = removed
+            .iter()
+            .flat_map(|block| block.transactions.iter().skip(1).cloned())
+            .chain(mempool.into_iter().map(|(_, transaction)| transaction))
+            .collect::<Vec<_>>();
+        for transaction in transactions {
+            let _ = self.add_to_mempool(transaction);
+        }
+
+        removed
+    }
+
+    // rewind_to와 같지만 max_depth개보다 많은 블록을 떼어내야 한다면
+    // 체인을 건드리지 않고 ReorgTooDeep으로 실패한다
+    pub fn rewind_within(
+        &mut self,
+        height: u64,
+        max_depth: u64,
+    ) -> Result<Vec<Block>> {
+        let depth = self.block_height().saturating_sub(height);
+        if depth > max_depth {
+            return Err(BtcError::ReorgTooDeep(depth, max_depth));
+        }
+        Ok(self.rewind_to(height))
     }
 
     // quite inefficient, but for simplicitiy.
     pub fn rebuild_utxos(&mut self) {
-        for block in &self.blocks {
-            for transaction in &block.transactions {
-                for input in &transaction.inputs {
-                    self.utxos.remove(&input.prev_transaction_output_hash);
-                }
-                for output in transaction.outputs.iter() {
-                    self.utxos
-                        .insert(transaction.hash(), (false, output.clone()));
-                }
+        self.utxos.clear();
+        self.utxo_order.clear();
+        self.burned = 0;
+
+        let blocks = std::mem::take(&mut self.blocks);
+        for block in &blocks {
+            self.apply_block_to_utxos(block);
+        }
+        self.blocks = blocks;
+        self.mark_mempool_spends();
+    }
+
+    // mempool tx가 사용 중인 utxo는 다시 마킹한다
+    fn mark_mempool_spends(&mut self) {
+        for (_, transaction) in &self.mempool {
+            for input in &transaction.inputs {
+                self.utxos
+                    .entry(input.prev_transaction_output_hash)
+                    .and_modify(|(marked, _)| {
+                        *marked = true;
+                    });
             }
         }
     }
@@ -289,75 +1628,74 @@ impl Blockchain {
         if self.blocks.is_empty() {
             return;
         }
-        if self.blocks.len() % crate::DIFFICULTY_UPDATE_INTERVAL as usize != 0 {
+        if !self
+            .blocks
+            .len()
+            .is_multiple_of(self.params.difficulty_update_interval as usize)
+        {
             return;
         }
 
-        // 현재보다 50개 이전의 timestamp
-        let start_time = self.blocks
-            [self.blocks.len() - crate::DIFFICULTY_UPDATE_INTERVAL as usize]
-            .header
-            .timestamp;
-        let end_time = self.blocks.last().unwrap().header.timestamp;
-
-        // 50개 블록이 만들어질 때 까지 걸린 시간
-        let time_diff = end_time - start_time;
-        let time_diff_seconds = time_diff.num_seconds();
-
-        // 이전 50개의 블록이 생성된 시간이 IDLE한 blocktime과 얼마나 차이가 났는지?
-        let target_seconds =
-            crate::IDEAL_BLOCK_TIME * crate::DIFFICULTY_UPDATE_INTERVAL;
-
-        // 실제 bitcoin에서는 leading zero 의 갯수를 늘려서 난이도를 증가 시킴.
-        // 여기서는 간이적으로 처리
-        // target * (실제 시간 / 기대시간)
-        // 너무 빨리 되었다면 (실제 시간 / 기대시간) < 1 -> target이 더 어려워지게 (target이 낮아질수록 조건을 만족하는 해시 만들기가 어려움)
-        // 너무 느리게 되었다면 (실제 시간 / 기대 시간) > 1 -> target이 더 쉬워지게
-        let new_target =
-            BigDecimal::parse_bytes(&self.target.to_string().as_bytes(), 10)
-                .expect("BUG: impossible")
-                * (BigDecimal::from(time_diff_seconds)
-                    / BigDecimal::from(target_seconds));
-
-        // cut off decimal point and everything after
-        // it from string representation of new_target
-        let new_target_str = new_target
-            .to_string()
-            .split('.')
-            .next()
-            .expect("BUG: Expected a decimal point")
-            .to_owned();
-
-        let new_target: U256 =
-            U256::from_str_radix(&new_target_str, 10).expect("BUG: impossible");
-
-        dbg!(new_target);
-
-        // 현재 난이도의 25%, 400% 내에서만 움직이도록 clamp 처리한다. 너무 급격한 난이도 변경을 방지.
-        let new_target = if new_target < self.target / 4 {
-            dbg!(self.target / 4)
-        } else if new_target > self.target * 4 {
-            dbg!(self.target * 4)
-        } else {
-            new_target
-        };
+        let height = self.block_height();
+        self.target = self.retarget(height, self.expected_target(height - 1));
+        self.record_target(height, self.target);
+    }
 
-        dbg!(new_target);
+    // 같은 높이에서 다시 조정했다면 덧붙이지 않고 덮어쓴다. 조정 한 번에 항목은 하나다
+    fn record_target(&mut self, height: u64, target: U256) {
+        match self.target_history.last_mut() {
+            Some(last) if last.0 == height => last.1 = target,
+            _ => self.target_history.push((height, target)),
+        }
+    }
 
-        // 최소보다는 커야 하므로
-        self.target = new_target.min(crate::MIN_TARGET);
-        dbg!(self.target);
+    // boundary 높이에서의 난이도 조정. 직전 interval개 블록의 생성 시간을 보고 target을 갱신한다
+    fn retarget(&self, boundary: u64, target: U256) -> U256 {
+        let interval = self.params.difficulty_update_interval as usize;
+        let boundary = boundary as usize;
+        // 현재보다 interval개 이전의 timestamp
+        let start_time = self.blocks[boundary - interval].header.timestamp;
+        let end_time = self.blocks[boundary - 1].header.timestamp;
+
+        // interval개 블록이 만들어질 때 까지 걸린 시간
+        self.params.adjust_target(target, end_time - start_time)
+    }
+
+    // header에 기록된 target을 믿지 않고, 체인 히스토리만으로 해당 높이의 블록이 가져야 할 target을 계산한다.
+    // 아직 블록이 없어 계산할 수 없는 높이는 다음에 채굴될 블록의 target으로 간주한다.
+    // 블록과 함께 갱신되는 target_history에서 height 이하의 마지막 조정을 찾으므로 O(log n)이다
+    pub fn expected_target(&self, height: u64) -> U256 {
+        let height = height.min(self.block_height());
+        let adjustments = self
+            .target_history
+            .partition_point(|(adjusted, _)| *adjusted <= height);
+        match adjustments.checked_sub(1) {
+            Some(last) => self.target_history[last].1,
+            None => self.params.min_target,
+        }
     }
 }
 
+// 비율을 계산하기 위한 근사값
+fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
+
 impl Savable for Blockchain {
     fn load<I: Read>(reader: I) -> IoResult<Self> {
-        ciborium::de::from_reader(reader).map_err(|_| {
-            IoError::new(
-                IoErrorKind::InvalidData,
-                "Failed to deseriailize blockchain",
-            )
-        })
+        let mut blockchain: Blockchain = ciborium::de::from_reader(reader)
+            .map_err(|_| {
+                IoError::new(
+                    IoErrorKind::InvalidData,
+                    "Failed to deseriailize blockchain",
+                )
+            })?;
+        blockchain.check_hash_encoding()?;
+        Ok(blockchain)
     }
 
     fn save<O: Write>(&self, writer: O) -> IoResult<()> {
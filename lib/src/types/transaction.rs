@@ -1,11 +1,15 @@
 use crate::{
     crypto::{PublicKey, Signature},
+    encoding::{HashEncoder, TAG_TXID, TAG_WTXID},
+    error::{BtcError, Result},
     sha256::Hash,
     util::Savable,
 };
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashSet;
 use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write};
-use uuid::Uuid;
+use uuid::{Builder, Uuid};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Transaction {
@@ -20,9 +24,187 @@ impl Transaction {
             outputs,
         }
     }
+    // output의 unique_id를 무작위 대신 input과 output 위치로부터 유도하는 생성자.
+    // 같은 input과 output으로 만들면 항상 같은 txid가 나오므로 재현 가능한 테스트나 중복 제거에 쓴다.
+    // input이 없는 coinbase tx에 쓰면 보상이 같은 블록끼리 output hash가 겹치므로 쓰지 않는다
+    pub fn with_derived_ids(
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<(PublicKey, u64)>,
+    ) -> Self {
+        let outputs = outputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, (pubkey, value))| TransactionOutput {
+                value,
+                unique_id: TransactionOutput::derive_unique_id(&inputs, index),
+                lock: LockingCondition::P2PK(pubkey),
+            })
+            .collect();
+        Transaction::new(inputs, outputs)
+    }
+
+    // 높이 height 블록의 coinbase tx. 보상(reward)과 수수료(fees)를 모두 payout에게 지급한다.
+    // reward + fees가 u64를 넘으면 검증을 통과할 수 없는 값이 되므로 호출하는 쪽에서 확인한다
+    pub fn coinbase(height: u64, reward: u64, fees: u64, payout: &PublicKey) -> Self {
+        Transaction::new(
+            vec![],
+            vec![TransactionOutput {
+                value: reward.saturating_add(fees),
+                unique_id: TransactionOutput::coinbase_unique_id(height),
+                lock: LockingCondition::P2PK(payout.clone()),
+            }],
+        )
+    }
+
+    // 보상과 수수료를 (pubkey, 지분) 목록의 지분 비율대로 나눠 지급하는 coinbase tx.
+    // 나누고 남은 자투리는 첫 번째 pubkey에게 주므로 output의 합은 항상 reward + fees와 같다.
+    // 목록이 비었거나, 지분이 0이거나, 몫이 0이 되는 output이 있으면 에러
+    pub fn coinbase_with_payouts(
+        height: u64,
+        reward: u64,
+        fees: u64,
+        payouts: &[(PublicKey, u64)],
+    ) -> Result<Self> {
+        if payouts.is_empty() || payouts.iter().any(|(_, share)| *share == 0) {
+            return Err(BtcError::InvalidTransaction);
+        }
+        let total_shares = payouts
+            .iter()
+            .try_fold(0u64, |sum, (_, share)| sum.checked_add(*share))
+            .ok_or(BtcError::InvalidTransaction)?;
+        let total = reward.checked_add(fees).ok_or(BtcError::InvalidTransaction)?;
+
+        let mut outputs: Vec<TransactionOutput> = payouts
+            .iter()
+            .map(|(pubkey, share)| TransactionOutput {
+                value: (total as u128 * *share as u128 / total_shares as u128) as u64,
+                unique_id: TransactionOutput::coinbase_unique_id(height),
+                lock: LockingCondition::P2PK(pubkey.clone()),
+            })
+            .collect();
+        let paid: u64 = outputs.iter().map(|output| output.value).sum();
+        outputs[0].value += total - paid;
+        // 값이 0인 coinbase output은 유효하지 않다
+        if outputs.iter().any(|output| output.value == 0) {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        Ok(Transaction::new(vec![], outputs))
+    }
+
+    // input 없이 새 코인을 만들어내는 블록 보상 tx인지.
+    // input도 output도 없는 tx는 아무 의미가 없으므로 coinbase로 보지 않는다
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.is_empty() && !self.outputs.is_empty()
+    }
+
+    // BIP 125처럼 input 중 하나라도 교체 가능 sequence를 가지면 RBF를 허용한다는 신호다
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs.iter().any(|input| input.sequence <= crate::MAX_RBF_SEQUENCE)
+    }
+
+    // input 중 이 tx가 만드는 output을 참조하는 것이 있는지.
+    // 자기 자신의 output은 tx가 확정되기 전에는 존재하지 않으므로 말이 안 되는 tx다
+    pub fn spends_own_output(&self) -> bool {
+        let own_outputs: HashSet<Hash> =
+            self.outputs.iter().map(|output| output.hash()).collect();
+        self.inputs
+            .iter()
+            .any(|input| own_outputs.contains(&input.prev_transaction_output_hash))
+    }
+
+    // tx 안의 output들이 서로 다른 unique_id를 가지는지.
+    // 같은 잠금 조건, 같은 값의 output이 같은 hash(outpoint)를 갖지 않도록 하는 것이 unique_id의 역할이다
+    pub fn has_unique_output_ids(&self) -> bool {
+        let mut ids = HashSet::new();
+        self.outputs.iter().all(|output| ids.insert(output.unique_id))
+    }
+
+    // txid. 서명(witness)은 제외하고 input이 참조하는 output과 output만 commit 한다.
+    // 서명을 변조해도 txid가 바뀌지 않으므로 transaction malleability를 막는다
     pub fn hash(&self) -> Hash {
-        Hash::hash(self)
+        Hash::hash_bytes(&self.canonical_bytes())
+    }
+
+    // witness(서명)까지 포함한 해시. merkle root 계산에 사용하여 서명도 블록에 commit 한다
+    pub fn wtxid(&self) -> Hash {
+        let mut encoder = HashEncoder::new(TAG_WTXID);
+        encoder.bytes(&self.canonical_bytes());
+        encoder.count(self.input_count());
+        for input in &self.inputs {
+            encoder.u32(input.sequence).count(input.extra_signatures.len() + 1);
+            for signature in input.signatures() {
+                encoder.fixed(&signature.to_bytes());
+            }
+        }
+        Hash::hash_bytes(&encoder.finish())
     }
+
+    // txid 계산에 쓰는 고정된 바이트 인코딩 (crate::encoding). witness는 들어가지 않는다
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = HashEncoder::new(TAG_TXID);
+        encoder.count(self.input_count());
+        for input in &self.inputs {
+            encoder.hash(&input.prev_transaction_output_hash);
+        }
+        encoder.count(self.output_count());
+        for output in &self.outputs {
+            encoder.u64(output.value).fixed(output.unique_id.as_bytes());
+            output.lock.encode(&mut encoder);
+        }
+        encoder.finish()
+    }
+
+    fn without_witness(&self) -> TransactionWithoutWitness<'_> {
+        TransactionWithoutWitness {
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| &input.prev_transaction_output_hash)
+                .collect(),
+            outputs: &self.outputs,
+        }
+    }
+
+    // CBOR로 직렬화한 크기 (byte). 서명까지 포함하므로 네트워크로 보내거나 저장할 때의 크기와 같다
+    pub fn serialized_size(&self) -> usize {
+        serialized_size(self)
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    // bitcoin과 동일하게 non-witness 데이터는 WITNESS_SCALE_FACTOR배,
+    // witness 데이터는 1배로 계산한다. 블록 크기 제한에 사용한다
+    pub fn weight(&self) -> usize {
+        let base_size = serialized_size(&self.without_witness());
+        let total_size = self.serialized_size();
+        base_size * (crate::WITNESS_SCALE_FACTOR - 1) + total_size
+    }
+
+    // virtual size. weight를 byte 단위로 환산한 값 (올림)
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(crate::WITNESS_SCALE_FACTOR)
+    }
+}
+
+// txid 계산에 쓰이는, 서명을 뺀 tx의 모습
+#[derive(Serialize)]
+struct TransactionWithoutWitness<'a> {
+    inputs: Vec<&'a Hash>,
+    outputs: &'a [TransactionOutput],
+}
+
+fn serialized_size<T: Serialize>(data: &T) -> usize {
+    let mut serialized: Vec<u8> = vec![];
+    ciborium::into_writer(data, &mut serialized)
+        .expect("BUG: transaction serialization cannot fail");
+    serialized.len()
 }
 
 impl Savable for Transaction {
@@ -42,18 +224,224 @@ impl Savable for Transaction {
 pub struct TransactionInput {
     /// input으로 사용할 이전 output tx.
     pub prev_transaction_output_hash: Hash,
+    /// witness. txid(`Transaction::hash`)에는 포함되지 않는다
     pub signature: Signature,
+    /// multisig output을 소비할 때 필요한 나머지 서명들. 역시 witness다.
+    /// 비어 있으면 직렬화하지 않으므로 단일 서명 input의 형식은 그대로다
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_signatures: Vec<Signature>,
+    /// crate::MAX_RBF_SEQUENCE 이하라면 이 input을 가진 tx는 교체(RBF)될 수 있다.
+    /// 기본값인 crate::SEQUENCE_FINAL은 직렬화하지 않으므로 기존 input의 형식은 그대로다
+    #[serde(default = "sequence_final", skip_serializing_if = "is_sequence_final")]
+    pub sequence: u32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+fn sequence_final() -> u32 {
+    crate::SEQUENCE_FINAL
+}
+
+fn is_sequence_final(sequence: &u32) -> bool {
+    *sequence == crate::SEQUENCE_FINAL
+}
+
+impl TransactionInput {
+    // 서명 하나로 output을 소비하는, 교체 불가능한 input
+    pub fn new(prev_transaction_output_hash: Hash, signature: Signature) -> Self {
+        TransactionInput {
+            prev_transaction_output_hash,
+            signature,
+            extra_signatures: vec![],
+            sequence: crate::SEQUENCE_FINAL,
+        }
+    }
+
+    // input에 담긴 모든 서명
+    pub fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        std::iter::once(&self.signature).chain(&self.extra_signatures)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "TransactionOutputRepr")]
 pub struct TransactionOutput {
     pub value: u64,
+    /// utxo는 output의 hash로 식별되므로, 값과 잠금 조건이 같은 output들을 구분하기 위한 id.
+    /// 한 tx 안에서 중복되면 안 된다
     pub unique_id: Uuid,
-    pub pubkey: PublicKey,
+    /// output을 소비하기 위해 만족해야 하는 조건
+    pub lock: LockingCondition,
+}
+
+/// output의 잠금 조건. 새로운 script 종류는 variant로 추가한다
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum LockingCondition {
+    /// pay-to-public-key. 해당 public key의 서명으로 소비한다
+    P2PK(PublicKey),
+    /// keys 중 threshold개 이상의 서로 다른 key의 서명으로 소비한다
+    MultiSig { keys: Vec<PublicKey>, threshold: u8 },
+    /// height 높이의 블록부터 소비할 수 있고, 그때부터 inner 조건을 따른다
+    CheckLockTimeVerify { height: u64, inner: Box<LockingCondition> },
+    /// 아직 해석하지 않는 raw script. 예약된 자리로, 소비하려 하면 거부된다
+    RawScript(Vec<u8>),
+    /// 누구도 소비할 수 없는 output. 값은 소각(burn)되어 utxo에 들어가지 않고
+    /// 유통량(Blockchain::total_supply)에서 빠진다
+    Unspendable,
+}
+
+impl LockingCondition {
+    // variant마다 tag를 붙여 hash용 인코딩에 쓴다
+    fn encode(&self, encoder: &mut HashEncoder) {
+        match self {
+            LockingCondition::P2PK(pubkey) => {
+                encoder.u8(0).bytes(&pubkey.to_sec1_bytes());
+            }
+            LockingCondition::MultiSig { keys, threshold } => {
+                encoder.u8(1).u8(*threshold).count(keys.len());
+                for key in keys {
+                    encoder.bytes(&key.to_sec1_bytes());
+                }
+            }
+            LockingCondition::CheckLockTimeVerify { height, inner } => {
+                encoder.u8(2).u64(*height);
+                inner.encode(encoder);
+            }
+            LockingCondition::RawScript(script) => {
+                encoder.u8(3).bytes(script);
+            }
+            LockingCondition::Unspendable => {
+                encoder.u8(4);
+            }
+        }
+    }
+
+    // 소각된 output이라 utxo에 넣지 않아야 하는지
+    pub fn is_unspendable(&self) -> bool {
+        matches!(self, LockingCondition::Unspendable)
+    }
+
+    // P2PK라면 소유자의 public key
+    pub fn pubkey(&self) -> Option<&PublicKey> {
+        match self {
+            LockingCondition::P2PK(pubkey) => Some(pubkey),
+            _ => None,
+        }
+    }
+
+    // 이 조건으로 잠긴 output을 block_height 높이의 블록에서 소비하는 input이
+    // 조건을 만족하는지 검증한다
+    pub fn verify(&self, input: &TransactionInput, block_height: u64) -> Result<()> {
+        let output_hash = &input.prev_transaction_output_hash;
+        match self {
+            LockingCondition::P2PK(pubkey) => {
+                if input.extra_signatures.is_empty() && input.signature.verify(output_hash, pubkey)
+                {
+                    Ok(())
+                } else {
+                    Err(BtcError::InvalidSignature)
+                }
+            }
+            LockingCondition::MultiSig { keys, threshold } => {
+                let threshold = *threshold as usize;
+                if threshold == 0 || threshold > keys.len() {
+                    return Err(BtcError::InvalidTransactionOutput);
+                }
+
+                // 모든 서명은 아직 서명하지 않은 key 중 하나에 대응해야 한다.
+                // 같은 key의 서명이 두 번 들어 있으면 threshold를 부풀리는 것이므로 거부한다
+                let mut signers = vec![false; keys.len()];
+                for signature in input.signatures() {
+                    let signer = keys
+                        .iter()
+                        .position(|key| signature.verify(output_hash, key))
+                        .ok_or(BtcError::InvalidSignature)?;
+                    if signers[signer] {
+                        return Err(BtcError::InvalidSignature);
+                    }
+                    signers[signer] = true;
+                }
+
+                if signers.iter().filter(|signed| **signed).count() < threshold {
+                    return Err(BtcError::InvalidSignature);
+                }
+                Ok(())
+            }
+            LockingCondition::CheckLockTimeVerify { height, inner } => {
+                // 아직 잠겨 있다면 서명은 볼 필요도 없다
+                if block_height < *height {
+                    return Err(BtcError::InvalidTransactionInput);
+                }
+                inner.verify(input, block_height)
+            }
+            LockingCondition::RawScript(_) | LockingCondition::Unspendable => {
+                Err(BtcError::InvalidTransactionOutput)
+            }
+        }
+    }
+}
+
+// P2PK output은 잠금 조건이 생기기 전과 같은 `pubkey` 필드로 직렬화한다.
+// 기존 블록과 output의 hash가 바뀌지 않고, 기존 파일도 그대로 읽힌다
+impl Serialize for TransactionOutput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TransactionOutput", 3)?;
+        state.serialize_field("value", &self.value)?;
+        state.serialize_field("unique_id", &self.unique_id)?;
+        match &self.lock {
+            LockingCondition::P2PK(pubkey) => state.serialize_field("pubkey", pubkey)?,
+            lock => state.serialize_field("lock", lock)?,
+        }
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct TransactionOutputRepr {
+    value: u64,
+    unique_id: Uuid,
+    pubkey: Option<PublicKey>,
+    lock: Option<LockingCondition>,
+}
+
+impl TryFrom<TransactionOutputRepr> for TransactionOutput {
+    type Error = &'static str;
+
+    fn try_from(repr: TransactionOutputRepr) -> std::result::Result<Self, Self::Error> {
+        let lock = match (repr.pubkey, repr.lock) {
+            (Some(pubkey), None) => LockingCondition::P2PK(pubkey),
+            (None, Some(lock)) => lock,
+            _ => return Err("output must have exactly one of pubkey or lock"),
+        };
+        Ok(TransactionOutput {
+            value: repr.value,
+            unique_id: repr.unique_id,
+            lock,
+        })
+    }
 }
 
 impl TransactionOutput {
     pub fn hash(&self) -> Hash {
         Hash::hash(self)
     }
+
+    // coinbase output의 unique_id. 앞 8바이트에 블록 높이를, 나머지에 무작위 값을 담는다.
+    // 높이가 다른 coinbase끼리는 보상과 payout이 같아도 output hash가 겹치지 않는다
+    fn coinbase_unique_id(height: u64) -> Uuid {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&height.to_be_bytes());
+        bytes[8..].copy_from_slice(&rand::random::<[u8; 8]>());
+        Builder::from_custom_bytes(bytes).into_uuid()
+    }
+
+    // 소비하는 이전 output들과 output의 위치로부터 unique_id를 유도한다.
+    // 이전 output은 한 번만 소비될 수 있으므로 서로 다른 tx끼리 id가 겹치지 않는다
+    pub fn derive_unique_id(inputs: &[TransactionInput], index: usize) -> Uuid {
+        let prev_outputs: Vec<&Hash> = inputs
+            .iter()
+            .map(|input| &input.prev_transaction_output_hash)
+            .collect();
+        let hash = Hash::hash(&(prev_outputs, index as u64));
+        let bytes: [u8; 16] = hash.as_bytes()[..16].try_into().unwrap();
+        Builder::from_custom_bytes(bytes).into_uuid()
+    }
 }
@@ -11,10 +11,15 @@ use crate::types::Transaction;
 pub struct MerkleRoot(Hash);
 
 impl MerkleRoot {
+    pub fn hash(&self) -> &Hash {
+        &self.0
+    }
+
     pub fn calculate(transactions: &[Transaction]) -> MerkleRoot {
         let mut layer: Vec<Hash> = vec![];
         for transaction in transactions {
-            layer.push(Hash::hash(transaction));
+            // txid가 아닌 wtxid를 사용하여 서명까지 블록에 commit 한다
+            layer.push(transaction.wtxid());
         }
         while layer.len() > 1 {
             let mut new_layer = vec![];
@@ -30,6 +35,91 @@ impl MerkleRoot {
     }
 }
 
+// tx를 하나씩 추가하면서 merkle root를 갱신한다. MerkleRoot::calculate와 같은 root를 만든다.
+// 완성된 subtree의 root(peak)만 높이별로 들고 있으므로 추가는 O(log n)이다.
+// 수수료가 정해진 뒤 coinbase를 바꿀 수 있도록 첫 번째 leaf에서 root로 가는
+// 경로의 형제 node도 기억해 둔다
+#[derive(Clone, Debug, Default)]
+pub struct MerkleAccumulator {
+    count: usize,
+    // peaks[level]: 2^level개의 leaf로 이루어진, 아직 짝이 없는 subtree의 root
+    peaks: Vec<Option<Hash>>,
+    // first_siblings[level]: 첫 번째 leaf를 포함한 subtree와 합쳐진 오른쪽 subtree의 root
+    first_siblings: Vec<Hash>,
+}
+
+impl MerkleAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn push(&mut self, transaction: &Transaction) {
+        let mut node = transaction.wtxid();
+        let mut level = 0;
+        // 같은 높이의 peak가 있다면 합쳐서 한 단계 위로 올린다
+        while let Some(left) = self.peaks.get_mut(level).and_then(Option::take)
+        {
+            // 첫 번째 leaf를 포함한 subtree는 전체 leaf 수가 2의 거듭제곱이 될 때 합쳐진다
+            if self.count + 1 == 2 << level {
+                self.first_siblings.push(node);
+            }
+            node = Hash::hash(&[left, node]);
+            level += 1;
+        }
+        if level == self.peaks.len() {
+            self.peaks.push(None);
+        }
+        self.peaks[level] = Some(node);
+        self.count += 1;
+    }
+
+    // 첫 번째 tx를 바꾼다. 첫 번째 leaf가 속한 peak만 다시 계산하므로 O(log n)
+    pub fn replace_first(&mut self, transaction: &Transaction) {
+        if self.count == 0 {
+            self.push(transaction);
+            return;
+        }
+        // 첫 번째 leaf는 가장 높은 peak에 속한다
+        let top = self.peaks.len() - 1;
+        let node = self.first_siblings[..top]
+            .iter()
+            .fold(transaction.wtxid(), |node, sibling| {
+                Hash::hash(&[node, *sibling])
+            });
+        self.peaks[top] = Some(node);
+    }
+
+    // 지금까지 추가한 tx들의 merkle root. 비어 있다면 None
+    pub fn root(&self) -> Option<MerkleRoot> {
+        if self.count == 0 {
+            return None;
+        }
+        // 아래 높이부터 올라가며 layer의 마지막 node(carry)를 짝지어 올린다.
+        // 짝이 없는 마지막 node는 calculate처럼 자기 자신과 짝짓는다
+        let mut carry = None;
+        let mut level = 0;
+        while (self.count - 1) >> level > 0 {
+            carry = match (self.peaks[level], carry) {
+                (Some(left), Some(right)) => Some(Hash::hash(&[left, right])),
+                (Some(node), None) | (None, Some(node)) => {
+                    Some(Hash::hash(&[node, node]))
+                }
+                (None, None) => None,
+            };
+            level += 1;
+        }
+        carry.or_else(|| self.peaks[level]).map(MerkleRoot)
+    }
+}
+
 pub trait Savable
 where
     Self: Sized,
@@ -40,6 +130,18 @@ where
         let file = File::create(&path)?;
         self.save(file)
     }
+    // 임시 파일에 모두 쓴 뒤 rename 한다.
+    // 쓰는 도중 죽더라도 기존 파일은 온전히 남아 있다
+    fn save_to_file_atomic<P: AsRef<Path>>(&self, path: P) -> IoResult<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let file = File::create(&tmp)?;
+        self.save(&file)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    }
     fn load_from_file<P: AsRef<Path>>(path: P) -> IoResult<Self> {
         let file = File::open(&path)?;
         Self::load(file)
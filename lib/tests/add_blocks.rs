@@ -0,0 +1,125 @@
+// add_blocks가 서명을 미리 병렬로 검증하더라도, 같은 묶음 안의 앞선 블록이 만든 output을
+// 소비하는 긴 체인을 add_block을 하나씩 부른 것과 똑같이 받아들이는지 확인한다
+mod common;
+
+use btclib::U256;
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::params::ChainParams;
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain, Transaction, TransactionInput};
+use btclib::util::MerkleRoot;
+use chrono::Duration;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const CHAIN_LENGTH: u64 = 200;
+const FEE: u64 = 10_000;
+
+// 블록이 많으므로 채굴이 빨리 끝나도록 target을 쉽게 한다
+fn params() -> ChainParams {
+    ChainParams {
+        min_target: U256::MAX >> 4,
+        ..ChainParams::default()
+    }
+}
+
+// utxo마다 소비 여부와 output hash
+fn utxos(blockchain: &Blockchain) -> HashMap<Hash, (bool, Hash)> {
+    blockchain
+        .utxos()
+        .iter()
+        .map(|(hash, (spent, output))| (*hash, (*spent, output.hash())))
+        .collect()
+}
+
+fn assert_same_chain(received: &Blockchain, source: &Blockchain) {
+    assert_eq!(received.block_height(), source.block_height());
+    assert_eq!(received.tip_hash(), source.tip_hash());
+    assert_eq!(utxos(received), utxos(source));
+    assert_eq!(received.target_history(), source.target_history());
+}
+
+// prev_hash가 가리키는 output을 key가 signer의 서명으로 소비하는 tx
+fn spend(
+    key: &PrivateKey,
+    signer: &PrivateKey,
+    prev: &Transaction,
+) -> Transaction {
+    let prev_hash = prev.outputs[0].hash();
+    Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, signer),
+        )],
+        vec![(key.public_key(), prev.outputs[0].value - FEE)],
+    )
+}
+
+// 블록마다 직전 블록의 coinbase를 소비하는 tx와, 그 tx의 output을 같은 블록에서 다시
+// 소비하는 tx가 담긴 체인. test들이 같은 체인을 나눠 쓴다
+fn chain() -> &'static (PrivateKey, Blockchain) {
+    static CHAIN: OnceLock<(PrivateKey, Blockchain)> = OnceLock::new();
+    CHAIN.get_or_init(|| {
+        let key = PrivateKey::new_key();
+        let mut blockchain = Blockchain::with_params(params()).unwrap();
+        common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+        while blockchain.block_height() < CHAIN_LENGTH {
+            let tip = blockchain.blocks_rev().next().unwrap();
+            let parent = spend(&key, &key, &tip.transactions[0]);
+            let child = spend(&key, &key, &parent);
+            blockchain.add_to_mempool(parent).unwrap();
+            blockchain.add_to_mempool(child).unwrap();
+            common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+        }
+        (key, blockchain)
+    })
+}
+
+#[test]
+fn long_chain_with_spends_inside_the_batch_matches_add_block() {
+    let (_, source) = chain();
+    let blocks: Vec<Block> = source.blocks().cloned().collect();
+    assert!(blocks[1..].iter().all(|block| block.transactions.len() == 3));
+
+    // genesis 다음의 모든 블록을 한 묶음으로 받는다
+    let mut receiver = Blockchain::with_params(params()).unwrap();
+    receiver.add_block(blocks[0].clone()).unwrap();
+    receiver.add_blocks(blocks[1..].to_vec()).unwrap();
+    assert_eq!(receiver.block_height(), CHAIN_LENGTH);
+    assert_same_chain(&receiver, source);
+
+    // 동기화처럼 작은 묶음으로 나눠 받아도 같다
+    let mut chunked = Blockchain::with_params(params()).unwrap();
+    for chunk in blocks.chunks(16) {
+        chunked.add_blocks(chunk.to_vec()).unwrap();
+    }
+    assert_same_chain(&chunked, source);
+}
+
+#[test]
+fn forged_spend_inside_the_batch_stops_at_that_block() {
+    let (key, source) = chain();
+    let mut blocks: Vec<Block> = source.blocks().cloned().collect();
+
+    // FORGED 높이 블록의 자식 tx를 다른 key로 서명한다. 부모 tx는 같은 블록에서 만들어지므로
+    // 미리 검증할 때도 묶음 안의 output으로 찾아야 한다
+    const FORGED: usize = 120;
+    let other = PrivateKey::new_key();
+    let forged = &mut blocks[FORGED];
+    let parent = forged.transactions[1].clone();
+    forged.transactions[2] = spend(key, &other, &parent);
+    forged.header.merkle_root = MerkleRoot::calculate(&forged.transactions);
+    common::mine(forged);
+    blocks.truncate(FORGED + 1);
+
+    let mut receiver = Blockchain::with_params(params()).unwrap();
+    receiver.add_block(blocks[0].clone()).unwrap();
+    assert!(matches!(
+        receiver.add_blocks(blocks[1..].to_vec()),
+        Err(BtcError::InvalidSignature)
+    ));
+    // 그 앞의 블록들까지만 추가된다
+    assert_eq!(receiver.block_height(), FORGED as u64);
+    assert_eq!(receiver.tip_hash(), Some(blocks[FORGED - 1].hash()));
+}
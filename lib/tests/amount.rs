@@ -0,0 +1,61 @@
+use bigdecimal::BigDecimal;
+use btclib::amount::{Amount, SATOSHIS_PER_BTC};
+use std::str::FromStr;
+
+fn btc(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[test]
+fn converts_between_btc_and_satoshis() {
+    assert_eq!(Amount::from_btc(&btc("1")).unwrap().to_sat(), SATOSHIS_PER_BTC);
+    assert_eq!(Amount::from_btc(&btc("0.00000001")).unwrap().to_sat(), 1);
+    assert_eq!(Amount::from_sat(150_000_000).to_btc(), btc("1.5"));
+    assert_eq!(
+        Amount::from_btc(&Amount::from_sat(123_456_789).to_btc()).unwrap(),
+        Amount::from_sat(123_456_789)
+    );
+}
+
+#[test]
+fn rounds_to_the_nearest_satoshi() {
+    let sat = |s: &str| Amount::from_btc(&btc(s)).unwrap().to_sat();
+
+    assert_eq!(sat("0.000000014"), 1);
+    assert_eq!(sat("0.000000016"), 2);
+    // 정확히 중간이면 짝수 쪽으로
+    assert_eq!(sat("0.000000015"), 2);
+    assert_eq!(sat("0.000000025"), 2);
+    assert_eq!(sat("0.000000004"), 0);
+}
+
+#[test]
+fn rejects_amounts_that_do_not_fit() {
+    let max = Amount::from_sat(u64::MAX).to_btc();
+    assert_eq!(Amount::from_btc(&max).unwrap().to_sat(), u64::MAX);
+
+    assert!(Amount::from_btc(&(max + btc("0.00000001"))).is_err());
+    assert!(Amount::from_btc(&btc("1e30")).is_err());
+    assert!(Amount::from_btc(&btc("-0.00000001")).is_err());
+    // 반올림해서 0이 되는 음수는 0이다
+    assert_eq!(Amount::from_btc(&btc("-0.000000001")).unwrap(), Amount::ZERO);
+}
+
+#[test]
+fn displays_eight_decimal_places() {
+    assert_eq!(Amount::ZERO.to_string(), "0.00000000 BTC");
+    assert_eq!(Amount::from_sat(1).to_string(), "0.00000001 BTC");
+    assert_eq!(Amount::from_sat(5_000_000_000).to_string(), "50.00000000 BTC");
+    assert_eq!(
+        Amount::from_sat(u64::MAX).to_string(),
+        "184467440737.09551615 BTC"
+    );
+}
+
+#[test]
+fn parses_what_it_displays() {
+    let amount = Amount::from_sat(123_456_789);
+    assert_eq!(amount.to_string().parse::<Amount>().unwrap(), amount);
+    assert_eq!("1.23456789".parse::<Amount>().unwrap(), amount);
+    assert!("one BTC".parse::<Amount>().is_err());
+}
@@ -0,0 +1,63 @@
+// 블록을 쌓아가며 key별 잔액이 바뀌는 체인에서, 중간 높이의 잔액을 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::types::{Blockchain, Transaction, TransactionInput};
+use chrono::Duration;
+use common::{mine_run, output};
+
+const FEE: u64 = 10_000;
+const PAYMENT: u64 = 1_000;
+
+#[test]
+fn balances_follow_each_block() {
+    let alice = PrivateKey::new_key();
+    let bob = PrivateKey::new_key();
+    let spacing = Duration::seconds(10);
+    let mut blockchain = Blockchain::new();
+
+    // 높이 0: alice가 보상을 받는다
+    mine_run(&mut blockchain, &alice, 1, spacing);
+
+    // 높이 1: alice가 bob에게 PAYMENT를 보내고 거스름돈과 수수료를 다시 받는다
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let prev_hash = prev.hash();
+    blockchain
+        .add_to_mempool(Transaction::new(
+            vec![TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, &alice),
+            )],
+            vec![
+                output(&bob, PAYMENT),
+                output(&alice, prev.value - PAYMENT - FEE),
+            ],
+        ))
+        .unwrap();
+    mine_run(&mut blockchain, &alice, 1, spacing);
+
+    // 높이 2: bob이 보상을 받는다
+    mine_run(&mut blockchain, &bob, 1, spacing);
+
+    let reward = Blockchain::block_reward_at;
+    let alice_at =
+        |height| blockchain.balance_for_at(&alice.public_key(), height);
+    let bob_at = |height| blockchain.balance_for_at(&bob.public_key(), height);
+
+    assert_eq!((alice_at(0), bob_at(0)), (0, 0));
+    assert_eq!((alice_at(1), bob_at(1)), (reward(0), 0));
+    assert_eq!(
+        (alice_at(2), bob_at(2)),
+        (reward(0) + reward(1) - PAYMENT, PAYMENT)
+    );
+    assert_eq!(
+        (alice_at(3), bob_at(3)),
+        (reward(0) + reward(1) - PAYMENT, PAYMENT + reward(2))
+    );
+
+    // 체인보다 높은 높이는 지금의 잔액과 같다
+    assert_eq!(alice_at(100), blockchain.balance_for(&alice.public_key()));
+    assert_eq!(bob_at(100), blockchain.balance_for(&bob.public_key()));
+    assert_eq!(bob_at(3), blockchain.balance_for(&bob.public_key()));
+}
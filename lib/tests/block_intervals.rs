@@ -0,0 +1,57 @@
+// timestamp를 알고 있는 체인에서 블록 간격과 평균 블록 시간을 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain};
+use chrono::{Duration, Utc};
+use common::{coinbase_block, load_unverified};
+
+// genesis 이후 블록마다 앞 블록과 offsets만큼 떨어진 체인
+fn chain_with_offsets(offsets: &[i64]) -> Blockchain {
+    let key = PrivateKey::new_key();
+    let mut timestamp = Utc::now() - Duration::hours(1);
+    let mut blocks: Vec<Block> =
+        vec![coinbase_block(&key, 0, Hash::zero(), timestamp)];
+    for offset in offsets {
+        timestamp += Duration::seconds(*offset);
+        let prev = blocks.last().unwrap().hash();
+        let height = blocks.len() as u64;
+        blocks.push(coinbase_block(&key, height, prev, timestamp));
+    }
+    load_unverified(&blocks)
+}
+
+#[test]
+fn intervals_follow_the_timestamps() {
+    let blockchain = chain_with_offsets(&[10, 30, 20, -5]);
+
+    // timestamp가 역전되면 음수 간격이 된다
+    assert_eq!(blockchain.block_intervals(), vec![10, 30, 20, -5]);
+    assert_eq!(blockchain.average_block_time(2), Some(7.5));
+    assert_eq!(blockchain.average_block_time(3), Some(15.0));
+    assert_eq!(blockchain.average_block_time(4), Some(13.75));
+    // window가 간격 수보다 크면 전체 평균이다
+    assert_eq!(blockchain.average_block_time(100), Some(13.75));
+    assert_eq!(blockchain.average_block_time(0), None);
+}
+
+#[test]
+fn chain_without_intervals_has_no_average() {
+    assert!(Blockchain::new().block_intervals().is_empty());
+    assert_eq!(Blockchain::new().average_block_time(10), None);
+
+    let genesis_only = chain_with_offsets(&[]);
+    assert!(genesis_only.block_intervals().is_empty());
+    assert_eq!(genesis_only.average_block_time(10), None);
+}
+
+#[test]
+fn intervals_match_the_mining_spacing() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 4, Duration::seconds(7));
+
+    assert_eq!(blockchain.block_intervals(), vec![7, 7, 7]);
+    assert_eq!(blockchain.average_block_time(3), Some(7.0));
+}
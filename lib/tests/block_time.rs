@@ -0,0 +1,61 @@
+mod common;
+
+use btclib::U256;
+use btclib::crypto::PrivateKey;
+use btclib::params::ChainParams;
+use btclib::types::Blockchain;
+use chrono::Duration;
+
+const INTERVAL: u64 = 4;
+
+// 블록 시간 2초, 4블록마다 조정하고 채굴이 금방 끝나는 쉬운 target을 쓰는 체인
+fn fast_params() -> ChainParams {
+    ChainParams {
+        ideal_block_time: 2,
+        difficulty_update_interval: INTERVAL,
+        min_target: U256::MAX >> 4,
+        ..ChainParams::default()
+    }
+}
+
+#[test]
+fn target_tightens_while_blocks_are_faster_than_ideal() {
+    let params = fast_params();
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::with_params(params.clone()).unwrap();
+
+    // 블록 시간의 절반 간격으로 조정 4번 분량을 채굴한다
+    common::mine_run(&mut blockchain, &key, INTERVAL * 4, Duration::seconds(1));
+
+    let history = blockchain.target_history();
+    assert_eq!(
+        history.iter().map(|(height, _)| *height).collect::<Vec<_>>(),
+        vec![4, 8, 12, 16]
+    );
+
+    // 구간마다 (INTERVAL - 1)초가 걸렸고 기대한 시간은 ideal * INTERVAL초이므로
+    // 조정마다 target은 3/8배가 된다
+    let mut previous = params.min_target;
+    for (_, target) in history {
+        assert_eq!(*target, previous * 3 / 8);
+        previous = *target;
+    }
+    assert_eq!(blockchain.target(), previous);
+}
+
+#[test]
+fn target_holds_at_the_easiest_while_blocks_are_slow() {
+    let params = fast_params();
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::with_params(params.clone()).unwrap();
+
+    common::mine_run(&mut blockchain, &key, INTERVAL * 2, Duration::seconds(4));
+
+    // 이미 가장 쉬운 target이므로 느려도 더 쉬워지지 않는다
+    assert!(
+        blockchain
+            .target_history()
+            .iter()
+            .all(|(_, target)| *target == params.min_target)
+    );
+}
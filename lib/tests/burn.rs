@@ -0,0 +1,123 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+const BURNED: u64 = 1_000_000;
+const FEE: u64 = 1_000;
+
+fn spend(
+    key: &PrivateKey,
+    prev: &TransactionOutput,
+    outputs: Vec<(LockingCondition, u64)>,
+) -> Transaction {
+    let prev_hash = prev.hash();
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        outputs
+            .into_iter()
+            .map(|(lock, value)| TransactionOutput {
+                value,
+                unique_id: Uuid::new_v4(),
+                lock,
+            })
+            .collect(),
+    )
+}
+
+// genesis의 보상 중 BURNED를 소각하는 tx가 mempool에 있는 체인
+fn chain_with_burn(key: &PrivateKey) -> (Blockchain, Transaction) {
+    let genesis = common::coinbase_block(
+        key,
+        0,
+        Hash::zero(),
+        Utc::now() - Duration::seconds(60),
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis.clone()).unwrap();
+
+    let prev = &genesis.transactions[0].outputs[0];
+    let burn = spend(
+        key,
+        prev,
+        vec![
+            (LockingCondition::Unspendable, BURNED),
+            (
+                LockingCondition::P2PK(key.public_key()),
+                prev.value - BURNED - FEE,
+            ),
+        ],
+    );
+    blockchain.add_to_mempool(burn.clone()).unwrap();
+    (blockchain, burn)
+}
+
+fn mined_template(blockchain: &Blockchain, key: &PrivateKey) -> Block {
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    common::mine(&mut block);
+    block
+}
+
+#[test]
+fn burning_reduces_total_supply() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, burn) = chain_with_burn(&key);
+    let block = mined_template(&blockchain, &key);
+    blockchain.add_block(block).unwrap();
+
+    let issued = Blockchain::cumulative_block_reward(2);
+    assert_eq!(blockchain.total_supply(), issued - BURNED);
+    assert_eq!(blockchain.burned_supply(), BURNED);
+
+    let burned_output = burn.outputs[0].hash();
+    assert!(!blockchain.utxos().contains_key(&burned_output));
+
+    // utxo를 다시 계산해도 같다
+    blockchain.rebuild_utxos();
+    assert_eq!(blockchain.total_supply(), issued - BURNED);
+    assert_eq!(blockchain.burned_supply(), BURNED);
+    assert!(!blockchain.utxos().contains_key(&burned_output));
+}
+
+#[test]
+fn burned_output_cannot_be_spent() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, burn) = chain_with_burn(&key);
+    let block = mined_template(&blockchain, &key);
+    blockchain.add_block(block).unwrap();
+
+    let respend = spend(
+        &key,
+        &burn.outputs[0],
+        vec![(LockingCondition::P2PK(key.public_key()), BURNED - FEE)],
+    );
+    assert!(blockchain.add_to_mempool(respend).is_err());
+}
+
+#[test]
+fn coinbase_cannot_reclaim_burned_value() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, _) = chain_with_burn(&key);
+
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    block.transactions[0].outputs[0].value += BURNED;
+    block.header.merkle_root = MerkleRoot::calculate(&block.transactions);
+    common::mine(&mut block);
+
+    assert!(matches!(
+        blockchain.add_block(block),
+        Err(BtcError::InvalidTransaction)
+    ));
+    assert_eq!(blockchain.burned_supply(), 0);
+}
@@ -0,0 +1,292 @@
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const PREV_OUTPUT_VALUE: u64 = 10_000;
+const FEE: u64 = 1_000;
+
+type Utxos = HashMap<Hash, (bool, TransactionOutput)>;
+
+// FEE를 내는 tx 하나와 그 tx가 소비하는 utxo
+fn paying_fee(key: &PrivateKey) -> (Transaction, Utxos) {
+    let prev = TransactionOutput {
+        value: PREV_OUTPUT_VALUE,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    };
+    let prev_hash = prev.hash();
+    let transaction = Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![(key.public_key(), PREV_OUTPUT_VALUE - FEE)],
+    );
+    (transaction, HashMap::from([(prev_hash, (false, prev))]))
+}
+
+fn block(transactions: Vec<Transaction>) -> Block {
+    Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    )
+}
+
+#[test]
+fn coinbase_passes_verification() {
+    let key = PrivateKey::new_key();
+    let (transaction, utxos) = paying_fee(&key);
+
+    for height in [0, 1, btclib::HALVING_INTERVAL * 3 + 7] {
+        let reward = Blockchain::block_reward_at(height);
+        let coinbase =
+            Transaction::coinbase(height, reward, FEE, &key.public_key());
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.outputs[0].value, reward + FEE);
+
+        let block = block(vec![coinbase, transaction.clone()]);
+        block.verify_coinbase_transaction(height, &utxos).unwrap();
+    }
+}
+
+#[test]
+fn coinbase_with_the_wrong_reward_is_rejected() {
+    let key = PrivateKey::new_key();
+    let (transaction, utxos) = paying_fee(&key);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+
+    for (reward, fees) in [(reward + 1, FEE), (reward, FEE + 1)] {
+        let coinbase =
+            Transaction::coinbase(height, reward, fees, &key.public_key());
+        let block = block(vec![coinbase, transaction.clone()]);
+        assert!(block.verify_coinbase_transaction(height, &utxos).is_err());
+    }
+}
+
+#[test]
+fn same_payout_at_different_heights_has_different_outputs() {
+    let key = PrivateKey::new_key().public_key();
+    let a = Transaction::coinbase(1, 100, 0, &key);
+    let b = Transaction::coinbase(2, 100, 0, &key);
+    assert_ne!(a.outputs[0].hash(), b.outputs[0].hash());
+    assert_ne!(a.hash(), b.hash());
+}
+
+#[test]
+fn payouts_are_split_by_share() {
+    let keys: Vec<_> = (0..3).map(|_| PrivateKey::new_key()).collect();
+    let (transaction, utxos) = paying_fee(&keys[0]);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+    let payouts: Vec<_> = keys
+        .iter()
+        .zip([1, 1, 2])
+        .map(|(key, share)| (key.public_key(), share))
+        .collect();
+
+    let coinbase =
+        Transaction::coinbase_with_payouts(height, reward, FEE, &payouts)
+            .unwrap();
+    let total = reward + FEE;
+    let values: Vec<u64> =
+        coinbase.outputs.iter().map(|output| output.value).collect();
+    // 자투리는 첫 번째 pubkey에게 간다
+    assert_eq!(values[1], total / 4);
+    assert_eq!(values[2], total / 2);
+    assert_eq!(values.iter().sum::<u64>(), total);
+
+    let block = block(vec![coinbase, transaction]);
+    block.verify_coinbase_transaction(height, &utxos).unwrap();
+
+    let no_payouts: &[_] = &[];
+    assert!(
+        Transaction::coinbase_with_payouts(height, reward, FEE, no_payouts)
+            .is_err()
+    );
+    let zero_share = [(keys[0].public_key(), 0)];
+    assert!(
+        Transaction::coinbase_with_payouts(height, reward, FEE, &zero_share)
+            .is_err()
+    );
+}
+
+#[test]
+fn two_way_split_must_still_sum_to_reward_and_fees() {
+    let keys: Vec<_> = (0..2).map(|_| PrivateKey::new_key()).collect();
+    let (transaction, utxos) = paying_fee(&keys[0]);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+    let payouts: Vec<_> =
+        keys.iter().map(|key| (key.public_key(), 1)).collect();
+
+    let coinbase =
+        Transaction::coinbase_with_payouts(height, reward, FEE, &payouts)
+            .unwrap();
+    assert_eq!(coinbase.outputs.len(), 2);
+    for (output, key) in coinbase.outputs.iter().zip(&keys) {
+        assert!(matches!(
+            &output.lock,
+            LockingCondition::P2PK(pubkey) if *pubkey == key.public_key()
+        ));
+    }
+    block(vec![coinbase.clone(), transaction.clone()])
+        .verify_coinbase_transaction(height, &utxos)
+        .unwrap();
+
+    // 한쪽 몫을 바꿔서 합계가 보상과 수수료보다 많거나 적으면 거부된다
+    let share = coinbase.outputs[1].value;
+    for wrong in [share + 1, share - 1] {
+        let mut split = coinbase.clone();
+        split.outputs[1].value = wrong;
+        let block = block(vec![split, transaction.clone()]);
+        assert!(matches!(
+            block.verify_coinbase_transaction(height, &utxos),
+            Err(BtcError::InvalidTransaction)
+        ));
+    }
+}
+
+#[test]
+fn block_whose_coinbase_spends_an_input_is_rejected() {
+    let key = PrivateKey::new_key();
+    let genesis_coinbase = Transaction::coinbase(
+        0,
+        Blockchain::block_reward_at(0),
+        0,
+        &key.public_key(),
+    );
+    let prev = genesis_coinbase.outputs[0].clone();
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(block(vec![genesis_coinbase])).unwrap();
+
+    // 템플릿을 거치지 않고 만든 블록. coinbase가 보상과 함께 기존 utxo까지 가져간다
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    let prev_hash = prev.hash();
+    let coinbase = &mut block.transactions[0];
+    coinbase.inputs.push(TransactionInput::new(
+        prev_hash,
+        Signature::sign_output(&prev_hash, &key),
+    ));
+    coinbase.outputs[0].value += prev.value;
+    block.header.merkle_root = MerkleRoot::calculate(&block.transactions);
+    while !block.header.mine(1_000_000) {}
+
+    assert!(matches!(
+        blockchain.add_block(block.clone()),
+        Err(BtcError::InvalidCoinbase)
+    ));
+    assert!(matches!(
+        block.verify_coinbase_transaction(1, blockchain.utxos()),
+        Err(BtcError::InvalidCoinbase)
+    ));
+    assert_eq!(blockchain.block_height(), 1);
+    assert!(!blockchain.utxos()[&prev_hash].0);
+}
+
+#[test]
+fn coinbase_with_a_zero_value_output_is_rejected() {
+    let key = PrivateKey::new_key();
+    let (transaction, utxos) = paying_fee(&key);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+
+    // 합계는 맞지만 값이 0인 output이 있다
+    let mut coinbase =
+        Transaction::coinbase(height, reward, FEE, &key.public_key());
+    let mut empty = coinbase.outputs[0].clone();
+    empty.value = 0;
+    empty.unique_id = Uuid::new_v4();
+    coinbase.outputs.push(empty);
+
+    let block = block(vec![coinbase, transaction]);
+    assert!(matches!(
+        block.verify_coinbase_transaction(height, &utxos),
+        Err(BtcError::InvalidTransactionOutput)
+    ));
+}
+
+#[test]
+fn coinbase_claiming_more_fees_than_paid_is_rejected() {
+    let key = PrivateKey::new_key();
+    let (transaction, utxos) = paying_fee(&key);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+
+    for claimed in [FEE + 1, FEE * 10, u64::MAX - reward] {
+        let coinbase =
+            Transaction::coinbase(height, reward, claimed, &key.public_key());
+        let block = block(vec![coinbase, transaction.clone()]);
+        assert!(matches!(
+            block.verify_coinbase_transaction(height, &utxos),
+            Err(BtcError::InvalidTransaction)
+        ));
+    }
+
+    // 수수료가 없는 블록이 수수료를 가져가려 한다
+    let coinbase = Transaction::coinbase(height, reward, 1, &key.public_key());
+    assert!(matches!(
+        block(vec![coinbase]).verify_coinbase_transaction(height, &utxos),
+        Err(BtcError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn zero_input_transaction_without_outputs_is_not_a_coinbase() {
+    let key = PrivateKey::new_key();
+    let (transaction, utxos) = paying_fee(&key);
+    let height = 1;
+    let reward = Blockchain::block_reward_at(height);
+    let coinbase =
+        Transaction::coinbase(height, reward, FEE, &key.public_key());
+    assert!(coinbase.is_coinbase());
+    assert!(!transaction.is_coinbase());
+
+    // input도 output도 없는 tx는 아무것도 지급하지 않으므로 coinbase가 아니다
+    let malformed = Transaction::new(vec![], vec![]);
+    assert!(!malformed.is_coinbase());
+    let without_coinbase = block(vec![malformed.clone(), transaction.clone()]);
+    assert!(matches!(
+        without_coinbase.verify_transactions(height, &utxos),
+        Err(BtcError::InvalidCoinbase)
+    ));
+
+    // 첫 번째 자리가 아닌 coinbase는 두 번째 coinbase로 보고 거부한다
+    let second = Transaction::coinbase(height, 1, 0, &key.public_key());
+    let two_coinbases = block(vec![coinbase, transaction, second.clone()]);
+    assert!(matches!(
+        two_coinbases.verify_transactions(height, &utxos),
+        Err(BtcError::InvalidTransaction)
+    ));
+
+    // mempool에는 어느 쪽도 들어가지 않는다
+    let mut blockchain = Blockchain::new();
+    blockchain
+        .add_block(block(vec![Transaction::coinbase(
+            0,
+            Blockchain::block_reward_at(0),
+            0,
+            &key.public_key(),
+        )]))
+        .unwrap();
+    assert!(matches!(
+        blockchain.add_to_mempool(second),
+        Err(BtcError::InvalidTransactionInput)
+    ));
+    assert!(blockchain.add_to_mempool(malformed).is_err());
+    assert!(blockchain.mempool().is_empty());
+}
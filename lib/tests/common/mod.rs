@@ -0,0 +1,124 @@
+// 여러 test에서 쓰는 체인 생성 도구
+#![allow(dead_code)]
+
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionOutput,
+};
+use btclib::util::{MerkleRoot, Savable};
+use btclib::{MIN_TARGET, U256};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// target_history가 생기기 전의 Blockchain 저장 형식
+#[derive(Serialize)]
+struct StoredChain<'a> {
+    utxos: HashMap<Hash, (bool, TransactionOutput)>,
+    target: U256,
+    blocks: &'a [Block],
+}
+
+// key에게 지급하는 value짜리 P2PK output
+pub fn output(key: &PrivateKey, value: u64) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    }
+}
+
+// key에게 보상만 지급하는 채굴하지 않은 블록
+pub fn coinbase_block(
+    key: &PrivateKey,
+    height: u64,
+    prev_block_hash: Hash,
+    timestamp: DateTime<Utc>,
+) -> Block {
+    let transactions = vec![Transaction::new(
+        vec![],
+        vec![output(key, Blockchain::block_reward_at(height))],
+    )];
+    Block::new(
+        BlockHeader::new(
+            timestamp,
+            0,
+            prev_block_hash,
+            MerkleRoot::calculate(&transactions),
+            MIN_TARGET,
+        ),
+        transactions,
+    )
+}
+
+// blocks 뒤에 interval초 간격으로 count개의 블록을 이어 붙인다.
+// 비어 있다면 마지막 블록이 지금쯤 되도록 genesis부터 만든다
+pub fn extend(
+    key: &PrivateKey,
+    blocks: &mut Vec<Block>,
+    count: u64,
+    interval: i64,
+) {
+    let mut timestamp = blocks.last().map_or(
+        Utc::now() - Duration::seconds(count as i64 * interval),
+        |prev| prev.header.timestamp,
+    );
+    for _ in 0..count {
+        let height = blocks.len() as u64;
+        let prev_block_hash =
+            blocks.last().map_or(Hash::zero(), |prev| prev.hash());
+        timestamp += Duration::seconds(interval);
+        blocks.push(coinbase_block(key, height, prev_block_hash, timestamp));
+    }
+}
+
+// 블록들만 담긴, target_history와 hash 인코딩 버전이 생기기 전 형식의 체인 파일
+pub fn stored_chain(blocks: &[Block]) -> Vec<u8> {
+    let mut serialized = vec![];
+    ciborium::into_writer(
+        &StoredChain {
+            utxos: HashMap::new(),
+            target: MIN_TARGET,
+            blocks,
+        },
+        &mut serialized,
+    )
+    .unwrap();
+    serialized
+}
+
+// 검증하지 않고 파일에서 읽은 것처럼 블록들로 체인을 만든다.
+// add_block은 PoW를 요구하므로 test에서 블록마다 채굴하지 않기 위함이다.
+// 읽은 직후처럼 index는 비어 있으므로 필요하면 rebuild_indexes를 부른다
+pub fn load_unverified(blocks: &[Block]) -> Blockchain {
+    Blockchain::load(stored_chain(blocks).as_slice()).unwrap()
+}
+
+// header의 target을 만족할 때까지 채굴한다
+pub fn mine(block: &mut Block) {
+    while !block.header.mine(1_000_000) {}
+}
+
+// prev 다음에 spacing 간격으로 채굴된 블록을 count개 추가한다.
+// 각 블록은 노드가 만드는 템플릿을 그대로 채굴한 것이다
+pub fn mine_run(
+    blockchain: &mut Blockchain,
+    key: &PrivateKey,
+    count: u64,
+    spacing: Duration,
+) {
+    for _ in 0..count {
+        let mut block = blockchain.build_template(key.public_key()).unwrap();
+        block.header.timestamp = blockchain
+            .blocks_rev()
+            .next()
+            .map_or(Utc::now() - Duration::hours(1), |prev| {
+                prev.header.timestamp + spacing
+            });
+        mine(&mut block);
+        blockchain.add_block(block).unwrap();
+    }
+}
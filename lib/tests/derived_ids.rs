@@ -0,0 +1,33 @@
+// Transaction::with_derived_ids로 만든 tx는 같은 input과 output이라면 항상 같은 hash를 갖는지 확인한다
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::sha256::Hash;
+use btclib::types::{Transaction, TransactionInput};
+
+fn input(key: &PrivateKey, prev: &[u8]) -> TransactionInput {
+    let prev_hash = Hash::hash_bytes(prev);
+    TransactionInput::new(prev_hash, Signature::sign_output(&prev_hash, key))
+}
+
+#[test]
+fn same_inputs_and_outputs_build_identical_transactions() {
+    let key = PrivateKey::new_key();
+    let build = |prev: &[u8]| {
+        Transaction::with_derived_ids(
+            vec![input(&key, prev)],
+            vec![(key.public_key(), 1_000), (key.public_key(), 1_000)],
+        )
+    };
+
+    let first = build(b"prev output");
+    let second = build(b"prev output");
+    assert_eq!(first.hash(), second.hash());
+    assert_eq!(first.wtxid(), second.wtxid());
+    assert_eq!(first.outputs[0].hash(), second.outputs[0].hash());
+
+    // 같은 pubkey와 값이라도 위치가 다르면 다른 output이다
+    assert_ne!(first.outputs[0].unique_id, first.outputs[1].unique_id);
+    assert_ne!(first.outputs[0].hash(), first.outputs[1].hash());
+
+    // 다른 output을 소비하면 다른 tx다
+    assert_ne!(build(b"other output").hash(), first.hash());
+}
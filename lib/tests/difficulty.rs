@@ -0,0 +1,137 @@
+// ChainParams::adjust_target의 성질을 무작위 입력으로 확인한다.
+// 실패하면 proptest가 가장 작은 반례로 줄여서 보여준다
+use btclib::params::ChainParams;
+use btclib::{MIN_DIFFICULTY_TARGET, MIN_TARGET, U256};
+use chrono::Duration;
+use proptest::prelude::*;
+
+// 조정 구간이 걸려야 하는 시간
+const TARGET_SECONDS: i64 =
+    (btclib::IDEAL_BLOCK_TIME * btclib::DIFFICULTY_UPDATE_INTERVAL) as i64;
+
+// [MIN_DIFFICULTY_TARGET, MIN_TARGET] 범위의 target
+fn target() -> impl Strategy<Value = U256> {
+    any::<[u64; 4]>().prop_map(|words| {
+        let span = MIN_TARGET - MIN_DIFFICULTY_TARGET + U256::one();
+        U256(words) % span + MIN_DIFFICULTY_TARGET
+    })
+}
+
+// 조정 구간의 블록 간격들. 음수는 timestamp가 역전된 경우
+fn block_times() -> impl Strategy<Value = Vec<i64>> {
+    prop::collection::vec(
+        -60i64..600,
+        btclib::DIFFICULTY_UPDATE_INTERVAL as usize - 1,
+    )
+}
+
+// 한 구간 전체가 걸린 시간. 극단적인 경우도 포함한다
+fn timespan() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        block_times().prop_map(|times| times.iter().sum()),
+        -1_000_000_000i64..1_000_000_000_000,
+    ]
+}
+
+fn adjust(target: U256, seconds: i64) -> U256 {
+    ChainParams::default().adjust_target(target, Duration::seconds(seconds))
+}
+
+proptest! {
+    #[test]
+    fn stays_within_bounds(target in target(), seconds in timespan()) {
+        let new_target = adjust(target, seconds);
+
+        prop_assert!(new_target >= MIN_DIFFICULTY_TARGET);
+        prop_assert!(new_target <= MIN_TARGET);
+        // 25% ~ 400% clamp
+        prop_assert!(new_target >= (target / 4).max(MIN_DIFFICULTY_TARGET));
+        prop_assert!(
+            new_target <= target.saturating_mul(U256::from(4)).min(MIN_TARGET)
+        );
+    }
+
+    #[test]
+    fn moves_in_the_right_direction(target in target(), seconds in timespan()) {
+        let new_target = adjust(target, seconds);
+
+        // 빨리 채굴되었다면 어려워지고 (target이 낮아지고), 느렸다면 쉬워진다
+        if seconds < TARGET_SECONDS {
+            prop_assert!(new_target <= target);
+        } else if seconds > TARGET_SECONDS {
+            prop_assert!(new_target >= target);
+        } else {
+            prop_assert_eq!(new_target, target);
+        }
+    }
+
+    #[test]
+    fn is_monotonic_in_timespan(
+        target in target(),
+        a in timespan(),
+        b in timespan(),
+    ) {
+        let (faster, slower) = (a.min(b), a.max(b));
+        let faster = adjust(target, faster);
+        let slower = adjust(target, slower);
+
+        prop_assert!(faster <= slower);
+    }
+}
+
+#[test]
+fn repeated_fast_adjustments_clamp_at_the_floor() {
+    // 매 구간이 0초 만에 채굴되어도 target은 0이 되지 않고 하한에 머문다
+    let mut target = MIN_TARGET;
+    for _ in 0..200 {
+        let new_target = adjust(target, 0);
+        assert!(new_target >= MIN_DIFFICULTY_TARGET);
+        assert!(new_target <= target);
+        target = new_target;
+    }
+    assert_eq!(target, MIN_DIFFICULTY_TARGET);
+    assert_eq!(adjust(target, -1_000), MIN_DIFFICULTY_TARGET);
+
+    // 하한에서도 느리게 채굴되면 다시 쉬워진다
+    assert!(adjust(target, TARGET_SECONDS * 2) > MIN_DIFFICULTY_TARGET);
+}
+
+#[test]
+fn slow_adjustment_at_the_easiest_target_saturates() {
+    // 가장 쉬운 target에서 느리게 채굴되어도 더 쉬워지지 않는다
+    for seconds in [TARGET_SECONDS * 2, TARGET_SECONDS * 100, i64::MAX / 1_000]
+    {
+        assert_eq!(adjust(MIN_TARGET, seconds), MIN_TARGET);
+    }
+
+    // min_target이 U256의 끝이라면 target * 4는 U256 범위를 넘는다.
+    // overflow로 panic 하거나 작은 값으로 감기지 않고 min_target에 머문다
+    let params = ChainParams {
+        min_target: U256::MAX,
+        ..ChainParams::default()
+    };
+    let slow = Duration::seconds(TARGET_SECONDS * 100);
+    for target in [U256::MAX, U256::MAX / 2, U256::MAX / 4 + U256::one()] {
+        assert_eq!(params.adjust_target(target, slow), U256::MAX);
+    }
+    // 4배가 범위 안이라면 그대로 4배까지만 쉬워진다
+    let target = U256::MAX / 8;
+    assert_eq!(params.adjust_target(target, slow), target * 4);
+}
+
+#[test]
+fn min_target_harder_than_the_floor_does_not_panic() {
+    // ChainParams의 필드는 공개되어 있으므로 하한보다 어려운 min_target도 만들 수 있다
+    let min_target = MIN_DIFFICULTY_TARGET / 2;
+    let params = ChainParams {
+        min_target,
+        ..ChainParams::default()
+    };
+    for seconds in [0, TARGET_SECONDS, TARGET_SECONDS * 100] {
+        let time_diff = Duration::seconds(seconds);
+        for target in [min_target, min_target / 4, U256::one()] {
+            // 하한보다 쉬워질 수 없으므로 min_target에 머문다
+            assert_eq!(params.adjust_target(target, time_diff), min_target);
+        }
+    }
+}
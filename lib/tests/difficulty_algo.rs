@@ -0,0 +1,130 @@
+// hashrate가 갑자기 바뀌었을 때 난이도 조정 알고리즘들이 어떻게 따라가는지 흉내 낸다.
+// 블록 시간은 target과 hashrate로 정해지는 평균을 갖는 지수 분포에서 뽑는다
+use btclib::U256;
+use btclib::difficulty::{DifficultyAlgo, Ema, SimpleRatio};
+use btclib::params::ChainParams;
+use chrono::Duration;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+
+const INTERVAL: u64 = 20;
+// hashrate가 바뀌기 전과, 바뀐 뒤 자리를 잡을 때까지 흘려보내는 구간 수
+const WARMUP_INTERVALS: usize = 40;
+// 진폭을 재는 구간 수
+const MEASURED_INTERVALS: usize = 200;
+// hashrate가 몇 배로 뛰는지
+const HASHRATE_STEP: f64 = 8.0;
+
+fn params(algo: Arc<dyn DifficultyAlgo>) -> ChainParams {
+    ChainParams {
+        difficulty_update_interval: INTERVAL,
+        difficulty_algo: algo,
+        ..ChainParams::default()
+    }
+}
+
+fn to_f64(target: U256) -> f64 {
+    (target >> 128).low_u128() as f64
+}
+
+// hashrate가 HASHRATE_STEP배로 뛴 뒤 구간마다 ln(target / 평형 target)을 기록한다.
+// 평형 target은 블록 시간이 정확히 ideal_block_time이 되는 target이다
+fn simulate(params: &ChainParams, seed: u64) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let ideal = params.ideal_block_time as f64;
+    let equilibrium_before = to_f64(params.min_target) / 16.0;
+    let equilibrium_after = equilibrium_before / HASHRATE_STEP;
+
+    let mut target = params.min_target / 16;
+    let mut deviations = vec![];
+    for step in 0..WARMUP_INTERVALS * 2 + MEASURED_INTERVALS {
+        let equilibrium = if step < WARMUP_INTERVALS {
+            equilibrium_before
+        } else {
+            equilibrium_after
+        };
+        // target이 쉬울수록(클수록) 블록이 빨리 나온다
+        let mean = ideal * equilibrium / to_f64(target);
+        let timespan: f64 = (0..INTERVAL)
+            .map(|_| -mean * (1.0 - rng.r#gen::<f64>()).ln())
+            .sum();
+
+        target = params.adjust_target(
+            target,
+            Duration::milliseconds((timespan * 1000.0) as i64),
+        );
+        if step >= WARMUP_INTERVALS * 2 {
+            deviations.push((to_f64(target) / equilibrium_after).ln());
+        }
+    }
+    deviations
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// 평형 target 주위로 흔들리는 정도
+fn amplitude(deviations: &[f64]) -> f64 {
+    let mean = mean(deviations);
+    let variance = deviations.iter().map(|d| (d - mean).powi(2)).sum::<f64>()
+        / deviations.len() as f64;
+    variance.sqrt()
+}
+
+#[test]
+fn ema_oscillates_less_than_simple_ratio() {
+    let simple = params(Arc::new(SimpleRatio));
+    let ema = params(Arc::new(Ema::default()));
+
+    for seed in 0..5 {
+        let simple = simulate(&simple, seed);
+        let ema = simulate(&ema, seed);
+
+        // 둘 다 새 hashrate의 평형 target 근처로 자리를 잡는다
+        assert!(mean(&simple).abs() < 0.25, "simple ratio: {}", mean(&simple));
+        assert!(mean(&ema).abs() < 0.25, "ema: {}", mean(&ema));
+
+        let (simple, ema) = (amplitude(&simple), amplitude(&ema));
+        assert!(
+            ema < simple / 2.0,
+            "seed {seed}: ema amplitude {ema}, simple ratio amplitude {simple}"
+        );
+    }
+}
+
+#[test]
+fn ema_with_a_window_of_one_is_simple_ratio() {
+    let simple = params(Arc::new(SimpleRatio));
+    let ema = params(Arc::new(Ema {
+        window: 1,
+    }));
+
+    for seconds in [1, 60, 199, 200, 201, 777, 5_000] {
+        let timespan = Duration::seconds(seconds);
+        let target = btclib::MIN_TARGET / 3;
+        assert_eq!(
+            ema.adjust_target(target, timespan),
+            simple.adjust_target(target, timespan)
+        );
+    }
+}
+
+#[test]
+fn ema_moves_part_of_the_way() {
+    let params = params(Arc::new(Ema {
+        window: 4,
+    }));
+    let target = btclib::MIN_TARGET / 16;
+    let expected = (params.ideal_block_time * INTERVAL) as i64;
+
+    // 구간이 절반의 시간에 끝났다면 SimpleRatio는 1/2배, window가 4인 EMA는 7/8배가 된다
+    let faster = params.adjust_target(target, Duration::seconds(expected / 2));
+    assert_eq!(faster, target * 7 / 8);
+    // 기대한 시간만큼 걸렸다면 그대로다
+    assert_eq!(
+        params.adjust_target(target, Duration::seconds(expected)),
+        target
+    );
+}
@@ -0,0 +1,102 @@
+// difficulty_info의 남은 블록 수가 블록마다 줄고 조정 지점에서 다시 시작하며,
+// 다음 target 추정은 조정 지점이 아니라 구간에 블록이 두 개 이상 쌓인 동안 나오는지 확인한다
+mod common;
+
+use btclib::U256;
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::params::ChainParams;
+use btclib::types::Blockchain;
+use chrono::Duration;
+
+const INTERVAL: u64 = 4;
+
+// 블록 시간 2초, 4블록마다 조정하고 채굴이 금방 끝나는 쉬운 target을 쓰는 체인
+fn fast_params() -> ChainParams {
+    ChainParams {
+        ideal_block_time: 2,
+        difficulty_update_interval: INTERVAL,
+        min_target: U256::MAX >> 4,
+        ..ChainParams::default()
+    }
+}
+
+#[test]
+fn countdown_restarts_at_each_adjustment() {
+    let params = fast_params();
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::with_params(params.clone()).unwrap();
+
+    let info = blockchain.difficulty_info();
+    assert_eq!(info.blocks_until_adjustment, INTERVAL);
+    assert_eq!(info.current_target, params.min_target);
+    assert_eq!(info.difficulty, 1.0);
+
+    let mut countdown = vec![];
+    for _ in 0..INTERVAL * 2 + 1 {
+        common::mine_run(&mut blockchain, &key, 1, Duration::seconds(1));
+        countdown.push(blockchain.difficulty_info().blocks_until_adjustment);
+    }
+    assert_eq!(countdown, vec![3, 2, 1, 4, 3, 2, 1, 4, 3]);
+}
+
+#[test]
+fn estimate_is_given_mid_window_not_at_the_boundary() {
+    let params = fast_params();
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::with_params(params.clone()).unwrap();
+
+    // 조정 구간에 블록이 하나 이하라면 간격을 알 수 없다
+    assert_eq!(blockchain.difficulty_info().estimated_next_target, None);
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(1));
+    assert_eq!(blockchain.difficulty_info().estimated_next_target, None);
+
+    // 블록 시간의 절반 간격이 유지된다면 조정 후 target은 3/8배다
+    let expected = params.min_target * 3 / 8;
+    for _ in 0..INTERVAL - 2 {
+        common::mine_run(&mut blockchain, &key, 1, Duration::seconds(1));
+        let info = blockchain.difficulty_info();
+        assert_eq!(info.current_target, params.min_target);
+        assert_eq!(info.estimated_next_target, Some(expected));
+    }
+
+    // 조정 지점에서는 추정했던 target이 실제로 적용되고 새 구간은 비어 있다
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(1));
+    let info = blockchain.difficulty_info();
+    assert_eq!(info.blocks_until_adjustment, INTERVAL);
+    assert_eq!(info.current_target, expected);
+    assert_eq!(info.estimated_next_target, None);
+    assert!(info.difficulty > 2.6 && info.difficulty < 2.7);
+}
+
+#[test]
+fn zero_interval_or_block_time_is_rejected() {
+    // 조정 구간이 0이면 height % interval이 0으로 나누게 된다
+    let zero_interval = ChainParams {
+        difficulty_update_interval: 0,
+        ..fast_params()
+    };
+    assert!(matches!(
+        Blockchain::with_params(zero_interval.clone()),
+        Err(BtcError::InvalidChainParams)
+    ));
+    let zero_block_time = ChainParams {
+        ideal_block_time: 0,
+        ..fast_params()
+    };
+    assert!(matches!(
+        Blockchain::with_params(zero_block_time),
+        Err(BtcError::InvalidChainParams)
+    ));
+
+    // 읽어 온 체인의 파라미터를 바꿀 때도 같고, 기존 파라미터는 그대로 남는다
+    let mut blockchain = Blockchain::new();
+    assert!(matches!(
+        blockchain.set_params(zero_interval),
+        Err(BtcError::InvalidChainParams)
+    ));
+    assert_eq!(
+        blockchain.difficulty_info().blocks_until_adjustment,
+        btclib::DIFFICULTY_UPDATE_INTERVAL
+    );
+}
@@ -0,0 +1,94 @@
+// 한 블록 안에서 서로 다른 tx가 같은 output을 소비하는 경우
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const PREV_OUTPUT_VALUE: u64 = 10_000;
+const FEE: u64 = 1_000;
+const HEIGHT: u64 = 1;
+
+type Utxos = HashMap<Hash, (bool, TransactionOutput)>;
+
+fn spend(key: &PrivateKey, prev_hash: Hash) -> Transaction {
+    Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![(key.public_key(), PREV_OUTPUT_VALUE - FEE)],
+    )
+}
+
+// 같은 utxo를 소비하는 두 tx와 coinbase가 claimed_fees를 가져가는 블록
+fn double_spending_block(claimed_fees: u64) -> (Block, Utxos) {
+    let key = PrivateKey::new_key();
+    let prev = TransactionOutput {
+        value: PREV_OUTPUT_VALUE,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    };
+    let prev_hash = prev.hash();
+    let utxos = HashMap::from([(prev_hash, (false, prev))]);
+
+    // 둘 다 유효한 서명을 가진, output만 다른 tx
+    let first = spend(&key, prev_hash);
+    let mut second = spend(&key, prev_hash);
+    second.outputs[0].value -= 1;
+    assert_ne!(first.hash(), second.hash());
+
+    let reward = Blockchain::block_reward_at(HEIGHT);
+    let coinbase =
+        Transaction::coinbase(HEIGHT, reward, claimed_fees, &key.public_key());
+    let transactions = vec![coinbase, first, second];
+    let block = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    (block, utxos)
+}
+
+#[test]
+fn double_spend_across_transactions_is_rejected_before_fees_are_counted() {
+    // 수수료를 두 번 센 만큼 가져가든, 한 번만 가져가든 이중 지출로 거부된다
+    for claimed_fees in [FEE * 2 + 1, FEE] {
+        let (block, utxos) = double_spending_block(claimed_fees);
+
+        assert!(matches!(
+            block.calculate_miner_fees(&utxos),
+            Err(BtcError::DoubleSpend)
+        ));
+        assert!(matches!(
+            block.verify_coinbase_transaction(HEIGHT, &utxos),
+            Err(BtcError::DoubleSpend)
+        ));
+        assert!(matches!(
+            block.verify_transactions(HEIGHT, &utxos),
+            Err(BtcError::DoubleSpend)
+        ));
+    }
+}
+
+#[test]
+fn fees_are_summed_per_transaction() {
+    let (mut block, utxos) = double_spending_block(FEE);
+    // 두 번째 tx를 빼면 정상적인 블록이다
+    block.transactions.pop();
+    block.header.merkle_root = MerkleRoot::calculate(&block.transactions);
+
+    assert_eq!(block.calculate_miner_fees(&utxos).unwrap(), FEE);
+    block.verify_transactions(HEIGHT, &utxos).unwrap();
+}
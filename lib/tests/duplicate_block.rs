@@ -0,0 +1,41 @@
+// 이미 체인에 있는 블록을 다시 추가하면 DuplicateBlock으로 거부하고 체인은 그대로인지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use chrono::Duration;
+
+fn to_bytes(blockchain: &Blockchain) -> Vec<u8> {
+    let mut bytes = vec![];
+    blockchain.save(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn submitting_the_tip_twice_is_a_duplicate() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 3, Duration::seconds(10));
+    let before = to_bytes(&blockchain);
+    let tip = blockchain.blocks_rev().next().unwrap().clone();
+    let genesis = blockchain.blocks().next().unwrap().clone();
+
+    for block in [tip.clone(), tip.clone(), genesis] {
+        assert!(matches!(
+            blockchain.add_block(block),
+            Err(BtcError::DuplicateBlock)
+        ));
+    }
+    assert_eq!(to_bytes(&blockchain), before);
+    assert_eq!(blockchain.height(), Some(2));
+    assert_eq!(blockchain.tip_hash(), Some(tip.hash()));
+    assert_eq!(blockchain.blocks().count(), 3);
+
+    // 다음 블록은 그대로 tip 뒤에 이어진다
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    assert_eq!(blockchain.height(), Some(3));
+    let next = blockchain.blocks_rev().next().unwrap();
+    assert_eq!(next.header.prev_block_hash, tip.hash());
+}
@@ -0,0 +1,145 @@
+// hash에 쓰는 고정 인코딩(canonical_bytes)이 바뀌지 않았는지 확인한다.
+// 값이 바뀌었다면 저장된 모든 체인의 hash가 바뀐 것이므로 HASH_ENCODING_VERSION을 올려야 한다.
+// 블록 id(Block::hash)는 아직 serde 직렬화에 기대고 있으므로 그 값도 함께 고정한다
+mod common;
+
+use btclib::MIN_TARGET;
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::{MerkleRoot, Savable};
+use chrono::DateTime;
+use ecdsa::SigningKey;
+use std::io::ErrorKind;
+use uuid::Uuid;
+
+const TRANSACTION_BYTES: &str = concat!(
+    // version
+    "01",
+    // tag
+    "54",
+    // input 수
+    "00000001",
+    // prev_transaction_output_hash
+    "625873fcae13b417fec24d7c4c51cb8f4d89dc7634bff1f7aee83df843d911fb",
+    // output 수
+    "00000001",
+    // value
+    "0000000000001388",
+    // unique_id
+    "09090909090909090909090909090909",
+    // P2PK
+    "00",
+    // public key 길이
+    "00000021",
+    // compressed public key
+    "02989c0b76cb563971fdc9bef31ec06c3560f3249d6ee9e5d83c57625596e05f",
+    "6f",
+);
+const TXID: &str =
+    "c72d8703c06bd7d81ae8ec8e904b12657a14def9c4ee98bda0877627f1b9b6fe";
+const WTXID: &str =
+    "b988417f9a30dfa3d1774444d6990aa2e8542972efef1ce30fe9b4e7b9ccaee9";
+const HEADER_BYTES: &str = concat!(
+    // version
+    "01",
+    // tag
+    "48",
+    // timestamp
+    "000000006553f100",
+    // nanos
+    "0000007b",
+    // nonce
+    "000000000000002a",
+    // prev_block_hash
+    "0eee47151fdc8714518b77f7002bc0872531d6832ce5dbbb9481f47a9b6e0aa5",
+    // merkle_root
+    "e9aeccb9e7b4e90fe31cefef722954e8a20a99d6444477d1a3df309a7f4188b9",
+    // target
+    "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000",
+);
+const HEADER_HASH: &str =
+    "65c258d0ea2b1123979edb57ffa09697f5c438f3a97a81bf2c11bd603fde3c46";
+const BLOCK_HASH: &str =
+    "270c2b7c8e9b5eca2e6cb4d26b41c4f0a61a373b8774c9d5a35359b554a3b25c";
+
+// 서명은 RFC 6979로 결정되므로 key가 같다면 항상 같은 tx가 만들어진다
+fn transaction() -> Transaction {
+    let key = PrivateKey(SigningKey::from_slice(&[7; 32]).unwrap());
+    let prev_hash = Hash::hash_bytes(b"prev output");
+    let mut input = TransactionInput::new(
+        prev_hash,
+        Signature::sign_output(&prev_hash, &key),
+    );
+    input.sequence = 1;
+    Transaction::new(
+        vec![input],
+        vec![TransactionOutput {
+            value: 5_000,
+            unique_id: Uuid::from_bytes([9; 16]),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    )
+}
+
+fn header() -> BlockHeader {
+    BlockHeader::new(
+        DateTime::from_timestamp(1_700_000_000, 123).unwrap(),
+        42,
+        Hash::hash_bytes(b"prev block"),
+        MerkleRoot::calculate(&[transaction()]),
+        MIN_TARGET,
+    )
+}
+
+#[test]
+fn transaction_encoding_is_pinned() {
+    let transaction = transaction();
+    assert_eq!(hex::encode(transaction.canonical_bytes()), TRANSACTION_BYTES);
+    assert_eq!(transaction.hash().to_string(), TXID);
+    assert_eq!(transaction.wtxid().to_string(), WTXID);
+}
+
+#[test]
+fn header_encoding_is_pinned() {
+    let header = header();
+    assert_eq!(hex::encode(header.canonical_bytes()), HEADER_BYTES);
+    assert_eq!(header.hash().to_string(), HEADER_HASH);
+}
+
+// 블록 id는 header hash가 아니라 블록 전체의 serde 직렬화로 정해진다.
+// 값이 바뀌었다면 저장된 체인의 prev_block_hash와 checkpoint가 더 이상 맞지 않는다
+#[test]
+fn block_hash_is_pinned() {
+    let block = Block::new(header(), vec![transaction()]);
+    assert_eq!(block.hash().to_string(), BLOCK_HASH);
+    assert_ne!(block.hash(), block.header.hash());
+}
+
+// 이전 인코딩으로 만든 체인은 검증 도중이 아니라 읽을 때 거부된다
+#[test]
+fn chain_from_another_hash_encoding_is_refused_on_load() {
+    let key = PrivateKey::new_key();
+    let mut blocks = vec![];
+    common::extend(&key, &mut blocks, 3, 10);
+
+    // 버전이 기록되기 전의 파일이라도 지금 인코딩으로 만든 체인은 그대로 읽는다
+    let blockchain = common::load_unverified(&blocks);
+    let mut saved = vec![];
+    blockchain.save(&mut saved).unwrap();
+    Blockchain::load(saved.as_slice()).unwrap();
+
+    // 이전 인코딩의 wtxid로 계산한 merkle root는 지금 인코딩과 맞지 않는다
+    blocks[0].header.merkle_root =
+        MerkleRoot::calculate(&blocks[1].transactions);
+    let error = match Blockchain::load(common::stored_chain(&blocks).as_slice())
+    {
+        Ok(_) => panic!("chain with another hash encoding was loaded"),
+        Err(error) => error,
+    };
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("hash encoding v0"), "{error}");
+}
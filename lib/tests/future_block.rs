@@ -0,0 +1,68 @@
+// 로컬 시계보다 MAX_FUTURE_BLOCK_TIME 안쪽으로 앞선 블록은 받고 그보다 앞선 블록은
+// BlockFromFuture로 거부하되, 이미 받아 저장해 둔 체인을 다시 검증할 때는 거부하지 않는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::types::{Block, Blockchain};
+use chrono::{Duration, Utc};
+
+// 로컬 시계와 블록 timestamp 사이의 여유. test가 도는 동안 시계가 흘러도 결과가 바뀌지 않는다
+const MARGIN_MINUTES: i64 = 5;
+
+fn max_future() -> Duration {
+    Duration::seconds(btclib::MAX_FUTURE_BLOCK_TIME as i64)
+}
+
+// tip 다음에 timestamp를 바꿔 채굴한 블록
+fn block_at(
+    blockchain: &Blockchain,
+    key: &PrivateKey,
+    ahead: Duration,
+) -> Block {
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    block.header.timestamp = Utc::now() + ahead;
+    common::mine(&mut block);
+    block
+}
+
+#[test]
+fn block_within_the_drift_limit_is_accepted() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+
+    let ahead = max_future() - Duration::minutes(MARGIN_MINUTES);
+    let block = block_at(&blockchain, &key, ahead);
+    blockchain.add_block(block.clone()).unwrap();
+    assert_eq!(blockchain.tip_hash(), Some(block.hash()));
+}
+
+#[test]
+fn block_beyond_the_drift_limit_is_rejected() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    let tip = blockchain.tip_hash();
+
+    let ahead = max_future() + Duration::minutes(MARGIN_MINUTES);
+    let block = block_at(&blockchain, &key, ahead);
+    assert!(matches!(
+        blockchain.would_accept(&block),
+        Err(BtcError::BlockFromFuture)
+    ));
+    assert!(matches!(
+        blockchain.add_block(block.clone()),
+        Err(BtcError::BlockFromFuture)
+    ));
+    assert_eq!(blockchain.block_height(), 1);
+    assert_eq!(blockchain.tip_hash(), tip);
+
+    // 받을 때는 유효했던 블록이라고 보고 저장된 체인을 다시 검증하면 거부하지 않는다
+    let mut blocks: Vec<Block> = blockchain.blocks().cloned().collect();
+    blocks.push(block);
+    let mut stored = common::load_unverified(&blocks);
+    stored.verify_integrity().unwrap();
+    stored.reindex().unwrap();
+    assert_eq!(stored.block_height(), 2);
+}
@@ -0,0 +1,119 @@
+// BlockHeader::validate가 각 검사를 따로따로 걸러내는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::params::ChainParams;
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain};
+use btclib::{MIN_TARGET, U256};
+use chrono::{Duration, Utc};
+use common::{coinbase_block, mine, mine_run};
+
+// 채굴된 genesis와, 그 다음에 올 채굴된 블록
+fn pair() -> (Block, Block) {
+    let key = PrivateKey::new_key();
+    let start = Utc::now() - Duration::minutes(10);
+    let mut prev = coinbase_block(&key, 0, Hash::zero(), start);
+    mine(&mut prev);
+    let mut next =
+        coinbase_block(&key, 1, prev.hash(), start + Duration::seconds(10));
+    mine(&mut next);
+    (prev, next)
+}
+
+#[test]
+fn valid_header_passes() {
+    let (prev, next) = pair();
+    next.header.validate(&prev, MIN_TARGET).unwrap();
+
+    // add_block도 같은 규칙으로 잇는다
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(prev).unwrap();
+    blockchain.add_block(next).unwrap();
+}
+
+#[test]
+fn prev_link_uses_the_block_hash() {
+    let (prev, mut next) = pair();
+
+    // 블록은 Block::hash로 식별되므로 header만의 hash로는 이어지지 않는다
+    next.header.prev_block_hash = prev.header.hash();
+    mine(&mut next);
+    assert!(matches!(
+        next.header.validate(&prev, MIN_TARGET),
+        Err(BtcError::InvalidBlock)
+    ));
+}
+
+#[test]
+fn target_easier_than_expected_is_rejected() {
+    let (prev, next) = pair();
+    let expected = next.header.target - U256::one();
+    assert!(matches!(
+        next.header.validate(&prev, expected),
+        Err(BtcError::InvalidTarget)
+    ));
+}
+
+#[test]
+fn insufficient_proof_of_work_is_rejected() {
+    let (prev, mut next) = pair();
+    // target 0을 만족하는 hash는 사실상 없다
+    next.header.target = U256::zero();
+    assert!(matches!(
+        next.header.validate(&prev, MIN_TARGET),
+        Err(BtcError::InvalidProofOfWork)
+    ));
+}
+
+#[test]
+fn timestamp_not_after_prev_is_rejected() {
+    let (prev, mut next) = pair();
+    next.header.timestamp = prev.header.timestamp;
+    mine(&mut next);
+    assert!(matches!(
+        next.header.validate(&prev, MIN_TARGET),
+        Err(BtcError::InvalidBlock)
+    ));
+}
+
+#[test]
+fn block_with_a_too_easy_target_is_rejected() {
+    let key = PrivateKey::new_key();
+
+    // genesis도 MIN_TARGET보다 쉬울 수 없다
+    let start = Utc::now() - Duration::minutes(10);
+    let mut genesis = coinbase_block(&key, 0, Hash::zero(), start);
+    genesis.header.target = MIN_TARGET + U256::one();
+    mine(&mut genesis);
+    assert!(matches!(
+        Blockchain::new().add_block(genesis),
+        Err(BtcError::InvalidTarget)
+    ));
+
+    // 블록이 빨리 쌓여 난이도가 올라간 뒤에는 MIN_TARGET도 너무 쉽다
+    let mut blockchain = Blockchain::with_params(ChainParams {
+        difficulty_update_interval: 2,
+        ..ChainParams::default()
+    })
+    .unwrap();
+    let spacing = Duration::seconds(1);
+    mine_run(&mut blockchain, &key, 2, spacing);
+    let expected = blockchain.expected_target(blockchain.block_height());
+    assert!(expected < MIN_TARGET);
+
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    block.header.timestamp =
+        blockchain.blocks_rev().next().unwrap().header.timestamp + spacing;
+    block.header.target = MIN_TARGET;
+    mine(&mut block);
+    assert!(matches!(
+        blockchain.add_block(block.clone()),
+        Err(BtcError::InvalidTarget)
+    ));
+
+    block.header.target = expected;
+    mine(&mut block);
+    blockchain.add_block(block).unwrap();
+}
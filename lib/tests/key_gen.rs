@@ -0,0 +1,90 @@
+// key_gen 바이너리가 만든 key 파일을 다시 읽어서 서명하고 검증한다
+use btclib::crypto::{PrivateKey, PublicKey, Signature};
+use btclib::sha256::Hash;
+use btclib::util::Savable;
+use ecdsa::SigningKey;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+// test가 끝나면 지워지는 디렉터리
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        let path =
+            std::env::temp_dir().join(format!("key-gen-{}", Uuid::new_v4()));
+        std::fs::create_dir(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// dir에서 key_gen을 실행하고 출력한 address를 돌려준다
+fn key_gen(dir: &Path, format: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_key_gen"))
+        .args(["alice", format])
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.trim().strip_prefix("address: ").unwrap().to_owned()
+}
+
+fn assert_signs(private_key: &PrivateKey, public_key: &PublicKey) {
+    let message = Hash::hash_bytes(b"key_gen");
+    let signature = Signature::sign_output(&message, private_key);
+    assert!(signature.verify(&message, public_key));
+    assert!(!signature.verify(&message, &PrivateKey::new_key().public_key()));
+}
+
+#[test]
+fn cbor_keys_load_and_sign() {
+    let dir = TempDir::new();
+    let address = key_gen(&dir.0, "cbor");
+
+    let private_key =
+        PrivateKey::load_from_file(dir.0.join("alice.priv.cbor")).unwrap();
+    let public_key =
+        PublicKey::load_from_file(dir.0.join("alice.pub.pem")).unwrap();
+    assert_eq!(private_key.public_key(), public_key);
+    assert_eq!(address, Hash::hash(&public_key).to_string());
+    assert_signs(&private_key, &public_key);
+}
+
+#[test]
+fn hex_keys_load_and_sign() {
+    let dir = TempDir::new();
+    let address = key_gen(&dir.0, "hex");
+
+    let secret = std::fs::read_to_string(dir.0.join("alice.priv.hex")).unwrap();
+    let private_key = PrivateKey(
+        SigningKey::from_slice(&hex::decode(secret).unwrap()).unwrap(),
+    );
+    let public_key =
+        PublicKey::load_from_file(dir.0.join("alice.pub.pem")).unwrap();
+    assert_eq!(private_key.public_key(), public_key);
+    assert_eq!(address, Hash::hash(&public_key).to_string());
+
+    let sec1 = std::fs::read_to_string(dir.0.join("alice.pub.hex")).unwrap();
+    assert_eq!(hex::decode(sec1).unwrap(), public_key.to_sec1_bytes());
+    assert_signs(&private_key, &public_key);
+}
+
+#[test]
+fn unknown_format_writes_nothing() {
+    let dir = TempDir::new();
+    let output = Command::new(env!("CARGO_BIN_EXE_key_gen"))
+        .args(["alice", "der"])
+        .current_dir(&dir.0)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 0);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::Blockchain;
+
+fn indexed(blocks: &[btclib::types::Block]) -> Blockchain {
+    let mut blockchain = common::load_unverified(blocks);
+    blockchain.rebuild_indexes();
+    blockchain
+}
+
+#[test]
+fn locator_is_dense_near_the_tip_and_ends_at_genesis() {
+    let mut blocks = vec![];
+    common::extend(&PrivateKey::new_key(), &mut blocks, 100, 10);
+    let blockchain = indexed(&blocks);
+
+    let heights: Vec<u64> = blockchain
+        .block_locator()
+        .iter()
+        .map(|hash| blockchain.find_fork_point(&[*hash]).unwrap())
+        .collect();
+    assert_eq!(
+        heights,
+        vec![99, 98, 97, 96, 95, 94, 93, 92, 91, 90, 88, 84, 76, 60, 28, 0]
+    );
+
+    assert!(Blockchain::new().block_locator().is_empty());
+}
+
+#[test]
+fn chains_sharing_a_prefix_find_the_fork_height() {
+    let mut common_prefix = vec![];
+    common::extend(&PrivateKey::new_key(), &mut common_prefix, 40, 10);
+
+    // 높이 39까지 같고 그 뒤로 갈라진 두 체인
+    let mut ours = common_prefix.clone();
+    common::extend(&PrivateKey::new_key(), &mut ours, 5, 10);
+    let mut theirs = common_prefix;
+    common::extend(&PrivateKey::new_key(), &mut theirs, 60, 10);
+
+    let ours = indexed(&ours);
+    let theirs = indexed(&theirs);
+    // 갈라진 지점이 우리 tip 근처의 촘촘한 구간에 있으므로 정확히 찾는다
+    assert_eq!(theirs.find_fork_point(&ours.block_locator()), Some(39));
+    // 반대로 locator 간격이 넓은 곳에서 갈라졌다면 그보다 앞선 공통 블록을 찾는다.
+    // 상대는 이미 가진 블록 몇 개를 더 받게 될 뿐이다
+    let fork = ours.find_fork_point(&theirs.block_locator()).unwrap();
+    assert!(fork <= 39);
+    assert_eq!(
+        ours.blocks().nth(fork as usize).unwrap().hash(),
+        theirs.blocks().nth(fork as usize).unwrap().hash()
+    );
+}
+
+#[test]
+fn a_prefix_of_the_chain_meets_at_its_tip() {
+    let mut blocks = vec![];
+    common::extend(&PrivateKey::new_key(), &mut blocks, 30, 10);
+    let shorter = indexed(&blocks[..12]);
+    let longer = indexed(&blocks);
+
+    assert_eq!(longer.find_fork_point(&shorter.block_locator()), Some(11));
+}
+
+#[test]
+fn unrelated_chains_share_nothing() {
+    let mut a = vec![];
+    common::extend(&PrivateKey::new_key(), &mut a, 20, 10);
+    let mut b = vec![];
+    common::extend(&PrivateKey::new_key(), &mut b, 20, 10);
+
+    assert_eq!(indexed(&a).find_fork_point(&indexed(&b).block_locator()), None);
+}
@@ -0,0 +1,279 @@
+// output의 잠금 조건(LockingCondition)별로 소비할 수 있는 경우와 없는 경우를 확인한다
+use btclib::crypto::{PrivateKey, PublicKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+const FEE: u64 = 10_000;
+
+fn output(value: u64, lock: LockingCondition) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock,
+    }
+}
+
+// lock으로 잠긴 output 하나를 가진 genesis로 시작하는 체인과 그 output
+fn chain_with(lock: LockingCondition) -> (Blockchain, TransactionOutput) {
+    let transactions = vec![Transaction::new(
+        vec![],
+        vec![output(Blockchain::block_reward_at(0), lock)],
+    )];
+    let prev = transactions[0].outputs[0].clone();
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+    (blockchain, prev)
+}
+
+// prev를 signers의 서명으로 소비해서 to에게 보내는 tx
+fn spend(
+    prev: &TransactionOutput,
+    signers: &[&PrivateKey],
+    to: &PublicKey,
+) -> Transaction {
+    let prev_hash = prev.hash();
+    let mut signatures =
+        signers.iter().map(|signer| Signature::sign_output(&prev_hash, signer));
+    let mut input =
+        TransactionInput::new(prev_hash, signatures.next().unwrap());
+    input.extra_signatures = signatures.collect();
+    Transaction::new(
+        vec![input],
+        vec![output(prev.value - FEE, LockingCondition::P2PK(to.clone()))],
+    )
+}
+
+fn to_cbor<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+// 잠금 조건이 생기기 전의 output 형식
+#[derive(Serialize)]
+struct LegacyOutput {
+    value: u64,
+    unique_id: Uuid,
+    pubkey: PublicKey,
+}
+
+#[test]
+fn p2pk_output_keeps_the_legacy_encoding_and_rules() {
+    let key = PrivateKey::new_key();
+    let legacy = LegacyOutput {
+        value: 5_000,
+        unique_id: Uuid::new_v4(),
+        pubkey: key.public_key(),
+    };
+    let bytes = to_cbor(&legacy);
+
+    // 기존 형식은 P2PK로 읽히고, 다시 쓰면 같은 바이트가 된다
+    let output: TransactionOutput =
+        ciborium::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(output.lock, LockingCondition::P2PK(key.public_key()));
+    assert_eq!(to_cbor(&output), bytes);
+
+    // 소유자의 서명으로만 소비할 수 있다
+    let (mut blockchain, prev) =
+        chain_with(LockingCondition::P2PK(key.public_key()));
+    let other = PrivateKey::new_key();
+    assert!(matches!(
+        blockchain.add_to_mempool(spend(&prev, &[&other], &key.public_key())),
+        Err(BtcError::InvalidSignature)
+    ));
+    blockchain
+        .add_to_mempool(spend(&prev, &[&key], &key.public_key()))
+        .unwrap();
+}
+
+#[test]
+fn raw_script_output_cannot_be_spent() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, prev) =
+        chain_with(LockingCondition::RawScript(vec![0x51]));
+
+    let transaction = spend(&prev, &[&key], &key.public_key());
+    assert!(matches!(
+        prev.lock.verify(&transaction.inputs[0], 1),
+        Err(BtcError::InvalidTransactionOutput)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InvalidTransactionOutput)
+    ));
+
+    // 블록에 담아도 거부된다
+    let reward = Blockchain::block_reward_at(1);
+    let coinbase = Transaction::coinbase(1, reward, FEE, &key.public_key());
+    let transactions = vec![coinbase, transaction];
+    let block = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            blockchain.tip_hash().unwrap(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    assert!(matches!(
+        block.verify_transactions(1, blockchain.utxos()),
+        Err(BtcError::InvalidTransactionOutput)
+    ));
+}
+
+#[test]
+fn every_condition_round_trips() {
+    let keys: Vec<_> =
+        (0..3).map(|_| PrivateKey::new_key().public_key()).collect();
+    let locks = [
+        LockingCondition::P2PK(keys[0].clone()),
+        LockingCondition::MultiSig {
+            keys: keys.clone(),
+            threshold: 2,
+        },
+        LockingCondition::CheckLockTimeVerify {
+            height: 10,
+            inner: Box::new(LockingCondition::P2PK(keys[1].clone())),
+        },
+        LockingCondition::RawScript(vec![0xde, 0xad]),
+        LockingCondition::Unspendable,
+    ];
+    for lock in locks {
+        let output = output(1_000, lock);
+        let decoded: TransactionOutput =
+            ciborium::from_reader(to_cbor(&output).as_slice()).unwrap();
+        assert_eq!(decoded.lock, output.lock);
+        assert_eq!(decoded.hash(), output.hash());
+    }
+
+    // pubkey와 lock이 둘 다 있는 output은 읽지 않는다
+    #[derive(Serialize)]
+    struct Ambiguous {
+        value: u64,
+        unique_id: Uuid,
+        pubkey: PublicKey,
+        lock: LockingCondition,
+    }
+    let ambiguous = Ambiguous {
+        value: 1_000,
+        unique_id: Uuid::new_v4(),
+        pubkey: keys[0].clone(),
+        lock: LockingCondition::Unspendable,
+    };
+    let bytes = to_cbor(&ambiguous);
+    assert!(
+        ciborium::from_reader::<TransactionOutput, _>(bytes.as_slice())
+            .is_err()
+    );
+}
+
+#[test]
+fn two_of_three_multisig_needs_two_distinct_signers() {
+    let keys: Vec<_> = (0..3).map(|_| PrivateKey::new_key()).collect();
+    let lock = LockingCondition::MultiSig {
+        keys: keys.iter().map(PrivateKey::public_key).collect(),
+        threshold: 2,
+    };
+    let (mut blockchain, prev) = chain_with(lock);
+    let to = keys[0].public_key();
+
+    // 한 명의 서명만으로는 부족하다
+    assert!(matches!(
+        blockchain.add_to_mempool(spend(&prev, &[&keys[1]], &to)),
+        Err(BtcError::InvalidSignature)
+    ));
+    // 같은 key가 두 번 서명해도 두 명이 되지 않는다
+    assert!(matches!(
+        blockchain.add_to_mempool(spend(&prev, &[&keys[1], &keys[1]], &to)),
+        Err(BtcError::InvalidSignature)
+    ));
+    // 목록에 없는 key의 서명은 받지 않는다
+    let outsider = PrivateKey::new_key();
+    assert!(matches!(
+        blockchain.add_to_mempool(spend(&prev, &[&keys[0], &outsider], &to)),
+        Err(BtcError::InvalidSignature)
+    ));
+    assert!(blockchain.mempool().is_empty());
+
+    // 순서와 상관없이 서로 다른 두 key면 된다
+    blockchain
+        .add_to_mempool(spend(&prev, &[&keys[2], &keys[0]], &to))
+        .unwrap();
+}
+
+#[test]
+fn timelocked_output_is_spendable_from_its_height() {
+    let key = PrivateKey::new_key();
+    let timelocked = |height| LockingCondition::CheckLockTimeVerify {
+        height,
+        inner: Box::new(LockingCondition::P2PK(key.public_key())),
+    };
+
+    // 다음 블록의 높이는 1이다
+    let (mut blockchain, prev) = chain_with(timelocked(2));
+    let transaction = spend(&prev, &[&key], &key.public_key());
+    let input = &transaction.inputs[0];
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InvalidTransactionInput)
+    ));
+    assert!(matches!(
+        prev.lock.verify(input, 1),
+        Err(BtcError::InvalidTransactionInput)
+    ));
+    prev.lock.verify(input, 2).unwrap();
+    prev.lock.verify(input, 3).unwrap();
+
+    // 블록은 담길 높이를 기준으로 검증한다
+    let in_block_at = |height| {
+        let reward = Blockchain::block_reward_at(height);
+        let coinbase =
+            Transaction::coinbase(height, reward, FEE, &key.public_key());
+        let transactions = vec![coinbase, transaction.clone()];
+        let block = Block::new(
+            BlockHeader::new(
+                Utc::now(),
+                0,
+                blockchain.tip_hash().unwrap(),
+                MerkleRoot::calculate(&transactions),
+                btclib::MIN_TARGET,
+            ),
+            transactions,
+        );
+        block.verify_transactions(height, blockchain.utxos())
+    };
+    assert!(matches!(in_block_at(1), Err(BtcError::InvalidTransactionInput)));
+    in_block_at(2).unwrap();
+
+    // 높이가 되어도 inner 조건은 그대로 지켜야 한다
+    let other = PrivateKey::new_key();
+    let forged = spend(&prev, &[&other], &key.public_key());
+    assert!(matches!(
+        prev.lock.verify(&forged.inputs[0], 2),
+        Err(BtcError::InvalidSignature)
+    ));
+
+    let (mut blockchain, prev) = chain_with(timelocked(1));
+    blockchain
+        .add_to_mempool(spend(&prev, &[&key], &key.public_key()))
+        .unwrap();
+}
@@ -0,0 +1,603 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, MempoolAcceptance, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use common::output;
+
+const OUTPUT_VALUE: u64 = 100_000;
+
+// coinbase가 OUTPUT_VALUE짜리 output count개와 나머지를 key에게 지급하는 genesis 블록
+fn genesis(key: &PrivateKey, count: usize) -> Block {
+    let reward = Blockchain::block_reward_at(0);
+    let mut outputs: Vec<_> =
+        (0..count).map(|_| output(key, OUTPUT_VALUE)).collect();
+    outputs.push(output(key, reward - OUTPUT_VALUE * count as u64));
+
+    let transactions = vec![Transaction::new(vec![], outputs)];
+    Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    )
+}
+
+fn spend(key: &PrivateKey, prev: &TransactionOutput, fee: u64) -> Transaction {
+    let prev_hash = prev.hash();
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![output(key, prev.value - fee)],
+    )
+}
+
+fn chain_with_outputs(key: &PrivateKey, count: usize) -> (Blockchain, Block) {
+    let genesis = genesis(key, count);
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis.clone()).unwrap();
+    (blockchain, genesis)
+}
+
+#[test]
+fn top_mempool_is_ordered_by_fee_rate() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 4);
+
+    // 크기가 같은 tx들이므로 수수료가 곧 수수료율이다
+    let fees = [2_000, 5_000, 1_000, 4_000];
+    let coinbase = &genesis.transactions[0];
+    for (prev, fee) in coinbase.outputs.iter().zip(fees) {
+        assert_eq!(
+            blockchain.add_to_mempool(spend(&key, prev, fee)).unwrap(),
+            MempoolAcceptance::Accepted
+        );
+    }
+
+    let fee_of = |transaction: &Transaction| {
+        OUTPUT_VALUE - transaction.outputs[0].value
+    };
+    let top: Vec<u64> = blockchain.top_mempool(3).iter().map(fee_of).collect();
+    assert_eq!(top, vec![5_000, 4_000, 2_000]);
+
+    assert_eq!(blockchain.top_mempool(10).len(), fees.len());
+    assert!(blockchain.top_mempool(0).is_empty());
+}
+
+#[test]
+fn non_replaceable_conflict_reports_the_mempool_txid() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+    let prev = &genesis.transactions[0].outputs[0];
+
+    // SEQUENCE_FINAL이므로 교체를 허용하지 않는다
+    let existing = spend(&key, prev, 1_000);
+    blockchain.add_to_mempool(existing.clone()).unwrap();
+
+    // 확정된 utxo를 두고 충돌하더라도 미확정 output과 같은 형태로 알린다
+    assert_eq!(
+        blockchain.add_to_mempool(spend(&key, prev, 2_000)).unwrap(),
+        MempoolAcceptance::Conflict(existing.hash())
+    );
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![existing.hash()]);
+}
+
+#[test]
+fn replacement_reports_the_replaced_txids() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+    let prev = &genesis.transactions[0].outputs[0];
+
+    let mut existing = spend(&key, prev, 1_000);
+    existing.inputs[0].sequence = btclib::MAX_RBF_SEQUENCE;
+    blockchain.add_to_mempool(existing.clone()).unwrap();
+    let child = spend(&key, &existing.outputs[0], 1_000);
+    blockchain.add_to_mempool(child.clone()).unwrap();
+
+    // 기존 tx와 그 output을 소비하던 자손이 함께 교체된다
+    let replacement = spend(&key, prev, 10_000);
+    match blockchain.add_to_mempool(replacement.clone()).unwrap() {
+        MempoolAcceptance::Replaced(mut replaced) => {
+            replaced.sort();
+            let mut expected = vec![existing.hash(), child.hash()];
+            expected.sort();
+            assert_eq!(replaced, expected);
+        }
+        acceptance => panic!("expected a replacement, got {acceptance:?}"),
+    }
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![replacement.hash()]);
+}
+
+#[test]
+fn unconfirmed_output_conflict_reports_the_mempool_txid() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+
+    let parent = spend(&key, &genesis.transactions[0].outputs[0], 1_000);
+    blockchain.add_to_mempool(parent.clone()).unwrap();
+    let child = spend(&key, &parent.outputs[0], 1_000);
+    blockchain.add_to_mempool(child.clone()).unwrap();
+
+    // 미확정 output을 두 번째로 소비하려는 tx
+    let rival = spend(&key, &parent.outputs[0], 2_000);
+    assert_eq!(
+        blockchain.add_to_mempool(rival).unwrap(),
+        MempoolAcceptance::Conflict(child.hash())
+    );
+}
+
+#[test]
+fn invalid_signature_is_rejected_at_mempool_entry() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+    let prev = &genesis.transactions[0].outputs[0];
+    let prev_hash = prev.hash();
+
+    // 다른 key의 서명, 그리고 다른 output에 대한 서명
+    let forgeries = [
+        Signature::sign_output(&prev_hash, &PrivateKey::new_key()),
+        Signature::sign_output(&Hash::zero(), &key),
+    ];
+    for signature in forgeries {
+        let transaction = Transaction::new(
+            vec![TransactionInput::new(prev_hash, signature)],
+            vec![output(&key, prev.value - 1_000)],
+        );
+        assert!(matches!(
+            blockchain.add_to_mempool(transaction),
+            Err(BtcError::InvalidSignature)
+        ));
+    }
+    assert!(blockchain.mempool().is_empty());
+    assert!(!blockchain.utxos()[&prev_hash].0);
+}
+
+#[test]
+fn invalid_signature_does_not_replace_a_mempool_transaction() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+    let prev = &genesis.transactions[0].outputs[0];
+
+    let mut existing = spend(&key, prev, 1_000);
+    existing.inputs[0].sequence = btclib::MAX_RBF_SEQUENCE;
+    blockchain.add_to_mempool(existing.clone()).unwrap();
+
+    // 수수료가 더 높더라도 서명이 틀렸다면 기존 tx를 밀어내지 못한다
+    let forged = spend(&PrivateKey::new_key(), prev, 2_000);
+    assert!(matches!(
+        blockchain.add_to_mempool(forged),
+        Err(BtcError::InvalidSignature)
+    ));
+
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![existing.hash()]);
+}
+
+#[test]
+fn only_a_signalling_transaction_can_be_replaced() {
+    let key = PrivateKey::new_key();
+    for (sequence, replaceable) in [
+        (btclib::SEQUENCE_FINAL, false),
+        (btclib::SEQUENCE_FINAL - 1, false),
+        (btclib::MAX_RBF_SEQUENCE, true),
+        (0, true),
+    ] {
+        let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+        let prev = &genesis.transactions[0].outputs[0];
+
+        let mut existing = spend(&key, prev, 1_000);
+        existing.inputs[0].sequence = sequence;
+        blockchain.add_to_mempool(existing.clone()).unwrap();
+
+        // 교체하려는 tx가 신호를 보내는지가 아니라 기존 tx가 신호를 보냈는지로 정해진다
+        let mut replacement = spend(&key, prev, 10_000);
+        replacement.inputs[0].sequence = btclib::MAX_RBF_SEQUENCE;
+        let acceptance =
+            blockchain.add_to_mempool(replacement.clone()).unwrap();
+
+        let (expected, remaining) = if replaceable {
+            (MempoolAcceptance::Replaced(vec![existing.hash()]), replacement)
+        } else {
+            (MempoolAcceptance::Conflict(existing.hash()), existing)
+        };
+        assert_eq!(acceptance, expected, "sequence {sequence}");
+        let mempool: Vec<_> =
+            blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+        assert_eq!(mempool, vec![remaining.hash()]);
+    }
+}
+
+#[test]
+fn conflicting_txid_is_reported_the_same_way_by_every_check() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+
+    let parent = spend(&key, &genesis.transactions[0].outputs[0], 1_000);
+    blockchain.add_to_mempool(parent.clone()).unwrap();
+    let child = spend(&key, &parent.outputs[0], 1_000);
+    blockchain.add_to_mempool(child.clone()).unwrap();
+
+    // 확정된 utxo를 두고 parent와, 미확정 output을 두고 child와 충돌한다
+    let rivals = [
+        (spend(&key, &genesis.transactions[0].outputs[0], 2_000), &parent),
+        (spend(&key, &parent.outputs[0], 2_000), &child),
+    ];
+    for (rival, existing) in rivals {
+        match blockchain.check_transaction(&rival) {
+            Err(BtcError::TxConflictNonReplaceable(txid)) => {
+                assert_eq!(txid, existing.hash())
+            }
+            result => panic!("expected a conflict, got {result:?}"),
+        }
+        assert_eq!(
+            blockchain.add_to_mempool(rival).unwrap(),
+            MempoolAcceptance::Conflict(existing.hash())
+        );
+    }
+    assert_eq!(blockchain.mempool().len(), 2);
+}
+
+#[test]
+fn replacement_rejected_by_policy_does_not_evict_anything() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+    let prev = &genesis.transactions[0].outputs[0];
+
+    let mut existing = spend(&key, prev, 1_000);
+    existing.inputs[0].sequence = btclib::MAX_RBF_SEQUENCE;
+    blockchain.add_to_mempool(existing.clone()).unwrap();
+    let generation = blockchain.mempool_generation();
+
+    // 수수료가 없는 tx와 dust output을 만드는 tx는 정책에 걸리므로
+    // 기존 tx를 먼저 밀어내고 거부되는 일이 없어야 한다
+    let zero_fee = spend(&key, prev, 0);
+    let dust = spend(&key, prev, prev.value - btclib::DUST_LIMIT);
+    assert!(matches!(
+        blockchain.add_to_mempool(zero_fee),
+        Err(BtcError::InsufficientFee)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(dust),
+        Err(BtcError::DustOutput)
+    ));
+
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![existing.hash()]);
+    assert_eq!(blockchain.mempool_generation(), generation);
+    assert!(blockchain.utxos()[&prev.hash()].0);
+}
+
+#[test]
+fn check_transaction_agrees_with_add_to_mempool() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 2);
+    let outputs = &genesis.transactions[0].outputs;
+
+    // 유효한 tx는 통과하지만 mempool에 들어가지는 않는다
+    let valid = spend(&key, &outputs[0], 1_000);
+    blockchain.check_transaction(&valid).unwrap();
+    assert!(blockchain.mempool().is_empty());
+    assert!(!blockchain.utxos()[&outputs[0].hash()].0);
+    assert_eq!(
+        blockchain.add_to_mempool(valid.clone()).unwrap(),
+        MempoolAcceptance::Accepted
+    );
+
+    // 같은 output을 한 tx 안에서 두 번 쓴다
+    let mut twice = spend(&key, &outputs[1], 1_000);
+    twice.inputs.push(twice.inputs[0].clone());
+    assert!(matches!(
+        blockchain.check_transaction(&twice),
+        Err(BtcError::DoubleSpend)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(twice),
+        Err(BtcError::DoubleSpend)
+    ));
+
+    // mempool의 tx가 이미 쓰고 있는 output을 쓴다
+    let rival = spend(&key, &outputs[0], 2_000);
+    assert!(matches!(
+        blockchain.check_transaction(&rival),
+        Err(BtcError::TxConflictNonReplaceable(txid)) if txid == valid.hash()
+    ));
+
+    // 다른 key의 서명
+    let forged = spend(&PrivateKey::new_key(), &outputs[1], 1_000);
+    assert!(matches!(
+        blockchain.check_transaction(&forged),
+        Err(BtcError::InvalidSignature)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(forged),
+        Err(BtcError::InvalidSignature)
+    ));
+
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![valid.hash()]);
+}
+
+// mempool에 있는 부모 tx와 그 output을 쓰는 자식 tx가 한 블록에서 함께 확정된다
+#[test]
+fn parent_and_child_confirm_together() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 2);
+    let outputs = &genesis.transactions[0].outputs;
+
+    let parent = spend(&key, &outputs[0], 1_000);
+    let child = spend(&key, &parent.outputs[0], 1_000);
+    blockchain.add_to_mempool(parent.clone()).unwrap();
+    blockchain.add_to_mempool(child.clone()).unwrap();
+
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    let included: Vec<_> =
+        block.transactions[1..].iter().map(|tx| tx.hash()).collect();
+    assert_eq!(included, vec![parent.hash(), child.hash()]);
+    while !block.header.mine(1_000_000) {}
+    blockchain.add_block(block).unwrap();
+
+    assert!(blockchain.mempool().is_empty());
+    assert!(blockchain.utxos().contains_key(&child.outputs[0].hash()));
+
+    // 블록 뒤에도 mempool은 계속 tx를 받는다
+    let next = spend(&key, &child.outputs[0], 1_000);
+    assert_eq!(
+        blockchain.add_to_mempool(next).unwrap(),
+        MempoolAcceptance::Accepted
+    );
+}
+
+// 블록이 mempool tx와 같은 output을 쓴다면 그 tx와 자손은 mempool에서 빠진다
+#[test]
+fn block_evicts_conflicting_mempool_transactions_and_descendants() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 2);
+    let outputs = &genesis.transactions[0].outputs;
+
+    // 다른 노드의 mempool에는 outputs[0]을 쓰는 다른 tx가 있었다
+    let mut other = blockchain.clone();
+    let rival = spend(&key, &outputs[0], 5_000);
+    other.add_to_mempool(rival.clone()).unwrap();
+    let mut block = other.build_template(key.public_key()).unwrap();
+    while !block.header.mine(1_000_000) {}
+
+    let parent = spend(&key, &outputs[0], 1_000);
+    let child = spend(&key, &parent.outputs[0], 1_000);
+    let unrelated = spend(&key, &outputs[1], 1_000);
+    for transaction in [parent, child, unrelated.clone()] {
+        blockchain.add_to_mempool(transaction).unwrap();
+    }
+    let generation = blockchain.mempool_generation();
+
+    blockchain.add_block(block).unwrap();
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![unrelated.hash()]);
+    assert_eq!(blockchain.mempool_generation(), generation + 1);
+
+    // 남은 mempool을 정렬할 때 사라진 utxo를 찾지 않는다
+    let next = spend(&key, &rival.outputs[0], 1_000);
+    assert_eq!(
+        blockchain.add_to_mempool(next).unwrap(),
+        MempoolAcceptance::Accepted
+    );
+}
+
+// 수수료를 거의 내지 않는 부모도 수수료가 높은 자식과 묶여서 먼저 담긴다 (CPFP)
+#[test]
+fn low_fee_parent_is_selected_with_its_high_fee_child() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 2);
+    let outputs = &genesis.transactions[0].outputs;
+
+    // 정책상 수수료 0은 받지 않으므로 최소 relay 수수료만 낸다
+    let min_fee = spend(&key, &outputs[0], 0).vsize() as u64
+        * btclib::MIN_RELAY_FEE_RATE;
+    let parent = spend(&key, &outputs[0], min_fee);
+    let child = spend(&key, &parent.outputs[0], 20_000);
+    let other = spend(&key, &outputs[1], 5_000);
+    for transaction in [parent.clone(), child.clone(), other.clone()] {
+        blockchain.add_to_mempool(transaction).unwrap();
+    }
+
+    let top: Vec<_> =
+        blockchain.top_mempool(3).iter().map(|tx| tx.hash()).collect();
+    assert_eq!(top, vec![parent.hash(), child.hash(), other.hash()]);
+    // 하나만 고르더라도 자식 없이 부모부터 담긴다
+    let first: Vec<_> =
+        blockchain.top_mempool(1).iter().map(|tx| tx.hash()).collect();
+    assert_eq!(first, vec![parent.hash()]);
+
+    // 블록 템플릿도 같은 순서로 담는다
+    let block = blockchain.build_template(key.public_key()).unwrap();
+    let included: Vec<_> =
+        block.transactions[1..].iter().map(|tx| tx.hash()).collect();
+    assert_eq!(included, top);
+}
+
+// change를 하는 동안 mempool generation이 몇 번 바뀌었는지와 change의 결과
+fn generation_bumps<T>(
+    blockchain: &mut Blockchain,
+    change: impl FnOnce(&mut Blockchain) -> T,
+) -> (u64, T) {
+    let before = blockchain.mempool_generation();
+    let result = change(blockchain);
+    (blockchain.mempool_generation() - before, result)
+}
+
+#[test]
+fn each_mempool_change_bumps_the_generation_once() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 3);
+    let outputs = &genesis.transactions[0].outputs;
+
+    let mut original = spend(&key, &outputs[0], 1_000);
+    original.inputs[0].sequence = btclib::MAX_RBF_SEQUENCE;
+    let (bumps, _) = generation_bumps(&mut blockchain, |blockchain| {
+        blockchain.add_to_mempool(original).unwrap()
+    });
+    assert_eq!(bumps, 1);
+
+    // 기존 tx를 밀어내고 들어가도 한 번의 변화다
+    let replacement = spend(&key, &outputs[0], 5_000);
+    let (bumps, acceptance) = generation_bumps(&mut blockchain, |blockchain| {
+        blockchain.add_to_mempool(replacement).unwrap()
+    });
+    assert!(matches!(acceptance, MempoolAcceptance::Replaced(_)));
+    assert_eq!(bumps, 1);
+
+    // 블록이 tx를 확정하면서 mempool에서 지운다
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    while !block.header.mine(1_000_000) {}
+    let (bumps, _) = generation_bumps(&mut blockchain, |blockchain| {
+        blockchain.add_block(block).unwrap()
+    });
+    assert!(blockchain.mempool().is_empty());
+    assert_eq!(bumps, 1);
+
+    let parent = spend(&key, &outputs[1], 1_000);
+    let child = spend(&key, &parent.outputs[0], 1_000);
+    for transaction in [parent, child] {
+        let (bumps, _) = generation_bumps(&mut blockchain, |blockchain| {
+            blockchain.add_to_mempool(transaction).unwrap()
+        });
+        assert_eq!(bumps, 1);
+    }
+
+    // 아무것도 만료되지 않으면 그대로다
+    let (bumps, evicted) = generation_bumps(&mut blockchain, |blockchain| {
+        blockchain.cleanup_mempool(chrono::Duration::hours(1))
+    });
+    assert!(evicted.is_empty());
+    assert_eq!(bumps, 0);
+
+    // 부모와 자식이 함께 만료되어도 한 번의 변화다
+    let (bumps, evicted) = generation_bumps(&mut blockchain, |blockchain| {
+        blockchain.cleanup_mempool(chrono::Duration::seconds(-1))
+    });
+    assert_eq!(evicted.len(), 2);
+    assert_eq!(bumps, 1);
+}
+
+#[test]
+fn cleanup_returns_the_aged_transactions_and_clears_their_marks() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 2);
+    let outputs = &genesis.transactions[0].outputs;
+    let is_marked = |blockchain: &Blockchain, output: &TransactionOutput| {
+        blockchain.utxos()[&output.hash()].0
+    };
+
+    let old = spend(&key, &outputs[0], 1_000);
+    let old_child = spend(&key, &old.outputs[0], 1_000);
+    blockchain.add_to_mempool(old.clone()).unwrap();
+    blockchain.add_to_mempool(old_child.clone()).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1_500));
+    let recent = spend(&key, &outputs[1], 1_000);
+    blockchain.add_to_mempool(recent.clone()).unwrap();
+    assert!(is_marked(&blockchain, &outputs[0]));
+    assert!(is_marked(&blockchain, &outputs[1]));
+
+    // 자식도 오래되었지만, 부모를 버리면서 함께 한 번만 버려진다
+    let mut evicted: Vec<Hash> = blockchain
+        .cleanup_mempool(chrono::Duration::seconds(1))
+        .iter()
+        .map(Transaction::hash)
+        .collect();
+    evicted.sort();
+    let mut aged = vec![old.hash(), old_child.hash()];
+    aged.sort();
+    assert_eq!(evicted, aged);
+
+    // 버린 tx가 쓰던 utxo는 다시 쓸 수 있고, 남은 tx의 utxo는 그대로다
+    assert!(!is_marked(&blockchain, &outputs[0]));
+    assert!(is_marked(&blockchain, &outputs[1]));
+    let remaining: Vec<Hash> = blockchain
+        .mempool()
+        .iter()
+        .map(|(_, transaction)| transaction.hash())
+        .collect();
+    assert_eq!(remaining, vec![recent.hash()]);
+    blockchain.add_to_mempool(spend(&key, &outputs[0], 2_000)).unwrap();
+}
+
+#[test]
+fn chain_is_cut_at_the_ancestor_limit() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+
+    // 확정된 output에서 시작해 한 줄로 이어지는 미확정 tx들
+    let mut prev = genesis.transactions[0].outputs[0].clone();
+    for _ in 0..btclib::MAX_ANCESTORS {
+        let transaction = spend(&key, &prev, 1_000);
+        blockchain.add_to_mempool(transaction.clone()).unwrap();
+        prev = transaction.outputs[0].clone();
+    }
+    assert_eq!(blockchain.mempool().len(), btclib::MAX_ANCESTORS);
+
+    let too_deep = spend(&key, &prev, 1_000);
+    assert!(matches!(
+        blockchain.add_to_mempool(too_deep),
+        Err(BtcError::MempoolChainTooLong)
+    ));
+    assert_eq!(blockchain.mempool().len(), btclib::MAX_ANCESTORS);
+}
+
+#[test]
+fn children_are_cut_at_the_descendant_limit() {
+    let key = PrivateKey::new_key();
+    let (mut blockchain, genesis) = chain_with_outputs(&key, 1);
+
+    // 자식들은 조상이 parent 하나뿐이므로 자손 수만 한도에 걸린다
+    let prev = genesis.transactions[0].outputs.last().unwrap();
+    let prev_hash = prev.hash();
+    let parent = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        (0..btclib::MAX_DESCENDANTS)
+            .map(|_| output(&key, OUTPUT_VALUE))
+            .collect(),
+    );
+    blockchain.add_to_mempool(parent.clone()).unwrap();
+
+    // parent 자신을 포함해 MAX_DESCENDANTS개까지 받는다
+    let (allowed, rest) = parent.outputs.split_at(btclib::MAX_DESCENDANTS - 1);
+    for output in allowed {
+        blockchain.add_to_mempool(spend(&key, output, 1_000)).unwrap();
+    }
+    assert_eq!(blockchain.mempool().len(), btclib::MAX_DESCENDANTS);
+    assert!(matches!(
+        blockchain.add_to_mempool(spend(&key, &rest[0], 1_000)),
+        Err(BtcError::MempoolChainTooLong)
+    ));
+
+    // 손자도 parent의 자손이므로 받지 않는다
+    let child = &blockchain.mempool()[1].1;
+    let grandchild = spend(&key, &child.outputs[0], 1_000);
+    assert!(matches!(
+        blockchain.add_to_mempool(grandchild),
+        Err(BtcError::MempoolChainTooLong)
+    ));
+    assert_eq!(blockchain.mempool().len(), btclib::MAX_DESCENDANTS);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, Transaction, TransactionInput,
+};
+use btclib::util::{MerkleAccumulator, MerkleRoot};
+use chrono::Utc;
+use common::output;
+
+fn transactions(key: &PrivateKey, count: usize) -> Vec<Transaction> {
+    (0..count).map(|_| Transaction::new(vec![], vec![output(key, 1)])).collect()
+}
+
+#[test]
+fn incremental_root_matches_calculate() {
+    let key = PrivateKey::new_key();
+    let transactions = transactions(&key, 70);
+
+    let mut merkle = MerkleAccumulator::new();
+    assert!(merkle.root().is_none());
+    // 홀수 개, 2의 거듭제곱 개, 그 사이의 모든 크기
+    for (count, transaction) in (1..).zip(&transactions) {
+        merkle.push(transaction);
+        assert_eq!(merkle.len(), count);
+        assert_eq!(
+            merkle.root(),
+            Some(MerkleRoot::calculate(&transactions[..count])),
+            "{count} transactions"
+        );
+    }
+}
+
+#[test]
+fn replacing_the_first_transaction_matches_calculate() {
+    let key = PrivateKey::new_key();
+    let transactions = transactions(&key, 40);
+
+    for count in 1..=transactions.len() {
+        let mut merkle = MerkleAccumulator::new();
+        for transaction in &transactions[..count] {
+            merkle.push(transaction);
+        }
+
+        let mut replaced = transactions[..count].to_vec();
+        replaced[0] = Transaction::new(vec![], vec![output(&key, 2)]);
+        merkle.replace_first(&replaced[0]);
+        assert_eq!(
+            merkle.root(),
+            Some(MerkleRoot::calculate(&replaced)),
+            "{count} transactions"
+        );
+
+        // 바꾼 뒤에도 이어서 추가할 수 있다
+        let next = Transaction::new(vec![], vec![output(&key, 3)]);
+        merkle.push(&next);
+        replaced.push(next);
+        assert_eq!(merkle.root(), Some(MerkleRoot::calculate(&replaced)));
+    }
+}
+
+#[test]
+fn template_merkle_root_commits_to_its_transactions() {
+    let key = PrivateKey::new_key();
+    let reward = Blockchain::block_reward_at(0);
+    let outputs: Vec<_> = (0..5).map(|_| output(&key, reward / 5)).collect();
+    let coinbase = Transaction::new(vec![], outputs);
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(std::slice::from_ref(&coinbase)),
+            btclib::MIN_TARGET,
+        ),
+        vec![coinbase.clone()],
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+
+    for (fee, prev) in (1_000..).zip(&coinbase.outputs) {
+        let prev_hash = prev.hash();
+        let transaction = Transaction::new(
+            vec![TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, &key),
+            )],
+            vec![output(&key, prev.value - fee)],
+        );
+        blockchain.add_to_mempool(transaction).unwrap();
+    }
+
+    let template = blockchain.build_template(key.public_key()).unwrap();
+    assert_eq!(template.transactions.len(), 6);
+    assert_eq!(
+        template.header.merkle_root,
+        MerkleRoot::calculate(&template.transactions)
+    );
+}
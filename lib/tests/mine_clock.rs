@@ -0,0 +1,40 @@
+// mine_with_clock은 header와 clock이 같다면 항상 같은 블록을 만든다
+use btclib::MIN_TARGET;
+use btclib::sha256::Hash;
+use btclib::types::{BlockHeader, Transaction};
+use btclib::util::MerkleRoot;
+use chrono::{DateTime, Duration, Utc};
+
+fn header() -> BlockHeader {
+    BlockHeader::new(
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        // 곧 nonce가 넘쳐서 clock으로 timestamp를 갱신하게 된다
+        u64::MAX - 3,
+        Hash::hash_bytes(b"prev block"),
+        MerkleRoot::calculate(&[Transaction::new(vec![], vec![])]),
+        MIN_TARGET,
+    )
+}
+
+// 호출될 때마다 1초씩 가는 시계
+fn clock() -> impl FnMut() -> DateTime<Utc> {
+    let mut now = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+    move || {
+        now += Duration::seconds(1);
+        now
+    }
+}
+
+#[test]
+fn same_clock_mines_identical_headers() {
+    let mut first = header();
+    let mut second = header();
+    assert!(first.mine_with_clock(10_000_000, clock()));
+    assert!(second.mine_with_clock(10_000_000, clock()));
+
+    assert_eq!(first.nonce, second.nonce);
+    assert_eq!(first.timestamp, second.timestamp);
+    assert_eq!(first.hash(), second.hash());
+    // nonce가 넘친 뒤 clock이 준 시간으로 채굴되었다
+    assert!(first.timestamp > header().timestamp);
+}
@@ -0,0 +1,90 @@
+// peer가 보낸 잘못된 바이트로 메시지 해석이 panic 하지 않는지 확인한다.
+// lib/fuzz의 message_decode와 같은 검사를 stable에서 돌린다
+use btclib::network::{MAX_MESSAGE_SIZE, Message, NetworkError, ServiceFlags};
+use proptest::prelude::*;
+
+fn frame(body: &[u8]) -> Vec<u8> {
+    let mut frame = (body.len() as u64).to_be_bytes().to_vec();
+    frame.extend_from_slice(body);
+    frame
+}
+
+proptest! {
+    #[test]
+    fn decode_never_panics(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = Message::decode(&data);
+    }
+
+    #[test]
+    fn receive_never_panics(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = Message::receive(&mut data.as_slice());
+    }
+
+    // 정상적인 메시지의 일부만 받았을 때
+    #[test]
+    fn truncated_message_is_an_error(cut in 0usize..64) {
+        let body = Message::FetchBlock(7).encode().unwrap();
+        let cut = cut.min(body.len() - 1);
+        prop_assert!(matches!(
+            Message::decode(&body[..cut]),
+            Err(NetworkError::Decode(_))
+        ));
+    }
+}
+
+#[test]
+fn oversized_frame_is_rejected_before_reading() {
+    let len = (MAX_MESSAGE_SIZE as u64 + 1).to_be_bytes();
+    assert!(matches!(
+        Message::receive(&mut len.as_slice()),
+        Err(NetworkError::TooLarge(_))
+    ));
+}
+
+#[test]
+fn frame_shorter_than_its_length_is_a_closed_peer() {
+    let mut data = frame(&Message::FetchBlock(7).encode().unwrap());
+    data.pop();
+    assert!(matches!(
+        Message::receive(&mut data.as_slice()),
+        Err(NetworkError::PeerClosed(_))
+    ));
+}
+
+#[test]
+fn deeply_nested_input_is_an_error() {
+    // CBOR array 안의 array가 끝없이 이어진다
+    let data = vec![0x81; 100_000];
+    assert!(Message::decode(&data).is_err());
+}
+
+#[test]
+fn round_trips_through_a_frame() {
+    let data = frame(&Message::FetchBlock(7).encode().unwrap());
+    assert!(matches!(
+        Message::receive(&mut data.as_slice()),
+        Ok(Message::FetchBlock(7))
+    ));
+}
+
+#[test]
+fn only_full_nodes_get_block_relays() {
+    assert!(ServiceFlags::NETWORK.wants_relay());
+    assert!((ServiceFlags::NETWORK | ServiceFlags::MINING).wants_relay());
+    // miner는 템플릿을 받아가므로 새 블록을 먼저 보내주지 않는다
+    assert!(!ServiceFlags::MINING.wants_relay());
+    assert!(!ServiceFlags::NONE.wants_relay());
+}
+
+#[test]
+fn version_carries_service_flags() {
+    let services = ServiceFlags::NETWORK | ServiceFlags::MINING;
+    let data = frame(&Message::Version(services).encode().unwrap());
+    match Message::receive(&mut data.as_slice()) {
+        Ok(Message::Version(received)) => {
+            assert_eq!(received, services);
+            assert!(received.contains(ServiceFlags::MINING));
+        }
+        result => panic!("expected Version, got {result:?}"),
+    }
+}
@@ -0,0 +1,102 @@
+// dust output이나 부족한 수수료처럼 정책(policy)만 어긴 tx는 mempool에는 들어가지 못하지만,
+// 다른 노드가 채굴한 블록에 담겨 있다면 합의 규칙(consensus)상 유효한지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, Transaction, TransactionInput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Duration;
+
+const FEE: u64 = 10_000;
+
+// tip 다음에 transactions와 fees를 가져가는 coinbase를 담아 채굴한 블록
+fn mined_block(
+    blockchain: &Blockchain,
+    key: &PrivateKey,
+    fees: u64,
+    transaction: Transaction,
+) -> Block {
+    let height = blockchain.block_height();
+    let reward = Blockchain::block_reward_at(height);
+    let coinbase =
+        Transaction::coinbase(height, reward, fees, &key.public_key());
+    let transactions = vec![coinbase, transaction];
+    let tip = blockchain.blocks_rev().next().unwrap();
+    let mut block = Block::new(
+        BlockHeader::new(
+            tip.header.timestamp + Duration::seconds(10),
+            0,
+            tip.hash(),
+            MerkleRoot::calculate(&transactions),
+            blockchain.target(),
+        ),
+        transactions,
+    );
+    common::mine(&mut block);
+    block
+}
+
+// genesis의 coinbase를 values로 나누는 tx
+fn split(
+    blockchain: &Blockchain,
+    key: &PrivateKey,
+    values: &[u64],
+) -> Transaction {
+    let prev = &blockchain.blocks().next().unwrap().transactions[0].outputs[0];
+    let prev_hash = prev.hash();
+    Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        values.iter().map(|value| (key.public_key(), *value)).collect(),
+    )
+}
+
+#[test]
+fn dust_output_is_refused_by_policy_but_accepted_in_a_block() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    let reward = Blockchain::block_reward_at(0);
+
+    let dust = btclib::DUST_LIMIT;
+    let transaction = split(&blockchain, &key, &[dust, reward - dust - FEE]);
+    assert!(matches!(
+        Blockchain::check_policy(&transaction, FEE),
+        Err(BtcError::DustOutput)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::DustOutput)
+    ));
+    assert!(blockchain.mempool().is_empty());
+
+    let block = mined_block(&blockchain, &key, FEE, transaction.clone());
+    blockchain.check_consensus(&block).unwrap();
+    blockchain.add_block(block).unwrap();
+    assert_eq!(blockchain.block_height(), 2);
+    let dust_output = &transaction.outputs[0];
+    assert_eq!(blockchain.utxos()[&dust_output.hash()].1.value, dust);
+}
+
+#[test]
+fn transaction_without_a_fee_is_refused_by_policy_but_accepted_in_a_block() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    let reward = Blockchain::block_reward_at(0);
+
+    let transaction = split(&blockchain, &key, &[reward]);
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InsufficientFee)
+    ));
+
+    let block = mined_block(&blockchain, &key, 0, transaction);
+    blockchain.add_block(block).unwrap();
+    assert_eq!(blockchain.block_height(), 2);
+}
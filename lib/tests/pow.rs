@@ -0,0 +1,46 @@
+// Block::check_pow와 BlockHeader::check_pow가 채굴된 header와 채굴되지 않은 header를 구분하는지 확인한다
+mod common;
+
+use btclib::U256;
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::Blockchain;
+use chrono::{Duration, Utc};
+use common::{coinbase_block, mine};
+
+#[test]
+fn mined_header_passes_and_unmined_header_fails() {
+    let key = PrivateKey::new_key();
+    let timestamp = Utc::now() - Duration::minutes(10);
+    let mut genesis = coinbase_block(&key, 0, Hash::zero(), timestamp);
+    mine(&mut genesis);
+    let next = timestamp + Duration::seconds(10);
+    let mut mined = coinbase_block(&key, 1, genesis.hash(), next);
+    mine(&mut mined);
+    assert!(mined.header.check_pow());
+    assert!(mined.check_pow());
+    assert!(mined.header.hash().matches_target(mined.header.target));
+
+    // 같은 블록이라도 target을 만족하지 못하는 nonce라면 실패한다
+    let mut unmined = mined.clone();
+    while unmined.header.check_pow() {
+        unmined.header.nonce += 1;
+    }
+    assert!(!unmined.check_pow());
+    assert!(!unmined.header.hash().matches_target(unmined.header.target));
+
+    // 어떤 hash도 만족할 수 없는 target이면 채굴된 header도 실패한다
+    let mut impossible = mined.clone();
+    impossible.header.target = U256::zero();
+    assert!(!impossible.header.check_pow());
+
+    // add_block도 같은 검사로 거른다
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+    assert!(matches!(
+        blockchain.add_block(unmined),
+        Err(BtcError::InvalidProofOfWork)
+    ));
+    blockchain.add_block(mined).unwrap();
+}
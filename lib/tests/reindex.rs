@@ -0,0 +1,105 @@
+// 저장된 파일의 utxo와 target이 손상되어도 reindex가 블록들로부터 바른 상태를 다시 만든다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::sha256::Hash;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::Savable;
+use btclib::{DIFFICULTY_UPDATE_INTERVAL, MIN_TARGET, U256};
+use chrono::Duration;
+use ciborium::Value;
+use uuid::Uuid;
+
+// coinbase가 아닌 tx도 담긴 체인
+fn chain(key: &PrivateKey) -> (Blockchain, Transaction) {
+    let mut blockchain = Blockchain::new();
+    let spacing = Duration::seconds(5);
+    common::mine_run(&mut blockchain, key, 2, spacing);
+
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let prev_hash = prev.hash();
+    let transaction = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - 10_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+    blockchain.add_to_mempool(transaction.clone()).unwrap();
+    common::mine_run(&mut blockchain, key, 2, spacing);
+    (blockchain, transaction)
+}
+
+// utxo마다 (key, output hash, mark). 값을 비교할 수 있게 정렬한다
+fn utxo_hashes(blockchain: &Blockchain) -> Vec<(Hash, Hash, bool)> {
+    let mut utxos: Vec<_> = blockchain
+        .utxos()
+        .iter()
+        .map(|(hash, (marked, output))| (*hash, output.hash(), *marked))
+        .collect();
+    utxos.sort();
+    utxos
+}
+
+// 저장된 blockchain의 field 하나를 value로 바꾼다
+fn corrupt(saved: &[u8], field: &str, value: Value) -> Vec<u8> {
+    let mut stored: Value = ciborium::de::from_reader(saved).unwrap();
+    let (_, stored_value) = stored
+        .as_map_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|(key, _)| key.as_text() == Some(field))
+        .unwrap();
+    *stored_value = value;
+    let mut corrupted = vec![];
+    ciborium::ser::into_writer(&stored, &mut corrupted).unwrap();
+    corrupted
+}
+
+#[test]
+fn corrupted_state_is_rebuilt_by_reindex() {
+    let key = PrivateKey::new_key();
+    let (blockchain, transaction) = chain(&key);
+
+    let mut saved = vec![];
+    blockchain.save(&mut saved).unwrap();
+    let saved = corrupt(&saved, "utxos", Value::Map(vec![]));
+    // 조정 구간에 이르지 않은 체인이지만 난이도가 조정된 것처럼 꾸민다
+    let harder = MIN_TARGET >> 1;
+    let saved = corrupt(&saved, "target", Value::serialized(&harder).unwrap());
+    let forged: Vec<(u64, U256)> = vec![(DIFFICULTY_UPDATE_INTERVAL, harder)];
+    let saved =
+        corrupt(&saved, "target_history", Value::serialized(&forged).unwrap());
+
+    let mut loaded = Blockchain::load(saved.as_slice()).unwrap();
+    assert!(loaded.utxos().is_empty());
+    assert_eq!(loaded.target(), harder);
+    // index는 저장되지 않는다
+    assert!(loaded.transaction_by_hash(&transaction.hash()).is_none());
+
+    loaded.reindex().unwrap();
+    assert_eq!(utxo_hashes(&loaded), utxo_hashes(&blockchain));
+    assert_eq!(loaded.target(), MIN_TARGET);
+    assert!(loaded.target_history().is_empty());
+    for block in blockchain.blocks() {
+        assert!(loaded.block_by_hash(&block.hash()).is_some());
+    }
+    let found = loaded.transaction_by_hash(&transaction.hash()).unwrap();
+    assert_eq!(found.hash(), transaction.hash());
+    let page = |blockchain: &Blockchain| -> Vec<_> {
+        blockchain
+            .utxos_page(0, 100)
+            .into_iter()
+            .map(|(hash, output)| (hash, output.hash()))
+            .collect()
+    };
+    assert_eq!(page(&loaded), page(&blockchain));
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use btclib::HALVING_INTERVAL;
+use chrono::Utc;
+use common::output;
+use std::collections::HashMap;
+
+const PREV_OUTPUT_VALUE: u64 = 10_000;
+const FEE: u64 = 1_000;
+
+// FEE를 내는 tx 하나와, coinbase_value를 지급하는 coinbase가 담긴 블록,
+// 그리고 그 tx가 소비하는 utxo
+fn block_with_fee(
+    key: &PrivateKey,
+    coinbase_value: u64,
+) -> (Block, HashMap<Hash, (bool, TransactionOutput)>) {
+    let prev = output(key, PREV_OUTPUT_VALUE);
+    let prev_hash = prev.hash();
+    let transactions = vec![
+        Transaction::new(vec![], vec![output(key, coinbase_value)]),
+        Transaction::new(
+            vec![TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, key),
+            )],
+            vec![output(key, PREV_OUTPUT_VALUE - FEE)],
+        ),
+    ];
+    let block = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    (block, HashMap::from([(prev_hash, (false, prev))]))
+}
+
+#[test]
+fn reward_is_zero_after_64_halvings() {
+    let key = PrivateKey::new_key();
+
+    // 2^64로 나누려 했다면 panic이 났을 높이들
+    for halvings in [64, 65, 100, u32::MAX as u64] {
+        let height = halvings * HALVING_INTERVAL;
+        assert_eq!(Blockchain::block_reward_at(height), 0);
+
+        // 수수료만 가져가는 coinbase는 유효하다
+        let (block, utxos) = block_with_fee(&key, FEE);
+        block.verify_transactions(height, &utxos).unwrap();
+
+        // 보상을 더 가져가려 하면 거절된다
+        let (block, utxos) = block_with_fee(&key, FEE + 1);
+        assert!(block.verify_transactions(height, &utxos).is_err());
+    }
+}
+
+#[test]
+fn reward_is_halved_every_interval() {
+    let key = PrivateKey::new_key();
+    let height = HALVING_INTERVAL;
+    let reward = Blockchain::block_reward_at(height);
+    assert_eq!(reward, Blockchain::block_reward_at(0) / 2);
+
+    let (block, utxos) = block_with_fee(&key, reward + FEE);
+    block.verify_transactions(height, &utxos).unwrap();
+}
+
+#[test]
+fn cumulative_reward_matches_the_sum_of_block_rewards() {
+    let mut heights = vec![0, 1, 1_000 * HALVING_INTERVAL, u64::MAX];
+    for era in [1, 2, 33, 63, 64, 65] {
+        let boundary = era * HALVING_INTERVAL;
+        heights.extend([boundary - 1, boundary, boundary + 1]);
+    }
+    for height in heights {
+        // 64번 반감된 뒤로는 보상이 0이므로 그 전까지만 더해도 같다
+        let summed: u64 = (0..height.min(65 * HALVING_INTERVAL))
+            .map(Blockchain::block_reward_at)
+            .sum();
+        assert_eq!(
+            Blockchain::cumulative_block_reward(height),
+            summed,
+            "height {height}"
+        );
+    }
+}
+
+// genesis의 coinbase도 다른 블록처럼 보상 검사를 거친다
+#[test]
+fn over_rewarding_genesis_is_rejected() {
+    let key = PrivateKey::new_key();
+    let transactions = vec![Transaction::new(
+        vec![],
+        vec![output(&key, Blockchain::block_reward_at(0) + 1)],
+    )];
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    let mut blockchain = Blockchain::new();
+    assert!(matches!(
+        blockchain.add_block(genesis),
+        Err(BtcError::InvalidTransaction)
+    ));
+}
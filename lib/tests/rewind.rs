@@ -0,0 +1,68 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::types::{Blockchain, Transaction, TransactionInput};
+use chrono::Duration;
+
+#[test]
+fn rewind_drops_blocks_and_returns_their_transactions_to_the_mempool() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 2, Duration::seconds(10));
+    let before = blockchain.clone();
+
+    // genesis의 coinbase를 소비하는 tx를 담아 블록을 하나 더 채굴한다
+    let prev = &blockchain.blocks().next().unwrap().transactions[0].outputs[0];
+    let prev_hash = prev.hash();
+    let transaction = Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        vec![(key.public_key(), prev.value - 1_000)],
+    );
+    blockchain.add_to_mempool(transaction.clone()).unwrap();
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    assert!(blockchain.mempool().is_empty());
+
+    let removed = blockchain.rewind_to(2);
+    assert_eq!(removed.len(), 1);
+    assert_eq!(blockchain.block_height(), 2);
+    assert_eq!(blockchain.total_supply(), before.total_supply());
+    assert_eq!(blockchain.utxos().len(), before.utxos().len());
+    assert_eq!(blockchain.target(), before.target());
+    assert!(blockchain.transaction_by_hash(&transaction.hash()).is_none());
+
+    // 떼어낸 블록의 tx는 다시 채굴할 수 있도록 mempool로 돌아온다
+    let mempool: Vec<_> =
+        blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect();
+    assert_eq!(mempool, vec![transaction.hash()]);
+
+    // 되돌린 체인 위에 다시 블록을 쌓을 수 있다
+    common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+    assert_eq!(blockchain.block_height(), 3);
+}
+
+#[test]
+fn rewind_within_refuses_reorgs_deeper_than_the_limit() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 4, Duration::seconds(10));
+    let tip = blockchain.tip_hash();
+
+    // 4개 중 3개를 떼어내야 하므로 깊이 2까지만 허용하면 거절한다
+    match blockchain.rewind_within(1, 2) {
+        Err(BtcError::ReorgTooDeep(depth, max)) => {
+            assert_eq!((depth, max), (3, 2))
+        }
+        result => panic!("expected ReorgTooDeep, got {result:?}"),
+    }
+    assert_eq!(blockchain.block_height(), 4);
+    assert_eq!(blockchain.tip_hash(), tip);
+
+    // 한도 안이라면 되돌린다
+    let removed = blockchain.rewind_within(2, 2).unwrap();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(blockchain.block_height(), 2);
+}
@@ -0,0 +1,80 @@
+// 자기 자신이 만드는 output을 input으로 참조하는 tx가 mempool과 블록 모두에서 거부되는지 확인한다
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use uuid::Uuid;
+
+const HEIGHT: u64 = 1;
+
+fn block(prev_block_hash: Hash, transactions: Vec<Transaction>) -> Block {
+    Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            prev_block_hash,
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    )
+}
+
+fn input(key: &PrivateKey, prev: &TransactionOutput) -> TransactionInput {
+    let prev_hash = prev.hash();
+    TransactionInput::new(prev_hash, Signature::sign_output(&prev_hash, key))
+}
+
+#[test]
+fn transaction_spending_its_own_output_is_rejected() {
+    let key = PrivateKey::new_key();
+    let prev = TransactionOutput {
+        value: Blockchain::block_reward_at(0),
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    };
+    let genesis =
+        block(Hash::zero(), vec![Transaction::new(vec![], vec![prev.clone()])]);
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+
+    // utxo에 있는 output과 똑같은 output을 다시 만들면서 그 output을 소비한다.
+    // input이 가리키는 utxo는 존재하고 서명도 맞지만 자기 자신의 output이기도 하다
+    let own = prev.clone();
+    let transaction = Transaction::new(vec![input(&key, &own)], vec![own]);
+    assert!(transaction.spends_own_output());
+
+    assert!(matches!(
+        blockchain.check_transaction(&transaction),
+        Err(BtcError::InvalidTransactionInput)
+    ));
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InvalidTransactionInput)
+    ));
+    assert!(blockchain.mempool().is_empty());
+
+    let reward = Blockchain::block_reward_at(HEIGHT);
+    let coinbase = Transaction::coinbase(HEIGHT, reward, 0, &key.public_key());
+    let block =
+        block(blockchain.tip_hash().unwrap(), vec![coinbase, transaction]);
+    assert!(matches!(
+        block.verify_transactions(HEIGHT, blockchain.utxos()),
+        Err(BtcError::InvalidTransaction)
+    ));
+
+    // 새 output을 만들면 같은 input으로 소비할 수 있다
+    let fresh = TransactionOutput {
+        value: prev.value - 10_000,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    };
+    let transaction = Transaction::new(vec![input(&key, &prev)], vec![fresh]);
+    assert!(!transaction.spends_own_output());
+    blockchain.add_to_mempool(transaction).unwrap();
+}
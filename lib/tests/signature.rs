@@ -0,0 +1,139 @@
+// 서명의 (r, s)와 (r, n - s)는 모두 수학적으로 유효하다.
+// 서명을 바꿔치기해도 txid가 그대로인지, high-S 형태는 거부되는지 확인한다
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, LockingCondition, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use k256::Secp256k1;
+use uuid::Uuid;
+
+// 같은 서명의 (r, n - s) 형태
+fn flip_s(signature: &Signature) -> Signature {
+    let bytes = signature.to_bytes();
+    let inner = ecdsa::Signature::<Secp256k1>::from_slice(&bytes).unwrap();
+    let (r, s) = inner.split_scalars();
+    let flipped =
+        ecdsa::Signature::<Secp256k1>::from_scalars(r, -*s.as_ref()).unwrap();
+
+    // Signature는 내부 표현을 감추므로 직렬화를 거쳐 만든다
+    let mut encoded = vec![];
+    ciborium::into_writer(&flipped, &mut encoded).unwrap();
+    ciborium::from_reader(encoded.as_slice()).unwrap()
+}
+
+fn transaction(key: &PrivateKey) -> Transaction {
+    let prev_hash = Hash::hash_bytes(b"prev output");
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![TransactionOutput {
+            value: 1_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    )
+}
+
+#[test]
+fn re_encoding_a_signature_keeps_the_txid() {
+    let key = PrivateKey::new_key();
+    let original = transaction(&key);
+
+    let mut malleated = original.clone();
+    let signature = &mut malleated.inputs[0].signature;
+    *signature = flip_s(signature);
+    assert_ne!(
+        malleated.inputs[0].signature.to_bytes(),
+        original.inputs[0].signature.to_bytes()
+    );
+
+    // txid는 서명을 commit 하지 않는다
+    assert_eq!(malleated.hash(), original.hash());
+    // 블록은 wtxid로 서명까지 commit 한다
+    assert_ne!(malleated.wtxid(), original.wtxid());
+
+    // 다른 key로 새로 서명해도 txid는 같다
+    let mut resigned = original.clone();
+    let prev_hash = resigned.inputs[0].prev_transaction_output_hash;
+    resigned.inputs[0].signature =
+        Signature::sign_output(&prev_hash, &PrivateKey::new_key());
+    assert_eq!(resigned.hash(), original.hash());
+}
+
+#[test]
+fn high_s_signature_is_rejected_and_normalize_s_fixes_it() {
+    let key = PrivateKey::new_key();
+    let hash = Hash::hash_bytes(b"output");
+    let low = Signature::sign_output(&hash, &key);
+    assert!(low.is_low_s());
+    assert!(low.verify(&hash, &key.public_key()));
+
+    let high = flip_s(&low);
+    assert!(!high.is_low_s());
+    assert!(!high.verify(&hash, &key.public_key()));
+
+    let normalized = high.normalize_s();
+    assert!(normalized.is_low_s());
+    assert_eq!(normalized.to_bytes(), low.to_bytes());
+    assert!(normalized.verify(&hash, &key.public_key()));
+    // 이미 low-S라면 그대로다
+    assert_eq!(low.normalize_s().to_bytes(), low.to_bytes());
+}
+
+#[test]
+fn transaction_with_a_high_s_signature_is_rejected() {
+    let key = PrivateKey::new_key();
+    let coinbase = Transaction::new(
+        vec![],
+        vec![TransactionOutput {
+            value: Blockchain::block_reward_at(0),
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+    let prev = coinbase.outputs[0].clone();
+    let transactions = vec![coinbase];
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+
+    let prev_hash = prev.hash();
+    let mut transaction = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - 10_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+    let low = transaction.inputs[0].signature.clone();
+    transaction.inputs[0].signature = flip_s(&low);
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InvalidSignature)
+    ));
+
+    // 서명한 쪽에서 normalize_s를 거치면 받아들여진다
+    transaction.inputs[0].signature =
+        transaction.inputs[0].signature.normalize_s();
+    blockchain.add_to_mempool(transaction).unwrap();
+}
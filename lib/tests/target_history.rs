@@ -0,0 +1,154 @@
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::{Block, Blockchain};
+use btclib::util::Savable;
+use btclib::{DIFFICULTY_UPDATE_INTERVAL, MIN_TARGET, U256};
+
+// 블록 간격이 IDEAL_BLOCK_TIME의 절반이고 target_history 없이 저장된 체인
+fn old_chain(length: u64) -> Blockchain {
+    let mut blocks = vec![];
+    common::extend(&PrivateKey::new_key(), &mut blocks, length, 5);
+    common::load_unverified(&blocks)
+}
+
+#[test]
+fn one_entry_per_adjustment() {
+    let mut blockchain = old_chain(DIFFICULTY_UPDATE_INTERVAL * 2 + 10);
+    assert!(blockchain.target_history().is_empty());
+
+    blockchain.rebuild_indexes();
+    let expected: Vec<(u64, U256)> = [1, 2]
+        .map(|n| {
+            let height = n * DIFFICULTY_UPDATE_INTERVAL;
+            (height, blockchain.expected_target(height))
+        })
+        .to_vec();
+    assert_eq!(blockchain.target_history(), expected.as_slice());
+    // 블록이 빨리 쌓였으므로 조정마다 어려워졌다
+    assert!(expected[0].1 < MIN_TARGET);
+    assert!(expected[1].1 < expected[0].1);
+
+    assert_eq!(blockchain.target_at_adjustment(1), Some(expected[1]));
+    assert_eq!(blockchain.target_at_adjustment(2), None);
+
+    // 조정 구간 경계가 아니라면 기록하지 않는다
+    blockchain.try_adjust_target();
+    assert_eq!(blockchain.target_history().len(), 2);
+}
+
+#[test]
+fn readjusting_at_the_same_height_does_not_append() {
+    let mut blockchain = old_chain(DIFFICULTY_UPDATE_INTERVAL);
+    blockchain.rebuild_indexes();
+    assert_eq!(blockchain.target_history().len(), 1);
+
+    blockchain.try_adjust_target();
+    assert_eq!(blockchain.target_history().len(), 1);
+}
+
+#[test]
+fn history_is_saved_with_the_chain() {
+    let mut blockchain = old_chain(DIFFICULTY_UPDATE_INTERVAL);
+    blockchain.rebuild_indexes();
+
+    let mut saved = vec![];
+    blockchain.save(&mut saved).unwrap();
+    let loaded = Blockchain::load(saved.as_slice()).unwrap();
+    assert_eq!(loaded.target_history(), blockchain.target_history());
+}
+
+// 조정마다 genesis부터 블록을 다시 훑어서 계산한 target. expected_target과 비교하는 기준이다
+fn replayed_target(blockchain: &Blockchain, height: u64) -> U256 {
+    let interval = DIFFICULTY_UPDATE_INTERVAL as usize;
+    let blocks: Vec<&Block> = blockchain.blocks().collect();
+    let mut target = MIN_TARGET;
+    let mut boundary = interval;
+    while boundary <= height as usize {
+        let start = blocks[boundary - interval].header.timestamp;
+        let end = blocks[boundary - 1].header.timestamp;
+        target = blockchain.params().adjust_target(target, end - start);
+        boundary += interval;
+    }
+    target
+}
+
+#[test]
+fn expected_target_changes_exactly_at_adjustment_boundaries() {
+    let mut blockchain = old_chain(DIFFICULTY_UPDATE_INTERVAL * 3);
+    blockchain.rebuild_indexes();
+
+    let boundaries = [1, 2, 3].map(|n| n * DIFFICULTY_UPDATE_INTERVAL);
+    for boundary in boundaries {
+        for height in [boundary - 1, boundary, boundary + 1] {
+            assert_eq!(
+                blockchain.expected_target(height),
+                replayed_target(&blockchain, height),
+                "height {height}"
+            );
+        }
+        // 경계 직전까지는 이전 구간의 target을 쓴다
+        assert_ne!(
+            blockchain.expected_target(boundary - 1),
+            blockchain.expected_target(boundary)
+        );
+    }
+    assert_eq!(blockchain.expected_target(0), MIN_TARGET);
+    // 아직 블록이 없는 높이는 다음 블록의 target이다
+    assert_eq!(
+        blockchain.expected_target(u64::MAX),
+        blockchain.expected_target(blockchain.block_height())
+    );
+}
+
+#[test]
+fn tampered_history_is_recomputed() {
+    let mut blockchain = old_chain(DIFFICULTY_UPDATE_INTERVAL * 2);
+    blockchain.rebuild_indexes();
+    let history = blockchain.target_history().to_vec();
+
+    // 저장된 history의 target만 바꾼다
+    let mut saved = vec![];
+    blockchain.save(&mut saved).unwrap();
+    let mut value: ciborium::Value =
+        ciborium::de::from_reader(saved.as_slice()).unwrap();
+    let fields = value.as_map_mut().unwrap();
+    let (_, stored) = fields
+        .iter_mut()
+        .find(|(key, _)| key.as_text() == Some("target_history"))
+        .unwrap();
+    let forged = [1, 2].map(|n| (n * DIFFICULTY_UPDATE_INTERVAL, MIN_TARGET));
+    *stored = ciborium::Value::serialized(&forged).unwrap();
+    let mut tampered = vec![];
+    ciborium::ser::into_writer(&value, &mut tampered).unwrap();
+
+    let mut loaded = Blockchain::load(tampered.as_slice()).unwrap();
+    loaded.rebuild_indexes();
+    assert_eq!(loaded.target_history(), history.as_slice());
+}
+
+#[test]
+fn equal_timestamps_across_the_window_give_a_nonzero_target() {
+    // 조정 구간의 모든 블록이 같은 timestamp를 가진다
+    let mut blocks = vec![];
+    common::extend(
+        &PrivateKey::new_key(),
+        &mut blocks,
+        DIFFICULTY_UPDATE_INTERVAL + 1,
+        0,
+    );
+    let timestamp = blocks[0].header.timestamp;
+    assert!(blocks.iter().all(|block| block.header.timestamp == timestamp));
+
+    let mut blockchain = common::load_unverified(&blocks);
+    blockchain.rebuild_indexes();
+
+    // 걸린 시간을 0이 아니라 최소 간격으로 보므로 한 번에 25%까지만 어려워진다
+    let target = blockchain.expected_target(DIFFICULTY_UPDATE_INTERVAL);
+    assert_eq!(target, MIN_TARGET / 4);
+    assert_eq!(
+        blockchain.target_history(),
+        [(DIFFICULTY_UPDATE_INTERVAL, target)]
+    );
+    assert!(!target.is_zero());
+}
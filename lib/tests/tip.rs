@@ -0,0 +1,62 @@
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::Blockchain;
+use chrono::Duration;
+
+#[test]
+fn empty_chain_has_no_tip() {
+    let blockchain = Blockchain::new();
+    assert_eq!(blockchain.height(), None);
+    assert_eq!(blockchain.tip_hash(), None);
+    assert!(blockchain.tip_header().is_none());
+}
+
+#[test]
+fn tip_accessors_match_the_last_block() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+
+    for height in 0..3 {
+        common::mine_run(&mut blockchain, &key, 1, Duration::seconds(10));
+        let last = blockchain.blocks().last().unwrap();
+
+        assert_eq!(blockchain.height(), Some(height));
+        assert_eq!(blockchain.tip_hash(), Some(last.hash()));
+        assert_eq!(
+            blockchain.block_by_hash(&last.hash()).unwrap().hash(),
+            last.hash()
+        );
+
+        let header = blockchain.tip_header().unwrap();
+        assert_eq!(header.hash(), last.header.hash());
+        assert_eq!(header.timestamp, last.header.timestamp);
+
+        // 다음 템플릿은 tip 블록의 hash로 이어진다
+        let template = blockchain.build_template(key.public_key()).unwrap();
+        assert_eq!(template.header.prev_block_hash, last.hash());
+    }
+}
+
+#[test]
+fn blocks_rev_goes_from_tip_to_genesis() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    assert!(blockchain.blocks_rev().next().is_none());
+
+    common::mine_run(&mut blockchain, &key, 4, Duration::seconds(10));
+    let reversed: Vec<_> =
+        blockchain.blocks_rev().map(|block| block.hash()).collect();
+    assert_eq!(reversed.len(), 4);
+    assert_eq!(reversed.first(), blockchain.tip_hash().as_ref());
+    assert_eq!(
+        reversed.last(),
+        Some(&blockchain.blocks().next().unwrap().hash())
+    );
+
+    // blocks를 뒤집은 순서와 같다
+    let mut forward: Vec<_> =
+        blockchain.blocks().map(|block| block.hash()).collect();
+    forward.reverse();
+    assert_eq!(reversed, forward);
+}
@@ -0,0 +1,187 @@
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::{MerkleRoot, Savable};
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn output(lock: LockingCondition, value: u64) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock,
+    }
+}
+
+fn input(key: &PrivateKey) -> TransactionInput {
+    let prev_hash = Hash::hash(&Uuid::new_v4());
+    TransactionInput::new(prev_hash, Signature::sign_output(&prev_hash, key))
+}
+
+fn encoded_len(transaction: &Transaction) -> usize {
+    let mut encoded = vec![];
+    transaction.save(&mut encoded).unwrap();
+    encoded.len()
+}
+
+// 모양이 서로 다른 tx들: coinbase, 단순 송금, RBF + multisig, 잠금 조건이 섞인 output들
+fn representative_transactions() -> Vec<Transaction> {
+    let key = PrivateKey::new_key();
+    let other = PrivateKey::new_key();
+    let p2pk = || LockingCondition::P2PK(key.public_key());
+
+    let coinbase = Transaction::coinbase(7, 5_000, 300, &key.public_key());
+
+    let payment = Transaction::new(vec![input(&key)], vec![output(p2pk(), 10)]);
+
+    let mut multisig_input = input(&key);
+    multisig_input
+        .extra_signatures
+        .push(Signature::sign_output(&Hash::zero(), &other));
+    multisig_input.sequence = btclib::MAX_RBF_SEQUENCE;
+    let replaceable = Transaction::new(
+        vec![multisig_input, input(&other), input(&key)],
+        vec![
+            output(
+                LockingCondition::MultiSig {
+                    keys: vec![key.public_key(), other.public_key()],
+                    threshold: 2,
+                },
+                1_000,
+            ),
+            output(p2pk(), 2_000),
+        ],
+    );
+
+    let mixed_outputs = Transaction::new(
+        vec![input(&other)],
+        vec![
+            output(
+                LockingCondition::CheckLockTimeVerify {
+                    height: 1_000,
+                    inner: Box::new(p2pk()),
+                },
+                u64::MAX,
+            ),
+            output(LockingCondition::RawScript(vec![0xab; 40]), 0),
+            output(LockingCondition::Unspendable, 1),
+        ],
+    );
+
+    vec![coinbase, payment, replaceable, mixed_outputs]
+}
+
+#[test]
+fn serialized_size_matches_the_cbor_encoding() {
+    for transaction in representative_transactions() {
+        assert_eq!(transaction.serialized_size(), encoded_len(&transaction));
+    }
+}
+
+#[test]
+fn serialized_size_grows_with_witness_data() {
+    let key = PrivateKey::new_key();
+    let mut transaction = Transaction::new(
+        vec![input(&key)],
+        vec![output(LockingCondition::P2PK(key.public_key()), 10)],
+    );
+    let size = transaction.serialized_size();
+    let weight = transaction.weight();
+
+    // 서명은 weight에 1배로만 들어간다
+    transaction.inputs[0]
+        .extra_signatures
+        .push(Signature::sign_output(&Hash::zero(), &key));
+    let added = transaction.serialized_size() - size;
+    assert!(added > 0);
+    assert_eq!(transaction.weight() - weight, added);
+}
+
+#[test]
+fn counts_inputs_and_outputs() {
+    let counts: Vec<_> = representative_transactions()
+        .iter()
+        .map(|transaction| {
+            (transaction.input_count(), transaction.output_count())
+        })
+        .collect();
+    assert_eq!(counts, vec![(0, 1), (1, 1), (3, 2), (1, 3)]);
+}
+
+#[test]
+fn weight_tracks_input_and_output_counts() {
+    let key = PrivateKey::new_key();
+    let p2pk = || output(LockingCondition::P2PK(key.public_key()), 10);
+    let weight = |inputs: usize, outputs: usize| {
+        Transaction::new(
+            (0..inputs).map(|_| input(&key)).collect(),
+            (0..outputs).map(|_| p2pk()).collect(),
+        )
+        .weight()
+    };
+
+    // input이나 output이 하나 늘 때마다 weight도 는다
+    for count in 1..10 {
+        assert!(weight(count + 1, 1) > weight(count, 1));
+        assert!(weight(1, count + 1) > weight(1, count));
+    }
+
+    // 서명은 할인되므로 input 하나는 직렬화된 크기보다 적게 무거워진다
+    let transaction = Transaction::new(vec![input(&key)], vec![p2pk()]);
+    let mut two_inputs = transaction.clone();
+    two_inputs.inputs.push(input(&key));
+    let added_size =
+        two_inputs.serialized_size() - transaction.serialized_size();
+    let added_weight = two_inputs.weight() - transaction.weight();
+    assert!(added_weight < added_size * btclib::WITNESS_SCALE_FACTOR);
+    assert_eq!(
+        transaction.vsize(),
+        transaction.weight().div_ceil(btclib::WITNESS_SCALE_FACTOR)
+    );
+}
+
+#[test]
+fn overweight_block_is_rejected() {
+    let key = PrivateKey::new_key();
+    let block = |script_len: usize| {
+        let coinbase = Transaction::new(
+            vec![],
+            vec![
+                output(LockingCondition::P2PK(key.public_key()), 10),
+                output(LockingCondition::RawScript(vec![0; script_len]), 1),
+            ],
+        );
+        let transactions = vec![coinbase];
+        Block::new(
+            BlockHeader::new(
+                Utc::now(),
+                0,
+                Hash::zero(),
+                MerkleRoot::calculate(&transactions),
+                btclib::MIN_TARGET,
+            ),
+            transactions,
+        )
+    };
+
+    // 서명이 없으므로 byte마다 WITNESS_SCALE_FACTOR만큼 무겁다
+    let heavy = block(btclib::MAX_BLOCK_WEIGHT / btclib::WITNESS_SCALE_FACTOR);
+    assert!(heavy.weight() > btclib::MAX_BLOCK_WEIGHT);
+    assert!(matches!(
+        heavy.verify_transactions(0, &HashMap::new()),
+        Err(BtcError::InvalidBlock)
+    ));
+
+    // 한도 안이라면 weight 때문에 거부되지는 않는다
+    let light = block(1_000);
+    assert!(light.weight() < btclib::MAX_BLOCK_WEIGHT);
+    assert!(!matches!(
+        light.verify_transactions(0, &HashMap::new()),
+        Err(BtcError::InvalidBlock)
+    ));
+}
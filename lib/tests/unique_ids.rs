@@ -0,0 +1,100 @@
+// 한 tx 안의 output들이 같은 unique_id를 쓰면 블록과 mempool 모두에서 거부되는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use common::output;
+use uuid::Uuid;
+
+const HEIGHT: u64 = 1;
+
+fn block(transactions: Vec<Transaction>) -> Block {
+    Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    )
+}
+
+// prev를 두 output으로 나누는 tx. 두 output은 같은 unique_id를 쓴다
+fn duplicated(key: &PrivateKey, prev: &TransactionOutput) -> Transaction {
+    let prev_hash = prev.hash();
+    let first = output(key, prev.value / 2);
+    let mut second = output(key, prev.value / 2 - 1_000);
+    second.unique_id = first.unique_id;
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![first, second],
+    )
+}
+
+#[test]
+fn duplicated_ids_in_one_transaction_are_rejected() {
+    let key = PrivateKey::new_key();
+    let genesis = block(vec![Transaction::new(
+        vec![],
+        vec![output(&key, Blockchain::block_reward_at(0))],
+    )]);
+    let prev = genesis.transactions[0].outputs[0].clone();
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+
+    let transaction = duplicated(&key, &prev);
+    assert!(!transaction.has_unique_output_ids());
+    assert!(matches!(
+        blockchain.add_to_mempool(transaction.clone()),
+        Err(BtcError::InvalidTransactionOutput)
+    ));
+    assert!(blockchain.mempool().is_empty());
+
+    let fees = prev.value
+        - transaction.outputs.iter().map(|output| output.value).sum::<u64>();
+    let reward = Blockchain::block_reward_at(HEIGHT);
+    let coinbase =
+        Transaction::coinbase(HEIGHT, reward, fees, &key.public_key());
+    let block = block(vec![coinbase, transaction.clone()]);
+    assert!(matches!(
+        block.verify_transactions(HEIGHT, blockchain.utxos()),
+        Err(BtcError::InvalidTransaction)
+    ));
+
+    // id만 다르게 하면 같은 tx가 받아들여진다
+    let mut distinct = transaction;
+    distinct.outputs[1].unique_id = Uuid::new_v4();
+    assert!(distinct.has_unique_output_ids());
+    blockchain.add_to_mempool(distinct).unwrap();
+}
+
+#[test]
+fn coinbase_with_duplicated_ids_is_rejected() {
+    let key = PrivateKey::new_key();
+    let reward = Blockchain::block_reward_at(HEIGHT);
+    let mut coinbase = Transaction::coinbase_with_payouts(
+        HEIGHT,
+        reward,
+        0,
+        &[(key.public_key(), 1), (PrivateKey::new_key().public_key(), 1)],
+    )
+    .unwrap();
+    coinbase.outputs[1].unique_id = coinbase.outputs[0].unique_id;
+
+    assert!(matches!(
+        block(vec![coinbase]).verify_transactions(HEIGHT, &Default::default()),
+        Err(BtcError::InvalidTransaction)
+    ));
+}
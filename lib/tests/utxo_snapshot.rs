@@ -0,0 +1,169 @@
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::params::{ChainParams, UtxoCheckpoint};
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain, Transaction, TransactionInput};
+use chrono::Duration;
+use std::collections::BTreeMap;
+
+// 채굴자 key와, 중간에 채굴자에게서 돈을 받은 key로 쌓은 체인
+fn chain() -> (Blockchain, PrivateKey, PrivateKey) {
+    let miner = PrivateKey::new_key();
+    let payee = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &miner, 2, Duration::seconds(10));
+
+    let prev = &blockchain.blocks().next().unwrap().transactions[0].outputs[0];
+    let prev_hash = prev.hash();
+    let payment = Transaction::with_derived_ids(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &miner),
+        )],
+        vec![
+            (payee.public_key(), 1_000_000),
+            (miner.public_key(), prev.value - 1_000_000 - 1_000),
+        ],
+    );
+    blockchain.add_to_mempool(payment).unwrap();
+    common::mine_run(&mut blockchain, &miner, 2, Duration::seconds(10));
+
+    (blockchain, miner, payee)
+}
+
+// 비교하기 쉽도록 utxo set을 (output hash -> (mark, 값)) 정렬된 map으로 바꾼다
+fn utxo_set(blockchain: &Blockchain) -> BTreeMap<Hash, (bool, u64)> {
+    blockchain
+        .utxos()
+        .iter()
+        .map(|(hash, (marked, output))| (*hash, (*marked, output.value)))
+        .collect()
+}
+
+fn blocks(blockchain: &Blockchain) -> Vec<Block> {
+    blockchain.blocks().cloned().collect()
+}
+
+// 검증 없이 읽은 블록들에 checkpoint를 아는 파라미터를 적용한다.
+// 읽은 직후의 체인은 utxo set이 비어 있다
+fn loaded_with_checkpoint(
+    blocks: &[Block],
+    checkpoint: UtxoCheckpoint,
+) -> Blockchain {
+    let mut blockchain = common::load_unverified(blocks);
+    blockchain
+        .set_params(ChainParams {
+            utxo_checkpoints: vec![checkpoint],
+            ..ChainParams::default()
+        })
+        .unwrap();
+    assert!(blockchain.utxos().is_empty());
+    blockchain
+}
+
+fn export(blockchain: &Blockchain, height: u64) -> (Vec<u8>, UtxoCheckpoint) {
+    let mut snapshot = vec![];
+    let utxo_hash =
+        blockchain.export_utxo_snapshot(height, &mut snapshot).unwrap();
+    let block_hash =
+        blockchain.blocks().nth(height as usize - 1).unwrap().hash();
+    let checkpoint = UtxoCheckpoint {
+        height,
+        block_hash,
+        utxo_hash,
+    };
+    (snapshot, checkpoint)
+}
+
+#[test]
+fn import_reproduces_the_utxo_set_and_balances() {
+    let (original, miner, payee) = chain();
+    let (snapshot, checkpoint) = export(&original, original.block_height());
+
+    let mut imported = loaded_with_checkpoint(&blocks(&original), checkpoint);
+    imported.import_utxo_snapshot(snapshot.as_slice()).unwrap();
+
+    assert_eq!(utxo_set(&imported), utxo_set(&original));
+    for key in [&miner, &payee] {
+        assert_eq!(
+            imported.balance_for(&key.public_key()),
+            original.balance_for(&key.public_key())
+        );
+    }
+    assert_eq!(imported.balance_for(&payee.public_key()), 1_000_000);
+    assert_eq!(imported.total_supply(), original.total_supply());
+}
+
+#[test]
+fn blocks_after_the_snapshot_are_applied_on_top() {
+    let (original, _, payee) = chain();
+    // 송금이 담긴 블록(높이 2) 이전의 snapshot
+    let (snapshot, checkpoint) = export(&original, 2);
+
+    let mut imported = loaded_with_checkpoint(&blocks(&original), checkpoint);
+    imported.import_utxo_snapshot(snapshot.as_slice()).unwrap();
+
+    assert_eq!(utxo_set(&imported), utxo_set(&original));
+    assert_eq!(imported.balance_for(&payee.public_key()), 1_000_000);
+}
+
+#[test]
+fn snapshot_is_deterministic() {
+    let (original, _, _) = chain();
+    let (first, _) = export(&original, 3);
+    let (second, _) = export(&original.clone(), 3);
+    assert_eq!(first, second);
+
+    assert!(
+        original
+            .export_utxo_snapshot(original.block_height() + 1, &mut vec![])
+            .is_err()
+    );
+}
+
+#[test]
+fn snapshot_must_match_a_checkpoint() {
+    let (original, _, _) = chain();
+    let (snapshot, checkpoint) = export(&original, 3);
+
+    // checkpoint가 없다면 받아들이지 않는다
+    let mut unknown = common::load_unverified(&blocks(&original));
+    assert!(matches!(
+        unknown.import_utxo_snapshot(snapshot.as_slice()),
+        Err(BtcError::UtxoSnapshotNotCheckpointed)
+    ));
+
+    // 다른 utxo set을 가리키는 checkpoint
+    let other = UtxoCheckpoint {
+        utxo_hash: Hash::zero(),
+        ..checkpoint.clone()
+    };
+    let mut mismatched = loaded_with_checkpoint(&blocks(&original), other);
+    assert!(matches!(
+        mismatched.import_utxo_snapshot(snapshot.as_slice()),
+        Err(BtcError::UtxoSnapshotNotCheckpointed)
+    ));
+    assert!(mismatched.utxos().is_empty());
+
+    // 손상된 파일
+    let mut corrupted = snapshot.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    let mut blockchain =
+        loaded_with_checkpoint(&blocks(&original), checkpoint.clone());
+    assert!(matches!(
+        blockchain.import_utxo_snapshot(corrupted.as_slice()),
+        Err(BtcError::InvalidUtxoSnapshot)
+    ));
+
+    // checkpoint는 맞지만 그 블록이 없는 다른 체인
+    let (other_chain, _, _) = chain();
+    let mut blockchain =
+        loaded_with_checkpoint(&blocks(&other_chain), checkpoint);
+    assert!(matches!(
+        blockchain.import_utxo_snapshot(snapshot.as_slice()),
+        Err(BtcError::InvalidUtxoSnapshot)
+    ));
+}
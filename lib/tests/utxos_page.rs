@@ -0,0 +1,66 @@
+// utxos_page를 offset을 늘려가며 부르면 전체 utxo를 겹치지 않게 한 번씩 훑는다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use chrono::Duration;
+use std::collections::HashSet;
+
+fn pages(blockchain: &Blockchain, limit: usize) -> Vec<Vec<Hash>> {
+    let mut pages = vec![];
+    let mut offset = 0;
+    loop {
+        let page: Vec<_> = blockchain
+            .utxos_page(offset, limit)
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+        if page.is_empty() {
+            return pages;
+        }
+        offset += page.len();
+        pages.push(page);
+    }
+}
+
+fn assert_covers(blockchain: &Blockchain, limit: usize) {
+    let pages = pages(blockchain, limit);
+    assert!(pages.iter().all(|page| page.len() <= limit));
+
+    let listed: Vec<Hash> = pages.into_iter().flatten().collect();
+    // hash 순으로 정렬되어 있으므로 겹치는 항목이 없다
+    assert!(listed.windows(2).all(|pair| pair[0] < pair[1]));
+    let listed: HashSet<Hash> = listed.into_iter().collect();
+    let all: HashSet<Hash> = blockchain.utxos().keys().copied().collect();
+    assert_eq!(listed, all);
+}
+
+#[test]
+fn pages_are_disjoint_and_cover_every_utxo() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 7, Duration::seconds(10));
+    assert_eq!(blockchain.utxos().len(), 7);
+
+    for limit in [1, 2, 3, 7, 100] {
+        assert_covers(&blockchain, limit);
+    }
+    assert!(blockchain.utxos_page(7, 10).is_empty());
+    assert!(blockchain.utxos_page(0, 0).is_empty());
+
+    // 블록이 더해지거나 체인을 되돌려도 순서가 utxo와 함께 바뀐다
+    common::mine_run(&mut blockchain, &key, 3, Duration::seconds(10));
+    assert_covers(&blockchain, 4);
+    blockchain.rewind_to(5);
+    assert_eq!(blockchain.utxos().len(), 5);
+    assert_covers(&blockchain, 4);
+
+    // 저장된 체인을 읽은 뒤에도 같은 page를 돌려준다
+    let mut saved = vec![];
+    blockchain.save(&mut saved).unwrap();
+    let mut loaded = Blockchain::load(saved.as_slice()).unwrap();
+    loaded.rebuild_indexes();
+    assert_eq!(pages(&loaded, 2), pages(&blockchain, 2));
+}
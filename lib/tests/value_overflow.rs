@@ -0,0 +1,87 @@
+// tx의 input, output 값을 더하다 u64를 넘어가면 wraparound 되지 않고 거부되는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use common::output;
+use std::collections::HashMap;
+
+const HEIGHT: u64 = 1;
+
+type Utxos = HashMap<Hash, (bool, TransactionOutput)>;
+
+// prevs를 모두 소비해서 outputs를 만드는 tx와 수수료 없는 coinbase의 블록
+fn block(
+    key: &PrivateKey,
+    prevs: &[TransactionOutput],
+    outputs: Vec<TransactionOutput>,
+) -> (Block, Utxos) {
+    let inputs = prevs
+        .iter()
+        .map(|prev| {
+            let prev_hash = prev.hash();
+            TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, key),
+            )
+        })
+        .collect();
+    let utxos =
+        prevs.iter().map(|prev| (prev.hash(), (false, prev.clone()))).collect();
+
+    let reward = Blockchain::block_reward_at(HEIGHT);
+    let coinbase = Transaction::coinbase(HEIGHT, reward, 0, &key.public_key());
+    let transactions = vec![coinbase, Transaction::new(inputs, outputs)];
+    let block = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    (block, utxos)
+}
+
+#[test]
+fn outputs_summing_past_u64_max_are_rejected() {
+    let key = PrivateKey::new_key();
+    // wraparound 된다면 output 합계는 u64::MAX - 1이 되어 수수료 1을 내는 tx처럼 보인다
+    let (block, utxos) = block(
+        &key,
+        &[output(&key, u64::MAX)],
+        vec![output(&key, u64::MAX), output(&key, u64::MAX)],
+    );
+    assert!(matches!(
+        block.verify_transactions(HEIGHT, &utxos),
+        Err(BtcError::InvalidTransaction)
+    ));
+    assert!(matches!(
+        block.calculate_miner_fees(&utxos),
+        Err(BtcError::InvalidTransaction)
+    ));
+}
+
+#[test]
+fn inputs_summing_past_u64_max_are_rejected() {
+    let key = PrivateKey::new_key();
+    // wraparound 된다면 input 합계는 u64::MAX - 1이 되어 output을 덮는 것처럼 보인다
+    let (block, utxos) = block(
+        &key,
+        &[output(&key, u64::MAX), output(&key, u64::MAX)],
+        vec![output(&key, u64::MAX - 1)],
+    );
+    assert!(matches!(
+        block.verify_transactions(HEIGHT, &utxos),
+        Err(BtcError::InvalidTransaction)
+    ));
+}
@@ -0,0 +1,128 @@
+// verify 바이너리로 체인 파일을 검증한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain, Transaction, TransactionInput};
+use btclib::util::{MerkleRoot, Savable};
+use chrono::Duration;
+use std::path::Path;
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+fn verify(path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_verify")).arg(path).output().unwrap()
+}
+
+// test가 끝나면 지워지는 체인 파일
+struct ChainFile(std::path::PathBuf);
+
+impl ChainFile {
+    fn new(blockchain: &Blockchain) -> Self {
+        let path = std::env::temp_dir()
+            .join(format!("verify-{}.cbor", Uuid::new_v4()));
+        blockchain.save_to_file(&path).unwrap();
+        ChainFile(path)
+    }
+}
+
+impl Drop for ChainFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn mined_blocks(count: u64) -> Vec<Block> {
+    let mut blockchain = Blockchain::new();
+    common::mine_run(
+        &mut blockchain,
+        &PrivateKey::new_key(),
+        count,
+        Duration::seconds(10),
+    );
+    blockchain.blocks().cloned().collect()
+}
+
+#[test]
+fn valid_chain_is_ok() {
+    let file = ChainFile::new(&common::load_unverified(&mined_blocks(4)));
+
+    let output = verify(&file.0);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "OK (4 blocks)\n");
+}
+
+#[test]
+fn tampered_chain_reports_the_first_invalid_block() {
+    let mut blocks = mined_blocks(4);
+    // 블록 2의 coinbase 보상을 부풀린다. 블록 3은 그대로다
+    blocks[2].transactions[0].outputs[0].value += 1;
+    let tampered = blocks[2].hash();
+    let file = ChainFile::new(&common::load_unverified(&blocks));
+
+    let output = verify(&file.0);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("FAILED at block 2 ({tampered})")));
+    assert!(stdout.contains("Invalid Merkle root"), "{stdout}");
+
+    // merkle root까지 맞춰 고치면 PoW가 깨진다
+    blocks[2].header.merkle_root =
+        MerkleRoot::calculate(&blocks[2].transactions);
+    let file = ChainFile::new(&common::load_unverified(&blocks));
+    let stdout = String::from_utf8_lossy(&verify(&file.0).stdout).to_string();
+    assert!(stdout.contains("FAILED at block 2"), "{stdout}");
+}
+
+#[test]
+fn unreadable_file_fails_to_load() {
+    let path =
+        std::env::temp_dir().join(format!("verify-{}.cbor", Uuid::new_v4()));
+    assert_eq!(verify(&path).status.code(), Some(1));
+}
+
+#[test]
+fn broken_prev_link_reports_the_block() {
+    let mut blocks = mined_blocks(4);
+    // 블록 2가 블록 1이 아닌 다른 블록을 가리키도록 다시 채굴한다
+    blocks[2].header.prev_block_hash = blocks[0].hash();
+    common::mine(&mut blocks[2]);
+    let relinked = blocks[2].hash();
+    let file = ChainFile::new(&common::load_unverified(&blocks));
+
+    let output = verify(&file.0);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("FAILED at block 2 ({relinked})")));
+    assert!(stdout.contains("Invalid block"), "{stdout}");
+}
+
+// genesis도 merkle root와 coinbase를 검증한다
+#[test]
+fn genesis_is_validated_like_any_other_block() {
+    let genesis = mined_blocks(1).remove(0);
+
+    let mut wrong_root = genesis.clone();
+    wrong_root.header.merkle_root =
+        MerkleRoot::calculate(&[Transaction::new(vec![], vec![])]);
+    assert!(matches!(
+        Blockchain::new().add_block(wrong_root),
+        Err(BtcError::InvalidMerkleRoot)
+    ));
+
+    // coinbase가 input을 가진다
+    let mut spending = genesis.clone();
+    let prev = Hash::hash_bytes(b"prev output");
+    let key = PrivateKey::new_key();
+    spending.transactions[0]
+        .inputs
+        .push(TransactionInput::new(prev, Signature::sign_output(&prev, &key)));
+    spending.header.merkle_root = MerkleRoot::calculate(&spending.transactions);
+    assert!(matches!(
+        Blockchain::new().add_block(spending),
+        Err(BtcError::InvalidCoinbase)
+    ));
+
+    Blockchain::new().add_block(genesis).unwrap();
+}
@@ -0,0 +1,112 @@
+// would_accept가 add_block과 같은 결과를 돌려주면서 체인은 바꾸지 않는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::error::{BtcError, Result};
+use btclib::sha256::Hash;
+use btclib::types::{Block, Blockchain};
+use btclib::util::{MerkleRoot, Savable};
+use chrono::Duration;
+use common::mine;
+
+// 체인을 저장한 바이트. 블록, utxo, target이 모두 들어 있다
+fn snapshot(blockchain: &Blockchain) -> Vec<u8> {
+    let mut bytes = vec![];
+    blockchain.save(&mut bytes).unwrap();
+    bytes
+}
+
+// tip 다음에 붙일 수 있는, 채굴하지 않은 템플릿
+fn template(blockchain: &Blockchain, key: &PrivateKey) -> Block {
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    block.header.timestamp =
+        blockchain.tip_header().unwrap().timestamp + Duration::seconds(10);
+    block
+}
+
+// would_accept의 결과가 add_block과 같고, would_accept는 체인을 바꾸지 않는지.
+// would_accept의 결과를 돌려준다
+fn dry_run(blockchain: &Blockchain, block: &Block) -> Result<()> {
+    let before = snapshot(blockchain);
+    let tip = blockchain.tip_hash();
+    let dry_run = blockchain.would_accept(block);
+    assert_eq!(snapshot(blockchain), before);
+    assert_eq!(blockchain.tip_hash(), tip);
+
+    let mut added = blockchain.clone();
+    let result = added.add_block(block.clone());
+    assert_eq!(format!("{dry_run:?}"), format!("{result:?}"));
+    if result.is_err() {
+        assert_eq!(snapshot(&added), before);
+    }
+    dry_run
+}
+
+#[test]
+fn dry_run_gives_the_add_block_result() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    common::mine_run(&mut blockchain, &key, 2, Duration::seconds(10));
+
+    let mut valid = template(&blockchain, &key);
+    mine(&mut valid);
+    dry_run(&blockchain, &valid).unwrap();
+
+    // 이미 체인에 있는 블록
+    let tip = blockchain.blocks_rev().next().unwrap().clone();
+    assert!(matches!(
+        dry_run(&blockchain, &tip),
+        Err(BtcError::DuplicateBlock)
+    ));
+
+    // 채굴하지 않은 블록
+    let mut unmined = template(&blockchain, &key);
+    while unmined.header.check_pow() {
+        unmined.header.nonce += 1;
+    }
+    assert!(matches!(
+        dry_run(&blockchain, &unmined),
+        Err(BtcError::InvalidProofOfWork)
+    ));
+
+    // 다른 블록에 이어지는 블록
+    let mut orphan = template(&blockchain, &key);
+    orphan.header.prev_block_hash = Hash::zero();
+    mine(&mut orphan);
+    assert!(matches!(
+        dry_run(&blockchain, &orphan),
+        Err(BtcError::InvalidBlock)
+    ));
+
+    // tx가 header의 merkle root와 맞지 않는 블록
+    let mut tampered = valid.clone();
+    tampered.transactions[0].outputs[0].value += 1;
+    assert!(matches!(
+        dry_run(&blockchain, &tampered),
+        Err(BtcError::InvalidMerkleRoot)
+    ));
+
+    // 보상보다 많이 가져가는 coinbase
+    let mut inflated = template(&blockchain, &key);
+    inflated.transactions[0].outputs[0].value += 1;
+    inflated.header.merkle_root = MerkleRoot::calculate(&inflated.transactions);
+    mine(&mut inflated);
+    assert!(matches!(
+        dry_run(&blockchain, &inflated),
+        Err(BtcError::InvalidTransaction)
+    ));
+
+    // 너무 먼 미래의 블록
+    let mut future = template(&blockchain, &key);
+    future.header.timestamp +=
+        Duration::seconds(btclib::MAX_FUTURE_BLOCK_TIME as i64 + 60 * 60);
+    mine(&mut future);
+    assert!(matches!(
+        dry_run(&blockchain, &future),
+        Err(BtcError::BlockFromFuture)
+    ));
+
+    // 확인만 한 블록은 그대로 추가할 수 있다
+    blockchain.add_block(valid.clone()).unwrap();
+    assert_eq!(blockchain.tip_hash(), Some(valid.hash()));
+}
@@ -0,0 +1,23 @@
+use btclib::types::Block;
+use flume::{Receiver, Sender, TrySendError};
+
+// 제출을 기다리는 채굴된 블록의 최대 수. 최신 블록만 의미가 있다
+pub const MINED_BLOCK_CHANNEL_CAPACITY: usize = 1;
+
+// 채굴된 블록을 제출하도록 넘긴다. 제출되지 못하고 밀려 있는 블록은 이미 낡은
+// 템플릿으로 만든 것이므로 버리고 최신 블록을 넣는다. 버린 블록들을 돌려준다.
+// 채굴 스레드는 제출을 기다리며 멈추지 않는다
+pub fn hand_off(
+    sender: &Sender<Block>,
+    receiver: &Receiver<Block>,
+    mut block: Block,
+) -> Vec<Block> {
+    let mut stale = vec![];
+    while let Err(TrySendError::Full(rejected)) = sender.try_send(block) {
+        if let Ok(discarded) = receiver.try_recv() {
+            stale.push(discarded);
+        }
+        block = rejected;
+    }
+    stale
+}
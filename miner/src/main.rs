@@ -1,9 +1,10 @@
 use anyhow::{anyhow, Result};
 use btclib::crypto::PublicKey;
-use btclib::network::Message;
+use btclib::network::{Message, ServiceFlags};
 use btclib::types::Block;
 use btclib::util::Savable;
 use clap::Parser;
+use miner::{hand_off, MINED_BLOCK_CHANNEL_CAPACITY};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
@@ -24,12 +25,14 @@ struct Cli {
 
 // 서버가 템플릿을 주면, 채굴 스레드가 그 템플릿으로 채굴을 하고, 결과물은 메인 스레드가 서버에 제출
 struct Miner {
-    public_key: PublicKey,
+    /// coinbase 보상을 받을 key. 채굴 중에도 바꿀 수 있다
+    public_key: std::sync::Mutex<PublicKey>,
+    /// public key를 다시 읽어 올 파일
+    public_key_file: String,
     /// node와의 연결
     stream: Mutex<TcpStream>,
     current_template: Arc<std::sync::Mutex<Option<Block>>>,
     mining: Arc<AtomicBool>,
-    /// 
     mined_block_sender: flume::Sender<Block>,
     mined_block_receiver: flume::Receiver<Block>,
 }
@@ -38,16 +41,22 @@ impl Miner {
     async fn new(
         address: String,
         public_key: PublicKey,
+        public_key_file: String,
     ) -> Result<Self> {
         // address와의 connection
-        let stream = TcpStream::connect(&address).await?;
+        let mut stream = TcpStream::connect(&address).await?;
+        // 템플릿만 받아가므로 새 블록과 tx를 전파받을 필요가 없다
+        Message::Version(ServiceFlags::MINING)
+            .send_async(&mut stream)
+            .await?;
 
-        // unbounded queue in heap memory
+        // 제출이 밀려도 메모리가 계속 늘지 않도록 크기를 제한한다
         let (mined_block_sender, mined_block_receiver) =
-            flume::unbounded();
+            flume::bounded(MINED_BLOCK_CHANNEL_CAPACITY);
 
         Ok(Self {
-            public_key,
+            public_key: std::sync::Mutex::new(public_key),
+            public_key_file,
             stream: Mutex::new(stream),
             current_template: Arc::new(std::sync::Mutex::new(
                 None,
@@ -69,6 +78,7 @@ impl Miner {
 
             tokio::select! {
                 _ = template_interval.tick() => {
+                    self.reload_public_key();
                     self.fetch_and_validate_template().await?;
                 }
                 // mining이 성공하면 flume mq를 통해서 submit_block이 트리거 된다. 
@@ -83,11 +93,13 @@ impl Miner {
         let template = self.current_template.clone();
         let mining = self.mining.clone();
         let sender = self.mined_block_sender.clone();
+        let receiver = self.mined_block_receiver.clone();
 
         // single thread dedicated to mining
         thread::spawn(move || loop {
             if mining.load(Ordering::Relaxed) {
-                if let Some(mut block) = template.lock().unwrap().clone() {
+                let current = template.lock().unwrap().clone();
+                if let Some(mut block) = current {
                     println!(
                         "Mining block with target: {}",
                         block.header.target
@@ -100,10 +112,13 @@ impl Miner {
                             block.hash()
                         );
 
-                        // 채굴 성공시 
-                        sender.send(block).expect(
-                            "Failed to send mined block",
-                        );
+                        // 채굴 성공시
+                        for stale in hand_off(&sender, &receiver, block) {
+                            println!(
+                                "Discarding stale mined block: {}",
+                                stale.hash()
+                            );
+                        }
 
                         mining.store(false, Ordering::Relaxed);
                     }
@@ -127,10 +142,36 @@ impl Miner {
         Ok(())
     }
 
+    // key 파일이 바뀌었다면 새 key로 보상을 받도록 한다.
+    // 파일을 읽지 못하면 기존 key로 계속 채굴한다
+    fn reload_public_key(&self) {
+        match PublicKey::load_from_file(&self.public_key_file) {
+            Ok(public_key) => self.set_public_key(public_key),
+            Err(e) => println!("Failed to reload public key: {e}"),
+        }
+    }
+
+    // coinbase 보상을 받을 key를 바꾼다. 이전 key로 만든 템플릿과 채굴된 블록은 버리고
+    // 다음 주기에 새 key로 템플릿을 다시 요청한다
+    fn set_public_key(&self, public_key: PublicKey) {
+        let mut current = self.public_key.lock().unwrap();
+        if *current == public_key {
+            return;
+        }
+        println!("Payout key changed, discarding current template");
+        *current = public_key;
+        drop(current);
+
+        *self.current_template.lock().unwrap() = None;
+        self.mining.store(false, Ordering::Relaxed);
+        self.mined_block_receiver.drain();
+    }
+
     // 서버로부터 template을 받아온다 
     async fn fetch_template(&self) -> Result<()> {
         println!("Fetching new template");
-        let message = Message::FetchTemplate(self.public_key.clone());
+        let public_key = self.public_key.lock().unwrap().clone();
+        let message = Message::FetchTemplate(public_key.clone());
 
         let mut stream_lock = self.stream.lock().await;
         message.send_async(&mut *stream_lock).await?;
@@ -143,6 +184,14 @@ impl Miner {
                 drop(stream_lock);
                 println!("Received new template with target: {}", template.header.target);
 
+                // 다른 key에게 보상을 주는 템플릿은 채굴해 봐야 남 좋은 일이다.
+                // miner를 멈추지는 않고, 버린 뒤 다음 주기에 다시 요청한다
+                if !pays_to(&template, &public_key) {
+                    println!("Template coinbase does not pay our public key, refusing to mine it");
+                    *self.current_template.lock().unwrap() = None;
+                    return Ok(());
+                }
+
                 // miner 객체에 template을 지정한다 
                 *self.current_template.lock().unwrap() = Some(template);
 
@@ -151,12 +200,18 @@ impl Miner {
 
                 Ok(())
             }
+            // 너무 자주 요청했다. 다음 주기에 다시 시도한다
+            Message::Throttled => {
+                println!("Node is throttling template requests");
+                Ok(())
+            }
             _ => Err(anyhow!("Unexpected message received when fetching template")),
         }
     }
 
     async fn validate_template(&self) -> Result<()> {
-        if let Some(template) = self.current_template.lock().unwrap().clone() {
+        let template = self.current_template.lock().unwrap().clone();
+        if let Some(template) = template {
 
             // 현 template의 validity를 확인하기 위해 node에 전송한다 
             let message = Message::ValidateTemplate(template);
@@ -186,6 +241,8 @@ impl Miner {
 
     // 채굴된 블록을 node로 전송한다  
     async fn submit_block(&self, block: Block) -> Result<()> {
+        // 채굴이 끝난 블록만 보내야 한다. PoW가 틀린 블록은 node가 거부한다
+        debug_assert!(block.check_pow(), "BUG: submitting an unmined block");
         println!("Submitting mined block");
         let message = Message::SubmitTemplate(block);
         let mut stream_lock = self.stream.lock().await;
@@ -197,6 +254,17 @@ impl Miner {
     }
 }
 
+// 템플릿의 coinbase output이 모두 public_key에게 가는지
+fn pays_to(template: &Block, public_key: &PublicKey) -> bool {
+    template.transactions.first().is_some_and(|coinbase| {
+        coinbase.is_coinbase()
+            && coinbase
+                .outputs
+                .iter()
+                .all(|output| output.lock.pubkey() == Some(public_key))
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -207,7 +275,8 @@ async fn main() -> Result<()> {
                 anyhow!("Error reading public key: {}", e)
             })?;
 
-    let miner = Miner::new(cli.address, public_key).await?;
+    let miner =
+        Miner::new(cli.address, public_key, cli.public_key_file).await?;
 
     // main loop 
     miner.run().await
@@ -0,0 +1,132 @@
+// 실제 miner 바이너리를 가짜 node에 연결해서 띄우는 test 도구
+#![allow(dead_code)]
+
+use btclib::crypto::PublicKey;
+use btclib::network::Message;
+use btclib::types::{Block, Blockchain};
+use btclib::util::Savable;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// miner가 연결할 node처럼 응답한다. 템플릿을 요청받은 key와 제출받은 블록을 기록한다
+pub struct FakeNode {
+    pub port: u16,
+    pub requested_keys: Arc<Mutex<Vec<PublicKey>>>,
+    pub submitted: Receiver<Block>,
+}
+
+impl FakeNode {
+    // FetchTemplate에는 pay_to(요청한 key)에게 보상을 주는 템플릿으로 답한다
+    pub fn start(
+        pay_to: impl Fn(&PublicKey) -> PublicKey + Send + 'static,
+    ) -> FakeNode {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requested_keys = Arc::new(Mutex::new(vec![]));
+        let (sender, submitted) = mpsc::channel();
+
+        let keys = requested_keys.clone();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Ok(message) = Message::receive(&mut stream) {
+                let reply = match message {
+                    Message::FetchTemplate(key) => {
+                        keys.lock().unwrap().push(key.clone());
+                        let template =
+                            Blockchain::new().build_template(pay_to(&key));
+                        Message::Template(template.unwrap())
+                    }
+                    Message::ValidateTemplate(_) => {
+                        Message::TemplateValidity(true)
+                    }
+                    Message::SubmitTemplate(block) => {
+                        let _ = sender.send(block);
+                        continue;
+                    }
+                    // handshake에는 답하지 않는다
+                    _ => continue,
+                };
+                if reply.send(&mut stream).is_err() {
+                    return;
+                }
+            }
+        });
+
+        FakeNode {
+            port,
+            requested_keys,
+            submitted,
+        }
+    }
+
+    // 제출된 블록을 기다린다. timeout 안에 오지 않으면 None
+    pub fn next_submitted(&self, timeout: Duration) -> Option<Block> {
+        self.submitted.recv_timeout(timeout).ok()
+    }
+}
+
+// 테스트가 끝나면 miner 프로세스를 종료하고 key 파일을 지운다
+pub struct Miner {
+    process: Child,
+    key_file: PathBuf,
+}
+
+impl Miner {
+    pub fn start(node: &FakeNode, key: &PublicKey) -> Miner {
+        let key_file =
+            std::env::temp_dir().join(format!("miner-{}.pub", Uuid::new_v4()));
+        key.save_to_file(&key_file).unwrap();
+        let process = Command::new(env!("CARGO_BIN_EXE_miner"))
+            .arg("--address")
+            .arg(format!("127.0.0.1:{}", node.port))
+            .arg("--public-key-file")
+            .arg(&key_file)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        Miner {
+            process,
+            key_file,
+        }
+    }
+
+    // 보상을 받을 key를 바꾼다. miner는 다음 템플릿 주기에 파일을 다시 읽는다
+    pub fn set_key(&self, key: &PublicKey) {
+        key.save_to_file_atomic(&self.key_file).unwrap();
+    }
+
+    // 프로세스가 끝나기를 기다린다. timeout 안에 끝나지 않으면 None
+    pub fn wait_exit(&mut self, timeout: Duration) -> Option<ExitStatus> {
+        let started = Instant::now();
+        while started.elapsed() < timeout {
+            if let Some(status) = self.process.try_wait().unwrap() {
+                return Some(status);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        None
+    }
+}
+
+impl Drop for Miner {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_file(&self.key_file);
+    }
+}
+
+// coinbase의 output이 모두 key에게 가는지
+pub fn pays(block: &Block, key: &PublicKey) -> bool {
+    block.transactions[0]
+        .outputs
+        .iter()
+        .all(|output| output.lock.pubkey() == Some(key))
+}
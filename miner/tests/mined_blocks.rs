@@ -0,0 +1,49 @@
+// 채굴된 블록을 넘기는 channel이 가득 차도 채굴 스레드가 멈추지 않고,
+// 제출되지 못한 낡은 블록은 버려지는지 확인한다
+use btclib::crypto::PrivateKey;
+use btclib::types::{Block, Blockchain};
+use miner::{MINED_BLOCK_CHANNEL_CAPACITY, hand_off};
+
+// nonce로 서로 구별되는 블록들
+fn blocks(count: u64) -> Vec<Block> {
+    let key = PrivateKey::new_key();
+    let template = Blockchain::new().build_template(key.public_key()).unwrap();
+    (0..count)
+        .map(|nonce| {
+            let mut block = template.clone();
+            block.header.nonce = nonce;
+            block
+        })
+        .collect()
+}
+
+#[test]
+fn full_channel_keeps_only_the_newest_block() {
+    let (sender, receiver) = flume::bounded(MINED_BLOCK_CHANNEL_CAPACITY);
+    let blocks = blocks(100);
+
+    // 아무도 받아 가지 않아도 넘기는 쪽은 기다리지 않는다
+    let mut stale = vec![];
+    for block in &blocks {
+        stale.extend(hand_off(&sender, &receiver, block.clone()));
+    }
+
+    let kept = blocks.len() - MINED_BLOCK_CHANNEL_CAPACITY;
+    assert_eq!(
+        stale.iter().map(Block::hash).collect::<Vec<_>>(),
+        blocks[..kept].iter().map(Block::hash).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        receiver.drain().map(|block| block.hash()).collect::<Vec<_>>(),
+        blocks[kept..].iter().map(Block::hash).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn nothing_is_discarded_while_blocks_are_submitted() {
+    let (sender, receiver) = flume::bounded(MINED_BLOCK_CHANNEL_CAPACITY);
+    for block in blocks(3) {
+        assert!(hand_off(&sender, &receiver, block.clone()).is_empty());
+        assert_eq!(receiver.try_recv().unwrap().hash(), block.hash());
+    }
+}
@@ -0,0 +1,56 @@
+// miner가 자기 key에게 보상을 주는 템플릿만 채굴하고(다른 key의 템플릿은 버리고 다시 요청한다),
+// key를 바꾸면 새 key로 템플릿을 다시 받아 채굴하는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use common::{FakeNode, Miner, pays};
+use std::time::Duration;
+
+// 템플릿은 5초마다 요청하므로 두세 주기를 기다린다
+const TIMEOUT: Duration = Duration::from_secs(30);
+// 처음 요청한 뒤 적어도 한 번은 다시 요청할 만큼
+const RETRY: Duration = Duration::from_secs(12);
+
+#[test]
+fn coinbase_pays_the_new_key_after_a_switch() {
+    let old_key = PrivateKey::new_key().public_key();
+    let new_key = PrivateKey::new_key().public_key();
+    let node = FakeNode::start(|key| key.clone());
+    let miner = Miner::start(&node, &old_key);
+
+    let block = node.next_submitted(TIMEOUT).expect("nothing was mined");
+    assert!(block.check_pow());
+    assert!(pays(&block, &old_key));
+
+    // 바꾸기 전에 채굴하던 블록이 더 올 수 있으므로 새 key의 블록이 나올 때까지 받는다
+    miner.set_key(&new_key);
+    let block = loop {
+        let block = node
+            .next_submitted(TIMEOUT)
+            .expect("nothing was mined with the new key");
+        if !pays(&block, &old_key) {
+            break block;
+        }
+    };
+    assert!(block.check_pow());
+    assert!(pays(&block, &new_key));
+    assert_eq!(node.requested_keys.lock().unwrap().last(), Some(&new_key));
+}
+
+#[test]
+fn template_paying_another_key_is_refused() {
+    let key = PrivateKey::new_key().public_key();
+    let thief = PrivateKey::new_key().public_key();
+    let node = FakeNode::start({
+        let thief = thief.clone();
+        move |_| thief.clone()
+    });
+    let mut miner = Miner::start(&node, &key);
+
+    // 채굴하지 않지만 멈추지도 않고, 주기마다 템플릿을 다시 요청한다
+    assert!(node.next_submitted(RETRY).is_none());
+    assert!(miner.wait_exit(Duration::from_secs(1)).is_none());
+    let requested = node.requested_keys.lock().unwrap().clone();
+    assert!(requested.len() >= 2, "template was requested once");
+    assert!(requested.iter().all(|requested| *requested == key));
+}
@@ -0,0 +1,157 @@
+use btclib::amount::Amount;
+use btclib::crypto::PublicKey;
+use btclib::network::Message;
+use btclib::sha256::Hash;
+use btclib::util::Savable;
+use serde_json::{Value, json};
+use std::env;
+use std::net::TcpStream;
+use std::process::exit;
+
+const USAGE: &str = "Usage: explore <node_address> <command>\n\
+    commands:\n  \
+    tip\n  \
+    difficulty\n  \
+    metrics\n  \
+    mempool <limit>\n  \
+    block <hash|height>\n  \
+    tx <hash>\n  \
+    balance <public_key_file>";
+
+// node에 질의만 하는 read-only 탐색기. 결과는 pretty JSON으로 출력한다
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        exit(1);
+    }
+
+    let mut stream = match TcpStream::connect(&args[1]) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {e}", args[1]);
+            exit(1);
+        }
+    };
+
+    match (args[2].as_str(), args.get(3)) {
+        ("tip", None) => match request(&mut stream, Message::FetchTip) {
+            Message::Tip(Some((height, hash))) => print_json(&json!({
+                "height": height,
+                "hash": hash.to_string(),
+            })),
+            Message::Tip(None) => fail("node has no blocks"),
+            message => unexpected(message),
+        },
+        ("difficulty", None) => {
+            match request(&mut stream, Message::FetchDifficulty) {
+                Message::Difficulty(info) => print_json(&json!(info)),
+                message => unexpected(message),
+            }
+        }
+        // Prometheus 형식 그대로 출력한다
+        ("metrics", None) => {
+            match request(&mut stream, Message::FetchMetrics) {
+                Message::Metrics(metrics) => print!("{metrics}"),
+                message => unexpected(message),
+            }
+        }
+        // 수수료율이 높은 순으로 최대 limit개
+        ("mempool", Some(arg)) => {
+            let limit = arg
+                .parse()
+                .unwrap_or_else(|_| fail(&format!("Invalid limit: {arg}")));
+            match request(&mut stream, Message::GetTopMempool { limit }) {
+                Message::TopMempool(transactions) => print_json(
+                    &transactions
+                        .iter()
+                        .map(|tx| {
+                            json!({
+                                "hash": tx.hash().to_string(),
+                                "transaction": tx,
+                            })
+                        })
+                        .collect(),
+                ),
+                Message::Throttled => fail("throttled, try again later"),
+                message => unexpected(message),
+            }
+        }
+        ("block", Some(arg)) => {
+            // 숫자면 높이, 아니면 해시로 조회한다
+            let message = if let Ok(height) = arg.parse::<usize>() {
+                Message::FetchBlock(height)
+            } else {
+                Message::FetchBlockByHash(parse_hash(arg))
+            };
+            match request(&mut stream, message) {
+                Message::NewBlock(block)
+                | Message::FoundBlock(Some(block)) => print_json(&json!({
+                    "hash": block.hash().to_string(),
+                    "block": block,
+                })),
+                Message::FoundBlock(None) => fail("block not found"),
+                message => unexpected(message),
+            }
+        }
+        ("tx", Some(arg)) => {
+            let message = Message::FetchTransaction(parse_hash(arg));
+            match request(&mut stream, message) {
+                Message::FoundTransaction(Some(tx)) => print_json(&json!({
+                    "hash": tx.hash().to_string(),
+                    "transaction": tx,
+                })),
+                Message::FoundTransaction(None) => {
+                    fail("transaction not found")
+                }
+                message => unexpected(message),
+            }
+        }
+        ("balance", Some(arg)) => {
+            let public_key = PublicKey::load_from_file(arg)
+                .unwrap_or_else(|e| fail(&format!("Invalid public key: {e}")));
+            match request(&mut stream, Message::FetchUTXOs(public_key)) {
+                Message::UTXOs(utxos) => {
+                    let balance: u64 =
+                        utxos.iter().map(|(output, _)| output.value).sum();
+                    print_json(&json!({
+                        "balance": Amount::from_sat(balance).to_string(),
+                        "balance_sat": balance,
+                        "utxos": utxos.len(),
+                    }))
+                }
+                message => unexpected(message),
+            }
+        }
+        _ => fail(USAGE),
+    }
+}
+
+fn request(stream: &mut TcpStream, message: Message) -> Message {
+    if let Err(e) = message.send(stream) {
+        fail(&format!("Failed to send request: {e}"));
+    }
+    // 존재하지 않는 높이의 FetchBlock에는 node가 응답 없이 연결을 닫는다
+    Message::receive(stream)
+        .unwrap_or_else(|e| fail(&format!("No response from node: {e}")))
+}
+
+fn parse_hash(s: &str) -> Hash {
+    s.parse().unwrap_or_else(|_| fail(&format!("Invalid hash: {s}")))
+}
+
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("Failed to encode JSON")
+    );
+}
+
+fn unexpected(message: Message) {
+    fail(&format!("Unexpected message from node: {message:?}"))
+}
+
+fn fail(reason: &str) -> ! {
+    eprintln!("{reason}");
+    exit(1);
+}
@@ -0,0 +1,69 @@
+use btclib::network::Message;
+use btclib::types::{MempoolAcceptance, Transaction};
+use btclib::util::Savable;
+use std::env;
+use std::net::TcpStream;
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: tx_send <node_address> <tx_file>");
+        exit(1);
+    }
+
+    let transaction = Transaction::load_from_file(&args[2])
+        .expect("Failed to load transaction");
+
+    let mut stream = match TcpStream::connect(&args[1]) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {e}", args[1]);
+            exit(1);
+        }
+    };
+
+    let txid = transaction.hash();
+    if let Err(e) = Message::SubmitTransaction(transaction).send(&mut stream) {
+        eprintln!("Failed to send transaction: {e}");
+        exit(1);
+    }
+
+    // node는 mempool 추가 결과를 응답한다
+    match Message::receive(&mut stream) {
+        Ok(Message::TransactionAcceptance(Some(
+            MempoolAcceptance::Accepted,
+        ))) => {
+            println!("transaction {txid} accepted");
+        }
+        Ok(Message::TransactionAcceptance(Some(
+            MempoolAcceptance::Replaced(replaced),
+        ))) => {
+            println!("transaction {txid} accepted, replacing:");
+            for replaced_txid in replaced {
+                println!("  {replaced_txid}");
+            }
+        }
+        Ok(Message::TransactionAcceptance(Some(
+            MempoolAcceptance::Conflict(conflicting),
+        ))) => {
+            eprintln!(
+                "transaction {txid} rejected: conflicts with mempool \
+                transaction {conflicting}"
+            );
+            exit(1);
+        }
+        Ok(Message::TransactionAcceptance(None)) => {
+            eprintln!("transaction {txid} rejected");
+            exit(1);
+        }
+        Ok(message) => {
+            eprintln!("Unexpected message from node: {message:?}");
+            exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to receive response: {e}");
+            exit(1);
+        }
+    }
+}
@@ -1,24 +1,252 @@
+use btclib::error::BtcError;
 use btclib::sha256::Hash;
-use chrono::Utc;
-use uuid::Uuid;
+use std::net::SocketAddr;
 
 use tokio::net::TcpStream;
+use tokio::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 
-use btclib::network::Message;
-use btclib::types::{
-    Block, BlockHeader, Transaction, TransactionOutput,
-};
-use btclib::util::MerkleRoot;
+use btclib::network::{Message, NetworkError, ServiceFlags};
+use btclib::crypto::PublicKey;
+use btclib::types::{Block, Blockchain, MempoolAcceptance};
+use std::collections::BTreeMap;
+
+use crate::mempool_events::MempoolChange;
+use crate::util;
+
+// 한 번의 FetchBlocks 요청에 응답할 최대 블록 수
+const MAX_BLOCKS_PER_REQUEST: usize = 500;
+
+// StreamBlocks에 응답할 때 BlockChunk 하나에 담을 블록 수
+const BLOCK_STREAM_CHUNK: usize = 16;
+
+// StreamBlocks에 응답할 때 ack를 받지 않고 보낼 수 있는 BlockChunk 수.
+// 받는 쪽이 검증하는 속도보다 빨리 보내서 버퍼가 끝없이 쌓이지 않도록 한다
+const BLOCK_STREAM_WINDOW: usize = 4;
+
+// GetBlocks의 locator에서 살펴볼 최대 hash 수.
+// 정상적인 locator는 체인 길이의 로그 정도 길이이므로 그 이상은 볼 필요가 없다
+const MAX_LOCATOR_LENGTH: usize = 101;
+
+// 한 번의 GetTopMempool 요청에 응답할 최대 tx 수
+const MAX_TOP_MEMPOOL_TRANSACTIONS: usize = 1000;
+
+// 연결당 TEMPLATE_RATE_WINDOW 동안 허용하는 FetchTemplate 요청 수
+const TEMPLATE_RATE_LIMIT: u32 = 12;
+const TEMPLATE_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// FetchTemplate(와 GetTopMempool)는 mempool을 훑는 비싼 요청이므로 연결마다 제한한다
+struct TemplateLimiter {
+    window_start: Instant,
+    requests: u32,
+}
+
+impl TemplateLimiter {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            requests: 0,
+        }
+    }
+
+    // 요청 한도를 넘었다면 false
+    fn allow(&mut self) -> bool {
+        if self.window_start.elapsed() >= TEMPLATE_RATE_WINDOW {
+            self.window_start = Instant::now();
+            self.requests = 0;
+        }
+        self.requests += 1;
+        self.requests <= TEMPLATE_RATE_LIMIT
+    }
+}
+
+// 조립된 템플릿 캐시. tip과 mempool generation이 그대로라면 템플릿도 그대로이므로
+// 다시 만들지 않고 같은 public key로 요청한 miner들에게 재사용한다
+pub struct TemplateCache {
+    tip: Hash,
+    mempool_generation: u64,
+    templates: BTreeMap<PublicKey, Block>,
+}
+
+impl TemplateCache {
+    pub fn new() -> Self {
+        Self {
+            tip: Hash::zero(),
+            mempool_generation: 0,
+            templates: BTreeMap::new(),
+        }
+    }
+
+    fn get(
+        &mut self,
+        blockchain: &Blockchain,
+        pubkey: &PublicKey,
+    ) -> Option<Block> {
+        self.invalidate_if_stale(blockchain);
+        self.templates.get(pubkey).cloned()
+    }
+
+    fn insert(
+        &mut self,
+        blockchain: &Blockchain,
+        pubkey: PublicKey,
+        block: Block,
+    ) {
+        self.invalidate_if_stale(blockchain);
+        self.templates.insert(pubkey, block);
+    }
+
+    // 블록이 추가되었거나 mempool이 바뀌었다면 캐시를 비운다
+    fn invalidate_if_stale(&mut self, blockchain: &Blockchain) {
+        let tip = blockchain.tip_hash().unwrap_or(Hash::zero());
+        if tip != self.tip
+            || blockchain.mempool_generation() != self.mempool_generation
+        {
+            self.tip = tip;
+            self.mempool_generation = blockchain.mempool_generation();
+            self.templates.clear();
+        }
+    }
+}
+
+// 규칙 위반 시 올릴 ban 점수. 세 번 위반하면 ban 된다
+const MISBEHAVIOR_SCORE: u32 = 34;
+
+// 단순히 경쟁 상황(이미 받은 블록, 더 먼저 채굴된 블록 등)이나 시계 차이에서도
+// 생길 수 있는 에러는 제외하고, 명백히 잘못된 블록만 위반으로 본다
+fn is_invalid_block(e: &BtcError) -> bool {
+    !matches!(
+        e,
+        BtcError::InvalidBlock
+            | BtcError::DuplicateBlock
+            | BtcError::BlockFromFuture
+    )
+}
+
+// miner나 RPC로 제출된 블록을 체인에 추가하고, 받아들였다면 구독자와 full node들에게 알린다.
+// 받아들이지 않았다면 그 이유를 돌려준다
+pub async fn accept_external_block(block: Block) -> Result<(), BtcError> {
+    {
+        let mut blockchain = crate::BLOCKCHAIN.write().await;
+        if let Err(e) = blockchain.add_block(block.clone()) {
+            crate::METRICS.block_rejected(&e);
+            return Err(e);
+        }
+
+        crate::METRICS.block_accepted();
+        crate::MEMPOOL_EVENTS.publish(&blockchain);
+    }
+
+    let _ = crate::BLOCK_EVENTS.send(block.header.clone());
+
+    println!("block looks good, broadcasting");
+
+    // send block to all friend nodes
+    let nodes = util::relay_nodes();
+
+    let message = Message::NewBlock(block);
+    for node in nodes {
+        if let Err(e) = util::send_to_node(&node, &message).await {
+            println!("failed to send block to {}: {e}", node);
+        }
+    }
+
+    Ok(())
+}
+
+// 요청한 peer에게 응답한다. 연결이 끊겼다면 false
+async fn reply(socket: &mut TcpStream, message: Message) -> bool {
+    match message.send_async(socket).await {
+        Ok(()) => true,
+        Err(e) => {
+            println!("failed to reply to peer: {e}");
+            false
+        }
+    }
+}
+
+// from_height부터 tip까지 블록을 BlockChunk로 나눠 보내고, 끝나면 빈 chunk를 보낸다.
+// 보내는 동안 채택된 블록도 이어서 보낸다. 연결을 닫아야 한다면 false
+async fn stream_blocks(socket: &mut TcpStream, from_height: u64) -> bool {
+    let mut height = from_height as usize;
+    let mut unacked = 0;
+
+    loop {
+        // 보내는 동안에는 lock을 잡고 있지 않는다
+        let chunk = crate::BLOCKCHAIN
+            .read()
+            .await
+            .blocks()
+            .skip(height)
+            .take(BLOCK_STREAM_CHUNK)
+            .cloned()
+            .collect::<Vec<_>>();
+        let done = chunk.is_empty();
+        height += chunk.len();
+
+        if !reply(socket, Message::BlockChunk(chunk)).await {
+            return false;
+        }
+        if done {
+            break;
+        }
+
+        unacked += 1;
+        while unacked >= BLOCK_STREAM_WINDOW {
+            if !receive_chunk_ack(socket).await {
+                return false;
+            }
+            unacked -= 1;
+        }
+    }
+
+    // 남은 ack를 마저 읽어서 다음 요청과 섞이지 않게 한다
+    for _ in 0..unacked {
+        if !receive_chunk_ack(socket).await {
+            return false;
+        }
+    }
+    true
+}
+
+// stream 도중에는 BlockChunkAck만 받는다. 다른 메시지가 오거나 연결이 끊겼다면 false
+async fn receive_chunk_ack(socket: &mut TcpStream) -> bool {
+    match Message::receive_async(socket).await {
+        Ok(Message::BlockChunkAck) => true,
+        Ok(message) => {
+            println!("expected BlockChunkAck, got {message:?}");
+            false
+        }
+        Err(e) => {
+            println!("block stream interrupted: {e}");
+            false
+        }
+    }
+}
 
 pub async fn handle_connection(mut socket: TcpStream) {
+    // dual-stack으로 listen하므로 IPv4 peer는 IPv4-mapped IPv6 주소로 보인다
+    let Ok(peer_ip) = socket.peer_addr().map(|addr| addr.ip().to_canonical())
+    else {
+        return;
+    };
+    let mut template_limiter = TemplateLimiter::new();
+    // Version을 보내지 않는 peer는 full node로 본다
+    let mut services = ServiceFlags::NETWORK;
+
     loop {
         // read a message from the socket
         let message = match Message::receive_async(&mut socket)
             .await
         {
             Ok(message) => message,
+            // 연결이 끊기거나 I/O 에러가 난 것일 뿐 위반은 아니다
+            Err(NetworkError::PeerClosed(_) | NetworkError::Io(_)) => {
+                return;
+            }
             Err(e) => {
                 println!("invalid message from peer: {e}, closing that connection");
+                util::misbehaving(peer_ip, MISBEHAVIOR_SCORE, "malformed message");
                 return;
             }
         };
@@ -26,7 +254,12 @@ pub async fn handle_connection(mut socket: TcpStream) {
         use btclib::network::Message::*;
         match message {
             UTXOs(_) | Template(_) | Difference(_)
-            | TemplateValidity(_) | NodeList(_) => {
+            | TemplateValidity(_) | NodeList(_)
+            | TransactionAcceptance(_) | Tip(_) | FoundBlock(_)
+            | FoundTransaction(_) | TopMempool(_) | BlockNotification(_) | Difficulty(_)
+            | MempoolEvent { .. }
+            | Metrics(_) | Genesis(_) | ChainWork(_, _) | Blocks(_)
+            | BlockChunk(_) | BlockChunkAck | Throttled => {
                 println!(
                     "I am neither a miner nor a \
                           wallet! Goodbye"
@@ -37,14 +270,231 @@ pub async fn handle_connection(mut socket: TcpStream) {
                 let blockchain = crate::BLOCKCHAIN.read().await;
                 let Some(block) = blockchain
                     .blocks()
-                    .nth(height as usize)
+                    .nth(height)
                     .cloned()
                 else {
                     return;
                 };
 
                 let message = NewBlock(block);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            Subscribe => {
+                println!("new block subscriber");
+                let mut events = crate::BLOCK_EVENTS.subscribe();
+
+                // 연결이 끊길 때까지 채택된 블록의 header를 흘려보낸다
+                loop {
+                    let header = match events.recv().await {
+                        Ok(header) => header,
+                        Err(RecvError::Lagged(skipped)) => {
+                            println!(
+                                "subscriber lagged, skipped {skipped} blocks"
+                            );
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return,
+                    };
+
+                    let message = BlockNotification(header);
+                    if message.send_async(&mut socket).await.is_err() {
+                        println!("subscriber disconnected");
+                        return;
+                    }
+                }
+            }
+            SubscribeMempool => {
+                println!("new mempool subscriber");
+                let mut events = crate::MEMPOOL_EVENTS.subscribe();
+
+                // 연결이 끊길 때까지 mempool의 변화를 흘려보낸다.
+                // 따라오지 못한 구독자는 놓친 변화를 알 수 없으므로 연결을 끊는다
+                loop {
+                    let message = match events.recv().await {
+                        Ok(MempoolChange::Added(tx)) => MempoolEvent {
+                            added: Some(tx),
+                            removed: None,
+                        },
+                        Ok(MempoolChange::Removed(txid)) => MempoolEvent {
+                            added: None,
+                            removed: Some(txid),
+                        },
+                        Err(RecvError::Lagged(skipped)) => {
+                            println!(
+                                "mempool subscriber lagged by {skipped} \
+                                events, disconnecting"
+                            );
+                            return;
+                        }
+                        Err(RecvError::Closed) => return,
+                    };
+
+                    if message.send_async(&mut socket).await.is_err() {
+                        println!("mempool subscriber disconnected");
+                        return;
+                    }
+                }
+            }
+            FetchTip => {
+                // 응답을 보내는 동안에는 lock을 잡고 있지 않는다
+                let tip = {
+                    let blockchain = crate::BLOCKCHAIN.read().await;
+                    blockchain.height().zip(blockchain.tip_hash())
+                };
+
+                let message = Tip(tip);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchDifficulty => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+
+                let message = Difficulty(blockchain.difficulty_info());
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchMetrics => {
+                let mempool_size =
+                    crate::BLOCKCHAIN.read().await.mempool().len();
+
+                let message = Metrics(crate::METRICS.render(mempool_size));
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchBlockByHash(hash) => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                let block = blockchain.block_by_hash(&hash).cloned();
+
+                let message = FoundBlock(block);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchTransaction(hash) => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                // 확정된 tx를 먼저 찾고, 없으면 mempool에서 찾는다
+                let transaction = blockchain
+                    .transaction_by_hash(&hash)
+                    .or_else(|| {
+                        blockchain
+                            .mempool()
+                            .iter()
+                            .map(|(_, tx)| tx)
+                            .find(|tx| tx.hash() == hash)
+                    })
+                    .cloned();
+
+                let message = FoundTransaction(transaction);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            GetTopMempool { limit } => {
+                // 템플릿을 만들 때처럼 mempool 전체를 훑으므로 같은 한도를 쓴다
+                if !template_limiter.allow() {
+                    println!("top mempool requests too frequent, throttling");
+                    if !reply(&mut socket, Throttled).await {
+                        return;
+                    }
+                    continue;
+                }
+
+                let limit =
+                    (limit as usize).min(MAX_TOP_MEMPOOL_TRANSACTIONS);
+                let transactions =
+                    crate::BLOCKCHAIN.read().await.top_mempool(limit);
+
+                let message = TopMempool(transactions);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            GetGenesis => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                let genesis = blockchain.blocks().next().cloned();
+
+                let message = Genesis(genesis);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchBlocks(start, count) => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                let blocks = blockchain
+                    .blocks()
+                    .skip(start)
+                    .take(count.min(MAX_BLOCKS_PER_REQUEST))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let message = Blocks(blocks);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            GetBlocks { locator, count } => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                let locator =
+                    &locator[..locator.len().min(MAX_LOCATOR_LENGTH)];
+                // 공유하는 마지막 블록 다음부터 보낸다
+                let start = blockchain
+                    .find_fork_point(locator)
+                    .map_or(0, |height| height as usize + 1);
+                let blocks = blockchain
+                    .blocks()
+                    .skip(start)
+                    .take(count.min(MAX_BLOCKS_PER_REQUEST))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let message = Blocks(blocks);
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            StreamBlocks { from_height } => {
+                println!("streaming blocks from height {from_height}");
+                if !stream_blocks(&mut socket, from_height).await {
+                    return;
+                }
+            }
+            Version(flags) => {
+                println!("peer {peer_ip} provides {flags:?}");
+                services = flags;
+            }
+            Announce(port) => {
+                let node = SocketAddr::new(peer_ip, port).to_string();
+                // 이미 서로 연결되어 있다면(outbound로도 알고 있다면) 다시 연결하지 않는다.
+                // 얼마 전에 제거한 노드에도 다시 연결하지 않는다
+                if crate::NODES.contains_key(&node) || util::is_removed(&node)
+                {
+                    continue;
+                }
+
+                // 들어온 연결은 이 handler가 읽고 있으므로, 알려준 주소로 따로 연결해서
+                // 우리 쪽에서도 블록과 tx를 보낼 수 있게 한다.
+                // 상대는 이미 우리를 알고 있으므로 Announce는 보내지 않는다.
+                // 시작하는 노드는 동기화를 마친 뒤에야 listen 하므로 다시 시도하며 기다린다
+                tokio::spawn(async move {
+                    match util::connect_with_retry(&node, None).await {
+                        Ok(peer) => {
+                            println!("connected back to {node}");
+                            util::add_node(node, services, peer);
+                        }
+                        Err(e) => {
+                            println!("failed to connect back to {node}: {e}")
+                        }
+                    }
+                });
+            }
+            Disconnect => {
+                println!("peer {peer_ip} is disconnecting, closing connection");
+                return;
             }
             DiscoverNodes => {
                 let nodes = crate::NODES
@@ -52,14 +502,28 @@ pub async fn handle_connection(mut socket: TcpStream) {
                     .map(|x| x.key().clone())
                     .collect::<Vec<_>>();
                 let message = NodeList(nodes);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
             }
             AskDifference(height) => {
                 let blockchain = crate::BLOCKCHAIN.read().await;
                 let count = blockchain.block_height() as i32
                     - height as i32;
                 let message = Difference(count);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
+            }
+            FetchChainWork => {
+                let blockchain = crate::BLOCKCHAIN.read().await;
+                let message = ChainWork(
+                    blockchain.total_work(),
+                    blockchain.block_height(),
+                );
+                if !reply(&mut socket, message).await {
+                    return;
+                }
             }
             FetchUTXOs(key) => {
                 println!("received request to fetch UTXOs");
@@ -69,7 +533,7 @@ pub async fn handle_connection(mut socket: TcpStream) {
                     .utxos()
                     .iter()
                     .filter(|(_, (_, txout))| {
-                        txout.pubkey == key
+                        txout.lock.pubkey() == Some(&key)
                     })
                     .map(|(_, (marked, txout))| {
                         (txout.clone(), *marked)
@@ -77,7 +541,9 @@ pub async fn handle_connection(mut socket: TcpStream) {
                     .collect::<Vec<_>>();
 
                 let message = UTXOs(utxos);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
             }
 
             NewBlock(block) => {
@@ -85,8 +551,24 @@ pub async fn handle_connection(mut socket: TcpStream) {
                     crate::BLOCKCHAIN.write().await;
                 println!("received new block");
 
-                if blockchain.add_block(block).is_err() {
-                    println!("block rejected");
+                let header = block.header.clone();
+                if let Err(e) = blockchain.add_block(block) {
+                    println!("block rejected: {e}");
+                    crate::METRICS.block_rejected(&e);
+                    if is_invalid_block(&e)
+                        && util::misbehaving(
+                            peer_ip,
+                            MISBEHAVIOR_SCORE,
+                            "invalid block",
+                        )
+                    {
+                        return;
+                    }
+                } else {
+                    crate::METRICS.block_accepted();
+                    // 구독자가 없으면 에러가 나지만 무시해도 된다
+                    let _ = crate::BLOCK_EVENTS.send(header);
+                    crate::MEMPOOL_EVENTS.publish(&blockchain);
                 }
             }
             NewTransaction(tx) => {
@@ -95,172 +577,150 @@ pub async fn handle_connection(mut socket: TcpStream) {
 
                 println!("received transaction from friend");
 
-                if blockchain.add_to_mempool(tx).is_err() {
-                    println!("transaction rejected, closing connection");
-                    return;
+                // 거부되었더라도 RBF로 기존 tx를 밀어냈을 수 있다
+                let result = blockchain.add_to_mempool(tx);
+                crate::MEMPOOL_EVENTS.publish(&blockchain);
+                match result {
+                    Ok(MempoolAcceptance::Conflict(_)) => {
+                        println!("conflicting transaction rejected, closing connection");
+                        crate::METRICS.transaction_rejected("Conflict");
+                        return;
+                    }
+                    Ok(_) => crate::METRICS.transaction_accepted(),
+                    Err(e) => {
+                        println!("transaction rejected, closing connection");
+                        crate::METRICS.transaction_failed(&e);
+                        if matches!(e, BtcError::InvalidSignature) {
+                            util::misbehaving(
+                                peer_ip,
+                                MISBEHAVIOR_SCORE,
+                                "invalid signature",
+                            );
+                        }
+                        return;
+                    }
                 }
             }
             ValidateTemplate(block_template) => {
-                let blockchain = crate::BLOCKCHAIN.read().await;
+                // 응답을 보내는 동안에는 lock을 잡고 있지 않는다
+                let tip = crate::BLOCKCHAIN.read().await.tip_hash();
 
-                let status =
-                    block_template.header.prev_block_hash
-                        == blockchain
-                            .blocks()
-                            .last()
-                            .map(|last_block| last_block.hash())
-                            .unwrap_or(Hash::zero());
+                let status = block_template.header.prev_block_hash
+                    == tip.unwrap_or(Hash::zero());
 
                 let message = TemplateValidity(status);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
             }
             SubmitTemplate(block) => {
                 println!("received allegedly mined template");
-                let mut blockchain =
-                    crate::BLOCKCHAIN.write().await;
-                if let Err(e) =
-                    blockchain.add_block(block.clone())
-                {
+                if let Err(e) = accept_external_block(block).await {
                     println!(
                         "block rejected: {e}, closing connection"
                     );
-                    return;
-                }
-
-                blockchain.rebuild_utxos();
-
-                println!("block looks good, broadcasting");
-
-                // send block to all friend nodes
-                let nodes = crate::NODES
-                    .iter()
-                    .map(|x| x.key().clone())
-                    .collect::<Vec<_>>();
-
-                for node in nodes {
-                    if let Some(mut stream) =
-                        crate::NODES.get_mut(&node)
-                    {
-                        let message =
-                            Message::NewBlock(block.clone());
-                        if message
-                            .send_async(&mut *stream)
-                            .await
-                            .is_err()
-                        {
-                            println!(
-                                "failed to send block to {}",
-                                node
-                            );
-                        }
+                    if is_invalid_block(&e) {
+                        util::misbehaving(
+                            peer_ip,
+                            MISBEHAVIOR_SCORE,
+                            "invalid block",
+                        );
                     }
+                    return;
                 }
             }
             SubmitTransaction(tx) => {
                 println!("submmit tx");
                 let mut blockchain =
                     crate::BLOCKCHAIN.write().await;
-                if let Err(e) =
-                    blockchain.add_to_mempool(tx.clone())
-                {
-                    println!("transaction rejected, closing connection: {e}");
+                let result = blockchain.add_to_mempool(tx.clone());
+                crate::MEMPOOL_EVENTS.publish(&blockchain);
+                let acceptance = match result {
+                    Ok(acceptance) => acceptance,
+                    Err(e) => {
+                        println!("transaction rejected, closing connection: {e}");
+                        crate::METRICS.transaction_failed(&e);
+                        if matches!(e, BtcError::InvalidSignature) {
+                            util::misbehaving(
+                                peer_ip,
+                                MISBEHAVIOR_SCORE,
+                                "invalid signature",
+                            );
+                        }
+                        let message = TransactionAcceptance(None);
+                        let _ = message.send_async(&mut socket).await;
+                        return;
+                    }
+                };
+
+                // 충돌로 거부된 tx는 지갑에 알리기만 하고 전파하지 않는다
+                let conflict =
+                    matches!(acceptance, MempoolAcceptance::Conflict(_));
+                let message = TransactionAcceptance(Some(acceptance));
+                if !reply(&mut socket, message).await {
                     return;
                 }
+                if conflict {
+                    println!("transaction conflicts with the mempool");
+                    crate::METRICS.transaction_rejected("Conflict");
+                    continue;
+                }
+                crate::METRICS.transaction_accepted();
 
                 println!("added transaction to mempool");
 
                 // send transaction to all friend nodes
-                let nodes = crate::NODES
-                    .iter()
-                    .map(|x| x.key().clone())
-                    .collect::<Vec<_>>();
+                let nodes = util::relay_nodes();
 
+                let message = Message::NewTransaction(tx);
                 for node in nodes {
                     println!("sending to friend: {node}");
-                    if let Some(mut stream) =
-                        crate::NODES.get_mut(&node)
+                    if let Err(e) = util::send_to_node(&node, &message).await
                     {
-                        let message =
-                            Message::NewTransaction(tx.clone());
-                        if message
-                            .send_async(&mut *stream)
-                            .await
-                            .is_err()
-                        {
-                            println!("failed to send transaction to {}", node);
-                        }
+                        println!("failed to send transaction to {}: {e}", node);
                     }
                 }
 
                 println!("transaction sent to friends");
             }
             FetchTemplate(pubkey) => {
-                let blockchain = crate::BLOCKCHAIN.read().await;
-
-                let mut transactions = vec![];
-                // insert transactions from mempool
-                transactions.extend(
-                    blockchain
-                        .mempool()
-                        .iter()
-                        .take(btclib::BLOCK_TRANSACTION_CAP)
-                        .map(|(_, tx)| tx)
-                        .cloned()
-                        .collect::<Vec<_>>(),
-                );
-                // insert coinbase tx with pubkey
-                transactions.insert(
-                    0,
-                    Transaction {
-                        inputs: vec![],
-                        outputs: vec![TransactionOutput {
-                            pubkey,
-                            unique_id: Uuid::new_v4(),
-                            value: 0,
-                        }],
-                    },
-                );
-
-                let merkle_root =
-                    MerkleRoot::calculate(&transactions);
-
-                let mut block = Block::new(
-                    BlockHeader {
-                        timestamp: Utc::now(),
-                        prev_block_hash: blockchain
-                            .blocks()
-                            .last()
-                            .map(|last_block| last_block.hash())
-                            .unwrap_or(Hash::zero()),
-                        nonce: 0,
-                        target: blockchain.target(),
-                        merkle_root,
-                    },
-                    transactions,
-                );
-
-                let miner_fees = match block
-                    .calculate_miner_fees(blockchain.utxos())
-                {
-                    Ok(fees) => fees,
-                    Err(e) => {
-                        eprintln!("{e}");
+                if !template_limiter.allow() {
+                    println!("template requests too frequent, throttling");
+                    let message = Throttled;
+                    if !reply(&mut socket, message).await {
                         return;
                     }
-                };
-
-                let reward = blockchain.calculate_block_reward();
+                    continue;
+                }
 
-                // update coinbase tx with reward
-                block.transactions[0].outputs[0].value =
-                    reward + miner_fees;
+                let blockchain = crate::BLOCKCHAIN.read().await;
 
-                // recalculate merkle root
-                block.header.merkle_root =
-                    MerkleRoot::calculate(&block.transactions);
+                let cached = crate::TEMPLATE_CACHE
+                    .lock()
+                    .unwrap()
+                    .get(&blockchain, &pubkey);
+                let block = match cached {
+                    Some(block) => block,
+                    None => match blockchain.build_template(pubkey.clone()) {
+                        Ok(block) => {
+                            crate::TEMPLATE_CACHE.lock().unwrap().insert(
+                                &blockchain,
+                                pubkey,
+                                block.clone(),
+                            );
+                            block
+                        }
+                        Err(e) => {
+                            eprintln!("{e}");
+                            return;
+                        }
+                    },
+                };
 
                 let message = Template(block);
-                message.send_async(&mut socket).await.unwrap();
+                if !reply(&mut socket, message).await {
+                    return;
+                }
             }
         }
     }
@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+// 노드가 지금 어느 단계에 있는지. RPC의 /health로 알려준다
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    // 체인 파일을 읽고 peer들에 연결하는 중
+    Initializing,
+    // peer로부터 블록을 받는 중
+    Syncing,
+    // peer들의 연결을 받고 요청에 응답하는 중
+    Ready,
+}
+
+pub struct Health {
+    phase: Mutex<Phase>,
+    // 가장 많은 작업량을 가진 peer의 높이. 아직 peer에게 물어보지 않았다면 None
+    best_peer_height: Mutex<Option<u64>>,
+}
+
+impl Health {
+    pub const fn new() -> Self {
+        Self {
+            phase: Mutex::new(Phase::Initializing),
+            best_peer_height: Mutex::new(None),
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        *self.phase.lock().unwrap()
+    }
+
+    pub fn set_phase(&self, phase: Phase) {
+        let mut current = self.phase.lock().unwrap();
+        if *current != phase {
+            println!("node is now {phase:?}");
+            *current = phase;
+        }
+    }
+
+    // 블록을 받는 동안 Syncing으로 바꾸고, guard가 사라지면 원래 단계로 되돌린다.
+    // Ready인 노드도 뒤처져서 다시 동기화하는 동안에는 Syncing이다
+    pub fn syncing(&self) -> SyncingGuard<'_> {
+        let previous = self.phase();
+        self.set_phase(Phase::Syncing);
+        SyncingGuard {
+            health: self,
+            previous,
+        }
+    }
+
+    pub fn best_peer_height(&self) -> Option<u64> {
+        *self.best_peer_height.lock().unwrap()
+    }
+
+    pub fn set_best_peer_height(&self, height: u64) {
+        *self.best_peer_height.lock().unwrap() = Some(height);
+    }
+}
+
+pub struct SyncingGuard<'a> {
+    health: &'a Health,
+    previous: Phase,
+}
+
+impl Drop for SyncingGuard<'_> {
+    fn drop(&mut self) {
+        self.health.set_phase(self.previous);
+    }
+}
@@ -1,20 +1,100 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use argh::FromArgs;
-use btclib::types::Blockchain;
+use btclib::network::ServiceFlags;
+use btclib::types::{BlockHeader, Blockchain};
 use dashmap::DashMap;
 use static_init::dynamic;
+use chrono::{DateTime, Utc};
+use node::peer;
+use std::net::IpAddr;
 use std::path::Path;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, RwLock};
 
 mod handler;
+mod health;
+mod mempool_events;
+mod metrics;
+mod rpc;
 mod util;
 
 #[dynamic]
 pub static BLOCKCHAIN: RwLock<Blockchain> = RwLock::new(Blockchain::new());
 
+// 알고 있는 노드들과의 연결 pool. 여러 task가 같은 stream에 동시에 쓰면 frame이 섞이므로
+// 연결마다 lock을 두고, 요청과 응답을 주고받는 동안 lock을 잡고 있는다
 #[dynamic]
-pub static NODES: DashMap<String, TcpStream> = DashMap::new();
+pub static NODES: DashMap<String, KnownNode> = DashMap::new();
+
+pub type PeerStream = Arc<tokio::sync::Mutex<peer::PeerConnection>>;
+
+// NODES의 항목. 상대가 handshake에서 알려준 service flags는 연결을 잠그지 않고도
+// 볼 수 있도록 연결 밖에 둔다
+pub struct KnownNode {
+    pub services: ServiceFlags,
+    pub stream: PeerStream,
+}
+
+// 채택된 블록의 header를 구독자들에게 전달한다.
+// 느린 구독자 때문에 메모리가 무한히 늘지 않도록 크기를 제한한다
+#[dynamic]
+pub static BLOCK_EVENTS: broadcast::Sender<BlockHeader> =
+    broadcast::channel(BLOCK_EVENTS_CAPACITY).0;
+
+const BLOCK_EVENTS_CAPACITY: usize = 64;
+
+// mempool에 들어오거나 빠진 tx를 구독자들에게 전달한다
+#[dynamic]
+pub static MEMPOOL_EVENTS: mempool_events::MempoolEvents =
+    mempool_events::MempoolEvents::new();
+
+#[dynamic]
+pub static TEMPLATE_CACHE: Mutex<handler::TemplateCache> =
+    Mutex::new(handler::TemplateCache::new());
+
+// 블록과 tx의 검증 결과 통계. FetchMetrics로 조회한다
+#[dynamic]
+pub static METRICS: metrics::Metrics = metrics::Metrics::new();
+
+// 시작 단계와 동기화 진행 상황. RPC의 /health로 조회한다
+pub static HEALTH: health::Health = health::Health::new();
+
+// 규칙을 어긴 peer의 누적 점수
+#[dynamic]
+pub static BAN_SCORES: DashMap<IpAddr, u32> = DashMap::new();
+
+// ban된 peer와 ban이 풀리는 시각
+#[dynamic]
+pub static BANNED: DashMap<IpAddr, DateTime<Utc>> = DashMap::new();
+
+// removepeer로 제거한 노드와 다시 받아들이는 시각.
+// 그 전까지는 Announce나 NodeList로 알게 되어도 연결하지 않는다
+#[dynamic]
+pub static REMOVED_NODES: DashMap<String, DateTime<Utc>> = DashMap::new();
+
+// 제거한 노드를 다시 받아들이기까지의 시간 (초)
+pub const REMOVED_NODE_COOLDOWN: i64 = 10 * 60;
+
+// 누적 점수가 이 값 이상이면 ban
+pub const BAN_THRESHOLD: u32 = 100;
+
+// ban 유지 시간 (초)
+pub const BAN_DURATION: i64 = 60 * 60;
+
+// mempool tx 최대 보관 시간(초)의 허용 범위
+const MEMPOOL_MAX_AGE_BOUNDS: (u64, u64) = (1, 7 * 24 * 60 * 60);
+
+// 이보다 깊은 reorg는 하지 않는다. --max-reorg-depth로 바꿀 수 있다
+pub static MAX_REORG_DEPTH: AtomicU64 = AtomicU64::new(btclib::MAX_REORG_DEPTH);
+
+// 이상적인 블록 시간의 몇 배 동안 새 블록이 없으면 tip이 뒤처졌다고 볼지.
+// --stale-tip-after로 바꿀 수 있다
+pub const STALE_TIP_BLOCK_TIMES: u64 = 10;
+
+// peer들에게도 더 나은 체인이 없을 때 다음 확인까지의 간격을 최대 몇 배까지 늘릴지
+pub const STALE_TIP_MAX_BACKOFF: u32 = 8;
 
 #[derive(FromArgs)]
 /// toy blockchain node
@@ -27,6 +107,34 @@ struct Args {
     /// blockchain file
     blockchain_file: String,
 
+    #[argh(option, default = "btclib::MAX_MEMPOOL_TRANSACTION_AGE")]
+    /// seconds an unconfirmed transaction may stay in the mempool
+    mempool_max_age: u64,
+
+    #[argh(option, default = "30")]
+    /// seconds between mempool cleanups
+    cleanup_interval: u64,
+
+    #[argh(option, default = "btclib::MAX_REORG_DEPTH")]
+    /// refuse to switch to a chain that forked more than this many blocks
+    /// below our tip
+    max_reorg_depth: u64,
+
+    #[argh(
+        option,
+        default = "btclib::IDEAL_BLOCK_TIME * STALE_TIP_BLOCK_TIMES"
+    )]
+    /// seconds without a new block before re-syncing with peers
+    stale_tip_after: u64,
+
+    #[argh(option)]
+    /// port for the HTTP RPC server (disabled if not set)
+    rpc_port: Option<u16>,
+
+    #[argh(switch)]
+    /// rebuild the utxo set and indexes by replaying every block
+    reindex: bool,
+
     #[argh(positional)]
     /// address of nodes
     nodes: Vec<String>,
@@ -40,55 +148,121 @@ async fn main() -> Result<()> {
     let blockchain_file = args.blockchain_file;
     let nodes = args.nodes;
 
-    if Path::new(&blockchain_file).exists() {
-        util::load_blockchain(&blockchain_file).await?;
+    let (min_age, max_age) = MEMPOOL_MAX_AGE_BOUNDS;
+    if !(min_age..=max_age).contains(&args.mempool_max_age) {
+        bail!(
+            "--mempool-max-age must be between {min_age} and {max_age} seconds"
+        );
+    }
+    // 보관 시간보다 드물게 정리하면 tx가 설정보다 훨씬 오래 남는다
+    if !(1..=args.mempool_max_age).contains(&args.cleanup_interval) {
+        bail!(
+            "--cleanup-interval must be between 1 and --mempool-max-age seconds"
+        );
+    }
+
+    MAX_REORG_DEPTH.store(args.max_reorg_depth, Ordering::Relaxed);
+
+    // 바이너리 프로토콜 없이 블록을 제출할 수 있는 HTTP RPC.
+    // 동기화하는 동안에도 /health에 답할 수 있도록 가장 먼저 띄운다
+    if let Some(rpc_port) = args.rpc_port {
+        tokio::spawn(async move {
+            if let Err(e) = rpc::serve(rpc_port).await {
+                println!("RPC server stopped: {e}");
+            }
+        });
+    }
+
+    let has_file = Path::new(&blockchain_file).exists();
+    if has_file {
+        util::load_blockchain(&blockchain_file, args.reindex).await?;
     } else {
         println!("blockchain file does not exist!");
+    }
 
-        // 주어진 nodes 주소를 순차적으로 connection 맺는다 
-        util::populate_connections(&nodes).await?;
-        println!("total amount of known nodes: {}", NODES.len());
+    // 주어진 nodes 주소들과 동시에 connection 맺는다. 닿지 않는 노드는 건너뛴다.
+    // 저장된 체인이 있다면 peer 없이도 시작할 수 있으므로 연결 실패는 경고만 한다
+    if let Err(e) = util::populate_connections(&nodes, port).await {
+        if !has_file {
+            return Err(e);
+        }
+        println!("failed to connect to nodes: {e}");
+    }
+    println!("total amount of known nodes: {}", NODES.len());
 
-        if nodes.is_empty() {
-            println!("no initial nodes provided, starting as a seed node");
-        } else {
-            let (longest_name, longest_count) = util::find_longest_chain_node().await?;
+    HEALTH.set_phase(health::Phase::Syncing);
+    if nodes.is_empty() {
+        println!("no initial nodes provided, starting as a seed node");
+    } else if has_file {
+        // 저장된 체인은 마지막으로 종료한 이후 peer들보다 뒤처졌거나 갈라졌을 수 있다.
+        // 따라잡지 못하더라도 저장된 체인으로 시작하고, stale tip 감시가 다시 시도한다
+        if let Err(e) = util::resync(&blockchain_file).await {
+            println!(
+                "WARNING: could not catch up with peers, \
+                starting from the local chain: {e}"
+            );
+        }
+    } else {
+        let (longest_name, longest_count, _) =
+            util::find_longest_chain_node().await?;
 
-            // request the blockchain from the node with the longest blockchain
-            util::download_blockchain(&longest_name, longest_count).await?;
+        // request the blockchain from the node with the longest blockchain
+        util::download_blockchain(
+            &longest_name,
+            longest_count,
+            &blockchain_file,
+        )
+        .await?;
 
-            println!("blockchain downloaded from {}", longest_name);
+        println!("blockchain downloaded from {}", longest_name);
 
-            // utxo를 채워 넣는다 
-            {
-                let mut blockchain = BLOCKCHAIN.write().await;
-                blockchain.rebuild_utxos();
-            }
+        // utxo를 채워 넣는다 
+        {
+            let mut blockchain = BLOCKCHAIN.write().await;
+            blockchain.rebuild_utxos();
+        }
 
-            // 난이도 조정 
-            {
-                let mut blockchain = BLOCKCHAIN.write().await;
-                blockchain.try_adjust_target();
-            }
+        // 난이도 조정 
+        {
+            let mut blockchain = BLOCKCHAIN.write().await;
+            blockchain.try_adjust_target();
         }
+    }
 
-        let addr = format!("0.0.0.0:{}", port);
-        let listener = TcpListener::bind(&addr).await?;
-        println!("Listening on {}", addr);
+    // IPv6 peer도 받을 수 있도록 dual-stack으로 listen 한다.
+    // IPv6를 쓸 수 없는 환경이라면 IPv4만 받는다
+    let listener = match TcpListener::bind(("::", port)).await {
+        Ok(listener) => listener,
+        Err(_) => TcpListener::bind(("0.0.0.0", port)).await?,
+    };
+    println!("Listening on {}", listener.local_addr()?);
+    HEALTH.set_phase(health::Phase::Ready);
 
-        // 주기적으로 mempool 내 오래 잔존한 tx를 제거함 
-        tokio::spawn(util::cleanup());
+    // 주기적으로 mempool 내 오래 잔존한 tx를 제거함 
+    tokio::spawn(util::cleanup(
+        chrono::Duration::seconds(args.mempool_max_age as i64),
+        tokio::time::Duration::from_secs(args.cleanup_interval),
+    ));
 
-        // 주기적으로 blockchain 스냅샷 떠서 저장함  
-        tokio::spawn(util::save(blockchain_file.clone()));
+    // 오랫동안 새 블록이 없으면 peer들과 다시 동기화함
+    tokio::spawn(util::watch_stale_tip(
+        blockchain_file.clone(),
+        tokio::time::Duration::from_secs(args.stale_tip_after),
+    ));
 
-        loop {
-            let (socket, _) = listener.accept().await?;
+    // 주기적으로 blockchain 스냅샷 떠서 저장함  
+    tokio::spawn(util::save(blockchain_file.clone()));
 
-            // message에 따른 핸들러들  
-            tokio::spawn(handler::handle_connection(socket));
+    loop {
+        let (socket, addr) = listener.accept().await?;
+
+        // ban된 peer는 바로 연결을 끊는다
+        if util::is_banned(addr.ip().to_canonical()) {
+            println!("rejecting connection from banned peer {}", addr);
+            continue;
         }
-    }
 
-    Ok(())
+        // message에 따른 핸들러들  
+        tokio::spawn(handler::handle_connection(socket));
+    }
 }
@@ -0,0 +1,81 @@
+use btclib::sha256::Hash;
+use btclib::types::{Blockchain, Transaction};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+// 구독자 한 명이 밀려 있을 수 있는 최대 변화 수.
+// 이보다 뒤처진 구독자는 놓친 변화를 알 수 없으므로 연결을 끊는다
+const CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub enum MempoolChange {
+    Added(Transaction),
+    Removed(Hash),
+}
+
+// mempool에 들어오거나 빠진 tx를 SubscribeMempool 구독자들에게 전달한다.
+// mempool은 add_to_mempool, 블록 추가, reorg, 만료 정리 등 여러 곳에서 바뀌므로
+// 바뀐 곳마다 이벤트를 만들지 않고, 마지막으로 알린 mempool과 비교해서 차이를 보낸다
+pub struct MempoolEvents {
+    sender: broadcast::Sender<MempoolChange>,
+    last: Mutex<Snapshot>,
+}
+
+// 마지막으로 알린 mempool
+struct Snapshot {
+    tip: Hash,
+    mempool_generation: u64,
+    txids: HashSet<Hash>,
+}
+
+impl MempoolEvents {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            last: Mutex::new(Snapshot {
+                tip: Hash::zero(),
+                mempool_generation: 0,
+                txids: HashSet::new(),
+            }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MempoolChange> {
+        self.sender.subscribe()
+    }
+
+    // mempool을 바꿀 수 있는 작업 뒤에 부른다.
+    // tip과 mempool generation이 그대로라면 mempool도 그대로이므로 비교하지 않는다
+    pub fn publish(&self, blockchain: &Blockchain) {
+        let mut last = self.last.lock().unwrap();
+        let tip = blockchain.tip_hash().unwrap_or(Hash::zero());
+        let generation = blockchain.mempool_generation();
+        if (tip, generation) == (last.tip, last.mempool_generation) {
+            return;
+        }
+        last.tip = tip;
+        last.mempool_generation = generation;
+
+        let current: Vec<_> = blockchain
+            .mempool()
+            .iter()
+            .map(|(_, transaction)| (transaction.hash(), transaction))
+            .collect();
+        let txids: HashSet<_> = current.iter().map(|(txid, _)| *txid).collect();
+
+        // RBF처럼 교체된 경우 빠진 tx를 먼저 알린다.
+        // 구독자가 없다면 send는 실패하지만 snapshot은 계속 갱신한다
+        for txid in last.txids.difference(&txids) {
+            let _ = self.sender.send(MempoolChange::Removed(*txid));
+        }
+        // mempool 순서대로 보내므로 부모 tx가 자식 tx보다 먼저 간다
+        for (txid, transaction) in &current {
+            if !last.txids.contains(txid) {
+                let change = MempoolChange::Added((*transaction).clone());
+                let _ = self.sender.send(change);
+            }
+        }
+        last.txids = txids;
+    }
+}
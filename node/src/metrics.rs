@@ -0,0 +1,137 @@
+use btclib::error::BtcError;
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+// 운영자가 노드 상태를 볼 수 있도록 검증 결과를 센다.
+// 거부 사유별 개수는 사유가 처음 나올 때 생긴다
+pub struct Metrics {
+    blocks_accepted: AtomicU64,
+    blocks_rejected: DashMap<String, u64>,
+    transactions_accepted: AtomicU64,
+    transactions_rejected: DashMap<String, u64>,
+    deep_reorgs_refused: AtomicU64,
+    // 한 번 켜지면 운영자가 상황을 확인하고 노드를 다시 시작할 때까지 유지된다
+    manual_intervention_required: AtomicBool,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            blocks_accepted: AtomicU64::new(0),
+            blocks_rejected: DashMap::new(),
+            transactions_accepted: AtomicU64::new(0),
+            transactions_rejected: DashMap::new(),
+            deep_reorgs_refused: AtomicU64::new(0),
+            manual_intervention_required: AtomicBool::new(false),
+        }
+    }
+
+    pub fn block_accepted(&self) {
+        self.blocks_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn block_rejected(&self, e: &BtcError) {
+        *self.blocks_rejected.entry(reason(e)).or_insert(0) += 1;
+    }
+
+    pub fn transaction_accepted(&self) {
+        self.transactions_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // 에러가 아닌 거부(mempool 충돌 등)도 있으므로 사유를 문자열로 받는다
+    pub fn transaction_rejected(&self, reason: impl Into<String>) {
+        *self.transactions_rejected.entry(reason.into()).or_insert(0) += 1;
+    }
+
+    pub fn transaction_failed(&self, e: &BtcError) {
+        self.transaction_rejected(reason(e));
+    }
+
+    // 더 무거운 체인이 MAX_REORG_DEPTH보다 깊이 갈라져 있어서 따라가지 않았다.
+    // 공격일 수도, 우리가 오래 고립되어 있었던 것일 수도 있으므로 사람이 판단해야 한다
+    pub fn deep_reorg_refused(&self) {
+        self.deep_reorgs_refused.fetch_add(1, Ordering::Relaxed);
+        self.manual_intervention_required.store(true, Ordering::Relaxed);
+    }
+
+    pub fn manual_intervention_required(&self) -> bool {
+        self.manual_intervention_required.load(Ordering::Relaxed)
+    }
+
+    // Prometheus text 형식으로 출력한다. mempool 크기는 요청 시점의 값을 받는다
+    pub fn render(&self, mempool_size: usize) -> String {
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "btc_blocks_accepted_total",
+            self.blocks_accepted.load(Ordering::Relaxed),
+        );
+        labeled_counter(
+            &mut out,
+            "btc_blocks_rejected_total",
+            &self.blocks_rejected,
+        );
+        counter(
+            &mut out,
+            "btc_transactions_accepted_total",
+            self.transactions_accepted.load(Ordering::Relaxed),
+        );
+        labeled_counter(
+            &mut out,
+            "btc_transactions_rejected_total",
+            &self.transactions_rejected,
+        );
+
+        counter(
+            &mut out,
+            "btc_deep_reorgs_refused_total",
+            self.deep_reorgs_refused.load(Ordering::Relaxed),
+        );
+        let _ = writeln!(out, "# TYPE btc_manual_intervention_required gauge");
+        let _ = writeln!(
+            out,
+            "btc_manual_intervention_required {}",
+            self.manual_intervention_required() as u8
+        );
+
+        let _ = writeln!(out, "# TYPE btc_mempool_size gauge");
+        let _ = writeln!(out, "btc_mempool_size {mempool_size}");
+
+        out
+    }
+}
+
+// 에러 variant 이름을 사유로 쓴다 (e.g. InvalidSignature).
+// variant에 담긴 값(txid 등)은 label 종류가 끝없이 늘어나지 않도록 버린다
+fn reason(e: &BtcError) -> String {
+    let debug = format!("{e:?}");
+    match debug.split_once('(') {
+        Some((variant, _)) => variant.to_owned(),
+        None => debug,
+    }
+}
+
+fn counter(out: &mut String, name: &str, value: u64) {
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+// scrape할 때마다 순서가 같도록 사유 순으로 정렬한다
+fn labeled_counter(
+    out: &mut String,
+    name: &str,
+    values: &DashMap<String, u64>,
+) {
+    let mut values = values
+        .iter()
+        .map(|entry| (entry.key().clone(), *entry.value()))
+        .collect::<Vec<_>>();
+    values.sort();
+
+    let _ = writeln!(out, "# TYPE {name} counter");
+    for (reason, value) in values {
+        let _ = writeln!(out, "{name}{{reason=\"{reason}\"}} {value}");
+    }
+}
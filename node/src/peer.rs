@@ -0,0 +1,127 @@
+use anyhow::Result;
+use btclib::network::{Message, NetworkError, ServiceFlags};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::net::SocketAddr;
+use tokio::net::{lookup_host, TcpStream};
+
+// 알고 있는 노드와의 outbound 연결.
+// 연결이 끊기면 stream을 버리고, 다음에 쓸 때 같은 주소로 다시 연결해서 handshake 한다.
+// handshake에서는 full node라는 것(Version)과 우리의 listen port(Announce)를 알린다
+pub struct PeerConnection {
+    address: String,
+    // 연결할 때마다 상대에게 알려줄 우리의 listen port (Announce).
+    // 상대가 이미 우리를 알고 있다면 None
+    announce: Option<u16>,
+    stream: Option<TcpStream>,
+    // address가 hostname이라면 실제로 연결된 주소
+    connected_to: Option<SocketAddr>,
+}
+
+impl PeerConnection {
+    // 연결하고 handshake까지 마친다
+    pub async fn connect(
+        address: String,
+        announce: Option<u16>,
+    ) -> Result<Self, NetworkError> {
+        let mut peer = Self {
+            address,
+            announce,
+            stream: None,
+            connected_to: None,
+        };
+        peer.stream().await?;
+        Ok(peer)
+    }
+
+    // 연결이 없다면 다시 연결한다
+    async fn stream(&mut self) -> Result<&mut TcpStream, NetworkError> {
+        if self.stream.is_none() {
+            let (mut stream, connected_to) = connect_any(&self.address).await?;
+            println!("connected to {} ({connected_to})", self.address);
+            self.connected_to = Some(connected_to);
+            Message::Version(ServiceFlags::NETWORK)
+                .send_async(&mut stream)
+                .await?;
+            if let Some(port) = self.announce {
+                Message::Announce(port).send_async(&mut stream).await?;
+            }
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+
+    // stream 도중에 그만두면 아직 읽지 않은 응답이 남으므로 연결을 버린다.
+    // 다음에 쓸 때 다시 연결한다
+    pub fn disconnect(&mut self) {
+        self.stream = None;
+    }
+
+    // 마지막으로 연결된 주소
+    pub fn connected_to(&self) -> Option<SocketAddr> {
+        self.connected_to
+    }
+
+    // 메시지를 보낸다. 보내다가 연결이 끊긴 것을 알게 되면 한 번 다시 연결해서 보낸다
+    pub async fn send(&mut self, message: &Message) -> Result<(), NetworkError> {
+        match message.send_async(self.stream().await?).await {
+            Err(NetworkError::PeerClosed(_) | NetworkError::Io(_)) => {
+                println!("lost connection to {}, reconnecting", self.address);
+                self.stream = None;
+                let result = message.send_async(self.stream().await?).await;
+                if result.is_err() {
+                    self.stream = None;
+                }
+                result
+            }
+            result => result,
+        }
+    }
+
+    // 메시지를 받는다. 실패하면 stream에서 어디까지 읽었는지 알 수 없으므로
+    // 버리고 다음에 다시 연결한다
+    pub async fn receive(&mut self) -> Result<Message> {
+        let result = Message::receive_async(self.stream().await?).await;
+        if result.is_err() {
+            self.stream = None;
+        }
+        Ok(result?)
+    }
+
+    // 요청을 보내고 응답을 받는다
+    pub async fn request(&mut self, message: &Message) -> Result<Message> {
+        self.send(message).await?;
+        self.receive().await
+    }
+}
+
+// address(host:port)가 가리키는 모든 주소에 차례로 연결을 시도하고, 처음 성공한 연결을 쓴다.
+// hostname은 IPv4와 IPv6 주소 여러 개로 풀릴 수 있고, 그중 일부만 닿을 수 있다.
+// IPv6 주소는 [::1]:9000처럼 괄호로 감싼다
+async fn connect_any(address: &str) -> Result<(TcpStream, SocketAddr), IoError> {
+    connect_first(address, lookup_host(address).await?).await
+}
+
+// address가 풀린 주소들(addrs)에 순서대로 연결을 시도하고, 처음 성공한 연결과 그 주소를 돌려준다.
+// 모두 실패하면 마지막 에러를 돌려준다
+pub async fn connect_first(
+    address: &str,
+    addrs: impl IntoIterator<Item = SocketAddr>,
+) -> Result<(TcpStream, SocketAddr), IoError> {
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok((stream, addr)),
+            Err(e) => {
+                println!("failed to connect to {address} ({addr}): {e}");
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        IoError::new(
+            IoErrorKind::NotFound,
+            format!("{address} did not resolve to any address"),
+        )
+    }))
+}
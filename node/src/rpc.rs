@@ -0,0 +1,258 @@
+use anyhow::Result;
+use btclib::network::MAX_MESSAGE_SIZE;
+use btclib::types::Block;
+use btclib::util::Savable;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::handler;
+use crate::health::Phase;
+
+// 요청 line과 header 한 줄의 최대 길이
+const MAX_HEADER_LINE: usize = 8 * 1024;
+
+// header의 최대 줄 수
+const MAX_HEADERS: usize = 64;
+
+// 바이너리 프로토콜 없이 쓸 수 있는 최소한의 HTTP/1.1 RPC 서버.
+// 요청 하나에 응답 하나를 보내고 연결을 닫는다
+//
+//   POST /submitblock
+//     body는 CBOR로 직렬화한 블록(Savable)의 16진수 문자열이다.
+//     Content-Type이 application/cbor라면 CBOR 바이트를 그대로 보내도 된다
+//   GET /health
+//     노드의 단계(Initializing, Syncing, Ready)와 우리 높이, 가장 앞선 peer의 높이.
+//     Ready일 때만 200이므로 orchestrator가 readiness check로 쓸 수 있다
+//   POST /removepeer
+//     body는 제거할 노드의 주소(host:port)다. 연결을 닫고 NODES에서 제거하며,
+//     한동안은 gossip으로 다시 알게 되어도 연결하지 않는다
+pub async fn serve(port: u16) -> Result<()> {
+    let listener = match TcpListener::bind(("::", port)).await {
+        Ok(listener) => listener,
+        Err(_) => TcpListener::bind(("0.0.0.0", port)).await?,
+    };
+    println!("RPC listening on {}", listener.local_addr()?);
+
+    loop {
+        let (socket, addr) = listener.accept().await?;
+        if crate::util::is_banned(addr.ip().to_canonical()) {
+            continue;
+        }
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(socket).await {
+                println!("RPC request from {addr} failed: {e}");
+            }
+        });
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl Response {
+    fn error(status: u16, reason: impl ToString) -> Self {
+        Response {
+            status,
+            body: json!({ "error": reason.to_string() }),
+        }
+    }
+}
+
+async fn handle_request(socket: TcpStream) -> Result<()> {
+    let mut socket = BufReader::new(socket);
+    let response = match read_request(&mut socket).await {
+        Ok(request) => route(request).await,
+        Err(reason) => Response::error(400, reason),
+    };
+
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        body.len()
+    );
+    let socket = socket.get_mut();
+    socket.write_all(head.as_bytes()).await?;
+    socket.write_all(body.as_bytes()).await?;
+    socket.shutdown().await?;
+
+    Ok(())
+}
+
+async fn route(request: Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/submitblock") => submit_block(request).await,
+        (_, "/submitblock") => Response::error(405, "use POST"),
+        ("GET", "/health") => health().await,
+        (_, "/health") => Response::error(405, "use GET"),
+        ("POST", "/removepeer") => remove_peer(request).await,
+        (_, "/removepeer") => Response::error(405, "use POST"),
+        _ => Response::error(404, "unknown method"),
+    }
+}
+
+// 200: Ready, 503: 아직 시작하거나 동기화하는 중
+async fn health() -> Response {
+    let phase = crate::HEALTH.phase();
+    let height = crate::BLOCKCHAIN.read().await.block_height();
+    Response {
+        status: if phase == Phase::Ready { 200 } else { 503 },
+        body: json!({
+            "state": format!("{phase:?}"),
+            "height": height,
+            "best_peer_height": crate::HEALTH.best_peer_height(),
+        }),
+    }
+}
+
+// 200: 받아들임, 400: 블록으로 해석할 수 없음, 422: 검증에 실패함,
+// 503: 동기화가 끝나지 않아 판단할 수 없음
+async fn submit_block(request: Request) -> Response {
+    if crate::HEALTH.phase() != Phase::Ready {
+        return Response::error(503, "node is not ready");
+    }
+
+    let bytes = if request.content_type.as_deref() == Some("application/cbor")
+    {
+        request.body
+    } else {
+        let text = String::from_utf8_lossy(&request.body);
+        match hex::decode(text.trim()) {
+            Ok(bytes) => bytes,
+            Err(e) => return Response::error(400, format!("invalid hex: {e}")),
+        }
+    };
+    let block = match Block::load(bytes.as_slice()) {
+        Ok(block) => block,
+        Err(e) => return Response::error(400, e),
+    };
+
+    let hash = block.hash().to_string();
+    println!("block {hash} submitted over RPC");
+    match handler::accept_external_block(block).await {
+        Ok(()) => Response {
+            status: 200,
+            body: json!({ "accepted": true, "hash": hash }),
+        },
+        Err(e) => {
+            println!("block rejected: {e}");
+            Response {
+                status: 422,
+                body: json!({
+                    "accepted": false,
+                    "hash": hash,
+                    "reason": e.to_string(),
+                }),
+            }
+        }
+    }
+}
+
+// 200: 제거함, 400: 주소가 없음, 404: 알지 못하는 노드
+async fn remove_peer(request: Request) -> Response {
+    let body = String::from_utf8_lossy(&request.body);
+    let node = body.trim();
+    if node.is_empty() {
+        return Response::error(400, "missing peer address");
+    }
+
+    if !crate::util::remove_node(node).await {
+        return Response::error(404, format!("unknown peer: {node}"));
+    }
+    Response {
+        status: 200,
+        body: json!({ "removed": node }),
+    }
+}
+
+// 요청을 읽는다. 잘못된 요청이라면 그 이유를 돌려준다
+async fn read_request(
+    socket: &mut BufReader<TcpStream>,
+) -> Result<Request, String> {
+    let request_line = read_line(socket).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(format!("malformed request line: {request_line}"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0;
+    let mut content_type = None;
+    for _ in 0..MAX_HEADERS {
+        let line = read_line(socket).await?;
+        if line.is_empty() {
+            // 16진수 문자열은 바이트의 두 배 길이다
+            if content_length > MAX_MESSAGE_SIZE * 2 {
+                return Err(format!("body too large: {content_length} bytes"));
+            }
+            let mut body = vec![0; content_length];
+            socket
+                .read_exact(&mut body)
+                .await
+                .map_err(|e| format!("failed to read body: {e}"))?;
+            return Ok(Request {
+                method,
+                path,
+                content_type,
+                body,
+            });
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("malformed header: {line}"));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| format!("invalid Content-Length: {value}"))?;
+        } else if name.eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.to_ascii_lowercase());
+        }
+    }
+
+    Err("too many headers".to_string())
+}
+
+// CRLF로 끝나는 한 줄을 읽는다. 끝의 CRLF는 떼어낸다
+async fn read_line(socket: &mut BufReader<TcpStream>) -> Result<String, String> {
+    let mut line = Vec::new();
+    let read = (&mut *socket)
+        .take(MAX_HEADER_LINE as u64)
+        .read_until(b'\n', &mut line)
+        .await
+        .map_err(|e| format!("failed to read request: {e}"))?;
+    if read == 0 || !line.ends_with(b"\n") {
+        return Err("request ended before the headers".to_string());
+    }
+
+    let line = String::from_utf8(line)
+        .map_err(|_| "request is not valid UTF-8".to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
@@ -1,11 +1,26 @@
-use anyhow::{Context, Result};
-use tokio::net::TcpStream;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use futures::future::join_all;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time;
-use btclib::network::Message;
-use btclib::types::Blockchain;
+use btclib::error::BtcError;
+use btclib::network::{Message, NetworkError, ServiceFlags};
+use btclib::types::{Block, Blockchain};
 use btclib::util::Savable;
+use btclib::U256;
 
-pub async fn load_blockchain(blockchain_file: &str) -> Result<()> {
+use crate::peer::PeerConnection;
+
+pub async fn load_blockchain(
+    blockchain_file: &str,
+    reindex: bool,
+) -> Result<()> {
     println!("blockchain file exists, loading...");
     let new_blockchain = Blockchain::load_from_file(blockchain_file)?;
     println!("blockchain loaded");
@@ -13,9 +28,26 @@ pub async fn load_blockchain(blockchain_file: &str) -> Result<()> {
     let mut blockchain = crate::BLOCKCHAIN.write().await;
     *blockchain = new_blockchain;
 
-    println!("rebuilding utxos...");
-    blockchain.rebuild_utxos();
-    println!("utxos rebuilt");
+    if reindex {
+        println!("reindexing {} blocks...", blockchain.block_height());
+        blockchain.reindex()?;
+        println!("reindex complete");
+    } else {
+        // 변조된 파일로 시작하지 않도록 체인 전체를 검증한다
+        println!("verifying blockchain integrity...");
+        blockchain.verify_integrity().context(
+            "blockchain file failed integrity check, refusing to start",
+        )?;
+        println!("blockchain is valid");
+
+        println!("rebuilding utxos...");
+        blockchain.rebuild_utxos();
+        println!("utxos rebuilt");
+
+        println!("rebuilding indexes...");
+        blockchain.rebuild_indexes();
+        println!("indexes rebuilt");
+    }
 
     println!("checking if target needs to be adjusted...");
     println!("current target: {}", blockchain.target());
@@ -27,74 +59,203 @@ pub async fn load_blockchain(blockchain_file: &str) -> Result<()> {
     Ok(())
 }
 
-pub async fn populate_connections(nodes: &[String]) -> Result<()> {
-    println!("trying to connect to other nodes...");
+// 연결과 handshake, DiscoverNodes 응답까지 기다리는 시간
+const CONNECT_TIMEOUT: time::Duration = time::Duration::from_secs(3);
 
-    for node in nodes {
-        println!("connecting to {}", node);
-        let mut stream = TcpStream::connect(&node).await?;
+// 노드 하나에 연결을 시도할 횟수. 실패할 때마다 기다리는 시간을 두 배로 늘린다
+const CONNECT_ATTEMPTS: u32 = 3;
+const CONNECT_BACKOFF: time::Duration = time::Duration::from_millis(500);
 
-        // msg send
-        let message = Message::DiscoverNodes;
-        message.send_async(&mut stream).await?;
-        println!("sent DiscoverNodes to {}", node);
+// port는 이 노드가 listen하는 port. 연결한 노드들이 우리에게 다시 연결할 수 있도록 알려준다.
+// 직접 지정했거나 NodeList로 알게 된 노드는 full node라고 본다.
+// 노드들에게 동시에 연결하고, 끝내 연결하지 못한 노드는 건너뛴다.
+// 지정한 노드 중 하나에도 연결하지 못했을 때만 에러
+pub async fn populate_connections(nodes: &[String], port: u16) -> Result<()> {
+    println!("trying to connect to other nodes...");
 
-        // msg receive
-        let message = Message::receive_async(&mut stream).await?;
-        match message {
-            Message::NodeList(child_nodes) => {
-                println!("received NodeList from {}", node);
-                for child_node in child_nodes {
-                    println!("adding node {}", child_node);
-                    let new_stream = TcpStream::connect(&child_node).await?;
-                    crate::NODES.insert(child_node, new_stream);
-                }
-            },
-            _ => {
-                println!("unexpected message from {}", node);
+    let results = join_all(
+        nodes.iter().map(|node| connect_and_discover(node, port)),
+    )
+    .await;
+
+    let mut child_nodes = vec![];
+    for (node, result) in nodes.iter().zip(results) {
+        match result {
+            Ok((peer, children)) => {
+                add_node(node.clone(), ServiceFlags::NETWORK, peer);
+                child_nodes.extend(children);
             }
+            Err(e) => println!("skipping node {node}: {e}"),
         }
+    }
+    if !nodes.is_empty() && crate::NODES.is_empty() {
+        bail!("could not connect to any of {} nodes", nodes.len());
+    }
+
+    // 이미 연결한 노드와 같은 노드를 여러 peer가 알려줬을 수 있다
+    child_nodes.sort();
+    child_nodes.dedup();
+    child_nodes.retain(|child_node| {
+        !crate::NODES.contains_key(child_node) && !is_removed(child_node)
+    });
 
-        crate::NODES.insert(node.clone(), stream);
+    let results = join_all(
+        child_nodes
+            .iter()
+            .map(|child_node| connect_with_retry(child_node, Some(port))),
+    )
+    .await;
+    for (child_node, result) in child_nodes.into_iter().zip(results) {
+        match result {
+            Ok(child_peer) => {
+                println!("adding node {}", child_node);
+                add_node(child_node, ServiceFlags::NETWORK, child_peer);
+            }
+            Err(e) => println!("skipping node {child_node}: {e}"),
+        }
     }
 
     Ok(())
 }
 
-pub async fn find_longest_chain_node() -> Result<(String, u32)> {
+// 노드에 연결하고 그 노드가 알고 있는 노드 목록을 받아온다
+async fn connect_and_discover(
+    node: &str,
+    port: u16,
+) -> Result<(PeerConnection, Vec<String>)> {
+    let mut attempt = 1;
+    loop {
+        let result = time::timeout(CONNECT_TIMEOUT, async {
+            // 연결하면서 우리의 port를 알린다
+            let mut peer =
+                PeerConnection::connect(node.to_string(), Some(port)).await?;
+            let message = peer.request(&Message::DiscoverNodes).await?;
+            println!("sent DiscoverNodes to {}", node);
+            let child_nodes = match message {
+                Message::NodeList(child_nodes) => {
+                    println!("received NodeList from {}", node);
+                    child_nodes
+                }
+                _ => {
+                    println!("unexpected message from {}", node);
+                    vec![]
+                }
+            };
+            anyhow::Ok((peer, child_nodes))
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(anyhow!("timed out after {CONNECT_TIMEOUT:?}"))
+        });
+
+        match result {
+            Ok(connected) => return Ok(connected),
+            Err(e) => attempt = retry_after(node, attempt, e).await?,
+        }
+    }
+}
+
+// 노드에 연결하고 handshake까지 마친다. announce는 handshake에서 알려줄 우리의 port
+pub async fn connect_with_retry(
+    node: &str,
+    announce: Option<u16>,
+) -> Result<PeerConnection> {
+    let mut attempt = 1;
+    loop {
+        let result = time::timeout(
+            CONNECT_TIMEOUT,
+            PeerConnection::connect(node.to_string(), announce),
+        )
+        .await
+        .map_err(|_| anyhow!("timed out after {CONNECT_TIMEOUT:?}"))
+        .and_then(|result| Ok(result?));
+
+        match result {
+            Ok(peer) => return Ok(peer),
+            Err(e) => attempt = retry_after(node, attempt, e).await?,
+        }
+    }
+}
+
+// attempt번째 시도가 실패했다. 더 시도할 수 있다면 기다린 뒤 다음 attempt를 돌려준다
+async fn retry_after(
+    node: &str,
+    attempt: u32,
+    error: anyhow::Error,
+) -> Result<u32> {
+    if attempt >= CONNECT_ATTEMPTS {
+        return Err(error.context(format!("gave up after {attempt} attempts")));
+    }
+    let backoff = CONNECT_BACKOFF * 2u32.pow(attempt - 1);
+    println!(
+        "failed to connect to {node}: {error}, \
+        retrying in {backoff:?} ({attempt}/{CONNECT_ATTEMPTS})"
+    );
+    time::sleep(backoff).await;
+    Ok(attempt + 1)
+}
+
+// 가장 긴 체인이 아니라 누적 작업량(work)이 가장 큰 체인을 가진 노드를 찾는다.
+// 난이도가 바뀌면 블록 수가 많다고 해서 더 많은 작업이 들어간 체인인 것은 아니다
+pub async fn find_longest_chain_node() -> Result<(String, u64, U256)> {
     println!(
-        "finding nodes with the highest blockchain length..."
+        "finding nodes with the most cumulative work..."
     );
     let mut longest_name = String::new();
     let mut longest_count = 0;
+    let mut most_work = U256::zero();
 
-    let all_nodes = crate::NODES
+    let mut all_nodes = crate::NODES
         .iter()
         .map(|x| x.key().clone())
         .collect::<Vec<_>>();
+    // 작업량과 높이가 같다면 주소 순으로 먼저 오는 노드를 고르도록 정렬해둔다
+    all_nodes.sort();
 
     for node in all_nodes {
-        println!("asking {} for blockchain length", node);
-
-        let mut stream =
-            crate::NODES.get_mut(&node).context("no node")?;
-
-        let message = Message::AskDifference(0);
-        message.send_async(&mut *stream).await.unwrap();
+        println!("asking {} for chain work", node);
 
-        println!("sent AskDifference to {}", node);
+        // 그 사이에 끊겨서 지워진 peer는 건너뛴다
+        let Some(peer) = get_node(&node) else {
+            continue;
+        };
+        let mut peer = peer.lock().await;
 
-        let message =
-            Message::receive_async(&mut *stream).await?;
+        // 답하지 않는 peer 하나 때문에 탐색 전체가 멈추거나 실패하지 않도록 건너뛴다
+        let message = match time::timeout(
+            CONNECT_TIMEOUT,
+            peer.request(&Message::FetchChainWork),
+        )
+        .await
+        {
+            Ok(Ok(message)) => message,
+            Ok(Err(e)) => {
+                println!(
+                    "failed to fetch chain work from {node}, skipping: {e}"
+                );
+                continue;
+            }
+            Err(_) => {
+                // 응답을 읽다 말았을 수 있으므로 연결을 버린다
+                peer.disconnect();
+                println!(
+                    "{node} did not answer FetchChainWork \
+                    within {CONNECT_TIMEOUT:?}, skipping"
+                );
+                continue;
+            }
+        };
+        println!("sent FetchChainWork to {}", node);
         match message {
-            Message::Difference(count) => {
-                println!("received Difference from {}", node);
-                if count > longest_count {
+            Message::ChainWork(work, count) => {
+                println!("received ChainWork from {}", node);
+                if (work, count) > (most_work, longest_count) {
                     println!(
-                        "new longest blockchain: \
-                   {} blocks from {node}",
-                        count
+                        "new heaviest blockchain: \
+                   {} blocks with work {} from {node}",
+                        count, work
                     );
+                    most_work = work;
                     longest_count = count;
                     longest_name = node;
                 }
@@ -108,44 +269,499 @@ pub async fn find_longest_chain_node() -> Result<(String, u32)> {
         }
     }
 
-    Ok((longest_name, longest_count as u32))
+    // 답한 peer가 없다면 아무것도 모르는 것이다
+    if !longest_name.is_empty() {
+        crate::HEALTH.set_best_peer_height(longest_count);
+    }
+    Ok((longest_name, longest_count, most_work))
 }
 
+// 한 번에 요청할 블록 수
+const BLOCK_DOWNLOAD_BATCH: usize = 16;
+
+// 연결이 끊겼을 때 다시 연결해서 이어받기를 시도할 횟수
+const BLOCK_DOWNLOAD_RETRIES: usize = 3;
+
+// 받은 블록은 구간마다 검증한 뒤 blockchain_file에 저장한다.
+// 도중에 죽더라도 다음 실행에서 저장된 높이부터 이어서 받는다
 pub async fn download_blockchain(
     node: &str,
-    count: u32,
+    count: u64,
+    blockchain_file: &str,
+) -> Result<()> {
+    let _syncing = crate::HEALTH.syncing();
+    let count = usize::try_from(count)
+        .with_context(|| format!("{node} has too many blocks: {count}"))?;
+    let mut retries = 0;
+
+    loop {
+        match download_blocks(node, count, blockchain_file).await {
+            Ok(()) => return Ok(()),
+            // 검증에 실패한 블록은 다시 받아도 실패하므로 네트워크 에러일 때만 재시도한다.
+            // 끊긴 연결은 다음 요청 때 PeerConnection이 다시 연결한다
+            Err(e)
+                if e.downcast_ref::<BtcError>().is_none()
+                    && retries < BLOCK_DOWNLOAD_RETRIES =>
+            {
+                retries += 1;
+                println!(
+                    "download from {node} interrupted: {e}, \
+                    retrying ({retries}/{BLOCK_DOWNLOAD_RETRIES})"
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// 현재 높이부터 count까지 블록을 구간 단위로 받아 하나씩 검증하며 체인에 추가한다.
+// 첫 구간은 GetBlocks로 받아서 갈라졌는지 확인하고, 나머지는 StreamBlocks로 받는다.
+// 구간 하나를 다 받을 때마다 디스크에 저장하므로 받은 블록을 따로 쌓아두지 않는다.
+// 중단되었다가 다시 호출되어도 이미 받은 블록은 건너뛰고 이어서 받는다
+async fn download_blocks(
+    node: &str,
+    count: usize,
+    blockchain_file: &str,
 ) -> Result<()> {
-    let mut stream = crate::NODES.get_mut(node).unwrap();
-    for i in 0..count as usize {
-        let message = Message::FetchBlock(i);
-        message.send_async(&mut *stream).await?;
+    let peer = get_node(node).context("no node")?;
+    let mut peer = peer.lock().await;
+
+    check_genesis(node, &mut peer).await?;
 
-        let message =
-            Message::receive_async(&mut *stream).await?;
+    loop {
+        let (height, locator, tip) = {
+            let blockchain = crate::BLOCKCHAIN.read().await;
+            let tip = blockchain.tip_hash();
+            let height = blockchain.block_height() as usize;
+            (height, blockchain.block_locator(), tip)
+        };
+
+        // 높이가 아니라 locator로 요청해서 peer가 우리와 공유하는 마지막 블록 다음부터 받는다.
+        // peer의 높이까지 받았더라도 한 번은 물어봐서, 갈라진 체인이 남아 있지 않은지 확인한다
+        let batch =
+            BLOCK_DOWNLOAD_BATCH.min(count.saturating_sub(height)).max(1);
+        let message = peer
+            .request(&Message::GetBlocks { locator, count: batch })
+            .await?;
         match message {
-            Message::NewBlock(block) => {
-                let mut blockchain =
-                    crate::BLOCKCHAIN.write().await;
-                blockchain.add_block(block)?;
+            Message::Blocks(blocks) => {
+                if blocks.is_empty() {
+                    if height >= count {
+                        return Ok(());
+                    }
+                    bail!("{node} has no block at height {height}");
+                }
+                // 우리 tip 다음 블록이 아니라면 peer의 체인이 tip 이전에서 갈라진 것이다
+                if let Some(tip) = tip
+                    && blocks[0].header.prev_block_hash != tip
+                {
+                    return reorg(
+                        node,
+                        &mut peer,
+                        blocks,
+                        count,
+                        blockchain_file,
+                    )
+                    .await;
+                }
+
+                let height =
+                    append_blocks(node, blocks, count, blockchain_file)
+                        .await?;
+                // 갈라지지 않았다는 것을 확인했으므로 나머지는 요청을 주고받지 않고 stream으로 받는다
+                if height < count as u64 {
+                    stream_blocks(node, &mut peer, count, blockchain_file)
+                        .await?;
+                }
             }
-            _ => {
-                println!("unexpected message from {}", node);
+            e => {
+                bail!("unexpected message from {}: {:?}", node, e);
             }
         }
     }
+}
+
+// 우리 tip에 이어지는 blocks를 검증해서 체인에 추가하고 저장한다. 추가한 뒤의 높이를 돌려준다
+async fn append_blocks(
+    node: &str,
+    blocks: Vec<Block>,
+    count: usize,
+    blockchain_file: &str,
+) -> Result<u64> {
+    let mut blockchain = crate::BLOCKCHAIN.write().await;
+    let height = blockchain.block_height();
+    // 서명은 구간 전체를 병렬로 미리 검증한다
+    let result = blockchain.add_blocks(blocks);
+    for _ in height..blockchain.block_height() {
+        crate::METRICS.block_accepted();
+    }
+    crate::MEMPOOL_EVENTS.publish(&blockchain);
+    if let Err(e) = result {
+        crate::METRICS.block_rejected(&e);
+        return Err(e.into());
+    }
+    // 구간 단위로 통째로 저장한다. 일부 블록만 저장되는 일은 없다
+    let height = blockchain.block_height();
+    save_snapshot(blockchain, blockchain_file).await?;
+    println!("downloaded {}/{} blocks from {}", height, count, node);
+
+    Ok(height)
+}
+
+// 우리 tip 다음부터 peer의 tip까지 StreamBlocks로 받는다.
+// chunk마다 검증해서 체인에 추가하고 저장한 뒤 ack를 보내므로, peer는 우리가 따라오는 만큼만 보낸다
+async fn stream_blocks(
+    node: &str,
+    peer: &mut PeerConnection,
+    count: usize,
+    blockchain_file: &str,
+) -> Result<()> {
+    let from_height = crate::BLOCKCHAIN.read().await.block_height();
+    peer.send(&Message::StreamBlocks { from_height }).await?;
+
+    loop {
+        let result = match peer.receive().await? {
+            Message::BlockChunk(blocks) if blocks.is_empty() => return Ok(()),
+            Message::BlockChunk(blocks) => {
+                append_blocks(node, blocks, count, blockchain_file).await
+            }
+            e => Err(anyhow::anyhow!("unexpected message from {node}: {e:?}")),
+        };
+        if let Err(e) = result {
+            // 아직 받지 않은 chunk가 다음 요청의 응답으로 읽히지 않도록 연결을 버린다
+            peer.disconnect();
+            return Err(e);
+        }
+        peer.send(&Message::BlockChunkAck).await?;
+    }
+}
+
+// peer의 체인이 우리 tip 아래에서 갈라졌다. blocks는 peer가 보낸 갈라진 뒤의 첫 구간이다.
+// 갈라진 지점까지 되돌린 사본에 peer의 블록을 count까지 이어 받아 보고,
+// 작업량이 우리 체인보다 많을 때만 그 사본으로 갈아탄다.
+// 다 받기 전까지는 원래 체인을 그대로 두므로 도중에 실패해도 잃는 것이 없다
+async fn reorg(
+    node: &str,
+    peer: &mut PeerConnection,
+    mut blocks: Vec<Block>,
+    count: usize,
+    blockchain_file: &str,
+) -> Result<()> {
+    let mut candidate = crate::BLOCKCHAIN.read().await.clone();
+    let prev = blocks[0].header.prev_block_hash;
+    let Some(fork) = candidate
+        .blocks()
+        .position(|block| block.hash() == prev)
+    else {
+        // 다시 받아도 이어 붙일 수 없으므로 재시도하지 않도록 BtcError로 실패한다
+        return Err(anyhow::Error::new(BtcError::InvalidBlock).context(
+            format!("{node} sent blocks that do not connect to our chain"),
+        ));
+    };
+    let max_depth = crate::MAX_REORG_DEPTH.load(Ordering::Relaxed);
+    let removed = match candidate.rewind_within(fork as u64 + 1, max_depth) {
+        Ok(removed) => removed,
+        Err(e) => {
+            crate::METRICS.deep_reorg_refused();
+            println!(
+                "MANUAL INTERVENTION REQUIRED: {node} forked from our chain \
+                after height {fork}, refusing to follow it: {e}"
+            );
+            return Err(anyhow::Error::new(e));
+        }
+    };
+    println!(
+        "{node} forked from our chain after height {fork}, \
+        downloading its branch to compare ({} of our blocks at stake)",
+        removed.len()
+    );
+
+    loop {
+        let height = candidate.block_height();
+        let result = candidate.add_blocks(blocks);
+        for _ in height..candidate.block_height() {
+            crate::METRICS.block_accepted();
+        }
+        if let Err(e) = result {
+            crate::METRICS.block_rejected(&e);
+            return Err(e.into());
+        }
+
+        let height = candidate.block_height() as usize;
+        if height >= count {
+            break;
+        }
+        let message = peer
+            .request(&Message::GetBlocks {
+                locator: candidate.block_locator(),
+                count: BLOCK_DOWNLOAD_BATCH.min(count - height),
+            })
+            .await?;
+        blocks = match message {
+            Message::Blocks(blocks) if blocks.is_empty() => break,
+            Message::Blocks(blocks) => blocks,
+            e => bail!("unexpected message from {}: {:?}", node, e),
+        };
+    }
+
+    let mut blockchain = crate::BLOCKCHAIN.write().await;
+    if candidate.total_work() <= blockchain.total_work() {
+        println!("{node}'s branch does not have more work, keeping our chain");
+        return Ok(());
+    }
+
+    // 받는 동안 들어온 mempool tx도 새 체인에서 다시 받아들인다
+    for (_, transaction) in blockchain.mempool() {
+        let _ = candidate.add_to_mempool(transaction.clone());
+    }
+    *blockchain = candidate;
+    crate::MEMPOOL_EVENTS.publish(&blockchain);
+    let height = blockchain.block_height();
+    save_snapshot(blockchain, blockchain_file).await?;
+    println!(
+        "switched to {node}'s chain: replaced {} blocks, now at {} blocks",
+        removed.len(),
+        height
+    );
 
     Ok(())
 }
 
-pub async fn cleanup() {
-    let mut interval = time::interval(time::Duration::from_secs(30));
+// 이미 체인이 있다면 peer의 genesis가 우리 것과 같은지 확인한다.
+// genesis가 다르면 다른 네트워크이므로 받아봐야 하나도 이어 붙일 수 없다.
+// 다시 받아도 같으므로 재시도하지 않도록 BtcError로 실패한다
+async fn check_genesis(node: &str, peer: &mut PeerConnection) -> Result<()> {
+    let local = crate::BLOCKCHAIN
+        .read()
+        .await
+        .blocks()
+        .next()
+        .map(|genesis| genesis.hash());
+    let Some(local) = local else {
+        // 처음 동기화하는 노드는 peer의 genesis를 그대로 따른다
+        return Ok(());
+    };
+
+    match peer.request(&Message::GetGenesis).await? {
+        Message::Genesis(Some(genesis)) if genesis.hash() == local => Ok(()),
+        Message::Genesis(genesis) => {
+            Err(anyhow::Error::new(BtcError::InvalidBlock).context(format!(
+                "{node} has genesis {:?}, expected {local}",
+                genesis.map(|genesis| genesis.hash())
+            )))
+        }
+        e => bail!("unexpected message from {}: {:?}", node, e),
+    }
+}
+
+// stale_after 동안 새 블록이 채택되지 않으면 tip이 뒤처졌다고 보고
+// 다른 노드들로부터 빠진 블록을 받아온다.
+// peer들에게도 더 나은 체인이 없다면 혼자 채굴하는 노드이거나 네트워크가 조용한 것이므로,
+// 같은 질문을 반복하지 않도록 새 블록이 채택될 때까지 확인 간격을 늘린다
+pub async fn watch_stale_tip(
+    blockchain_file: String,
+    stale_after: time::Duration,
+) {
+    let max_wait = stale_after * crate::STALE_TIP_MAX_BACKOFF;
+    let mut wait = stale_after;
+    let mut events = crate::BLOCK_EVENTS.subscribe();
+
+    loop {
+        match time::timeout(wait, events.recv()).await {
+            // 블록이 채택되었으므로 tip은 최신이다
+            Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => {
+                wait = stale_after;
+                continue;
+            }
+            Ok(Err(RecvError::Closed)) => return,
+            Err(_) => {}
+        }
+
+        // 물어볼 peer가 없다
+        if crate::NODES.is_empty() {
+            continue;
+        }
+
+        println!("no new block for {}s, re-syncing with peers", wait.as_secs());
+        wait = match resync(&blockchain_file).await {
+            Ok(true) => stale_after,
+            Ok(false) => (wait * 2).min(max_wait),
+            Err(e) => {
+                println!("re-sync failed: {e}");
+                (wait * 2).min(max_wait)
+            }
+        };
+    }
+}
+
+// 우리보다 작업량이 많은 체인을 가진 peer가 있다면 그 체인을 따라잡는다.
+// 뒤처졌다면 빠진 블록을 받고, 갈라졌다면 갈라진 지점부터 peer의 체인으로 갈아탄다.
+// 따라잡았다면 true, 이미 가장 좋은 체인이라면 false
+pub async fn resync(blockchain_file: &str) -> Result<bool> {
+    let (longest_name, longest_count, most_work) =
+        find_longest_chain_node().await?;
+
+    // 더 많은 작업이 들어간 체인을 가진 peer가 없다면 받아올 것도 없다
+    let (height, work) = {
+        let blockchain = crate::BLOCKCHAIN.read().await;
+        (blockchain.block_height(), blockchain.total_work())
+    };
+    if most_work <= work {
+        println!("already at the best known tip");
+        return Ok(false);
+    }
+    println!(
+        "local chain has {height} blocks with work {work}, but {longest_name} \
+        has {longest_count} blocks with work {most_work}"
+    );
+
+    download_blockchain(&longest_name, longest_count, blockchain_file)
+        .await?;
+    println!("re-synced up to {} blocks from {}", longest_count, longest_name);
+
+    Ok(true)
+}
+
+// services는 상대가 Version으로 알려준 service flags
+pub fn add_node(node: String, services: ServiceFlags, peer: PeerConnection) {
+    crate::NODES.insert(
+        node,
+        crate::KnownNode {
+            services,
+            stream: Arc::new(Mutex::new(peer)),
+        },
+    );
+}
+
+// DashMap의 guard를 await 너머로 들고 있지 않도록 연결의 handle만 복사해 온다
+pub fn get_node(node: &str) -> Option<crate::PeerStream> {
+    crate::NODES.get(node).map(|known| known.stream.clone())
+}
+
+// 새 블록과 tx를 전파할 노드들
+pub fn relay_nodes() -> Vec<String> {
+    crate::NODES
+        .iter()
+        .filter(|known| known.services.wants_relay())
+        .map(|known| known.key().clone())
+        .collect()
+}
+
+// 알고 있는 노드에게 메시지를 보낸다.
+// 다시 연결해 봐도 보낼 수 없다면 더 이상 쓸 수 없는 노드이므로 NODES에서 제거한다
+pub async fn send_to_node(
+    node: &str,
+    message: &Message,
+) -> Result<(), NetworkError> {
+    let result = match get_node(node) {
+        Some(peer) => peer.lock().await.send(message).await,
+        None => {
+            return Err(std::io::Error::from(ErrorKind::NotConnected).into());
+        }
+    };
+
+    if let Err(NetworkError::PeerClosed(_) | NetworkError::Io(_)) = &result {
+        println!("lost connection to {node}, forgetting it");
+        crate::NODES.remove(node);
+    }
+
+    result
+}
+
+// 규칙을 어긴 peer의 점수를 올리고, 한도를 넘으면 BAN_DURATION 동안 ban한다.
+// ban 되었다면 true
+pub fn misbehaving(ip: IpAddr, score: u32, reason: &str) -> bool {
+    let total = {
+        let mut total = crate::BAN_SCORES.entry(ip).or_insert(0);
+        *total += score;
+        *total
+    };
+    println!("peer {ip} misbehaving ({reason}), ban score {total}");
+
+    if total < crate::BAN_THRESHOLD {
+        return false;
+    }
+
+    println!("banning peer {ip}");
+    crate::BAN_SCORES.remove(&ip);
+    crate::BANNED.insert(
+        ip,
+        Utc::now() + chrono::Duration::seconds(crate::BAN_DURATION),
+    );
+
+    // 알고 있는 노드 목록에서도 제거한다.
+    // hostname으로 알고 있는 노드는 실제로 연결된 주소로 비교한다.
+    // 사용 중인 연결은 확인할 수 없으므로 남겨둔다
+    crate::NODES.retain(|node, known| {
+        let addr = node.parse::<SocketAddr>().ok().or_else(|| {
+            known.stream.try_lock().ok().and_then(|peer| peer.connected_to())
+        });
+        addr.is_none_or(|addr| addr.ip().to_canonical() != ip)
+    });
+
+    true
+}
+
+pub fn is_banned(ip: IpAddr) -> bool {
+    let until = crate::BANNED.get(&ip).map(|until| *until);
+    match until {
+        Some(until) if until > Utc::now() => true,
+        Some(_) => {
+            // ban 기간이 지났다
+            crate::BANNED.remove(&ip);
+            false
+        }
+        None => false,
+    }
+}
+
+// 노드를 NODES에서 제거하고 REMOVED_NODE_COOLDOWN 동안 다시 연결하지 않는다.
+// 상대에게 Disconnect를 보내 우리 쪽 연결을 닫는다는 것을 알린다.
+// 알고 있던 노드였다면 true
+pub async fn remove_node(node: &str) -> bool {
+    crate::REMOVED_NODES.insert(
+        node.to_string(),
+        Utc::now() + chrono::Duration::seconds(crate::REMOVED_NODE_COOLDOWN),
+    );
+
+    let Some((_, known)) = crate::NODES.remove(node) else {
+        return false;
+    };
+    println!("removing node {node}");
+    let mut peer = known.stream.lock().await;
+    if let Err(e) = peer.send(&Message::Disconnect).await {
+        println!("failed to send Disconnect to {node}: {e}");
+    }
+    peer.disconnect();
+    true
+}
+
+pub fn is_removed(node: &str) -> bool {
+    let until = crate::REMOVED_NODES.get(node).map(|until| *until);
+    match until {
+        Some(until) if until > Utc::now() => true,
+        Some(_) => {
+            // cooldown이 지났다
+            crate::REMOVED_NODES.remove(node);
+            false
+        }
+        None => false,
+    }
+}
+
+// every마다 mempool에서 max_age보다 오래된 tx를 버린다
+pub async fn cleanup(max_age: chrono::Duration, every: time::Duration) {
+    let mut interval = time::interval(every);
 
     loop {
         interval.tick().await;
 
         println!("cleaning the mempool from old transactions");
         let mut blockchain = crate::BLOCKCHAIN.write().await;
-        blockchain.cleanup_mempool();
+        for transaction in blockchain.cleanup_mempool(max_age) {
+            println!("evicted transaction {}", transaction.hash());
+        }
+        crate::MEMPOOL_EVENTS.publish(&blockchain);
     }
 }
 
@@ -157,6 +773,37 @@ pub async fn save(name: String) {
 
         println!("saving blockchain to drive...");
         let blockchain = crate::BLOCKCHAIN.read().await;
-        blockchain.save_to_file(name.clone()).unwrap();
+        save_snapshot(blockchain, &name).await.unwrap();
     }
 }
+
+// 체인 lock을 잡은 동안 찍은 snapshot의 순번
+static SNAPSHOTS: AtomicU64 = AtomicU64::new(0);
+
+// 체인 파일에 마지막으로 저장한 snapshot의 순번.
+// 저장은 같은 임시 파일을 쓰므로 한 번에 하나씩 하고,
+// 더 늦게 찍은 snapshot이 이미 저장되었다면 먼저 찍은 snapshot은 버린다
+static SAVED_SNAPSHOT: std::sync::Mutex<u64> = std::sync::Mutex::new(0);
+
+// 체인 lock을 잡은 채로 넘겨받아 snapshot을 찍고, lock을 놓은 뒤 blocking thread에서 저장한다.
+// 파일을 쓰고 fsync 하는 동안에도 다른 task가 체인을 읽고 바꿀 수 있다
+async fn save_snapshot(
+    blockchain: impl Deref<Target = Blockchain>,
+    blockchain_file: &str,
+) -> Result<()> {
+    let snapshot = blockchain.clone();
+    let number = SNAPSHOTS.fetch_add(1, Ordering::Relaxed) + 1;
+    drop(blockchain);
+
+    let blockchain_file = blockchain_file.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut saved = SAVED_SNAPSHOT.lock().unwrap();
+        if *saved > number {
+            return Ok(());
+        }
+        snapshot.save_to_file_atomic(blockchain_file)?;
+        *saved = number;
+        anyhow::Ok(())
+    })
+    .await?
+}
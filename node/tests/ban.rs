@@ -0,0 +1,62 @@
+// 잘못된 블록을 계속 보내는 peer가 ban 되어 바로 다시 연결할 수 없는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[test]
+fn peer_sending_three_invalid_blocks_is_banned() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    // 채굴하지 않은 블록
+    let mut invalid = blockchain.build_template(key.public_key()).unwrap();
+    while invalid.header.check_pow() {
+        invalid.header.nonce += 1;
+    }
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    for _ in 0..3 {
+        Message::NewBlock(invalid.clone()).send(&mut stream).unwrap();
+    }
+    // 세 번째 블록을 받은 뒤 연결을 끊는다
+    assert!(Message::receive(&mut stream).is_err());
+
+    // 다시 연결하더라도 요청을 처리하지 않고 바로 끊는다
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let _ = Message::FetchTip.send(&mut stream);
+    assert!(Message::receive(&mut stream).is_err());
+}
+
+#[test]
+fn two_invalid_blocks_are_tolerated() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    let mut invalid = blockchain.build_template(key.public_key()).unwrap();
+    while invalid.header.check_pow() {
+        invalid.header.nonce += 1;
+    }
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    for _ in 0..2 {
+        Message::NewBlock(invalid.clone()).send(&mut stream).unwrap();
+    }
+    // 같은 연결로 계속 요청할 수 있다
+    Message::FetchTip.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::Tip(tip) => assert_eq!(tip, Some(node.tip())),
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
@@ -0,0 +1,50 @@
+// Subscribe 구독자가 채택된 블록의 header를 채택된 순서대로 받는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::{Block, Blockchain};
+use btclib::util::Savable;
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+fn submit(node: &Node, block: &Block) {
+    let mut cbor = vec![];
+    block.save(&mut cbor).unwrap();
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "application/cbor", &cbor);
+    assert_eq!(status, 200, "{body}");
+}
+
+#[test]
+fn subscriber_receives_mined_blocks_in_order() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    // node가 받을 두 블록
+    let mut extended = blockchain.clone();
+    mine_run(&mut extended, &key, 2);
+    let blocks: Vec<_> = extended.blocks().skip(1).cloned().collect();
+
+    let mut subscriber = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    subscriber.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    Message::Subscribe.send(&mut subscriber).unwrap();
+    // 구독은 응답이 없으므로 node가 처리할 때까지 잠시 기다린다
+    thread::sleep(Duration::from_millis(500));
+
+    for block in &blocks {
+        submit(&node, block);
+    }
+    for block in &blocks {
+        match Message::receive(&mut subscriber).unwrap() {
+            Message::BlockNotification(header) => {
+                assert_eq!(header.hash(), block.header.hash());
+            }
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+}
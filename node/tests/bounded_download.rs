@@ -0,0 +1,136 @@
+// 받는 블록을 따로 쌓아두지 않고 chunk마다 검증해서 디스크에 저장하는지 확인한다.
+// 받은 체인 자체는 메모리에 남으므로 메모리 사용량은 재지 않는다.
+// peer는 chunk 하나를 보내고 ack를 받을 때마다 node의 체인 파일을 읽어서,
+// 그때까지 보낸 블록이 모두 저장되어 있는지 본다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use common::{Node, last_hash, mine_run};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+const CHAIN_LENGTH: u64 = 40;
+// node가 한 번에 들고 있는 받은 블록의 수. 체인보다 훨씬 작다
+const CHUNK: usize = 4;
+
+// ack를 받았을 때의 (보낸 블록까지의 높이, 체인 파일에 저장된 높이)
+type Saved = Mutex<Vec<(u64, u64)>>;
+
+fn serve(
+    mut stream: TcpStream,
+    blockchain: &Blockchain,
+    chain_file: &OnceLock<PathBuf>,
+    requested: &Mutex<Vec<usize>>,
+    saved: &Saved,
+) {
+    let blocks: Vec<_> = blockchain.blocks().cloned().collect();
+    while let Ok(message) = Message::receive(&mut stream) {
+        let reply = match message {
+            Message::DiscoverNodes => Message::NodeList(vec![]),
+            Message::FetchChainWork => Message::ChainWork(
+                blockchain.total_work(),
+                blockchain.block_height(),
+            ),
+            Message::GetGenesis => Message::Genesis(Some(blocks[0].clone())),
+            Message::GetBlocks {
+                locator,
+                count,
+            } => {
+                requested.lock().unwrap().push(count);
+                let start = blockchain
+                    .find_fork_point(&locator)
+                    .map_or(0, |height| height as usize + 1);
+                let end = (start + count).min(blocks.len());
+                Message::Blocks(blocks[start..end].to_vec())
+            }
+            Message::StreamBlocks {
+                from_height,
+            } => {
+                let mut height = from_height;
+                for chunk in blocks[from_height as usize..].chunks(CHUNK) {
+                    Message::BlockChunk(chunk.to_vec())
+                        .send(&mut stream)
+                        .unwrap();
+                    match Message::receive(&mut stream) {
+                        Ok(Message::BlockChunkAck) => {}
+                        message => panic!("unexpected message: {message:?}"),
+                    }
+                    height += chunk.len() as u64;
+                    let stored =
+                        Blockchain::load_from_file(chain_file.wait()).unwrap();
+                    saved.lock().unwrap().push((height, stored.block_height()));
+                }
+                Message::BlockChunk(vec![])
+            }
+            // handshake와 그 밖의 메시지에는 답하지 않는다
+            _ => continue,
+        };
+        if reply.send(&mut stream).is_err() {
+            return;
+        }
+    }
+}
+
+#[test]
+fn downloaded_blocks_are_saved_chunk_by_chunk() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, CHAIN_LENGTH);
+    let blockchain = Arc::new(blockchain);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let chain_file = Arc::new(OnceLock::new());
+    let requested = Arc::new(Mutex::new(vec![]));
+    let saved = Arc::new(Mutex::new(vec![]));
+    {
+        let blockchain = blockchain.clone();
+        let chain_file = chain_file.clone();
+        let requested = requested.clone();
+        let saved = saved.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let blockchain = blockchain.clone();
+                let chain_file = chain_file.clone();
+                let requested = requested.clone();
+                let saved = saved.clone();
+                thread::spawn(move || {
+                    serve(
+                        stream.unwrap(),
+                        &blockchain,
+                        &chain_file,
+                        &requested,
+                        &saved,
+                    )
+                });
+            }
+        });
+    }
+
+    // genesis만 가진 node가 peer의 체인을 받는다
+    let mut genesis = Blockchain::new();
+    genesis.add_block(blockchain.blocks().next().unwrap().clone()).unwrap();
+    let node = Node::spawn(&genesis, &[address], &[]);
+    chain_file.set(node.blockchain_file.clone()).unwrap();
+    node.wait_until_ready();
+    assert_eq!(node.tip(), (CHAIN_LENGTH - 1, last_hash(&blockchain)));
+
+    // 요청으로 받는 구간도 체인 전체가 아니라 정해진 크기 이하다
+    let requested = requested.lock().unwrap();
+    assert!(!requested.is_empty());
+    assert!(requested.iter().all(|count| *count < CHAIN_LENGTH as usize));
+
+    // ack를 보낼 때마다 그때까지 받은 블록이 모두 디스크에 있다
+    let saved = saved.lock().unwrap();
+    // 첫 구간(16개) 이후의 블록은 모두 chunk로 받았다
+    assert!(saved.len() >= (CHAIN_LENGTH as usize - 17) / CHUNK);
+    for (sent, stored) in saved.iter() {
+        assert_eq!(stored, sent);
+    }
+    assert_eq!(saved.last().unwrap().1, CHAIN_LENGTH);
+}
@@ -0,0 +1,79 @@
+// 보내는 도중 연결이 끊기고 다시 연결할 수도 없는 peer는 NODES에서 빠지고,
+// 노드는 계속 요청을 처리하는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::Message;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use uuid::Uuid;
+
+fn known_nodes(stream: &mut TcpStream) -> Vec<String> {
+    Message::DiscoverNodes.send(stream).unwrap();
+    match Message::receive(stream).unwrap() {
+        Message::NodeList(nodes) => nodes,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+// mempool에 받아들여지면 peer들에게 relay 된다
+fn submit(stream: &mut TcpStream, transaction: Transaction) {
+    Message::SubmitTransaction(transaction).send(stream).unwrap();
+    match Message::receive(stream).unwrap() {
+        Message::TransactionAcceptance(Some(_)) => {}
+        message => panic!("transaction was not accepted: {message:?}"),
+    }
+}
+
+#[test]
+fn closed_peer_is_removed_after_a_failed_relay() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 3);
+    let mut spends = blockchain.blocks().map(|block| {
+        let prev = &block.transactions[0].outputs[0];
+        let prev_hash = prev.hash();
+        Transaction::new(
+            vec![TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, &key),
+            )],
+            vec![TransactionOutput {
+                value: prev.value - 10_000,
+                unique_id: Uuid::new_v4(),
+                lock: LockingCondition::P2PK(key.public_key()),
+            }],
+        )
+    });
+
+    let peer = Node::start(&blockchain, &[]);
+    let peer_address = format!("127.0.0.1:{}", peer.port);
+    let node = Node::start(&blockchain, &[&peer]);
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    assert_eq!(known_nodes(&mut stream), vec![peer_address]);
+
+    // 살아 있는 peer에게는 relay가 성공하므로 그대로 남는다
+    submit(&mut stream, spends.next().unwrap());
+    assert_eq!(known_nodes(&mut stream).len(), 1);
+
+    // 닫힌 연결에 쓴 첫 번째 write는 성공할 수 있으므로 빠질 때까지 몇 번 relay 한다
+    drop(peer);
+    for transaction in spends {
+        submit(&mut stream, transaction);
+        if known_nodes(&mut stream).is_empty() {
+            break;
+        }
+    }
+    assert!(known_nodes(&mut stream).is_empty());
+
+    // broken pipe에도 노드는 멈추지 않고 계속 응답한다
+    Message::FetchTip.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::Tip(tip) => assert_eq!(tip, Some(node.tip())),
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
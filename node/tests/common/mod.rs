@@ -0,0 +1,185 @@
+// 실제 node 바이너리를 띄우는 test 도구
+#![allow(dead_code)]
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::sha256::Hash;
+use btclib::types::{Blockchain, LockingCondition, TransactionOutput};
+use btclib::util::Savable;
+use chrono::{Duration, Utc};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+use uuid::Uuid;
+
+const STARTUP_TIMEOUT: StdDuration = StdDuration::from_secs(60);
+
+// 테스트가 끝나면 node 프로세스를 종료하고 체인 파일을 지운다
+pub struct Node {
+    process: Child,
+    pub port: u16,
+    pub rpc_port: u16,
+    pub blockchain_file: PathBuf,
+}
+
+impl Node {
+    pub fn start(blockchain: &Blockchain, peers: &[&Node]) -> Node {
+        Node::start_with_args(blockchain, peers, &[])
+    }
+
+    // 추가 옵션을 주고 띄운다
+    pub fn start_with_args(
+        blockchain: &Blockchain,
+        peers: &[&Node],
+        args: &[&str],
+    ) -> Node {
+        let peers = peers
+            .iter()
+            .map(|peer| format!("127.0.0.1:{}", peer.port))
+            .collect::<Vec<_>>();
+        let node = Node::spawn(blockchain, &peers, args);
+        node.wait_until_ready();
+        node
+    }
+
+    // 띄우고 RPC 서버가 뜰 때까지만 기다린다. peer와 동기화하는 중일 수 있다
+    pub fn spawn(
+        blockchain: &Blockchain,
+        peers: &[String],
+        args: &[&str],
+    ) -> Node {
+        let blockchain_file =
+            std::env::temp_dir().join(format!("node-{}.cbor", Uuid::new_v4()));
+        blockchain.save_to_file(&blockchain_file).unwrap();
+
+        let port = free_port();
+        let rpc_port = free_port();
+        let process = Command::new(env!("CARGO_BIN_EXE_node"))
+            .arg("--port")
+            .arg(port.to_string())
+            .arg("--rpc-port")
+            .arg(rpc_port.to_string())
+            .arg("--blockchain-file")
+            .arg(&blockchain_file)
+            .args(args)
+            .args(peers)
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let node = Node {
+            process,
+            port,
+            rpc_port,
+            blockchain_file,
+        };
+        wait_until_listening(rpc_port);
+        node
+    }
+
+    // node는 peer와 동기화를 마친 뒤에 listen 한다
+    pub fn wait_until_ready(&self) {
+        wait_until_listening(self.port);
+    }
+
+    // RPC 서버에 HTTP 요청을 보내고 (status, body)를 돌려받는다
+    pub fn rpc(
+        &self,
+        method: &str,
+        path: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> (u16, String) {
+        let mut stream =
+            TcpStream::connect(("127.0.0.1", self.rpc_port)).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\n\
+            Host: 127.0.0.1\r\n\
+            Content-Type: {content_type}\r\n\
+            Content-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+
+        // 응답을 보낸 뒤 연결을 닫는다
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    pub fn tip(&self) -> (u64, Hash) {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        Message::FetchTip.send(&mut stream).unwrap();
+        match Message::receive(&mut stream).unwrap() {
+            Message::Tip(Some(tip)) => tip,
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+
+    // Prometheus text 형식의 metrics
+    pub fn metrics(&self) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        Message::FetchMetrics.send(&mut stream).unwrap();
+        match Message::receive(&mut stream).unwrap() {
+            Message::Metrics(metrics) => metrics,
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_file(&self.blockchain_file);
+    }
+}
+
+fn wait_until_listening(port: u16) {
+    let started = Instant::now();
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        assert!(
+            started.elapsed() < STARTUP_TIMEOUT,
+            "node did not listen on port {port}"
+        );
+        thread::sleep(StdDuration::from_millis(100));
+    }
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+// blockchain 뒤에 10초 간격으로 채굴한 블록을 count개 추가한다
+pub fn mine_run(blockchain: &mut Blockchain, key: &PrivateKey, count: u64) {
+    for _ in 0..count {
+        let mut block = blockchain.build_template(key.public_key()).unwrap();
+        block.header.timestamp = blockchain
+            .blocks_rev()
+            .next()
+            .map_or(Utc::now() - Duration::hours(1), |prev| {
+                prev.header.timestamp + Duration::seconds(10)
+            });
+        while !block.header.mine(1_000_000) {}
+        blockchain.add_block(block).unwrap();
+    }
+}
+
+// key에게 지급하는 value짜리 P2PK output
+pub fn output(key: &PrivateKey, value: u64) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    }
+}
+
+pub fn last_hash(blockchain: &Blockchain) -> Hash {
+    blockchain.tip_hash().unwrap()
+}
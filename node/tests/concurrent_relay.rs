@@ -0,0 +1,101 @@
+// 여러 연결이 동시에 받은 tx를 같은 peer에게 relay 해도 메시지가 섞이지 않고
+// peer가 모든 메시지를 온전히 읽을 수 있는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::{Message, ServiceFlags};
+use btclib::types::{
+    Blockchain, Transaction, TransactionInput, TransactionOutput,
+};
+use common::{Node, mine_run, output};
+use std::collections::HashSet;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const SENDERS: usize = 8;
+const PER_SENDER: usize = 5;
+
+fn spend(key: &PrivateKey, prev: &TransactionOutput) -> Transaction {
+    let prev_hash = prev.hash();
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, key),
+        )],
+        vec![output(key, prev.value - 10_000)],
+    )
+}
+
+#[test]
+fn concurrent_relays_to_one_peer_keep_frames_intact() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+
+    // 보상을 SENDERS * PER_SENDER개의 output으로 나눠서 동시에 쓸 수 있게 한다
+    let reward =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let count = (SENDERS * PER_SENDER) as u64;
+    let prev_hash = reward.hash();
+    let fan_out = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        (0..count).map(|_| output(&key, reward.value / (count + 1))).collect(),
+    );
+    blockchain.add_to_mempool(fan_out.clone()).unwrap();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    // Announce로 우리 port를 알리면 node가 이 listener로 다시 연결해서 relay 한다
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut announcer = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::Version(ServiceFlags::NETWORK).send(&mut announcer).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    Message::Announce(port).send(&mut announcer).unwrap();
+    let (mut relayed, _) = listener.accept().unwrap();
+    relayed.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    match Message::receive(&mut relayed).unwrap() {
+        Message::Version(_) => {}
+        message => panic!("unexpected handshake: {message:?}"),
+    }
+    // node가 NODES에 넣을 때까지 잠시 기다린다
+    thread::sleep(Duration::from_millis(500));
+
+    let transactions: Vec<_> =
+        fan_out.outputs.iter().map(|prev| spend(&key, prev)).collect();
+    thread::scope(|scope| {
+        for chunk in transactions.chunks(PER_SENDER) {
+            let port = node.port;
+            scope.spawn(move || {
+                let mut stream =
+                    TcpStream::connect(("127.0.0.1", port)).unwrap();
+                for transaction in chunk {
+                    Message::SubmitTransaction(transaction.clone())
+                        .send(&mut stream)
+                        .unwrap();
+                    match Message::receive(&mut stream).unwrap() {
+                        Message::TransactionAcceptance(Some(_)) => {}
+                        message => panic!("not accepted: {message:?}"),
+                    }
+                }
+            });
+        }
+    });
+
+    // 모든 tx가 한 번씩, 온전한 메시지로 도착한다
+    let mut received = HashSet::new();
+    while received.len() < transactions.len() {
+        match Message::receive(&mut relayed).unwrap() {
+            Message::NewTransaction(transaction) => {
+                assert!(received.insert(transaction.hash()));
+            }
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+    let expected: HashSet<_> =
+        transactions.iter().map(Transaction::hash).collect();
+    assert_eq!(received, expected);
+}
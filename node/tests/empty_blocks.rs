@@ -0,0 +1,86 @@
+// mempool이 비어 있어도 miner처럼 템플릿을 받아 채굴하면 체인이 자라는지 확인한다
+mod common;
+
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::{Block, Blockchain};
+use common::{Node, mine_run};
+
+const EMPTY_BLOCKS: u64 = 3;
+
+// miner와 같은 순서로 템플릿을 받아 채굴하고 제출한다
+fn mine_template(stream: &mut TcpStream, key: &PrivateKey) -> Block {
+    Message::FetchTemplate(key.public_key()).send(stream).unwrap();
+    let mut block = match Message::receive(stream).unwrap() {
+        Message::Template(block) => block,
+        message => panic!("unexpected message: {message:?}"),
+    };
+    while !block.header.mine(1_000_000) {}
+    Message::SubmitTemplate(block.clone()).send(stream).unwrap();
+    block
+}
+
+fn wait_for_height(node: &Node, height: u64) {
+    let started = Instant::now();
+    while node.tip().0 < height {
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "node did not reach height {height}"
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn empty_mempool_templates_are_mined_into_coinbase_only_blocks() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    let mut mined = vec![];
+    for height in 1..=EMPTY_BLOCKS {
+        let block = mine_template(&mut stream, &key);
+        assert_eq!(block.transactions.len(), 1);
+        wait_for_height(&node, height);
+        assert_eq!(node.tip(), (height, block.hash()));
+        mined.push(block);
+    }
+
+    // node가 가진 블록을 처음부터 다시 검증하며 이어 붙인다
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::FetchBlocks(0, EMPTY_BLOCKS as usize + 1)
+        .send(&mut stream)
+        .unwrap();
+    let blocks = match Message::receive(&mut stream).unwrap() {
+        Message::Blocks(blocks) => blocks,
+        message => panic!("unexpected message: {message:?}"),
+    };
+    assert_eq!(blocks.len(), EMPTY_BLOCKS as usize + 1);
+
+    let mut replayed = Blockchain::new();
+    for block in blocks {
+        replayed.add_block(block).unwrap();
+    }
+    for (height, block) in (1..).zip(replayed.blocks().skip(1)) {
+        assert_eq!(block.hash(), mined[height as usize - 1].hash());
+        let [coinbase] = block.transactions.as_slice() else {
+            panic!("block {height} is not coinbase-only");
+        };
+        assert!(coinbase.is_coinbase());
+        // 수수료가 없으므로 보상만 받는다
+        let value: u64 = coinbase.outputs.iter().map(|o| o.value).sum();
+        assert_eq!(value, Blockchain::block_reward_at(height));
+        assert!(
+            coinbase
+                .outputs
+                .iter()
+                .all(|output| output.lock.pubkey() == Some(&key.public_key()))
+        );
+    }
+}
@@ -0,0 +1,88 @@
+// explore 바이너리의 각 명령이 node에 질의한 결과를 JSON으로 출력하는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use common::{Node, last_hash, mine_run};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+// test가 끝나면 지워지는 public key 파일
+struct PublicKeyFile(PathBuf);
+
+impl Drop for PublicKeyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn explore(node: &Node, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_explore"))
+        .arg(format!("127.0.0.1:{}", node.port))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// 성공한 명령의 JSON 출력
+fn query(node: &Node, args: &[&str]) -> Value {
+    let output = explore(node, args);
+    assert!(output.status.success(), "{args:?}: {output:?}");
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn each_command_prints_what_the_node_has() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 2);
+    let tip = last_hash(&blockchain).to_string();
+    let node = Node::start(&blockchain, &[]);
+
+    let result = query(&node, &["tip"]);
+    assert_eq!(result["height"], 1);
+    assert_eq!(result["hash"], tip.as_str());
+
+    // 높이로 찾든 해시로 찾든 같은 블록이다
+    assert_eq!(query(&node, &["block", "1"])["hash"], tip.as_str());
+    assert_eq!(query(&node, &["block", &tip])["hash"], tip.as_str());
+
+    let coinbase = &blockchain.blocks().nth(1).unwrap().transactions[0];
+    let txid = coinbase.hash().to_string();
+    assert_eq!(query(&node, &["tx", &txid])["hash"], txid.as_str());
+
+    let file = PublicKeyFile(
+        std::env::temp_dir().join(format!("explore-{}.pub", Uuid::new_v4())),
+    );
+    key.public_key().save_to_file(&file.0).unwrap();
+    let balance: u64 = blockchain
+        .blocks()
+        .map(|block| block.transactions[0].outputs[0].value)
+        .sum();
+    let result = query(&node, &["balance", file.0.to_str().unwrap()]);
+    assert_eq!(result["balance_sat"], balance);
+    assert_eq!(result["utxos"], 2);
+}
+
+#[test]
+fn missing_items_are_reported() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    let unknown = btclib::sha256::Hash::hash_bytes(b"unknown").to_string();
+    for args in [["block", unknown.as_str()], ["tx", unknown.as_str()]] {
+        let output = explore(&node, &args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{args:?}");
+        assert!(stderr.contains("not found"), "{stderr}");
+    }
+    // 잘못된 명령에는 사용법을 알려준다
+    let output = explore(&node, &["blocks"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage"));
+}
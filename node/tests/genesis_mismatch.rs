@@ -0,0 +1,39 @@
+// genesis가 다른 peer의 체인은 더 무겁더라도 받지 않고,
+// genesis가 같은 peer에게서는 받는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, last_hash, mine_run};
+use std::net::TcpStream;
+
+#[test]
+fn peer_with_another_genesis_is_not_synced_from() {
+    let key = PrivateKey::new_key();
+    let mut heavier = Blockchain::new();
+    mine_run(&mut heavier, &key, 3);
+    let peer = Node::start(&heavier, &[]);
+
+    // peer는 자신의 genesis를 알려준다
+    let mut stream = TcpStream::connect(("127.0.0.1", peer.port)).unwrap();
+    Message::GetGenesis.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::Genesis(Some(genesis)) => {
+            assert_eq!(genesis.hash(), heavier.blocks().next().unwrap().hash())
+        }
+        message => panic!("unexpected message: {message:?}"),
+    }
+
+    // 다른 genesis로 시작한 체인은 저장된 체인을 그대로 유지한다
+    let mut other = Blockchain::new();
+    mine_run(&mut other, &PrivateKey::new_key(), 1);
+    let node = Node::start(&other, &[&peer]);
+    assert_eq!(node.tip(), (0, last_hash(&other)));
+
+    // 같은 genesis라면 따라간다
+    let mut same = Blockchain::new();
+    same.add_block(heavier.blocks().next().unwrap().clone()).unwrap();
+    let node = Node::start(&same, &[&peer]);
+    assert_eq!(node.tip(), (2, last_hash(&heavier)));
+}
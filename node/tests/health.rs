@@ -0,0 +1,158 @@
+// RPC의 /health가 동기화 중에는 Syncing, 끝나면 Ready를 알려주는지 확인한다.
+// 동기화 도중을 관찰할 수 있도록 test가 직접 peer 역할을 하며 블록을 늦게 보낸다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, mine_run};
+use serde_json::Value;
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// blockchain을 가진 peer. released가 true가 될 때까지 블록을 보내지 않는다
+fn slow_peer(blockchain: Blockchain, released: Arc<AtomicBool>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let blockchain = Arc::new(blockchain);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let (blockchain, released) = (blockchain.clone(), released.clone());
+            thread::spawn(move || {
+                serve(stream.unwrap(), &blockchain, &released)
+            });
+        }
+    });
+    port
+}
+
+fn serve(
+    mut stream: TcpStream,
+    blockchain: &Blockchain,
+    released: &AtomicBool,
+) {
+    while let Ok(message) = Message::receive(&mut stream) {
+        let reply = match message {
+            Message::Version(_) | Message::Announce(_) => continue,
+            Message::DiscoverNodes => Message::NodeList(vec![]),
+            Message::FetchChainWork => Message::ChainWork(
+                blockchain.total_work(),
+                blockchain.block_height(),
+            ),
+            Message::GetGenesis => {
+                Message::Genesis(blockchain.blocks().next().cloned())
+            }
+            Message::GetBlocks {
+                locator,
+                count,
+            } => {
+                while !released.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                let start = blockchain
+                    .find_fork_point(&locator)
+                    .map_or(0, |height| height as usize + 1);
+                Message::Blocks(
+                    blockchain
+                        .blocks()
+                        .skip(start)
+                        .take(count)
+                        .cloned()
+                        .collect(),
+                )
+            }
+            Message::StreamBlocks {
+                from_height,
+            } => {
+                let blocks = blockchain
+                    .blocks()
+                    .skip(from_height as usize)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !blocks.is_empty() {
+                    Message::BlockChunk(blocks).send(&mut stream).unwrap();
+                    match Message::receive(&mut stream).unwrap() {
+                        Message::BlockChunkAck => {}
+                        message => panic!("expected ack, got {message:?}"),
+                    }
+                }
+                Message::BlockChunk(vec![])
+            }
+            message => panic!("unexpected message: {message:?}"),
+        };
+        reply.send(&mut stream).unwrap();
+    }
+}
+
+fn health(node: &Node) -> (u16, Value) {
+    let (status, body) = node.rpc("GET", "/health", "text/plain", b"");
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+// /health가 state를 알려줄 때까지 기다린다
+fn wait_for_state(node: &Node, state: &str) -> (u16, Value) {
+    let started = Instant::now();
+    loop {
+        let (status, body) = health(node);
+        if body["state"] == state {
+            return (status, body);
+        }
+        assert!(started.elapsed() < TIMEOUT, "still {body} instead of {state}");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn health_reports_syncing_until_the_download_finishes() {
+    let key = PrivateKey::new_key();
+    let mut heavier = Blockchain::new();
+    mine_run(&mut heavier, &key, 3);
+    let genesis = {
+        let mut genesis = Blockchain::new();
+        genesis.add_block(heavier.blocks().next().unwrap().clone()).unwrap();
+        genesis
+    };
+
+    let released = Arc::new(AtomicBool::new(false));
+    let peer = slow_peer(heavier.clone(), released.clone());
+    let node = Node::spawn(&genesis, &[format!("127.0.0.1:{peer}")], &[]);
+
+    // peer가 블록을 보내기 전까지는 동기화 중이다
+    let (status, body) = wait_for_state(&node, "Syncing");
+    assert_eq!(status, 503);
+    assert_eq!(body["height"], 1);
+    assert_eq!(body["best_peer_height"], 3);
+    let (status, _) = node.rpc("POST", "/submitblock", "text/plain", b"00");
+    assert_eq!(status, 503);
+
+    released.store(true, Ordering::SeqCst);
+    let (status, body) = wait_for_state(&node, "Ready");
+    assert_eq!(status, 200);
+    assert_eq!(body["height"], 3);
+    assert_eq!(body["best_peer_height"], 3);
+
+    node.wait_until_ready();
+    assert_eq!(node.tip(), (2, heavier.tip_hash().unwrap()));
+}
+
+#[test]
+fn seed_node_is_ready_without_peers() {
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &PrivateKey::new_key(), 1);
+    let node = Node::start(&blockchain, &[]);
+
+    let (status, body) = health(&node);
+    assert_eq!(status, 200);
+    assert_eq!(body["state"], "Ready");
+    assert_eq!(body["height"], 1);
+    assert_eq!(body["best_peer_height"], Value::Null);
+
+    let (status, _) = node.rpc("POST", "/health", "text/plain", b"");
+    assert_eq!(status, 405);
+}
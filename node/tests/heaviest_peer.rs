@@ -0,0 +1,45 @@
+// 높이가 같은 두 peer 중 작업량이 더 많은 체인을 가진 peer를 따라가는지 확인한다
+mod common;
+
+use btclib::DIFFICULTY_UPDATE_INTERVAL;
+use btclib::crypto::PrivateKey;
+use btclib::types::Blockchain;
+use chrono::Duration;
+use common::{Node, last_hash, mine_run};
+
+// 직전 블록보다 spacing 뒤의 timestamp로 블록 하나를 채굴한다
+fn mine_after(blockchain: &mut Blockchain, key: &PrivateKey, spacing: i64) {
+    let mut block = blockchain.build_template(key.public_key()).unwrap();
+    block.header.timestamp =
+        blockchain.blocks_rev().next().unwrap().header.timestamp
+            + Duration::seconds(spacing);
+    while !block.header.mine(1_000_000) {}
+    blockchain.add_block(block).unwrap();
+}
+
+#[test]
+fn heavier_of_two_equally_long_peers_is_followed() {
+    let key = PrivateKey::new_key();
+    let mut common = Blockchain::new();
+    mine_run(&mut common, &key, DIFFICULTY_UPDATE_INTERVAL - 1);
+
+    // 조정 구간의 마지막 블록이 빨리 나온 쪽만 다음 블록부터 난이도가 오른다
+    let mut heavier = common.clone();
+    mine_after(&mut heavier, &key, 1);
+    mine_after(&mut heavier, &key, 10);
+    let mut lighter = common.clone();
+    mine_after(&mut lighter, &key, 30);
+    mine_after(&mut lighter, &key, 10);
+    assert_eq!(heavier.block_height(), lighter.block_height());
+    assert!(heavier.total_work() > lighter.total_work());
+
+    let light_peer = Node::start(&lighter, &[]);
+    let heavy_peer = Node::start(&heavier, &[]);
+    let height = heavier.block_height() - 1;
+
+    // peer를 어떤 순서로 알려주든 같은 쪽을 고른다
+    for peers in [[&light_peer, &heavy_peer], [&heavy_peer, &light_peer]] {
+        let node = Node::start(&common, &peers);
+        assert_eq!(node.tip(), (height, last_hash(&heavier)));
+    }
+}
@@ -0,0 +1,58 @@
+// 우리에게 연결해 온(inbound) peer에게도 새 블록을 보내는지 확인한다.
+// node는 peer를 설정으로 알지 못하고, peer가 연결하면서 알려준 port로만 알게 된다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use common::{Node, last_hash, mine_run};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn known_nodes(node: &Node) -> Vec<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::DiscoverNodes.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::NodeList(nodes) => nodes,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+fn wait_for(what: &str, mut condition: impl FnMut() -> bool) {
+    let started = Instant::now();
+    while !condition() {
+        assert!(started.elapsed() < Duration::from_secs(30), "{what}");
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn block_is_pushed_to_an_inbound_peer() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+    let peer = Node::start(&blockchain, &[&node]);
+
+    // peer가 Announce로 알려준 주소로 node가 다시 연결한다
+    let peer_address = format!("127.0.0.1:{}", peer.port);
+    wait_for("node never connected back to the peer", || {
+        known_nodes(&node).contains(&peer_address)
+    });
+
+    let mut extended = blockchain.clone();
+    mine_run(&mut extended, &key, 1);
+    let mut cbor = vec![];
+    extended.blocks().last().unwrap().save(&mut cbor).unwrap();
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "application/cbor", &cbor);
+    assert_eq!(status, 200, "{body}");
+
+    let expected = (1, last_hash(&extended));
+    assert_eq!(node.tip(), expected);
+    wait_for("block was not relayed to the inbound peer", || {
+        peer.tip() == expected
+    });
+}
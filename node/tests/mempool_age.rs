@@ -0,0 +1,103 @@
+// 설정한 mempool 보관 시간에 따라 같은 tx가 한 노드에서는 버려지고 다른 노드에는 남는지,
+// 말이 안 되는 설정으로는 시작하지 않는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::Message;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+fn mempool(node: &Node) -> Vec<Hash> {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::GetTopMempool {
+        limit: 100,
+    }
+    .send(&mut stream)
+    .unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TopMempool(transactions) => {
+            transactions.iter().map(Transaction::hash).collect()
+        }
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+fn submit(node: &Node, transaction: Transaction) {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::SubmitTransaction(transaction).send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TransactionAcceptance(Some(_)) => {}
+        message => panic!("transaction was not accepted: {message:?}"),
+    }
+}
+
+#[test]
+fn short_max_age_evicts_what_a_long_one_keeps() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let prev_hash = prev.hash();
+    let transaction = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - 10_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+
+    let short = Node::start_with_args(
+        &blockchain,
+        &[],
+        &["--mempool-max-age", "2", "--cleanup-interval", "1"],
+    );
+    let long = Node::start_with_args(
+        &blockchain,
+        &[],
+        &["--mempool-max-age", "3600", "--cleanup-interval", "1"],
+    );
+    for node in [&short, &long] {
+        submit(node, transaction.clone());
+        assert_eq!(mempool(node), vec![transaction.hash()]);
+    }
+
+    // 짧은 보관 시간과 정리 주기를 충분히 넘기도록 기다린다
+    thread::sleep(Duration::from_secs(5));
+    assert!(mempool(&short).is_empty());
+    assert_eq!(mempool(&long), vec![transaction.hash()]);
+}
+
+#[test]
+fn insane_ages_are_refused_at_startup() {
+    for args in [
+        ["--mempool-max-age", "0", "--cleanup-interval", "1"],
+        ["--mempool-max-age", "99999999", "--cleanup-interval", "1"],
+        // 보관 시간보다 드물게 정리할 수는 없다
+        ["--mempool-max-age", "60", "--cleanup-interval", "120"],
+        ["--mempool-max-age", "60", "--cleanup-interval", "0"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_node"))
+            .args(["--port", &common::free_port().to_string()])
+            .args(args)
+            .stdout(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{args:?} was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(args[0]) || stderr.contains(args[2]));
+    }
+}
@@ -0,0 +1,79 @@
+// SubscribeMempool 구독자가 mempool에 tx가 들어오고 빠질 때마다 알림을 받는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::Message;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+// RBF로 교체할 수 있도록 prev를 소비하는 tx
+fn spend(key: &PrivateKey, prev: &TransactionOutput, fee: u64) -> Transaction {
+    let prev_hash = prev.hash();
+    let mut input = TransactionInput::new(
+        prev_hash,
+        Signature::sign_output(&prev_hash, key),
+    );
+    input.sequence = btclib::MAX_RBF_SEQUENCE;
+    Transaction::new(
+        vec![input],
+        vec![TransactionOutput {
+            value: prev.value - fee,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    )
+}
+
+fn submit(node: &Node, transaction: Transaction) {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::SubmitTransaction(transaction).send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TransactionAcceptance(Some(_)) => {}
+        message => panic!("transaction was not accepted: {message:?}"),
+    }
+}
+
+// (추가된 tx의 txid, 제거된 tx의 txid)
+fn next_event(subscriber: &mut TcpStream) -> (Option<Hash>, Option<Hash>) {
+    match Message::receive(subscriber).unwrap() {
+        Message::MempoolEvent {
+            added,
+            removed,
+        } => (added.map(|transaction| transaction.hash()), removed),
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+#[test]
+fn subscriber_sees_added_and_evicted_transactions() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let node = Node::start(&blockchain, &[]);
+
+    let mut subscriber = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    subscriber.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    Message::SubscribeMempool.send(&mut subscriber).unwrap();
+    // 구독은 응답이 없으므로 node가 처리할 때까지 잠시 기다린다
+    thread::sleep(Duration::from_millis(500));
+
+    let original = spend(&key, &prev, 10_000);
+    submit(&node, original.clone());
+    assert_eq!(next_event(&mut subscriber), (Some(original.hash()), None));
+
+    // 수수료가 더 높은 tx가 기존 tx를 밀어낸다. 빠진 tx를 먼저 알린다
+    let replacement = spend(&key, &prev, 20_000);
+    submit(&node, replacement.clone());
+    assert_eq!(next_event(&mut subscriber), (None, Some(original.hash())));
+    assert_eq!(next_event(&mut subscriber), (Some(replacement.hash()), None));
+}
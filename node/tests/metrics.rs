@@ -0,0 +1,93 @@
+// 받아들인 것과 거부한 블록과 tx가 섞여 있을 때 metrics가 각각을 사유별로 세는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::Message;
+use btclib::types::{
+    Block, Blockchain, LockingCondition, MempoolAcceptance, Transaction,
+    TransactionInput, TransactionOutput,
+};
+use btclib::util::Savable;
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use uuid::Uuid;
+
+fn submit_block(node: &Node, block: &Block) -> u16 {
+    let mut cbor = vec![];
+    block.save(&mut cbor).unwrap();
+    node.rpc("POST", "/submitblock", "application/cbor", &cbor).0
+}
+
+// mempool에 받아들여졌는지. 잘못된 tx를 보내면 node가 연결을 끊으므로 매번 새로 연결한다
+fn submit_transaction(node: &Node, transaction: Transaction) -> bool {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::SubmitTransaction(transaction).send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TransactionAcceptance(Some(acceptance)) => {
+            !matches!(acceptance, MempoolAcceptance::Conflict(_))
+        }
+        Message::TransactionAcceptance(None) => false,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+// prev를 signer의 서명으로 소비하는 tx
+fn spend(
+    prev: &TransactionOutput,
+    signer: &PrivateKey,
+    fee: u64,
+) -> Transaction {
+    let prev_hash = prev.hash();
+    Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, signer),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - fee,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(signer.public_key()),
+        }],
+    )
+}
+
+#[test]
+fn valid_and_invalid_items_are_counted_by_reason() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let node = Node::start(&blockchain, &[]);
+
+    let mut extended = blockchain.clone();
+    mine_run(&mut extended, &key, 1);
+    let valid = extended.blocks().last().unwrap().clone();
+    let mut unmined = valid.clone();
+    while unmined.header.check_pow() {
+        unmined.header.nonce += 1;
+    }
+    assert_ne!(submit_block(&node, &unmined), 200);
+    assert_eq!(submit_block(&node, &valid), 200);
+    assert_ne!(submit_block(&node, &valid), 200);
+
+    assert!(submit_transaction(&node, spend(&prev, &key, 10_000)));
+    // 다른 key의 서명
+    let thief = PrivateKey::new_key();
+    assert!(!submit_transaction(&node, spend(&prev, &thief, 20_000)));
+    // 교체할 수 없는 tx와 같은 output을 쓴다
+    assert!(!submit_transaction(&node, spend(&prev, &key, 20_000)));
+
+    let metrics = node.metrics();
+    for line in [
+        "btc_blocks_accepted_total 1",
+        "btc_blocks_rejected_total{reason=\"DuplicateBlock\"} 1",
+        "btc_blocks_rejected_total{reason=\"InvalidProofOfWork\"} 1",
+        "btc_transactions_accepted_total 1",
+        "btc_transactions_rejected_total{reason=\"Conflict\"} 1",
+        "btc_transactions_rejected_total{reason=\"InvalidSignature\"} 1",
+        "btc_mempool_size 1",
+    ] {
+        assert!(metrics.lines().any(|l| l == line), "{line} in\n{metrics}");
+    }
+}
@@ -0,0 +1,56 @@
+// hostname이 여러 주소로 풀릴 때 닿지 않는 주소는 건너뛰고 연결된 주소를 기록하는지 확인한다
+mod common;
+
+use btclib::network::Message;
+use node::peer::{PeerConnection, connect_first};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+// 아무도 listen 하지 않는 주소
+fn unreachable() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], common::free_port()))
+}
+
+#[tokio::test]
+async fn first_reachable_address_is_used() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let reachable = listener.local_addr().unwrap();
+
+    // peer.example이 닿지 않는 주소 두 개와 닿는 주소 하나로 풀린 경우
+    let addrs = [unreachable(), unreachable(), reachable];
+    let (_stream, connected_to) =
+        connect_first("peer.example", addrs).await.unwrap();
+    assert_eq!(connected_to, reachable);
+    listener.accept().await.unwrap();
+
+    // 모두 닿지 않으면 마지막 에러를 돌려준다
+    let error = connect_first("peer.example", [unreachable(), unreachable()])
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    let error = connect_first("peer.example", []).await.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn hostname_peer_records_the_connected_address() {
+    // localhost가 ::1로도 풀린다면 그 주소는 닿지 않으므로 127.0.0.1로 연결된다
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let reachable = listener.local_addr().unwrap();
+    let address = format!("localhost:{}", reachable.port());
+
+    let peer = PeerConnection::connect(address, Some(9000)).await.unwrap();
+    assert_eq!(peer.connected_to(), Some(reachable));
+
+    // 연결된 주소로 handshake를 보낸다
+    let (mut stream, _) = listener.accept().await.unwrap();
+    match Message::receive_async(&mut stream).await.unwrap() {
+        Message::Version(_) => {}
+        message => panic!("unexpected handshake: {message:?}"),
+    }
+    match Message::receive_async(&mut stream).await.unwrap() {
+        Message::Announce(port) => assert_eq!(port, 9000),
+        message => panic!("unexpected handshake: {message:?}"),
+    }
+}
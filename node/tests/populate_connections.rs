@@ -0,0 +1,65 @@
+// 시작할 때 닿지 않는 peer가 있어도 기다리다 멈추지 않고,
+// 연결된 peer만 기억하는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, mine_run};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 연결은 받지만 아무 응답도 하지 않는 peer
+fn silent_peer() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut held = vec![];
+        for stream in listener.incoming() {
+            held.push(stream.unwrap());
+        }
+    });
+    port
+}
+
+// 아무도 listen 하지 않는 port
+fn closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn known_nodes(node: &Node) -> Vec<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::DiscoverNodes.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::NodeList(nodes) => nodes,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+#[test]
+fn startup_skips_unreachable_peers() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 2);
+    let reachable = Node::start(&blockchain, &[]);
+
+    let reachable_address = format!("127.0.0.1:{}", reachable.port);
+    let peers = [
+        format!("127.0.0.1:{}", closed_port()),
+        reachable_address.clone(),
+        format!("127.0.0.1:{}", silent_peer()),
+    ];
+    let started = Instant::now();
+    let node = Node::spawn(&blockchain, &peers, &[]);
+    node.wait_until_ready();
+
+    // 연결은 동시에 시도하므로 응답 없는 peer의 timeout과 재시도만큼만 걸린다
+    assert!(
+        started.elapsed() < Duration::from_secs(30),
+        "startup took {:?}",
+        started.elapsed()
+    );
+    assert_eq!(known_nodes(&node), vec![reachable_address]);
+    assert_eq!(node.tip(), reachable.tip());
+}
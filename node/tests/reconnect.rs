@@ -0,0 +1,132 @@
+// peer와의 연결이 끊겨도 peer가 살아 있다면, 다음 relay 때 노드가 다시 연결해서
+// handshake를 마치고 메시지를 보내며 peer를 잊지 않는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::{Message, ServiceFlags};
+use btclib::sha256::Hash;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use common::{Node, mine_run};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+fn known_nodes(stream: &mut TcpStream) -> Vec<String> {
+    Message::DiscoverNodes.send(stream).unwrap();
+    match Message::receive(stream).unwrap() {
+        Message::NodeList(nodes) => nodes,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+fn submit(stream: &mut TcpStream, transaction: Transaction) {
+    Message::SubmitTransaction(transaction).send(stream).unwrap();
+    match Message::receive(stream).unwrap() {
+        Message::TransactionAcceptance(Some(_)) => {}
+        message => panic!("transaction was not accepted: {message:?}"),
+    }
+}
+
+// handshake 메시지는 건너뛰고 relay 된 tx의 hash를 읽는다
+fn next_relayed(stream: &mut TcpStream) -> Hash {
+    loop {
+        match Message::receive(stream).unwrap() {
+            Message::Version(_) | Message::Announce(_) => {}
+            Message::NewTransaction(transaction) => return transaction.hash(),
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+}
+
+// timeout 안에 들어온 연결이 있다면 받는다
+fn accept_within(
+    listener: &TcpListener,
+    timeout: Duration,
+) -> Option<TcpStream> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false).unwrap();
+                stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+                return Some(stream);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => panic!("accept failed: {e}"),
+        }
+    }
+    None
+}
+
+#[test]
+fn dropped_peer_stream_is_reconnected_on_next_relay() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 8);
+    let mut spends = blockchain.blocks().map(|block| {
+        let prev = &block.transactions[0].outputs[0];
+        let prev_hash = prev.hash();
+        Transaction::new(
+            vec![TransactionInput::new(
+                prev_hash,
+                Signature::sign_output(&prev_hash, &key),
+            )],
+            vec![TransactionOutput {
+                value: prev.value - 10_000,
+                unique_id: Uuid::new_v4(),
+                lock: LockingCondition::P2PK(key.public_key()),
+            }],
+        )
+    });
+    let node = Node::start(&blockchain, &[]);
+
+    // Announce로 우리 port를 알리면 node가 이 listener로 연결해서 relay 한다
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut announcer = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::Version(ServiceFlags::NETWORK).send(&mut announcer).unwrap();
+    Message::Announce(port).send(&mut announcer).unwrap();
+    let mut relayed = accept_within(&listener, Duration::from_secs(10))
+        .expect("node never connected back");
+    // node가 NODES에 넣을 때까지 잠시 기다린다
+    thread::sleep(Duration::from_millis(500));
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    let peer_address = format!("127.0.0.1:{port}");
+    assert_eq!(known_nodes(&mut stream), vec![peer_address.clone()]);
+    let transaction = spends.next().unwrap();
+    submit(&mut stream, transaction.clone());
+    assert_eq!(next_relayed(&mut relayed), transaction.hash());
+
+    // 연결만 끊는다. 닫힌 연결에 쓴 첫 번째 write는 성공할 수 있으므로
+    // 다시 연결할 때까지 몇 번 relay 한다
+    drop(relayed);
+    let mut reconnected = None;
+    let mut last = None;
+    for transaction in spends.by_ref() {
+        last = Some(transaction.hash());
+        submit(&mut stream, transaction);
+        reconnected = accept_within(&listener, Duration::from_secs(1));
+        if reconnected.is_some() {
+            break;
+        }
+    }
+    let mut reconnected = reconnected.expect("node never reconnected");
+
+    // 실패한 relay를 새 연결로 다시 보낸다
+    assert_eq!(Some(next_relayed(&mut reconnected)), last);
+    assert_eq!(known_nodes(&mut stream), vec![peer_address]);
+
+    // 다음 relay도 새 연결로 온다
+    let transaction = spends.next().expect("ran out of transactions");
+    submit(&mut stream, transaction.clone());
+    assert_eq!(next_relayed(&mut reconnected), transaction.hash());
+}
@@ -0,0 +1,79 @@
+// RPC의 /removepeer로 제거한 노드가 NODES에서 빠지고,
+// gossip으로 다시 알게 되어도 한동안 연결하지 않는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Announce로 노드들을 알린 뒤 상대가 알고 있는 노드 목록을 받는다.
+// 알린 노드에는 따로 연결하므로, 목록에 expected가 모두 나타날 때까지 기다린다
+fn announce(node: &Node, ports: &[u16], expected: &[&str]) -> Vec<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    for port in ports {
+        Message::Announce(*port).send(&mut stream).unwrap();
+    }
+    let started = Instant::now();
+    loop {
+        Message::DiscoverNodes.send(&mut stream).unwrap();
+        let nodes = match Message::receive(&mut stream).unwrap() {
+            Message::NodeList(nodes) => nodes,
+            message => panic!("unexpected message: {message:?}"),
+        };
+        if expected.iter().all(|node| nodes.iter().any(|known| known == node)) {
+            return nodes;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "{expected:?} never appeared in {nodes:?}"
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn removed_peer_is_not_re_added_by_gossip() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let removed = Node::start(&blockchain, &[]);
+    let other = Node::start(&blockchain, &[]);
+    let node = Node::start(&blockchain, &[&removed]);
+
+    let removed_address = format!("127.0.0.1:{}", removed.port);
+    let other_address = format!("127.0.0.1:{}", other.port);
+    assert_eq!(
+        announce(&node, &[], &[&removed_address]),
+        vec![removed_address.clone()]
+    );
+
+    let (status, body) = node.rpc(
+        "POST",
+        "/removepeer",
+        "text/plain",
+        removed_address.as_bytes(),
+    );
+    assert_eq!(status, 200, "{body}");
+    assert!(announce(&node, &[], &[]).is_empty());
+
+    // 다른 노드는 여전히 Announce로 알게 되지만, 제거한 노드는 다시 연결하지 않는다
+    let nodes = announce(&node, &[removed.port, other.port], &[&other_address]);
+    assert_eq!(nodes, vec![other_address]);
+
+    // 이미 제거했으므로 더 이상 알지 못한다
+    let (status, body) = node.rpc(
+        "POST",
+        "/removepeer",
+        "text/plain",
+        removed_address.as_bytes(),
+    );
+    assert_eq!(status, 404, "{body}");
+    let (status, _) = node.rpc("POST", "/removepeer", "text/plain", b"");
+    assert_eq!(status, 400);
+    let (status, _) = node.rpc("GET", "/removepeer", "text/plain", b"");
+    assert_eq!(status, 405);
+}
@@ -0,0 +1,137 @@
+// 블록을 받는 도중 peer와의 연결이 끊기면, 다시 연결해서 처음부터가 아니라
+// 이미 받은 높이부터 이어서 받는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, last_hash, mine_run};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const CHAIN_LENGTH: u64 = 60;
+const INTERRUPT_AT: u64 = 30;
+
+// peer가 받은 블록 요청. 각 요청이 시작하는 높이
+#[derive(Debug, PartialEq)]
+enum Request {
+    GetBlocks(u64),
+    StreamBlocks(u64),
+}
+
+// blockchain을 가진 peer처럼 응답한다.
+// 처음으로 INTERRUPT_AT까지 보낸 연결은 나머지를 보내지 않고 끊는다
+fn serve(
+    mut stream: TcpStream,
+    blockchain: &Blockchain,
+    requests: &Mutex<Vec<Request>>,
+    interrupted: &AtomicBool,
+) {
+    let blocks: Vec<_> = blockchain.blocks().cloned().collect();
+    while let Ok(message) = Message::receive(&mut stream) {
+        let reply = match message {
+            Message::DiscoverNodes => Message::NodeList(vec![]),
+            Message::FetchChainWork => Message::ChainWork(
+                blockchain.total_work(),
+                blockchain.block_height(),
+            ),
+            Message::GetGenesis => Message::Genesis(Some(blocks[0].clone())),
+            Message::GetBlocks {
+                locator,
+                count,
+            } => {
+                let start = blockchain
+                    .find_fork_point(&locator)
+                    .map_or(0, |height| height + 1);
+                requests.lock().unwrap().push(Request::GetBlocks(start));
+                let start = start as usize;
+                let end = (start + count).min(blocks.len());
+                Message::Blocks(blocks[start..end].to_vec())
+            }
+            Message::StreamBlocks {
+                from_height,
+            } => {
+                requests
+                    .lock()
+                    .unwrap()
+                    .push(Request::StreamBlocks(from_height));
+                let interrupt = !interrupted.swap(true, Ordering::SeqCst);
+                let end = if interrupt {
+                    INTERRUPT_AT as usize
+                } else {
+                    blocks.len()
+                };
+                let chunk = blocks[from_height as usize..end].to_vec();
+                Message::BlockChunk(chunk).send(&mut stream).unwrap();
+                match Message::receive(&mut stream) {
+                    Ok(Message::BlockChunkAck) => {}
+                    message => panic!("unexpected message: {message:?}"),
+                }
+                if interrupt {
+                    return;
+                }
+                Message::BlockChunk(vec![])
+            }
+            // handshake와 그 밖의 메시지에는 답하지 않는다
+            _ => continue,
+        };
+        if reply.send(&mut stream).is_err() {
+            return;
+        }
+    }
+}
+
+#[test]
+fn interrupted_download_resumes_from_the_received_height() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, CHAIN_LENGTH);
+    let blockchain = Arc::new(blockchain);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let requests = Arc::new(Mutex::new(vec![]));
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let blockchain = blockchain.clone();
+        let requests = requests.clone();
+        let interrupted = interrupted.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let blockchain = blockchain.clone();
+                let requests = requests.clone();
+                let interrupted = interrupted.clone();
+                thread::spawn(move || {
+                    serve(stream.unwrap(), &blockchain, &requests, &interrupted)
+                });
+            }
+        });
+    }
+
+    // genesis만 가진 node가 peer의 체인을 받는다
+    let mut genesis = Blockchain::new();
+    genesis.add_block(blockchain.blocks().next().unwrap().clone()).unwrap();
+    let node = Node::spawn(&genesis, &[address], &[]);
+    node.wait_until_ready();
+
+    assert_eq!(node.tip(), (CHAIN_LENGTH - 1, last_hash(&blockchain)));
+    assert!(interrupted.load(Ordering::SeqCst));
+    // 첫 구간(16개)은 GetBlocks로, 나머지는 stream으로 받다가 끊겼다.
+    // 다시 연결한 뒤에는 받은 높이부터 요청한다
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[..3],
+        [
+            Request::GetBlocks(1),
+            Request::StreamBlocks(17),
+            Request::GetBlocks(INTERRUPT_AT)
+        ]
+    );
+    assert!(requests[2..].iter().all(|request| match request {
+        Request::GetBlocks(height) | Request::StreamBlocks(height) => {
+            *height >= INTERRUPT_AT
+        }
+    }));
+}
@@ -0,0 +1,45 @@
+// 체인 작업량을 물어도 답하지 않는 peer가 있어도 노드가 멈추지 않고,
+// 답한 peer 중 가장 무거운 체인을 따라가는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, last_hash, mine_run};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// DiscoverNodes에만 답하고 다른 요청은 읽기만 하는 peer
+fn silent_peer() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            thread::spawn(move || serve(stream.unwrap()));
+        }
+    });
+    address
+}
+
+fn serve(mut stream: TcpStream) {
+    while let Ok(message) = Message::receive(&mut stream) {
+        if let Message::DiscoverNodes = message {
+            Message::NodeList(vec![]).send(&mut stream).unwrap();
+        }
+    }
+}
+
+#[test]
+fn silent_peer_is_skipped_when_finding_the_heaviest_chain() {
+    let key = PrivateKey::new_key();
+    let mut heavier = Blockchain::new();
+    mine_run(&mut heavier, &key, 3);
+    let peer = Node::start(&heavier, &[]);
+
+    let mut local = Blockchain::new();
+    local.add_block(heavier.blocks().next().unwrap().clone()).unwrap();
+    let peers = [silent_peer(), format!("127.0.0.1:{}", peer.port)];
+    let node = Node::spawn(&local, &peers, &[]);
+    node.wait_until_ready();
+    assert_eq!(node.tip(), (2, last_hash(&heavier)));
+}
@@ -0,0 +1,47 @@
+// 이미 연결된 peer가 나중에 더 긴 체인을 갖게 되면,
+// 새 블록을 전달받지 못한 노드도 stale tip 감시로 따라잡는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, last_hash, mine_run};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn stale_tip_catches_up_with_a_longer_peer() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 3);
+
+    let peer = Node::start(&blockchain, &[]);
+    let node = Node::start_with_args(
+        &blockchain,
+        &[&peer],
+        &["--stale-tip-after", "2"],
+    );
+    assert_eq!(node.tip(), (2, last_hash(&blockchain)));
+
+    // peer에게만 블록을 넣는다. NewBlock으로 받은 블록은 다시 전파하지 않으므로
+    // node는 이 블록들을 stale tip 감시로만 알 수 있다
+    let mut longer = blockchain.clone();
+    mine_run(&mut longer, &key, 3);
+    let mut stream = TcpStream::connect(("127.0.0.1", peer.port)).unwrap();
+    for block in longer.blocks().skip(3) {
+        Message::NewBlock(block.clone()).send(&mut stream).unwrap();
+    }
+
+    let started = Instant::now();
+    while node.tip() != (5, last_hash(&longer)) {
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "node did not catch up: {:?}, peer is at {:?}",
+            node.tip(),
+            peer.tip()
+        );
+        thread::sleep(Duration::from_millis(200));
+    }
+    assert_eq!(peer.tip(), (5, last_hash(&longer)));
+}
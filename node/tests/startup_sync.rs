@@ -0,0 +1,73 @@
+// 저장된 체인으로 시작하는 노드가 더 많은 작업이 들어간 체인을 가진 peer를 따라잡는지
+// 실제 node 바이너리 두 개를 띄워서 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::Blockchain;
+use btclib::util::Savable;
+use common::{Node, last_hash, mine_run};
+
+#[test]
+fn stale_file_catches_up_on_startup() {
+    let key = PrivateKey::new_key();
+    let mut stale = Blockchain::new();
+    mine_run(&mut stale, &key, 3);
+    let mut heavier = stale.clone();
+    mine_run(&mut heavier, &key, 3);
+
+    let peer = Node::start(&heavier, &[]);
+    let node = Node::start(&stale, &[&peer]);
+
+    assert_eq!(node.tip(), (5, last_hash(&heavier)));
+    let saved = Blockchain::load_from_file(&node.blockchain_file).unwrap();
+    assert_eq!(saved.block_height(), 6);
+}
+
+#[test]
+fn forked_file_switches_to_heavier_chain_on_startup() {
+    let key = PrivateKey::new_key();
+    let mut common = Blockchain::new();
+    mine_run(&mut common, &key, 3);
+
+    // 같은 높이에서 갈라진 뒤 peer 쪽이 블록을 더 쌓았다
+    let mut forked = common.clone();
+    mine_run(&mut forked, &PrivateKey::new_key(), 1);
+    let mut heavier = common;
+    mine_run(&mut heavier, &key, 3);
+
+    let peer = Node::start(&heavier, &[]);
+    let node = Node::start(&forked, &[&peer]);
+
+    assert_eq!(node.tip(), (5, last_hash(&heavier)));
+    let saved = Blockchain::load_from_file(&node.blockchain_file).unwrap();
+    assert_eq!(last_hash(&saved), last_hash(&heavier));
+}
+
+#[test]
+fn fork_deeper_than_max_reorg_depth_is_refused() {
+    let key = PrivateKey::new_key();
+    let mut common = Blockchain::new();
+    mine_run(&mut common, &key, 2);
+
+    // 우리 쪽에 2개, peer 쪽에 3개가 갈라진 뒤 쌓였다
+    let mut forked = common.clone();
+    mine_run(&mut forked, &PrivateKey::new_key(), 2);
+    let mut heavier = common;
+    mine_run(&mut heavier, &key, 3);
+
+    let peer = Node::start(&heavier, &[]);
+    let node =
+        Node::start_with_args(&forked, &[&peer], &["--max-reorg-depth", "1"]);
+
+    // 더 무거운 체인이지만 2개를 되돌려야 하므로 따라가지 않고 운영자에게 알린다
+    assert_eq!(node.tip(), (3, last_hash(&forked)));
+    let metrics = node.metrics();
+    assert!(metrics.contains("btc_deep_reorgs_refused_total 1"));
+    assert!(metrics.contains("btc_manual_intervention_required 1"));
+
+    // 한도 안이라면 따라간다
+    let node =
+        Node::start_with_args(&forked, &[&peer], &["--max-reorg-depth", "2"]);
+    assert_eq!(node.tip(), (4, last_hash(&heavier)));
+    assert!(node.metrics().contains("btc_manual_intervention_required 0"));
+}
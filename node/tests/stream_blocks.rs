@@ -0,0 +1,74 @@
+// StreamBlocks로 체인을 받아오는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::network::Message;
+use btclib::types::Blockchain;
+use common::{Node, last_hash, mine_run};
+use std::net::TcpStream;
+use std::sync::OnceLock;
+
+const STREAMED_BLOCKS: u64 = 100;
+
+// 채굴에 시간이 걸리므로 test들이 같은 체인을 나눠 쓴다
+fn chain() -> &'static Blockchain {
+    static CHAIN: OnceLock<Blockchain> = OnceLock::new();
+    CHAIN.get_or_init(|| {
+        let mut blockchain = Blockchain::new();
+        mine_run(&mut blockchain, &PrivateKey::new_key(), STREAMED_BLOCKS + 1);
+        blockchain
+    })
+}
+
+#[test]
+fn stream_delivers_every_block_up_to_the_tip() {
+    let sender = Node::start(chain(), &[]);
+
+    // genesis만 가진 receiver처럼 chunk마다 검증하고 ack를 보낸다
+    let mut receiver = Blockchain::new();
+    receiver.add_block(chain().blocks().next().unwrap().clone()).unwrap();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", sender.port)).unwrap();
+    Message::StreamBlocks {
+        from_height: 1,
+    }
+    .send(&mut stream)
+    .unwrap();
+    let mut chunks = 0;
+    loop {
+        match Message::receive(&mut stream).unwrap() {
+            Message::BlockChunk(blocks) if blocks.is_empty() => break,
+            Message::BlockChunk(blocks) => {
+                receiver.add_blocks(blocks).unwrap();
+                Message::BlockChunkAck.send(&mut stream).unwrap();
+                chunks += 1;
+            }
+            message => panic!("unexpected message: {message:?}"),
+        }
+    }
+
+    assert!(chunks > 1);
+    assert_eq!(receiver.block_height(), STREAMED_BLOCKS + 1);
+    assert_eq!(
+        (receiver.height().unwrap(), last_hash(&receiver)),
+        sender.tip()
+    );
+
+    // stream이 끝난 연결로 다른 요청을 이어서 보낼 수 있다
+    Message::FetchTip.send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::Tip(tip) => assert_eq!(tip, Some(sender.tip())),
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+#[test]
+fn node_streams_the_chain_from_its_peer() {
+    let sender = Node::start(chain(), &[]);
+
+    let mut genesis = Blockchain::new();
+    genesis.add_block(chain().blocks().next().unwrap().clone()).unwrap();
+    let receiver = Node::start(&genesis, &[&sender]);
+
+    assert_eq!(receiver.tip(), sender.tip());
+}
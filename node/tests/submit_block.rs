@@ -0,0 +1,85 @@
+// HTTP RPC로 블록을 제출했을 때의 응답을 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::types::{Block, Blockchain};
+use btclib::util::Savable;
+use common::{Node, mine_run};
+
+fn hex(block: &Block) -> Vec<u8> {
+    let mut bytes = vec![];
+    block.save(&mut bytes).unwrap();
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>()
+        .into_bytes()
+}
+
+// node의 체인 다음에 올 채굴된 블록과, 같은 템플릿으로 만든 채굴하지 않은 블록
+fn next_blocks(blockchain: &Blockchain, key: &PrivateKey) -> (Block, Block) {
+    let mut extended = blockchain.clone();
+    mine_run(&mut extended, key, 1);
+    let mined = extended.blocks_rev().next().unwrap().clone();
+
+    let mut unmined = mined.clone();
+    while unmined.header.check_pow() {
+        unmined.header.nonce += 1;
+    }
+    (mined, unmined)
+}
+
+#[test]
+fn submitblock_accepts_a_valid_block() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 2);
+    let node = Node::start(&blockchain, &[]);
+    let (mined, _) = next_blocks(&blockchain, &key);
+
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "text/plain", &hex(&mined));
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains(r#""accepted":true"#), "{body}");
+    assert!(body.contains(&mined.hash().to_string()), "{body}");
+    assert_eq!(node.tip(), (2, mined.hash()));
+
+    // 이미 받은 블록은 이유와 함께 거절한다
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "text/plain", &hex(&mined));
+    assert_eq!(status, 422, "{body}");
+    assert!(body.contains("already in the chain"), "{body}");
+}
+
+#[test]
+fn submitblock_rejects_invalid_blocks_with_a_reason() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 2);
+    let node = Node::start(&blockchain, &[]);
+    let (_, unmined) = next_blocks(&blockchain, &key);
+
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "text/plain", &hex(&unmined));
+    assert_eq!(status, 422, "{body}");
+    assert!(body.contains(r#""accepted":false"#), "{body}");
+    assert!(body.contains("does not meet target"), "{body}");
+
+    // CBOR 바이트를 그대로 보낼 수도 있다
+    let mut cbor = vec![];
+    unmined.save(&mut cbor).unwrap();
+    let (status, _) =
+        node.rpc("POST", "/submitblock", "application/cbor", &cbor);
+    assert_eq!(status, 422);
+
+    let (status, body) =
+        node.rpc("POST", "/submitblock", "text/plain", b"not hex");
+    assert_eq!(status, 400, "{body}");
+    let (status, _) = node.rpc("POST", "/submitblock", "text/plain", b"00ff");
+    assert_eq!(status, 400);
+    let (status, _) = node.rpc("GET", "/submitblock", "text/plain", b"");
+    assert_eq!(status, 405);
+
+    // 거절된 블록은 체인에 들어가지 않았다
+    assert_eq!(node.tip().0, 1);
+}
@@ -0,0 +1,88 @@
+// FetchTemplate가 연결마다 제한되고, 체인과 mempool이 그대로라면 만들어둔 템플릿을 재사용하며
+// mempool이 바뀌면 다시 만드는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, PublicKey, Signature};
+use btclib::network::Message;
+use btclib::types::{
+    Block, Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use common::{Node, mine_run};
+use std::net::TcpStream;
+use uuid::Uuid;
+
+// 템플릿을 다시 만들면 timestamp와 coinbase의 output id가 달라지므로 hash도 달라진다
+fn fetch_template(stream: &mut TcpStream, key: &PublicKey) -> Option<Block> {
+    Message::FetchTemplate(key.clone()).send(stream).unwrap();
+    match Message::receive(stream).unwrap() {
+        Message::Template(block) => Some(block),
+        Message::Throttled => None,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+#[test]
+fn rapid_fetches_reuse_the_template_until_throttled() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let node = Node::start(&blockchain, &[]);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    let first = fetch_template(&mut stream, &key.public_key()).unwrap();
+    let mut served = 1;
+    while let Some(template) = fetch_template(&mut stream, &key.public_key()) {
+        assert_eq!(template.hash(), first.hash());
+        served += 1;
+        assert!(served <= 100, "template requests were never throttled");
+    }
+    assert!(served > 1);
+
+    // 제한은 연결마다 따로 센다
+    let mut other = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    let template = fetch_template(&mut other, &key.public_key()).unwrap();
+    assert_eq!(template.hash(), first.hash());
+}
+
+#[test]
+fn new_transaction_invalidates_the_cached_template() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let node = Node::start(&blockchain, &[]);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    let cached = fetch_template(&mut stream, &key.public_key()).unwrap();
+    let again = fetch_template(&mut stream, &key.public_key()).unwrap();
+    assert_eq!(again.hash(), cached.hash());
+    assert_eq!(cached.transactions.len(), 1);
+
+    let prev_hash = prev.hash();
+    let transaction = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - 10_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+    Message::SubmitTransaction(transaction.clone()).send(&mut stream).unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TransactionAcceptance(Some(_)) => {}
+        message => panic!("transaction was not accepted: {message:?}"),
+    }
+
+    // mempool이 바뀌었으므로 새로 만든 템플릿에는 tx가 들어 있다
+    let rebuilt = fetch_template(&mut stream, &key.public_key()).unwrap();
+    assert_ne!(rebuilt.hash(), cached.hash());
+    assert_eq!(rebuilt.transactions.len(), 2);
+    assert_eq!(rebuilt.transactions[1].hash(), transaction.hash());
+    let again = fetch_template(&mut stream, &key.public_key()).unwrap();
+    assert_eq!(again.hash(), rebuilt.hash());
+}
@@ -0,0 +1,102 @@
+// tx_send 바이너리로 보낸 tx가 node의 mempool에 들어가는지 확인한다
+mod common;
+
+use btclib::crypto::{PrivateKey, Signature};
+use btclib::network::Message;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use btclib::util::Savable;
+use common::{Node, free_port, mine_run};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+// test가 끝나면 지워지는 tx 파일
+struct TransactionFile(PathBuf);
+
+impl TransactionFile {
+    fn new(transaction: &Transaction) -> Self {
+        let path = std::env::temp_dir()
+            .join(format!("tx-send-{}.cbor", Uuid::new_v4()));
+        transaction.save_to_file(&path).unwrap();
+        TransactionFile(path)
+    }
+}
+
+impl Drop for TransactionFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn tx_send(address: &str, file: &TransactionFile) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tx_send"))
+        .arg(address)
+        .arg(&file.0)
+        .output()
+        .unwrap()
+}
+
+fn top_mempool(node: &Node) -> Vec<Transaction> {
+    let mut stream = TcpStream::connect(("127.0.0.1", node.port)).unwrap();
+    Message::GetTopMempool {
+        limit: 10,
+    }
+    .send(&mut stream)
+    .unwrap();
+    match Message::receive(&mut stream).unwrap() {
+        Message::TopMempool(transactions) => transactions,
+        message => panic!("unexpected message: {message:?}"),
+    }
+}
+
+#[test]
+fn sent_transaction_lands_in_the_mempool() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = Blockchain::new();
+    mine_run(&mut blockchain, &key, 1);
+    let prev =
+        blockchain.blocks().next().unwrap().transactions[0].outputs[0].clone();
+    let node = Node::start(&blockchain, &[]);
+
+    // 미리 서명해 둔 tx
+    let prev_hash = prev.hash();
+    let transaction = Transaction::new(
+        vec![TransactionInput::new(
+            prev_hash,
+            Signature::sign_output(&prev_hash, &key),
+        )],
+        vec![TransactionOutput {
+            value: prev.value - 10_000,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(key.public_key()),
+        }],
+    );
+    let file = TransactionFile::new(&transaction);
+
+    let output = tx_send(&format!("127.0.0.1:{}", node.port), &file);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout.contains(&transaction.hash().to_string()), "{stdout}");
+    assert!(stdout.contains("accepted"), "{stdout}");
+
+    let mempool: Vec<_> =
+        top_mempool(&node).iter().map(Transaction::hash).collect();
+    assert_eq!(mempool, vec![transaction.hash()]);
+}
+
+#[test]
+fn unreachable_node_is_reported() {
+    let transaction = Transaction::new(vec![], vec![]);
+    let file = TransactionFile::new(&transaction);
+
+    // 아무도 listen 하지 않는 port
+    let port = free_port();
+    let output = tx_send(&format!("127.0.0.1:{port}"), &file);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Failed to connect"), "{stderr}");
+}
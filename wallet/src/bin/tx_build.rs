@@ -0,0 +1,55 @@
+use btclib::crypto::{PrivateKey, PublicKey};
+use btclib::types::TransactionOutput;
+use btclib::util::Savable;
+use std::env;
+use std::fs::File;
+use std::process::exit;
+use wallet::{LargestFirst, Wallet};
+
+// 오프라인 서명용. 네트워크 없이 utxo 목록과 private key만으로 서명된 tx를 만든다
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 7 {
+        eprintln!(
+            "Usage: tx_build <private_key_file> <utxo_file> \
+            <recipient_public_key_file> <amount> <fee> <tx_file>"
+        );
+        exit(1);
+    }
+
+    let private_key = PrivateKey::load_from_file(&args[1])
+        .expect("Failed to load private key");
+    let recipient = PublicKey::load_from_file(&args[3])
+        .expect("Failed to load recipient public key");
+    let amount: u64 = args[4].parse().expect("Invalid amount");
+    let fee: u64 = args[5].parse().expect("Invalid fee");
+
+    // utxo 파일은 CBOR로 직렬화된 Vec<TransactionOutput>
+    let utxos: Vec<TransactionOutput> = ciborium::from_reader(
+        File::open(&args[2]).expect("Failed to open utxo file"),
+    )
+    .expect("Failed to load utxos");
+    let utxos: Vec<_> =
+        utxos.into_iter().map(|utxo| (utxo.hash(), utxo)).collect();
+
+    // 내 키로 잠긴 utxo 중 필요한 만큼을 골라 서명한다.
+    // 수수료와 거스름돈은 wallet이 하는 방식 그대로 정한다
+    let wallet = Wallet::new(private_key);
+    let transaction = match wallet.build_transaction(
+        recipient,
+        amount,
+        fee,
+        &utxos,
+        &LargestFirst,
+    ) {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            eprintln!("Failed to build transaction: {e}");
+            exit(1);
+        }
+    };
+
+    transaction.save_to_file(&args[6]).expect("Failed to save transaction");
+
+    println!("transaction {} saved", transaction.hash());
+}
@@ -0,0 +1,172 @@
+use btclib::sha256::Hash;
+use btclib::types::TransactionOutput;
+use std::cmp::Reverse;
+
+// input 하나를 추가할 때마다 더 내야 하는 수수료 (satoshi).
+// 가치가 이보다 작은 utxo는 쓸수록 손해이므로 선택하지 않는다
+pub const FEE_PER_INPUT: u64 = 100;
+
+// BranchAndBound가 포기하기 전까지 탐색할 최대 노드 수
+const BNB_MAX_TRIES: usize = 100_000;
+
+// 사용할 utxo를 고르는 전략.
+// target은 input 비용을 제외한 금액이고, 각 utxo는 FEE_PER_INPUT을 뺀 실효 가치로 센다.
+// 실효 가치의 합이 target 이상이 되도록 고르고, 불가능하면 None
+pub trait CoinSelector {
+    fn select(
+        &self,
+        utxos: &[(Hash, TransactionOutput)],
+        target: u64,
+    ) -> Option<Vec<(Hash, TransactionOutput)>>;
+}
+
+// input 비용을 뺀 utxo의 가치
+pub fn effective_value(output: &TransactionOutput) -> u64 {
+    output.value.saturating_sub(FEE_PER_INPUT)
+}
+
+// 가치가 큰 utxo부터 사용한다. input 수가 가장 적다
+pub struct LargestFirst;
+
+// 가치가 작은 utxo부터 사용한다. 자잘한 utxo를 정리할 수 있다
+pub struct SmallestFirst;
+
+// 거스름돈이 필요 없을 만큼 target에 딱 맞는 조합을 찾는다.
+// target을 넘는 금액(낭비)이 거스름돈 output을 나중에 쓰는 비용(FEE_PER_INPUT) 이하인 조합 중
+// 낭비가 가장 적은 것을 고르고, 그런 조합이 없으면 LargestFirst로 대신한다
+pub struct BranchAndBound;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        utxos: &[(Hash, TransactionOutput)],
+        target: u64,
+    ) -> Option<Vec<(Hash, TransactionOutput)>> {
+        let mut candidates = utxos.to_vec();
+        candidates.sort_by_key(|(_, output)| Reverse(output.value));
+        accumulate(candidates, target)
+    }
+}
+
+impl CoinSelector for SmallestFirst {
+    fn select(
+        &self,
+        utxos: &[(Hash, TransactionOutput)],
+        target: u64,
+    ) -> Option<Vec<(Hash, TransactionOutput)>> {
+        let mut candidates = utxos.to_vec();
+        candidates.sort_by_key(|(_, output)| output.value);
+        accumulate(candidates, target)
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(
+        &self,
+        utxos: &[(Hash, TransactionOutput)],
+        target: u64,
+    ) -> Option<Vec<(Hash, TransactionOutput)>> {
+        let mut candidates: Vec<_> = utxos
+            .iter()
+            .filter(|(_, output)| effective_value(output) > 0)
+            .cloned()
+            .collect();
+        candidates.sort_by_key(|(_, output)| Reverse(output.value));
+
+        let values: Vec<u64> = candidates
+            .iter()
+            .map(|(_, output)| effective_value(output))
+            .collect();
+        // remaining[i]: i번째 이후 utxo를 모두 더한 값. 가지치기에 쓴다
+        let mut remaining = vec![0u64; values.len() + 1];
+        for i in (0..values.len()).rev() {
+            remaining[i] = remaining[i + 1].saturating_add(values[i]);
+        }
+
+        let mut search = BnbSearch {
+            values: &values,
+            remaining: &remaining,
+            target,
+            upper_bound: target.saturating_add(FEE_PER_INPUT),
+            tries: 0,
+            best: None,
+        };
+        search.search(0, 0, &mut vec![]);
+
+        match search.best {
+            Some((_, selected)) => Some(
+                selected
+                    .into_iter()
+                    .map(|index| candidates[index].clone())
+                    .collect(),
+            ),
+            None => LargestFirst.select(utxos, target),
+        }
+    }
+}
+
+// 정렬된 순서대로 실효 가치를 더해 target을 넘는 순간 멈춘다
+fn accumulate(
+    candidates: Vec<(Hash, TransactionOutput)>,
+    target: u64,
+) -> Option<Vec<(Hash, TransactionOutput)>> {
+    let mut selected = vec![];
+    let mut total: u64 = 0;
+    for (hash, output) in candidates {
+        if total >= target {
+            break;
+        }
+        if effective_value(&output) == 0 {
+            continue;
+        }
+        total = total.saturating_add(effective_value(&output));
+        selected.push((hash, output));
+    }
+    (total >= target).then_some(selected)
+}
+
+struct BnbSearch<'a> {
+    values: &'a [u64],
+    remaining: &'a [u64],
+    target: u64,
+    upper_bound: u64,
+    tries: usize,
+    // (낭비, 선택한 utxo의 index)
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl BnbSearch<'_> {
+    // index번째 utxo를 넣는 경우와 넣지 않는 경우로 나눠 깊이 우선 탐색한다
+    fn search(&mut self, index: usize, sum: u64, selected: &mut Vec<usize>) {
+        if self.tries >= BNB_MAX_TRIES {
+            return;
+        }
+        self.tries += 1;
+
+        // 너무 많이 골랐거나, 나머지를 다 더해도 모자라면 더 볼 필요가 없다
+        if sum > self.upper_bound
+            || sum.saturating_add(self.remaining[index]) < self.target
+        {
+            return;
+        }
+
+        if sum >= self.target {
+            // 여기서 더 고르면 낭비만 늘어난다
+            let waste = sum - self.target;
+            if self.best.as_ref().is_none_or(|(best, _)| waste < *best) {
+                self.best = Some((waste, selected.clone()));
+            }
+            return;
+        }
+
+        if index == self.values.len() {
+            return;
+        }
+
+        selected.push(index);
+        let with_index = sum.saturating_add(self.values[index]);
+        self.search(index + 1, with_index, selected);
+        selected.pop();
+        self.search(index + 1, sum, selected);
+    }
+}
@@ -0,0 +1,220 @@
+use btclib::crypto::{PrivateKey, PublicKey, Signature};
+use btclib::error::{BtcError, Result};
+use btclib::sha256::Hash;
+use btclib::types::{
+    Blockchain, LockingCondition, Transaction, TransactionInput,
+    TransactionOutput,
+};
+use uuid::Uuid;
+
+mod coin_selection;
+
+pub use coin_selection::{
+    effective_value, BranchAndBound, CoinSelector, LargestFirst, SmallestFirst,
+    FEE_PER_INPUT,
+};
+
+pub struct Wallet {
+    private_key: PrivateKey,
+}
+
+impl Wallet {
+    pub fn new(private_key: PrivateKey) -> Self {
+        Wallet { private_key }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.private_key.public_key()
+    }
+
+    // 내 key로 잠긴 output인지
+    fn owns(&self, output: &TransactionOutput) -> bool {
+        output.lock.pubkey() == Some(&self.public_key())
+    }
+
+    // recipient에게 amount를 보내는 tx를 만든다. 사용할 utxo는 selector가 고른다.
+    // 수수료는 fee에 input마다 FEE_PER_INPUT을 더한 값이고, 남는 금액은 거스름돈으로 돌려받는다.
+    // 거스름돈이 FEE_PER_INPUT 이하라면 나중에 쓰는 비용이 더 크므로 수수료로 넘긴다
+    pub fn build_transaction(
+        &self,
+        recipient: PublicKey,
+        amount: u64,
+        fee: u64,
+        utxos: &[(Hash, TransactionOutput)],
+        selector: &impl CoinSelector,
+    ) -> Result<Transaction> {
+        let mine: Vec<_> = utxos
+            .iter()
+            .filter(|(_, output)| self.owns(output))
+            .cloned()
+            .collect();
+        let target = amount
+            .checked_add(fee)
+            .ok_or(BtcError::InvalidTransaction)?;
+        let selected = selector
+            .select(&mine, target)
+            .ok_or(BtcError::InvalidTransaction)?;
+
+        let input_value = selected.iter().try_fold(0u64, |sum, (_, output)| {
+            sum.checked_add(effective_value(output))
+        });
+        let change = input_value
+            .and_then(|value| value.checked_sub(target))
+            .ok_or(BtcError::InvalidTransaction)?;
+
+        let mut outputs = vec![TransactionOutput {
+            value: amount,
+            unique_id: Uuid::new_v4(),
+            lock: LockingCondition::P2PK(recipient),
+        }];
+        if change > FEE_PER_INPUT {
+            outputs.push(TransactionOutput {
+                value: change,
+                unique_id: Uuid::new_v4(),
+                lock: LockingCondition::P2PK(self.public_key()),
+            });
+        }
+
+        // 나중에 bump_fee로 수수료를 올릴 수 있도록 RBF 신호를 보낸다
+        let inputs = selected
+            .into_iter()
+            .map(|(hash, _)| self.sign_input(hash))
+            .collect();
+        Ok(Transaction::new(inputs, outputs))
+    }
+
+    // 내 output을 소비하는, 교체 가능한(RBF) input
+    fn sign_input(&self, prev_transaction_output_hash: Hash) -> TransactionInput {
+        TransactionInput {
+            sequence: btclib::MAX_RBF_SEQUENCE,
+            ..TransactionInput::new(
+                prev_transaction_output_hash,
+                Signature::sign_output(
+                    &prev_transaction_output_hash,
+                    &self.private_key,
+                ),
+            )
+        }
+    }
+
+    // original보다 additional_fee만큼 수수료를 더 내는 교체(RBF) tx를 만든다.
+    // 거스름돈을 줄여서 수수료를 마련하고, 부족하면 utxos 중 내 output을 input으로 추가한다.
+    // utxos에는 original이 소비하는 output들도 들어 있어야 한다.
+    // additional_fee가 교체 tx의 전파 비용보다 작으면 mempool이 받지 않으므로 에러
+    pub fn bump_fee(
+        &self,
+        original: &Transaction,
+        additional_fee: u64,
+        utxos: &[(Hash, TransactionOutput)],
+    ) -> Result<Transaction> {
+        if additional_fee == 0 {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        let find_utxo = |hash: &Hash| {
+            utxos
+                .iter()
+                .find(|(utxo_hash, _)| utxo_hash == hash)
+                .map(|(_, output)| output)
+        };
+
+        // 다시 서명해야 하므로 original의 input은 모두 내 것이어야 한다
+        let mut inputs = vec![];
+        let mut input_value: u64 = 0;
+        for input in &original.inputs {
+            let output = find_utxo(&input.prev_transaction_output_hash)
+                .filter(|output| self.owns(output))
+                .ok_or(BtcError::InvalidTransactionInput)?;
+            inputs.push(input.prev_transaction_output_hash);
+            input_value = input_value
+                .checked_add(output.value)
+                .ok_or(BtcError::InvalidTransactionInput)?;
+        }
+
+        let output_value = checked_sum(&original.outputs)?;
+        let original_fee = input_value
+            .checked_sub(output_value)
+            .ok_or(BtcError::InvalidTransaction)?;
+        let fee = original_fee
+            .checked_add(additional_fee)
+            .ok_or(BtcError::InvalidTransaction)?;
+
+        // 마지막으로 나에게 돌아오는 output을 거스름돈으로 본다
+        let mut outputs = original.outputs.clone();
+        let change_index = outputs.iter().rposition(|output| self.owns(output));
+        let payment_value = output_value
+            - change_index.map(|index| outputs[index].value).unwrap_or(0);
+        let required = payment_value
+            .checked_add(fee)
+            .ok_or(BtcError::InvalidTransaction)?;
+
+        // 거스름돈만으로 부족하면 아직 쓰지 않은 내 utxo를 더한다
+        for (hash, output) in utxos {
+            if input_value >= required {
+                break;
+            }
+            if !self.owns(output) || inputs.contains(hash) {
+                continue;
+            }
+            inputs.push(*hash);
+            input_value = input_value
+                .checked_add(output.value)
+                .ok_or(BtcError::InvalidTransactionInput)?;
+        }
+        if input_value < required {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        let change = input_value - required;
+        match change_index {
+            Some(index) if change > 0 => outputs[index].value = change,
+            Some(index) => {
+                outputs.remove(index);
+            }
+            None if change > 0 => outputs.push(TransactionOutput {
+                value: change,
+                unique_id: Uuid::new_v4(),
+                lock: LockingCondition::P2PK(self.public_key()),
+            }),
+            None => {}
+        }
+        if outputs.is_empty() {
+            return Err(BtcError::InvalidTransaction);
+        }
+
+        let replacement = Transaction::new(
+            inputs.into_iter().map(|hash| self.sign_input(hash)).collect(),
+            outputs,
+        );
+
+        // 교체 정책: RBF 신호를 보내고, original과 input을 공유해야 한다.
+        // 수수료는 mempool과 같은 검사(check_policy, check_replacement)로 확인해서
+        // 노드가 거부할 tx를 만들지 않는다
+        let replacement_fee = input_value - checked_sum(&replacement.outputs)?;
+        let conflicts = original.inputs.iter().all(|input| {
+            replacement.inputs.iter().any(|replacement_input| {
+                replacement_input.prev_transaction_output_hash
+                    == input.prev_transaction_output_hash
+            })
+        });
+        if !replacement.signals_rbf() || !conflicts {
+            return Err(BtcError::InvalidTransaction);
+        }
+        Blockchain::check_policy(&replacement, replacement_fee)?;
+        Blockchain::check_replacement(
+            &replacement,
+            replacement_fee,
+            original_fee,
+        )?;
+
+        Ok(replacement)
+    }
+}
+
+// output value의 합. u64를 넘어가면 에러
+fn checked_sum(outputs: &[TransactionOutput]) -> Result<u64> {
+    outputs.iter().try_fold(0u64, |sum, output| {
+        sum.checked_add(output.value)
+            .ok_or(BtcError::InvalidTransactionOutput)
+    })
+}
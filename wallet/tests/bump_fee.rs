@@ -0,0 +1,132 @@
+// bump_fee로 만든 교체 tx가 mempool의 교체 정책을 통과해 원래 tx를 밀어내는지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::error::BtcError;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, MempoolAcceptance, Transaction,
+    TransactionOutput,
+};
+use btclib::util::MerkleRoot;
+use chrono::Utc;
+use common::output;
+use wallet::{LargestFirst, Wallet};
+
+// key에게 보상을 지급하는 genesis 하나로 이루어진 체인
+fn chain(key: &PrivateKey) -> Blockchain {
+    let transactions = vec![Transaction::new(
+        vec![],
+        vec![output(key, Blockchain::block_reward_at(0))],
+    )];
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+    blockchain
+}
+
+fn utxos(blockchain: &Blockchain) -> Vec<(Hash, TransactionOutput)> {
+    blockchain
+        .utxos()
+        .iter()
+        .map(|(hash, (_, output))| (*hash, output.clone()))
+        .collect()
+}
+
+fn fee(transaction: &Transaction, utxos: &[(Hash, TransactionOutput)]) -> u64 {
+    let input_value: u64 = transaction
+        .inputs
+        .iter()
+        .map(|input| {
+            utxos
+                .iter()
+                .find(|(hash, _)| *hash == input.prev_transaction_output_hash)
+                .unwrap()
+                .1
+                .value
+        })
+        .sum();
+    let output_value: u64 =
+        transaction.outputs.iter().map(|output| output.value).sum();
+    input_value - output_value
+}
+
+fn mempool(blockchain: &Blockchain) -> Vec<Hash> {
+    blockchain.mempool().iter().map(|(_, tx)| tx.hash()).collect()
+}
+
+#[test]
+fn bumped_transaction_replaces_the_original() {
+    let key = PrivateKey::new_key();
+    let wallet = Wallet::new(key.clone());
+    let mut blockchain = chain(&key);
+    let utxos = utxos(&blockchain);
+    let recipient = PrivateKey::new_key().public_key();
+
+    let original = wallet
+        .build_transaction(recipient, 10_000, 1_000, &utxos, &LargestFirst)
+        .unwrap();
+    assert_eq!(
+        blockchain.add_to_mempool(original.clone()).unwrap(),
+        MempoolAcceptance::Accepted
+    );
+
+    let bumped = wallet.bump_fee(&original, 5_000, &utxos).unwrap();
+    assert!(fee(&bumped, &utxos) > fee(&original, &utxos));
+    assert_eq!(
+        blockchain.add_to_mempool(bumped.clone()).unwrap(),
+        MempoolAcceptance::Replaced(vec![original.hash()])
+    );
+    assert_eq!(mempool(&blockchain), vec![bumped.hash()]);
+}
+
+#[test]
+fn replacement_must_pay_more_than_the_relay_fee_on_top() {
+    let key = PrivateKey::new_key();
+    let wallet = Wallet::new(key.clone());
+    let mut blockchain = chain(&key);
+    let utxos = utxos(&blockchain);
+    let recipient = PrivateKey::new_key().public_key();
+
+    let original = wallet
+        .build_transaction(
+            recipient.clone(),
+            10_000,
+            1_000,
+            &utxos,
+            &LargestFirst,
+        )
+        .unwrap();
+    blockchain.add_to_mempool(original.clone()).unwrap();
+
+    // 수수료가 같거나, 더 높더라도 교체 tx의 전파 비용만큼 높지 않으면 교체하지 않는다
+    for extra in [0, 1] {
+        let rival = wallet
+            .build_transaction(
+                recipient.clone(),
+                10_000,
+                1_000 + extra,
+                &utxos,
+                &LargestFirst,
+            )
+            .unwrap();
+        assert!(matches!(
+            blockchain.add_to_mempool(rival),
+            Err(BtcError::InsufficientReplacementFee)
+        ));
+    }
+    assert!(matches!(
+        wallet.bump_fee(&original, 1, &utxos),
+        Err(BtcError::InsufficientReplacementFee)
+    ));
+    assert_eq!(mempool(&blockchain), vec![original.hash()]);
+}
@@ -0,0 +1,117 @@
+// 각 coin selection 전략이 고른 utxo가 target을 채우고, 전략에 맞게 낭비가 가장 적은지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::types::TransactionOutput;
+use common::output;
+use wallet::{
+    BranchAndBound, CoinSelector, FEE_PER_INPUT, LargestFirst, SmallestFirst,
+    effective_value,
+};
+
+// 실효 가치가 values인 utxo들
+fn utxos(values: &[u64]) -> Vec<(Hash, TransactionOutput)> {
+    let key = PrivateKey::new_key();
+    values
+        .iter()
+        .map(|value| {
+            let output = output(&key, value + FEE_PER_INPUT);
+            (output.hash(), output)
+        })
+        .collect()
+}
+
+// 고른 utxo의 실효 가치. 비교하기 쉽도록 정렬한다
+fn selected_values(
+    selector: &impl CoinSelector,
+    utxos: &[(Hash, TransactionOutput)],
+    target: u64,
+) -> Option<Vec<u64>> {
+    let selected = selector.select(utxos, target)?;
+    assert!(
+        selected
+            .iter()
+            .all(|(hash, _)| utxos.iter().any(|(utxo, _)| utxo == hash))
+    );
+    let mut values: Vec<_> =
+        selected.iter().map(|(_, output)| effective_value(output)).collect();
+    values.sort();
+    assert!(values.iter().sum::<u64>() >= target);
+    Some(values)
+}
+
+#[test]
+fn each_strategy_covers_the_target_its_own_way() {
+    let utxos = utxos(&[1_000, 2_000, 5_000, 10_000]);
+    let target = 7_000;
+
+    // input 하나로 끝내지만 3_000이 남는다
+    assert_eq!(
+        selected_values(&LargestFirst, &utxos, target),
+        Some(vec![10_000])
+    );
+    // 작은 것부터 모으다 target을 넘는 순간 멈춘다
+    assert_eq!(
+        selected_values(&SmallestFirst, &utxos, target),
+        Some(vec![1_000, 2_000, 5_000])
+    );
+    // 딱 맞는 조합이 있으면 그것을 고른다
+    assert_eq!(
+        selected_values(&BranchAndBound, &utxos, target),
+        Some(vec![2_000, 5_000])
+    );
+}
+
+#[test]
+fn branch_and_bound_picks_the_least_waste_within_the_bound() {
+    let utxos = utxos(&[3_000, 4_020, 4_050, 8_000]);
+
+    // 7_050(낭비 50)과 7_020(낭비 20) 중 낭비가 적은 쪽
+    assert_eq!(
+        selected_values(&BranchAndBound, &utxos, 7_000),
+        Some(vec![3_000, 4_020])
+    );
+    // FEE_PER_INPUT 이내로 맞는 조합이 없으면 LargestFirst와 같다
+    let target = 5_000;
+    assert_eq!(
+        selected_values(&BranchAndBound, &utxos, target),
+        selected_values(&LargestFirst, &utxos, target)
+    );
+    assert_eq!(
+        selected_values(&BranchAndBound, &utxos, target),
+        Some(vec![8_000])
+    );
+}
+
+#[test]
+fn dust_is_never_selected() {
+    let mut utxos = utxos(&[1_000, 2_000]);
+    let key = PrivateKey::new_key();
+    let dust = output(&key, FEE_PER_INPUT);
+    utxos.push((dust.hash(), dust));
+
+    assert_eq!(
+        selected_values(&SmallestFirst, &utxos, 1_500),
+        Some(vec![1_000, 2_000])
+    );
+    assert_eq!(
+        selected_values(&BranchAndBound, &utxos, 3_000),
+        Some(vec![1_000, 2_000])
+    );
+    assert_eq!(
+        selected_values(&LargestFirst, &utxos, 3_000),
+        Some(vec![1_000, 2_000])
+    );
+}
+
+#[test]
+fn insufficient_balance_selects_nothing() {
+    let utxos = utxos(&[1_000, 2_000, 5_000]);
+    // 액면가의 합은 8_300이지만 input 비용을 빼면 8_000이다
+    let target = 8_001;
+    assert!(LargestFirst.select(&utxos, target).is_none());
+    assert!(SmallestFirst.select(&utxos, target).is_none());
+    assert!(BranchAndBound.select(&utxos, target).is_none());
+    assert!(BranchAndBound.select(&[], 1).is_none());
+}
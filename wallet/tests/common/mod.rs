@@ -0,0 +1,15 @@
+// 여러 test에서 쓰는 tx 생성 도구
+#![allow(dead_code)]
+
+use btclib::crypto::PrivateKey;
+use btclib::types::{LockingCondition, TransactionOutput};
+use uuid::Uuid;
+
+// key에게 지급하는 value짜리 P2PK output
+pub fn output(key: &PrivateKey, value: u64) -> TransactionOutput {
+    TransactionOutput {
+        value,
+        unique_id: Uuid::new_v4(),
+        lock: LockingCondition::P2PK(key.public_key()),
+    }
+}
@@ -0,0 +1,138 @@
+// tx_build 바이너리로 오프라인에서 서명한 tx가 참조한 utxo에 대해 유효한지 확인한다
+mod common;
+
+use btclib::crypto::PrivateKey;
+use btclib::sha256::Hash;
+use btclib::types::{
+    Block, BlockHeader, Blockchain, MempoolAcceptance, Transaction,
+    TransactionOutput,
+};
+use btclib::util::{MerkleRoot, Savable};
+use chrono::Utc;
+use common::output;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+// test가 끝나면 지워지는 디렉터리
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        let path =
+            std::env::temp_dir().join(format!("tx-build-{}", Uuid::new_v4()));
+        std::fs::create_dir(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// key에게 보상을 나눠서 지급하는 genesis 하나로 이루어진 체인
+fn chain(key: &PrivateKey) -> Blockchain {
+    let reward = Blockchain::block_reward_at(0);
+    let transactions = vec![Transaction::new(
+        vec![],
+        vec![output(key, 50_000), output(key, reward - 50_000)],
+    )];
+    let genesis = Block::new(
+        BlockHeader::new(
+            Utc::now(),
+            0,
+            Hash::zero(),
+            MerkleRoot::calculate(&transactions),
+            btclib::MIN_TARGET,
+        ),
+        transactions,
+    );
+    let mut blockchain = Blockchain::new();
+    blockchain.add_block(genesis).unwrap();
+    blockchain
+}
+
+// key와 utxo 파일을 dir에 쓰고 tx_build를 실행한다
+fn tx_build(
+    dir: &Path,
+    key: &PrivateKey,
+    utxos: &[TransactionOutput],
+    amount: u64,
+    fee: u64,
+) -> Output {
+    key.save_to_file(dir.join("sender.cbor")).unwrap();
+    PrivateKey::new_key()
+        .public_key()
+        .save_to_file(dir.join("recipient.pem"))
+        .unwrap();
+    ciborium::into_writer(utxos, File::create(dir.join("utxos.cbor")).unwrap())
+        .unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_tx_build"))
+        .args([
+            "sender.cbor",
+            "utxos.cbor",
+            "recipient.pem",
+            &amount.to_string(),
+            &fee.to_string(),
+            "tx.cbor",
+        ])
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn built_transaction_verifies_against_the_referenced_utxos() {
+    let key = PrivateKey::new_key();
+    let mut blockchain = chain(&key);
+    let utxos: Vec<_> =
+        blockchain.utxos().values().map(|(_, output)| output.clone()).collect();
+
+    let dir = TempDir::new();
+    let output = tx_build(&dir.0, &key, &utxos, 10_000, 1_000);
+    assert!(output.status.success(), "{output:?}");
+    let transaction =
+        Transaction::load_from_file(dir.0.join("tx.cbor")).unwrap();
+
+    // 모든 input이 utxo를 참조하고 그 utxo의 key로 서명되어 있다
+    for input in &transaction.inputs {
+        let (_, utxo) =
+            &blockchain.utxos()[&input.prev_transaction_output_hash];
+        let public_key = utxo.lock.pubkey().unwrap();
+        assert!(
+            input
+                .signature
+                .verify(&input.prev_transaction_output_hash, public_key)
+        );
+    }
+    assert_eq!(transaction.outputs[0].value, 10_000);
+
+    blockchain.check_transaction(&transaction).unwrap();
+    assert_eq!(
+        blockchain.add_to_mempool(transaction).unwrap(),
+        MempoolAcceptance::Accepted
+    );
+}
+
+#[test]
+fn inputs_must_cover_amount_and_fee() {
+    let key = PrivateKey::new_key();
+    let blockchain = chain(&key);
+    let utxos: Vec<_> =
+        blockchain.utxos().values().map(|(_, output)| output.clone()).collect();
+    let total: u64 = utxos.iter().map(|output| output.value).sum();
+
+    let dir = TempDir::new();
+    let output = tx_build(&dir.0, &key, &utxos, total, 1_000);
+    assert!(!output.status.success());
+    assert!(!dir.0.join("tx.cbor").exists());
+
+    // 다른 key의 utxo로는 만들 수 없다
+    let output =
+        tx_build(&dir.0, &PrivateKey::new_key(), &utxos, 10_000, 1_000);
+    assert!(!output.status.success());
+}